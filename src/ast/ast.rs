@@ -1,25 +1,383 @@
+use crate::util::span::Span;
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct Ast {
     pub items: Vec<Statement>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
-pub enum Statement {
+pub struct Statement {
+    pub kind: StatementKind,
+    pub span: Span,
+    /// Lints silenced by `#allow(...)` above the statement.
+    pub allow: Vec<Allow>,
+    /// Exported with `pub`, so other modules can `use` it.
+    pub public: bool,
+}
+
+impl Statement {
+    pub fn new(kind: StatementKind, span: Span) -> Self {
+        Self {
+            kind,
+            span,
+            allow: Vec::new(),
+            public: false,
+        }
+    }
+}
+
+/// One lint name in an `#allow(...)` attribute.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Allow {
+    pub lint: String,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum StatementKind {
     ExprStmt(Expr),
-    Let { name: String, expr: Expr },
-    Yell { expr: Expr },
-    Toss { expr: Expr },
+    /// `grab x = expr;`, or `grab x: Int = expr;` with an annotation.
+    Let {
+        name: String,
+        ty: Option<TypeExpr>,
+        expr: Expr,
+    },
+    /// `grab (x, y) = pair;`, binding the parts of a tuple.
+    Destructure {
+        pattern: Pattern,
+        ty: Option<TypeExpr>,
+        expr: Expr,
+    },
+    Yell {
+        expr: Expr,
+    },
+    Toss {
+        expr: Expr,
+    },
+    /// `test "name" { ... }`, only run by `sauce test`.
+    Test {
+        name: String,
+        body: Vec<Statement>,
+    },
+    /// `assert expr;`, only inside a test.
+    Assert {
+        expr: Expr,
+    },
+    /// `assert_eq left, right;`, only inside a test.
+    AssertEq {
+        left: Expr,
+        right: Expr,
+    },
+    /// `use path::to::module::name;` binds `alias` to `name` from that
+    /// module. The parser sets `alias` to `name`; linking may qualify it.
+    Use {
+        path: Vec<String>,
+        name: String,
+        alias: String,
+    },
+    /// `type Person = { name: String, age: Int };` names a type.
+    TypeDecl {
+        name: String,
+        ty: TypeExpr,
+    },
+    /// `type Shape = Circle(Int) | Rect(Int, Int);` declares a sum type
+    /// and a constructor for each variant.
+    SumDecl {
+        name: String,
+        variants: Vec<Variant>,
+    },
+    /// `effect network_error(String);` declares an effect, and the type
+    /// of what is tossed with it, if anything is.
+    EffectDecl {
+        name: String,
+        payload: Option<TypeExpr>,
+    },
+}
+
+/// One variant of a sum type: `Rect(Int, Int)`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Variant {
+    pub name: String,
+    pub fields: Vec<TypeExpr>,
+    pub span: Span,
+}
+
+/// A type as written in the source.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypeExpr {
+    pub kind: TypeExprKind,
+    pub span: Span,
+}
+
+impl TypeExpr {
+    pub fn new(kind: TypeExprKind, span: Span) -> Self {
+        Self { kind, span }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TypeExprKind {
+    /// `Int`, `Person`, or `List<Int>` with its arguments.
+    Named(String, Vec<TypeExpr>),
+    /// `{ name: String, age: Int }`
+    Record(Vec<(String, TypeExpr)>),
+    /// `(Int, String)`
+    Tuple(Vec<TypeExpr>),
+    /// `fn(Int, Int) -> Int`
+    Function(Vec<TypeExpr>, Box<TypeExpr>),
+}
+
+/// The name a top-level binding of module `path` gets once modules are
+/// linked into one program: `math::square`.
+pub fn qualify(path: &[String], name: &str) -> String {
+    format!("{}::{name}", path.join("::"))
+}
+
+/// `name` without the module path [`qualify`] gave it: `Circle` for
+/// `shapes::Circle`.
+pub fn unqualified(name: &str) -> &str {
+    name.rsplit("::").next().unwrap_or(name)
+}
+
+/// Built-in pipeline stages and the number of arguments each takes,
+/// applied to the value piped into them: `xs |> len`,
+/// `xs |> map(fn(x) x * 2)`. A binding of the same name hides them.
+pub const STAGES: [(&str, usize); 6] = [
+    ("len", 0),
+    ("sum", 0),
+    ("map", 1),
+    ("filter", 1),
+    ("take", 1),
+    ("fold", 2),
+];
+
+/// Whether `name` is a built-in stage.
+pub fn is_stage(name: &str) -> bool {
+    stage_arity(name).is_some()
+}
+
+/// The number of arguments built-in stage `name` takes.
+pub fn stage_arity(name: &str) -> Option<usize> {
+    STAGES
+        .iter()
+        .find(|(stage, _)| *stage == name)
+        .map(|(_, arity)| *arity)
+}
+
+/// A built-in stage on the right of a pipeline, with its arguments.
+#[derive(Debug, Clone, Copy)]
+pub struct Stage<'a> {
+    pub name: &'a str,
+    pub args: &'a [Expr],
+}
+
+impl<'a> Stage<'a> {
+    /// The stage `expr` names, `len` or `map(f)`, whether or not a
+    /// binding hides it.
+    pub fn of(expr: &'a Expr) -> Option<Self> {
+        let (name, args) = match &expr.kind {
+            ExprKind::Ident(name) => (name, &[][..]),
+            ExprKind::Call(callee, args) => match &callee.kind {
+                ExprKind::Ident(name) => (name, args.as_slice()),
+                _ => return None,
+            },
+            _ => return None,
+        };
+        is_stage(name).then_some(Self { name, args })
+    }
+
+    /// Whether the stage turns a list into another list one element at a
+    /// time, so the stages after it can be fused with it.
+    pub fn is_streaming(&self) -> bool {
+        matches!(self.name, "map" | "filter" | "take")
+    }
+}
+
+/// Splits a pipeline ending in built-in stages into the value they start
+/// from and the stages, in order: `xs |> map(f) |> sum` gives `xs` and
+/// `[map(f), sum]`. Every stage but the last is a streaming one. `bound`
+/// tells whether a name is bound, hiding the stage of that name.
+pub fn stage_chain<'a>(
+    expr: &'a Expr,
+    bound: impl Fn(&str) -> bool,
+) -> Option<(&'a Expr, Vec<Stage<'a>>)> {
+    let stage = |expr: &'a Expr| match &expr.kind {
+        ExprKind::Pipeline(lhs, rhs) => Stage::of(rhs)
+            .filter(|stage| !bound(stage.name))
+            .map(|stage| (&**lhs, stage)),
+        _ => None,
+    };
+
+    let (mut source, last) = stage(expr)?;
+    let mut stages = vec![last];
+    while let Some((lhs, stage)) = stage(source).filter(|(_, stage)| stage.is_streaming()) {
+        source = lhs;
+        stages.push(stage);
+    }
+    stages.reverse();
+    Some((source, stages))
+}
+
+/// A binary operator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl BinOp {
+    pub fn symbol(self) -> &'static str {
+        match self {
+            BinOp::Add => "+",
+            BinOp::Sub => "-",
+            BinOp::Mul => "*",
+            BinOp::Div => "/",
+            BinOp::Rem => "%",
+            BinOp::Eq => "==",
+            BinOp::Ne => "!=",
+            BinOp::Lt => "<",
+            BinOp::Le => "<=",
+            BinOp::Gt => ">",
+            BinOp::Ge => ">=",
+        }
+    }
+
+    /// Comparisons give `1` when they hold and `0` otherwise.
+    pub fn is_comparison(self) -> bool {
+        matches!(
+            self,
+            BinOp::Eq | BinOp::Ne | BinOp::Lt | BinOp::Le | BinOp::Gt | BinOp::Ge
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expr {
+    pub kind: ExprKind,
+    pub span: Span,
+}
+
+impl Expr {
+    pub fn new(kind: ExprKind, span: Span) -> Self {
+        Self { kind, span }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
-pub enum Expr {
+pub enum ExprKind {
     Ident(String),
     Int(i64),
     String(String),
     Pipeline(Box<Expr>, Box<Expr>),
+    /// `[a, b, c]`
+    List(Vec<Expr>),
+    /// `(a, b)`, or `(a,)` with one item.
+    Tuple(Vec<Expr>),
+    /// `list[index]`
+    Index(Box<Expr>, Box<Expr>),
+    /// `fn(x, y) body`, or `fn(x: Int, y: Int) -> Int body` with
+    /// annotations.
+    Lambda {
+        params: Vec<Param>,
+        ret: Option<TypeExpr>,
+        body: Box<Expr>,
+    },
+    /// `f(a, b)`
+    Call(Box<Expr>, Vec<Expr>),
+    /// `a + b`
+    Binary(BinOp, Box<Expr>, Box<Expr>),
+    /// `{ name: "a", age: 3 }`, fields in source order.
+    Record(Vec<(String, Expr)>),
+    /// `record.field`
+    Field(Box<Expr>, String),
+    /// `{ record with age: 4 }`, a copy of `record` with new values for
+    /// some of its fields.
+    Update {
+        record: Box<Expr>,
+        fields: Vec<(String, Expr)>,
+    },
+
+    /// `match shape { Circle(r) => r * r, Rect(w, h) => w * h }`
+    Match {
+        scrutinee: Box<Expr>,
+        arms: Vec<MatchArm>,
+    },
 
     Toss {
         effect: String,
         arg: Option<Box<Expr>>,
     },
+    /// `catch effect expr`: `Ok` of the value of `expr`, or `Err` of the
+    /// payload of a `toss effect` while evaluating it.
+    Catch {
+        effect: String,
+        expr: Box<Expr>,
+    },
+    /// `retoss effect expr`: the value in an `Ok` or `Some`, tossing
+    /// `effect` with the payload of an `Err`, or none for a `None`.
+    Retoss {
+        effect: String,
+        expr: Box<Expr>,
+    },
+}
+
+/// A parameter of a `fn`: `x`, or `x: Int` with an annotation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Param {
+    pub name: String,
+    pub ty: Option<TypeExpr>,
+    pub span: Span,
+}
+
+/// `pattern => body` in a `match`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MatchArm {
+    pub pattern: Pattern,
+    pub body: Expr,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pattern {
+    pub kind: PatternKind,
+    pub span: Span,
+}
+
+impl Pattern {
+    pub fn new(kind: PatternKind, span: Span) -> Self {
+        Self { kind, span }
+    }
+
+    /// The names the pattern binds, left to right.
+    pub fn bindings(&self) -> Vec<(&str, Span)> {
+        match &self.kind {
+            PatternKind::Bind(name) => vec![(name, self.span)],
+            PatternKind::Variant(_, args) | PatternKind::Tuple(args) => {
+                args.iter().flat_map(Pattern::bindings).collect()
+            }
+            PatternKind::Wildcard | PatternKind::Int(_) | PatternKind::String(_) => Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PatternKind {
+    /// `_`
+    Wildcard,
+    /// `x`, matching anything and binding it.
+    Bind(String),
+    Int(i64),
+    String(String),
+    /// `Circle(r)` or `Red`. Constructor names start with a capital letter.
+    Variant(String, Vec<Pattern>),
+    /// `(x, _)`
+    Tuple(Vec<Pattern>),
 }
@@ -1,5 +1,22 @@
+use crate::codegen::{
+    debug::DebugInfo,
+    error::CodegenError,
+    list,
+    llvm::Libc,
+    record,
+    runtime::Runtime,
+    sum, tuple,
+    types::{STRING_DATA, is_tuple, list_element, record_fields, string_type, sum_name},
+    unwind::{Catch, Cleanup},
+};
+use crate::typechecker::checker::{Facts, TypeEnv};
 use inkwell::{
-    AddressSpace, builder::Builder, context::Context, module::Module, values::FunctionValue,
+    builder::Builder,
+    context::Context,
+    module::{Linkage, Module},
+    targets::TargetMachine,
+    types::BasicTypeEnum,
+    values::{BasicMetadataValueEnum, BasicValueEnum, FunctionValue, IntValue, PointerValue},
 };
 
 pub struct Codegen<'ctx> {
@@ -7,14 +24,51 @@ pub struct Codegen<'ctx> {
     pub module: Module<'ctx>,
     pub builder: Builder<'ctx>,
     pub current_fn: FunctionValue<'ctx>,
-    pub printf: FunctionValue<'ctx>,
+    pub libc: Libc<'ctx>,
+    pub runtime: Runtime<'ctx>,
+    pub debug: Option<DebugInfo<'ctx>>,
+    pub target_machine: Option<TargetMachine>,
+    /// The types of the statements compiled so far, and the sum types
+    /// and constructors they declare.
+    pub types: TypeEnv,
+    /// What checking the statement being compiled found.
+    pub facts: Facts,
+    /// The references held by the expressions being compiled, released
+    /// when a `toss` jumps out of them.
+    pub cleanups: Vec<Cleanup<'ctx>>,
+    /// The `catch`es being compiled, innermost last.
+    pub catches: Vec<Catch<'ctx>>,
+    /// The first part of the program found that cannot be compiled.
+    pub error: Option<CodegenError>,
 }
 
 impl<'ctx> Codegen<'ctx> {
     pub fn new(context: &'ctx Context, name: &str) -> Self {
+        Self::with_target(context, name, None)
+    }
+
+    /// Creates a module whose triple and data layout match `target_machine`.
+    pub fn with_target(
+        context: &'ctx Context,
+        name: &str,
+        target_machine: Option<TargetMachine>,
+    ) -> Self {
         let module = context.create_module(name);
         let builder = context.create_builder();
 
+        let size_type = match &target_machine {
+            Some(machine) => {
+                module.set_triple(&machine.get_triple());
+                let data = machine.get_target_data();
+                module.set_data_layout(&data.get_data_layout());
+                context.ptr_sized_int_type(&data, None)
+            }
+            None => context.i64_type(),
+        };
+
+        let libc = Libc::declare(context, &module, size_type);
+        let runtime = Runtime::emit(context, &module, &libc, size_type);
+
         let i32_type = context.i32_type();
         let fn_type = i32_type.fn_type(&[], false);
         let function = module.add_function("main", fn_type, None);
@@ -22,18 +76,207 @@ impl<'ctx> Codegen<'ctx> {
         let entry = context.append_basic_block(function, "entry");
         builder.position_at_end(entry);
 
-        let i8ptr = context.ptr_type(AddressSpace::default());
-
-        let printf_type = context.i32_type().fn_type(&[i8ptr.into()], true);
-
-        let printf = module.add_function("printf", printf_type, None);
-
         Self {
             context,
             module,
             builder,
             current_fn: function,
-            printf,
+            libc,
+            runtime,
+            debug: None,
+            target_machine,
+            types: TypeEnv::new(),
+            facts: Facts::default(),
+            cleanups: Vec::new(),
+            catches: Vec::new(),
+            error: None,
         }
     }
+
+    /// Starts emitting DWARF debug info for `src`, read from `filename`.
+    pub fn enable_debug_info(&mut self, filename: &str, src: &str) {
+        self.debug = Some(DebugInfo::new(
+            self.context,
+            &self.module,
+            self.current_fn,
+            filename,
+            src,
+        ));
+    }
+
+    /// A stack slot for a value of type `ty`. Slots live in the entry block,
+    /// so code that runs in a loop does not grow the stack.
+    pub fn alloca(&self, ty: BasicTypeEnum<'ctx>, name: &str) -> PointerValue<'ctx> {
+        let block = self.builder.get_insert_block().expect("no insert block");
+        let entry = self
+            .current_fn
+            .get_first_basic_block()
+            .expect("function has an entry block");
+        match entry.get_first_instruction() {
+            Some(first) => self.builder.position_before(&first),
+            None => self.builder.position_at_end(entry),
+        }
+        let ptr = self.builder.build_alloca(ty, name).expect("alloca failed");
+        self.builder.position_at_end(block);
+        ptr
+    }
+
+    /// Stops the program with `message` unless `ok` holds.
+    pub fn check(&self, ok: IntValue<'ctx>, message: &str) {
+        let fail = self
+            .context
+            .append_basic_block(self.current_fn, "check_failed");
+        let pass = self
+            .context
+            .append_basic_block(self.current_fn, "check_passed");
+        self.builder
+            .build_conditional_branch(ok, pass, fail)
+            .expect("branch failed");
+
+        self.builder.position_at_end(fail);
+        let message = self
+            .builder
+            .build_global_string_ptr(message, "check_message")
+            .expect("failed to create panic message")
+            .as_pointer_value();
+        self.builder
+            .build_call(self.runtime.panic, &[message.into()], "")
+            .expect("panic call failed");
+        self.builder
+            .build_unreachable()
+            .expect("unreachable failed");
+
+        self.builder.position_at_end(pass);
+    }
+
+    /// Whether the code being emitted can never run: after a `toss` that
+    /// jumps to its `catch`, or a `retoss` of a value that is never `Ok`.
+    pub fn is_dead(&self) -> bool {
+        let block = self.builder.get_insert_block().expect("no insert block");
+        block.get_first_use().is_none() && Some(block) != self.current_fn.get_first_basic_block()
+    }
+
+    /// The value of Unit-typed expressions, an empty struct.
+    pub fn unit_value(&self) -> BasicValueEnum<'ctx> {
+        self.context.const_struct(&[], false).into()
+    }
+
+    /// A string literal: a constant header with a static refcount.
+    pub fn string_literal(&self, value: &str) -> PointerValue<'ctx> {
+        let i64_type = self.context.i64_type();
+        let header = self.context.const_struct(
+            &[
+                i64_type.const_all_ones().into(),
+                i64_type.const_int(value.len() as u64, false).into(),
+                self.context.const_string(value.as_bytes(), true).into(),
+            ],
+            false,
+        );
+
+        let global = self.module.add_global(header.get_type(), None, "str");
+        global.set_linkage(Linkage::Private);
+        global.set_constant(true);
+        global.set_unnamed_addr(true);
+        global.set_initializer(&header);
+        global.as_pointer_value()
+    }
+
+    /// Takes another reference to `value` if it is a string, a list or a
+    /// sum type value, or to the fields of a record or tuple.
+    ///
+    /// Strings are the only pointer-valued Sauce type, and lists and
+    /// variants share their fields; everything else is copied by value.
+    pub fn retain(&self, value: BasicValueEnum<'ctx>) {
+        match value {
+            BasicValueEnum::PointerValue(s) => {
+                self.builder
+                    .build_call(self.runtime.str_retain, &[s.into()], "")
+                    .expect("retain call failed");
+            }
+            BasicValueEnum::StructValue(l) if list_element(l.get_type()).is_some() => {
+                list::retain(self, l);
+            }
+            BasicValueEnum::StructValue(r) if record_fields(r.get_type()).is_some() => {
+                record::retain(self, r);
+            }
+            BasicValueEnum::StructValue(v) if sum_name(v.get_type()).is_some() => {
+                sum::retain(self, v);
+            }
+            BasicValueEnum::StructValue(t) if is_tuple(t.get_type()) => {
+                tuple::retain(self, t);
+            }
+            _ => {}
+        }
+    }
+
+    /// Drops a reference to `value` if it is a string, a list or a sum
+    /// type value, or to the fields of a record or tuple.
+    pub fn release(&self, value: BasicValueEnum<'ctx>) {
+        match value {
+            BasicValueEnum::PointerValue(s) => {
+                self.builder
+                    .build_call(self.runtime.str_release, &[s.into()], "")
+                    .expect("release call failed");
+            }
+            BasicValueEnum::StructValue(l) if list_element(l.get_type()).is_some() => {
+                list::release(self, l);
+            }
+            BasicValueEnum::StructValue(r) if record_fields(r.get_type()).is_some() => {
+                record::release(self, r);
+            }
+            BasicValueEnum::StructValue(v) if sum_name(v.get_type()).is_some() => {
+                sum::release(self, v);
+            }
+            BasicValueEnum::StructValue(t) if is_tuple(t.get_type()) => {
+                tuple::release(self, t);
+            }
+            _ => {}
+        }
+    }
+
+    /// Prints `value` as it appears inside a list, record, tuple or variant,
+    /// without a newline: strings are quoted.
+    pub fn print_nested(&self, value: BasicValueEnum<'ctx>) {
+        match value {
+            BasicValueEnum::IntValue(_) => self.printf("%lld", &[value]),
+            BasicValueEnum::PointerValue(s) => {
+                let data = self
+                    .builder
+                    .build_struct_gep(string_type(self.context), s, STRING_DATA, "data")
+                    .expect("string data gep failed");
+                self.printf("\"%s\"", &[data.into()]);
+            }
+            BasicValueEnum::StructValue(l) if list_element(l.get_type()).is_some() => {
+                list::print(self, l);
+            }
+            BasicValueEnum::StructValue(r) if record_fields(r.get_type()).is_some() => {
+                record::print(self, r);
+            }
+            BasicValueEnum::StructValue(v) if sum_name(v.get_type()).is_some() => {
+                sum::print(self, v);
+            }
+            BasicValueEnum::StructValue(t) if is_tuple(t.get_type()) => {
+                tuple::print(self, t);
+            }
+            _ => self.printf("()", &[]),
+        }
+    }
+
+    /// Calls `printf` with a constant `format`.
+    pub fn printf(&self, format: &str, args: &[BasicValueEnum<'ctx>]) {
+        let mut call_args: Vec<BasicMetadataValueEnum> = vec![self.global_string(format).into()];
+        call_args.extend(args.iter().map(|&arg| BasicMetadataValueEnum::from(arg)));
+        self.builder
+            .build_call(self.libc.printf, &call_args, "printf_call")
+            .expect("printf call failed");
+    }
+
+    /// A pointer to a constant NUL-terminated copy of `value`.
+    pub fn global_string(&self, value: &str) -> BasicValueEnum<'ctx> {
+        self.builder
+            .build_global_string_ptr(value, "fmt")
+            .expect("failed to create string")
+            .as_pointer_value()
+            .into()
+    }
 }
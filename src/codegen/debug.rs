@@ -0,0 +1,245 @@
+use crate::util::{line_index::LineIndex, span::Span};
+use inkwell::{
+    AddressSpace,
+    basic_block::BasicBlock,
+    builder::Builder,
+    context::Context,
+    debug_info::{
+        AsDIScope, DICompileUnit, DIFile, DIFlags, DIFlagsConstants, DISubprogram, DIType,
+        DWARFEmissionKind, DWARFSourceLanguage, DebugInfoBuilder,
+    },
+    module::{FlagBehavior, Module},
+    targets::TargetData,
+    values::{BasicValueEnum, FunctionValue, PointerValue},
+};
+use std::path::Path;
+
+const DW_ATE_SIGNED: u32 = 0x05;
+const DW_ATE_SIGNED_CHAR: u32 = 0x06;
+
+/// DWARF debug info for a compiled Sauce module, emitted with `-g`.
+pub struct DebugInfo<'ctx> {
+    pub builder: DebugInfoBuilder<'ctx>,
+    pub compile_unit: DICompileUnit<'ctx>,
+    pub file: DIFile<'ctx>,
+    pub scope: DISubprogram<'ctx>,
+    int_type: DIType<'ctx>,
+    string_type: DIType<'ctx>,
+    lines: LineIndex,
+    /// Length of the file's source. Spans past it come from other modules.
+    len: usize,
+}
+
+impl<'ctx> DebugInfo<'ctx> {
+    pub fn new(
+        context: &'ctx Context,
+        module: &Module<'ctx>,
+        main: FunctionValue<'ctx>,
+        filename: &str,
+        src: &str,
+    ) -> Self {
+        let path = Path::new(filename);
+        let name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_else(|| filename.to_string());
+        let directory = match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir.to_string_lossy().into_owned(),
+            _ => ".".to_string(),
+        };
+
+        module.add_basic_value_flag(
+            "Debug Info Version",
+            FlagBehavior::Warning,
+            context.i32_type().const_int(3, false),
+        );
+        module.add_basic_value_flag(
+            "Dwarf Version",
+            FlagBehavior::Warning,
+            context.i32_type().const_int(4, false),
+        );
+
+        let (builder, compile_unit) = module.create_debug_info_builder(
+            true,
+            DWARFSourceLanguage::C,
+            &name,
+            &directory,
+            concat!("sauce ", env!("CARGO_PKG_VERSION")),
+            false,
+            "",
+            0,
+            "",
+            DWARFEmissionKind::Full,
+            0,
+            false,
+            false,
+            "",
+            "",
+        );
+        let file = compile_unit.get_file();
+
+        // Pointers are as wide as the module's data layout says, which is
+        // the target's when there is one.
+        let layout = module.get_data_layout();
+        let data = TargetData::create(layout.as_str().to_str().expect("an ASCII data layout"));
+        let pointer_bits = u64::from(data.get_pointer_byte_size(None)) * 8;
+        let pointer_align = data.get_abi_alignment(&context.ptr_type(AddressSpace::default())) * 8;
+
+        let i32_type = builder
+            .create_basic_type("int", 32, DW_ATE_SIGNED, DIFlags::PUBLIC)
+            .expect("debug type `int` failed")
+            .as_type();
+        let int_type = builder
+            .create_basic_type("Int", 64, DW_ATE_SIGNED, DIFlags::PUBLIC)
+            .expect("debug type `Int` failed")
+            .as_type();
+        let char_type = builder
+            .create_basic_type("char", 8, DW_ATE_SIGNED_CHAR, DIFlags::PUBLIC)
+            .expect("debug type `char` failed")
+            .as_type();
+        let header_fields = [("refcount", 0), ("len", 64)].map(|(name, offset)| {
+            builder
+                .create_member_type(
+                    file.as_debug_info_scope(),
+                    name,
+                    file,
+                    0,
+                    64,
+                    64,
+                    offset,
+                    DIFlags::ZERO,
+                    int_type,
+                )
+                .as_type()
+        });
+        let data_type = builder.create_array_type(char_type, 0, 8, &[]).as_type();
+        let data_field = builder
+            .create_member_type(
+                file.as_debug_info_scope(),
+                "data",
+                file,
+                0,
+                0,
+                8,
+                128,
+                DIFlags::ZERO,
+                data_type,
+            )
+            .as_type();
+        let header_type = builder
+            .create_struct_type(
+                file.as_debug_info_scope(),
+                "StringHeader",
+                file,
+                0,
+                128,
+                64,
+                DIFlags::ZERO,
+                None,
+                &[header_fields[0], header_fields[1], data_field],
+                0,
+                None,
+                "",
+            )
+            .as_type();
+        let string_type = builder
+            .create_pointer_type(
+                "String",
+                header_type,
+                pointer_bits,
+                pointer_align,
+                AddressSpace::default(),
+            )
+            .as_type();
+
+        let main_type = builder.create_subroutine_type(file, Some(i32_type), &[], DIFlags::PUBLIC);
+        let scope = builder.create_function(
+            file.as_debug_info_scope(),
+            "main",
+            None,
+            file,
+            1,
+            main_type,
+            false,
+            true,
+            1,
+            DIFlags::PUBLIC,
+            false,
+        );
+        main.set_subprogram(scope);
+
+        Self {
+            builder,
+            compile_unit,
+            file,
+            scope,
+            int_type,
+            string_type,
+            lines: LineIndex::new(src),
+            len: src.len(),
+        }
+    }
+
+    /// Line 0 tells debuggers there is no source line, which is true for
+    /// code from other modules: only the entry file is described.
+    fn line_col(&self, span: Span) -> (u32, u32) {
+        if span.start > self.len {
+            return (0, 0);
+        }
+        self.lines.line_col(span.start)
+    }
+
+    /// Attaches the location of `span` to every instruction built from here on.
+    pub fn set_location(&self, context: &'ctx Context, builder: &Builder<'ctx>, span: Span) {
+        let (line, col) = self.line_col(span);
+        let loc = self.builder.create_debug_location(
+            context,
+            line,
+            col,
+            self.scope.as_debug_info_scope(),
+            None,
+        );
+        builder.set_current_debug_location(loc);
+    }
+
+    /// Describes the stack slot of a `grab` binding as a `DILocalVariable`.
+    pub fn declare_local(
+        &self,
+        context: &'ctx Context,
+        name: &str,
+        ptr: PointerValue<'ctx>,
+        value: BasicValueEnum<'ctx>,
+        span: Span,
+        block: BasicBlock<'ctx>,
+    ) {
+        let ty = match value {
+            BasicValueEnum::IntValue(_) => self.int_type,
+            BasicValueEnum::PointerValue(_) => self.string_type,
+            _ => return,
+        };
+        let (line, col) = self.line_col(span);
+        let var = self.builder.create_auto_variable(
+            self.scope.as_debug_info_scope(),
+            name,
+            self.file,
+            line,
+            ty,
+            true,
+            DIFlags::ZERO,
+            0,
+        );
+        let loc = self.builder.create_debug_location(
+            context,
+            line,
+            col,
+            self.scope.as_debug_info_scope(),
+            None,
+        );
+        self.builder
+            .insert_declare_at_end(ptr, Some(var), None, loc, block);
+    }
+
+    pub fn finalize(&self) {
+        self.builder.finalize();
+    }
+}
@@ -0,0 +1,46 @@
+use crate::typechecker::TypeError;
+use crate::util::span::Span;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum CodegenError {
+    #[error(
+        "unsupported target `{0}` (expected x86_64-unknown-linux-gnu, aarch64-unknown-linux-gnu or wasm32-unknown-unknown)"
+    )]
+    UnsupportedTarget(String),
+
+    #[error("failed to initialize target `{0}`: {1}")]
+    TargetInit(String, String),
+
+    #[error("failed to write build output: {0}")]
+    Emit(String),
+
+    /// A function used other than by calling it, binding it with `grab`
+    /// or passing it to a function.
+    #[error("cannot compile a function used as a value")]
+    FunctionValue(Span),
+
+    /// A program that does not typecheck.
+    #[error(transparent)]
+    Type(#[from] TypeError),
+}
+
+impl CodegenError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            CodegenError::UnsupportedTarget(_) => "E0901",
+            CodegenError::TargetInit(..) => "E0902",
+            CodegenError::Emit(_) => "E0903",
+            CodegenError::FunctionValue(_) => "E0904",
+            CodegenError::Type(e) => e.code(),
+        }
+    }
+
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            CodegenError::FunctionValue(span) => Some(*span),
+            CodegenError::Type(e) => Some(e.span()),
+            _ => None,
+        }
+    }
+}
@@ -1,36 +1,89 @@
-use crate::ast::ast::Expr;
-use crate::codegen::{context::Codegen, stmt::LocalEnv};
-use inkwell::values::BasicValueEnum;
+use crate::ast::ast::{BinOp, Expr, ExprKind, stage_chain};
+use crate::codegen::{
+    context::Codegen,
+    function, list,
+    matching::build_match,
+    record,
+    runtime::{TAG_INT, TAG_STRING, TAG_UNIT},
+    stages::build_stages,
+    stmt::{Local, LocalEnv},
+    sum, tuple,
+    types::{STRING_DATA, string_type},
+    unwind::{self, Cleanup},
+};
+use inkwell::IntPredicate;
+use inkwell::values::{BasicValueEnum, IntValue};
 
 pub fn codegen_expr<'ctx>(
     cg: &mut Codegen<'ctx>,
     env: &mut LocalEnv<'ctx>,
     expr: &Expr,
 ) -> BasicValueEnum<'ctx> {
-    match expr {
-        Expr::Int(n) => cg.context.i64_type().const_int(*n as u64, true).into(),
+    match &expr.kind {
+        ExprKind::Int(n) => cg.context.i64_type().const_int(*n as u64, true).into(),
 
-        Expr::Ident(name) => {
-            let ptr = env.vars.get(name).expect("unknown variable");
-            cg.builder
-                .build_load(cg.context.i64_type(), *ptr, "loadtmp")
-                .expect("load failed")
+        ExprKind::Ident(name)
+            if env.functions.contains_key(name) && !env.vars.contains_key(name) =>
+        {
+            function::unsupported(cg, expr.span)
         }
 
-        Expr::Pipeline(lhs, rhs) => {
-            let lhs_val = codegen_expr(cg, env, lhs);
+        ExprKind::Ident(name) if !env.vars.contains_key(name) => {
+            build_constructor(cg, name, &[]).expect("unknown variable")
+        }
 
-            let tmp = cg
+        ExprKind::Ident(name) => {
+            let local = env.vars[name];
+            let value = cg
                 .builder
-                .build_alloca(cg.context.i64_type(), "_")
-                .expect("alloca _ failed");
+                .build_load(local.ty, local.ptr, "loadtmp")
+                .expect("load failed");
+            cg.retain(value);
+            value
+        }
+
+        ExprKind::List(items) => {
+            let items = codegen_all(cg, env, items.iter());
+            list::build_list(cg, &items).into()
+        }
+
+        ExprKind::Tuple(items) => {
+            let items = codegen_all(cg, env, items.iter());
+            tuple::build_tuple(cg, &items).into()
+        }
+
+        ExprKind::Index(target, index) => {
+            let depth = cg.cleanups.len();
+            let list = codegen_expr(cg, env, target);
+            unwind::push(cg, Cleanup::Value(list));
+            let index = codegen_expr(cg, env, index).into_int_value();
+            unwind::pop_to(cg, depth);
+            list::build_index(cg, list.into_struct_value(), index)
+        }
+
+        ExprKind::Pipeline(lhs, rhs) => {
+            if let Some((source, stages)) = stage_chain(expr, |name| env.vars.contains_key(name)) {
+                let piped = codegen_expr(cg, env, source);
+                return build_stages(cg, env, piped, &stages);
+            }
+
+            let lhs_val = codegen_expr(cg, env, lhs);
+            let tmp = cg.alloca(lhs_val.get_type(), "_");
 
             cg.builder
                 .build_store(tmp, lhs_val)
                 .expect("store _ failed");
 
-            let old = env.vars.insert("_".to_string(), tmp);
+            let local = Local {
+                ptr: tmp,
+                ty: lhs_val.get_type(),
+            };
+            let old = env.vars.insert("_".to_string(), local);
+            let depth = cg.cleanups.len();
+            unwind::push(cg, Cleanup::Slot(local));
             let result = codegen_expr(cg, env, rhs);
+            unwind::pop_to(cg, depth);
+            cg.release(lhs_val);
 
             if let Some(prev) = old {
                 env.vars.insert("_".to_string(), prev);
@@ -40,21 +93,242 @@ pub fn codegen_expr<'ctx>(
 
             result
         }
-        Expr::Toss { .. } => {
-            eprintln!(
-                "codegen error: `toss` is not supported in the LLVM backend yet.\n\
-         Hint: use the interpreter backend to run programs with effects."
-            );
-            std::process::exit(1);
+        ExprKind::Binary(op, left, right) => {
+            let [left, right] = codegen_all(cg, env, [&**left, &**right])
+                .try_into()
+                .expect("two operands");
+            match (left, right) {
+                (BasicValueEnum::PointerValue(a), BasicValueEnum::PointerValue(b)) => {
+                    let joined = cg
+                        .builder
+                        .build_call(cg.runtime.str_concat, &[a.into(), b.into()], "joined")
+                        .expect("str_concat call failed")
+                        .try_as_basic_value()
+                        .unwrap_basic();
+                    cg.release(left);
+                    cg.release(right);
+                    joined
+                }
+                (BasicValueEnum::IntValue(a), BasicValueEnum::IntValue(b)) => {
+                    build_arithmetic(cg, *op, a, b).into()
+                }
+                // An operand of type `Never` leaves the code dead, so any
+                // value will do.
+                (value @ (BasicValueEnum::IntValue(_) | BasicValueEnum::PointerValue(_)), _)
+                | (_, value @ (BasicValueEnum::IntValue(_) | BasicValueEnum::PointerValue(_))) => {
+                    value
+                }
+                _ => unreachable!("operands are typechecked"),
+            }
+        }
+
+        ExprKind::Record(fields) => {
+            let values = codegen_all(cg, env, fields.iter().map(|(_, value)| value));
+            let fields: Vec<_> = fields
+                .iter()
+                .zip(values)
+                .map(|((name, _), value)| (name.as_str(), value))
+                .collect();
+            record::build_record(cg, &fields).into()
+        }
+
+        ExprKind::Field(target, name) => {
+            let target = codegen_expr(cg, env, target).into_struct_value();
+            record::build_field(cg, target, name)
+        }
+
+        ExprKind::Update {
+            record: target,
+            fields,
+        } => {
+            let depth = cg.cleanups.len();
+            let target = codegen_expr(cg, env, target);
+            unwind::push(cg, Cleanup::Value(target));
+            let values = codegen_all(cg, env, fields.iter().map(|(_, value)| value));
+            unwind::pop_to(cg, depth);
+            let fields: Vec<_> = fields
+                .iter()
+                .zip(values)
+                .map(|((name, _), value)| (name.as_str(), value))
+                .collect();
+            record::build_update(cg, target.into_struct_value(), &fields).into()
+        }
+
+        ExprKind::Match { scrutinee, arms } => build_match(cg, env, scrutinee, arms),
+
+        ExprKind::Call(callee, args) => {
+            if let ExprKind::Ident(name) = &callee.kind
+                && !env.vars.contains_key(name)
+                && !env.functions.contains_key(name)
+            {
+                let args = codegen_all(cg, env, args.iter());
+                return build_constructor(cg, name, &args).expect("unknown constructor");
+            }
+            function::build_call(cg, env, callee, args)
+        }
+        // Functions are only inlined where they are called.
+        ExprKind::Lambda { .. } => function::unsupported(cg, expr.span),
+
+        ExprKind::Toss { effect, arg } => {
+            let payload = arg.as_ref().map(|arg| codegen_expr(cg, env, arg));
+            let caught = payload.unwrap_or_else(|| cg.unit_value());
+            if !unwind::toss_to_catch(cg, effect, caught) {
+                build_toss(cg, effect, payload);
+            }
+            cg.unit_value()
         }
 
-        Expr::String(s) => {
-            let global = cg.builder.build_global_string_ptr(s, "str");
+        ExprKind::Catch { effect, expr } => unwind::build_catch(cg, env, effect, expr),
+
+        ExprKind::Retoss { effect, expr } => unwind::build_retoss(cg, env, effect, expr),
+
+        ExprKind::String(s) => cg.string_literal(s).into(),
+    }
+}
+
+/// The values of `exprs`, in order. Each is held on to while the ones
+/// after it are computed.
+pub fn codegen_all<'a, 'ctx>(
+    cg: &mut Codegen<'ctx>,
+    env: &mut LocalEnv<'ctx>,
+    exprs: impl IntoIterator<Item = &'a Expr>,
+) -> Vec<BasicValueEnum<'ctx>> {
+    let depth = cg.cleanups.len();
+    let mut values = Vec::new();
+    for expr in exprs {
+        let value = codegen_expr(cg, env, expr);
+        unwind::push(cg, Cleanup::Value(value));
+        values.push(value);
+    }
+    unwind::pop_to(cg, depth);
+    values
+}
+
+/// Hands a `toss` of `effect` with `payload` to the runtime, which calls
+/// its handler or stops the program. Consumes `payload`.
+pub fn build_toss<'ctx>(cg: &Codegen<'ctx>, effect: &str, payload: Option<BasicValueEnum<'ctx>>) {
+    let i64_type = cg.context.i64_type();
+    let (tag, bits) = match payload {
+        Some(BasicValueEnum::IntValue(n)) => (TAG_INT, n),
+        Some(BasicValueEnum::PointerValue(s)) => {
+            let data = cg
+                .builder
+                .build_struct_gep(string_type(cg.context), s, STRING_DATA, "data")
+                .expect("string data gep failed");
+            (
+                TAG_STRING,
+                cg.builder
+                    .build_ptr_to_int(data, i64_type, "payload")
+                    .expect("ptrtoint failed"),
+            )
+        }
+        _ => (TAG_UNIT, i64_type.const_zero()),
+    };
+
+    let name = cg
+        .builder
+        .build_global_string_ptr(effect, "effect")
+        .expect("failed to create effect name")
+        .as_pointer_value();
+
+    cg.builder
+        .build_call(
+            cg.runtime.toss,
+            &[
+                name.into(),
+                cg.context.i32_type().const_int(tag, false).into(),
+                bits.into(),
+            ],
+            "",
+        )
+        .expect("toss call failed");
+    if let Some(payload) = payload {
+        cg.release(payload);
+    }
+}
+
+/// The variant constructor `name` builds from `fields`, if `name` is a
+/// constructor in scope.
+fn build_constructor<'ctx>(
+    cg: &Codegen<'ctx>,
+    name: &str,
+    fields: &[BasicValueEnum<'ctx>],
+) -> Option<BasicValueEnum<'ctx>> {
+    let (sum, tag) = cg.types.constructor(name)?;
+    Some(sum::build_variant(cg, sum, tag, fields).into())
+}
+
+/// `a op b` on integers. Arithmetic wraps around on overflow, and
+/// comparisons give 1 or 0.
+fn build_arithmetic<'ctx>(
+    cg: &Codegen<'ctx>,
+    op: BinOp,
+    a: IntValue<'ctx>,
+    b: IntValue<'ctx>,
+) -> IntValue<'ctx> {
+    let builder = &cg.builder;
+    let predicate = match op {
+        BinOp::Add => return builder.build_int_add(a, b, "add").expect("add failed"),
+        BinOp::Sub => return builder.build_int_sub(a, b, "sub").expect("sub failed"),
+        BinOp::Mul => return builder.build_int_mul(a, b, "mul").expect("mul failed"),
+        BinOp::Div | BinOp::Rem => return build_division(cg, op, a, b),
+        BinOp::Eq => IntPredicate::EQ,
+        BinOp::Ne => IntPredicate::NE,
+        BinOp::Lt => IntPredicate::SLT,
+        BinOp::Le => IntPredicate::SLE,
+        BinOp::Gt => IntPredicate::SGT,
+        BinOp::Ge => IntPredicate::SGE,
+    };
+    let holds = builder
+        .build_int_compare(predicate, a, b, "holds")
+        .expect("compare failed");
+    builder
+        .build_int_z_extend(holds, cg.context.i64_type(), "cmp")
+        .expect("zext failed")
+}
+
+/// `a / b` or `a % b`, stopping the program when `b` is zero.
+///
+/// LLVM leaves `i64::MIN / -1` undefined, so division by -1 is done by
+/// negating `a`, which wraps like the interpreter does.
+fn build_division<'ctx>(
+    cg: &Codegen<'ctx>,
+    op: BinOp,
+    a: IntValue<'ctx>,
+    b: IntValue<'ctx>,
+) -> IntValue<'ctx> {
+    let builder = &cg.builder;
+    let i64_type = cg.context.i64_type();
+    let zero = i64_type.const_zero();
+    let nonzero = builder
+        .build_int_compare(IntPredicate::NE, b, zero, "nonzero")
+        .expect("compare failed");
+    cg.check(nonzero, "division by zero");
+
+    let minus_one = builder
+        .build_int_compare(IntPredicate::EQ, b, i64_type.const_all_ones(), "minus_one")
+        .expect("compare failed");
+    let divisor = builder
+        .build_select(minus_one, i64_type.const_int(1, false), b, "divisor")
+        .expect("select failed")
+        .into_int_value();
 
-            global
-                .expect("failed to create string literal")
-                .as_pointer_value()
-                .into()
+    match op {
+        BinOp::Div => {
+            let quotient = builder
+                .build_int_signed_div(a, divisor, "quotient")
+                .expect("div failed");
+            let negated = builder
+                .build_int_sub(zero, a, "negated")
+                .expect("sub failed");
+            builder
+                .build_select(minus_one, negated, quotient, "div")
+                .expect("select failed")
+                .into_int_value()
         }
+        // `a % 1` is 0, like `a % -1`.
+        _ => builder
+            .build_int_signed_rem(a, divisor, "rem")
+            .expect("rem failed"),
     }
 }
@@ -0,0 +1,195 @@
+//! Functions. A compiled program has no function values: a function bound
+//! with `grab` is a [`Closure`], inlined wherever it is called with the
+//! bindings it saw where it was written.
+
+use crate::ast::ast::{Expr, ExprKind};
+use crate::codegen::{
+    context::Codegen,
+    error::CodegenError,
+    expr::{codegen_all, codegen_expr},
+    stmt::{Local, LocalEnv},
+    types::llvm_type,
+    unwind::{self, Cleanup},
+};
+use crate::typechecker::types::Type;
+use crate::util::span::Span;
+use inkwell::values::BasicValueEnum;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+/// A function and what it needs to be inlined anywhere.
+pub struct Closure<'ctx> {
+    pub params: Vec<String>,
+    pub body: Expr,
+    /// The bindings where the function was written.
+    pub env: LocalEnv<'ctx>,
+    /// What checking found about its body, see
+    /// [`Facts::types`](crate::typechecker::checker::Facts::types).
+    pub types: HashMap<Span, Type>,
+    /// The types uses in its body are at, see
+    /// [`Facts::instances`](crate::typechecker::checker::Facts::instances).
+    pub instances: HashMap<Span, Vec<Type>>,
+}
+
+impl<'ctx> Closure<'ctx> {
+    /// This function with its type parameters replaced by `args`.
+    fn specialize(&self, args: &[Type]) -> Self {
+        Closure {
+            params: self.params.clone(),
+            body: self.body.clone(),
+            env: LocalEnv {
+                vars: self.env.vars.clone(),
+                functions: self.env.functions.clone(),
+                scope: Vec::new(),
+            },
+            types: self
+                .types
+                .iter()
+                .map(|(span, ty)| (*span, ty.substitute(args)))
+                .collect(),
+            instances: self
+                .instances
+                .iter()
+                .map(|(span, types)| (*span, types.iter().map(|ty| ty.substitute(args)).collect()))
+                .collect(),
+        }
+    }
+}
+
+/// An argument of an inlined call.
+pub enum Arg<'ctx> {
+    Value(BasicValueEnum<'ctx>),
+    Function(Rc<Closure<'ctx>>),
+}
+
+/// The function `expr` is, if it is a `fn` or names a bound function,
+/// at the types it is used at there.
+pub fn closure<'ctx>(
+    cg: &Codegen<'ctx>,
+    env: &LocalEnv<'ctx>,
+    expr: &Expr,
+) -> Option<Rc<Closure<'ctx>>> {
+    match &expr.kind {
+        ExprKind::Lambda { params, body, .. } => Some(Rc::new(Closure {
+            params: params.iter().map(|param| param.name.clone()).collect(),
+            body: (**body).clone(),
+            env: LocalEnv {
+                vars: env.vars.clone(),
+                functions: env.functions.clone(),
+                scope: Vec::new(),
+            },
+            types: cg.facts.types.clone(),
+            instances: cg.facts.instances.clone(),
+        })),
+        ExprKind::Ident(name) if !env.vars.contains_key(name) => {
+            let function = env.functions.get(name)?;
+            match cg.facts.instances.get(&expr.span) {
+                Some(args) => Some(Rc::new(function.specialize(args))),
+                None => Some(function.clone()),
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Inlines a call of `function`, or records that it cannot be compiled
+/// if it is not a function known here. Its parameters take over the
+/// references in `args`.
+pub fn inline<'ctx>(
+    cg: &mut Codegen<'ctx>,
+    env: &LocalEnv<'ctx>,
+    function: &Expr,
+    args: Vec<Arg<'ctx>>,
+) -> BasicValueEnum<'ctx> {
+    let Some(closure) = closure(cg, env, function) else {
+        for arg in args {
+            if let Arg::Value(value) = arg {
+                cg.release(value);
+            }
+        }
+        return unsupported(cg, function.span);
+    };
+
+    let mut inner = LocalEnv {
+        vars: closure.env.vars.clone(),
+        functions: closure.env.functions.clone(),
+        scope: Vec::new(),
+    };
+    let depth = cg.cleanups.len();
+    let mut bound = Vec::new();
+    for (param, arg) in closure.params.iter().zip(args) {
+        match arg {
+            Arg::Value(value) => {
+                let ptr = cg.alloca(value.get_type(), param);
+                cg.builder.build_store(ptr, value).expect("store failed");
+                let local = Local {
+                    ptr,
+                    ty: value.get_type(),
+                };
+                inner.functions.remove(param);
+                inner.vars.insert(param.clone(), local);
+                unwind::push(cg, Cleanup::Slot(local));
+                bound.push(local);
+            }
+            Arg::Function(function) => {
+                inner.vars.remove(param);
+                inner.functions.insert(param.clone(), function);
+            }
+        }
+    }
+
+    let types = std::mem::replace(&mut cg.facts.types, closure.types.clone());
+    let instances = std::mem::replace(&mut cg.facts.instances, closure.instances.clone());
+    let result = codegen_expr(cg, &mut inner, &closure.body);
+    cg.facts.types = types;
+    cg.facts.instances = instances;
+    unwind::pop_to(cg, depth);
+
+    for local in bound.into_iter().rev() {
+        let value = cg
+            .builder
+            .build_load(local.ty, local.ptr, "dead")
+            .expect("load failed");
+        cg.release(value);
+    }
+    result
+}
+
+/// `callee(args)`, where `callee` is not a constructor.
+pub fn build_call<'ctx>(
+    cg: &mut Codegen<'ctx>,
+    env: &mut LocalEnv<'ctx>,
+    callee: &Expr,
+    args: &[Expr],
+) -> BasicValueEnum<'ctx> {
+    let functions: Vec<_> = args.iter().map(|arg| closure(cg, env, arg)).collect();
+    let values = codegen_all(
+        cg,
+        env,
+        args.iter()
+            .zip(&functions)
+            .filter(|(_, function)| function.is_none())
+            .map(|(arg, _)| arg),
+    );
+    let mut values = values.into_iter();
+    let args = functions
+        .into_iter()
+        .map(|function| match function {
+            Some(function) => Arg::Function(function),
+            None => Arg::Value(values.next().expect("a value for each argument")),
+        })
+        .collect();
+    inline(cg, env, callee, args)
+}
+
+/// Records that the function at `span` cannot be compiled, giving a
+/// stand-in for the value it would give: what a call there returns, or
+/// `Unit` for a function used as a value.
+pub fn unsupported<'ctx>(cg: &mut Codegen<'ctx>, span: Span) -> BasicValueEnum<'ctx> {
+    cg.error.get_or_insert(CodegenError::FunctionValue(span));
+    cg.facts
+        .types
+        .get(&span)
+        .and_then(|ty| llvm_type(cg.context, ty))
+        .map_or_else(|| cg.unit_value(), |ty| ty.const_zero())
+}
@@ -0,0 +1,356 @@
+//! Lists, see [`list_type`]. Retaining, releasing and printing a list
+//! depend on its element type, so each list type gets its own private
+//! helper functions, emitted the first time they are needed.
+
+use crate::codegen::context::Codegen;
+use crate::codegen::types::{
+    LIST_CAP, LIST_DATA, LIST_HEADER_SIZE, LIST_LEN, holds_references, list_element, list_type,
+};
+use inkwell::{
+    IntPredicate,
+    module::Linkage,
+    types::{BasicType, BasicTypeEnum, StructType},
+    values::{BasicValueEnum, FunctionValue, IntValue, PointerValue, StructValue},
+};
+
+/// Builds a list owning `items`, which must all have the same type.
+pub fn build_list<'ctx>(cg: &Codegen<'ctx>, items: &[BasicValueEnum<'ctx>]) -> StructValue<'ctx> {
+    let elem = items
+        .first()
+        .expect("empty lists do not typecheck")
+        .get_type();
+    let len = cg.context.i64_type().const_int(items.len() as u64, false);
+
+    let data = alloc(cg, len, elem);
+    for (i, item) in items.iter().enumerate() {
+        let index = cg.context.i64_type().const_int(i as u64, false);
+        store(cg, data, elem, index, *item);
+    }
+    from_parts(cg, elem, data, len, len)
+}
+
+/// Allocates a block for `cap` elements of type `elem`, with a refcount
+/// of 1.
+pub fn alloc<'ctx>(
+    cg: &Codegen<'ctx>,
+    cap: IntValue<'ctx>,
+    elem: BasicTypeEnum<'ctx>,
+) -> PointerValue<'ctx> {
+    let elem_size = elem.size_of().expect("elements are sized");
+    cg.builder
+        .build_call(
+            cg.runtime.list_alloc,
+            &[cap.into(), elem_size.into()],
+            "data",
+        )
+        .expect("list_alloc call failed")
+        .try_as_basic_value()
+        .unwrap_basic()
+        .into_pointer_value()
+}
+
+/// A list of the first `len` elements of block `data`, taking over its
+/// reference.
+pub fn from_parts<'ctx>(
+    cg: &Codegen<'ctx>,
+    elem: BasicTypeEnum<'ctx>,
+    data: PointerValue<'ctx>,
+    len: IntValue<'ctx>,
+    cap: IntValue<'ctx>,
+) -> StructValue<'ctx> {
+    let mut list = list_type(cg.context, elem).get_undef();
+    for (field, value) in [
+        (LIST_DATA, BasicValueEnum::from(data)),
+        (LIST_LEN, len.into()),
+        (LIST_CAP, cap.into()),
+    ] {
+        list = cg
+            .builder
+            .build_insert_value(list, value, field, "list")
+            .expect("insert_value failed")
+            .into_struct_value();
+    }
+    list
+}
+
+/// Moves `value` into element `index` of block `data`.
+pub fn store<'ctx>(
+    cg: &Codegen<'ctx>,
+    data: PointerValue<'ctx>,
+    elem: BasicTypeEnum<'ctx>,
+    index: IntValue<'ctx>,
+    value: BasicValueEnum<'ctx>,
+) {
+    let ptr = element_ptr(cg, data, elem, index);
+    cg.builder.build_store(ptr, value).expect("store failed");
+}
+
+/// Element `index` of `list`, which must be in bounds, as a new reference.
+pub fn get<'ctx>(
+    cg: &Codegen<'ctx>,
+    list: StructValue<'ctx>,
+    index: IntValue<'ctx>,
+) -> BasicValueEnum<'ctx> {
+    let elem = list_element(list.get_type()).expect("not a list");
+    let data = field(cg, list, LIST_DATA).into_pointer_value();
+    let ptr = element_ptr(cg, data, elem, index);
+    let value = cg
+        .builder
+        .build_load(elem, ptr, "elem")
+        .expect("load failed");
+    cg.retain(value);
+    value
+}
+
+/// `list[index]`, panicking when `index` is out of bounds. Consumes `list`.
+pub fn build_index<'ctx>(
+    cg: &Codegen<'ctx>,
+    list: StructValue<'ctx>,
+    index: IntValue<'ctx>,
+) -> BasicValueEnum<'ctx> {
+    // Negative indices wrap around to huge unsigned ones.
+    let in_bounds = cg
+        .builder
+        .build_int_compare(IntPredicate::ULT, index, len(cg, list), "in_bounds")
+        .expect("compare failed");
+    cg.check(in_bounds, "index out of bounds");
+
+    let value = get(cg, list, index);
+    release(cg, list);
+    value
+}
+
+/// The number of elements in `list`.
+pub fn len<'ctx>(cg: &Codegen<'ctx>, list: StructValue<'ctx>) -> IntValue<'ctx> {
+    field(cg, list, LIST_LEN).into_int_value()
+}
+
+/// The number of elements in `list`. Consumes `list`.
+pub fn build_len<'ctx>(cg: &Codegen<'ctx>, list: StructValue<'ctx>) -> IntValue<'ctx> {
+    let len = len(cg, list);
+    release(cg, list);
+    len
+}
+
+pub fn retain<'ctx>(cg: &Codegen<'ctx>, list: StructValue<'ctx>) {
+    let data = field(cg, list, LIST_DATA);
+    cg.builder
+        .build_call(cg.runtime.list_retain, &[data.into()], "")
+        .expect("list_retain call failed");
+}
+
+pub fn release<'ctx>(cg: &Codegen<'ctx>, list: StructValue<'ctx>) {
+    let release = helper(cg, "sauce_list_release", list.get_type(), emit_release);
+    cg.builder
+        .build_call(release, &[list.into()], "")
+        .expect("list release call failed");
+}
+
+/// Prints `list` like `[1, 2, 3]`, without a newline.
+pub fn print<'ctx>(cg: &Codegen<'ctx>, list: StructValue<'ctx>) {
+    let print = helper(cg, "sauce_list_print", list.get_type(), emit_print);
+    cg.builder
+        .build_call(print, &[list.into()], "")
+        .expect("list print call failed");
+}
+
+fn field<'ctx>(cg: &Codegen<'ctx>, list: StructValue<'ctx>, index: u32) -> BasicValueEnum<'ctx> {
+    cg.builder
+        .build_extract_value(list, index, "field")
+        .expect("extract_value failed")
+}
+
+/// A pointer to element `index` of block `data`.
+pub fn element_ptr<'ctx>(
+    cg: &Codegen<'ctx>,
+    data: PointerValue<'ctx>,
+    elem: BasicTypeEnum<'ctx>,
+    index: IntValue<'ctx>,
+) -> PointerValue<'ctx> {
+    let i64_type = cg.context.i64_type();
+    unsafe {
+        let elems = cg
+            .builder
+            .build_in_bounds_gep(
+                cg.context.i8_type(),
+                data,
+                &[i64_type.const_int(LIST_HEADER_SIZE, false)],
+                "elems",
+            )
+            .expect("gep failed");
+        cg.builder
+            .build_in_bounds_gep(elem, elems, &[index], "elem_ptr")
+            .expect("gep failed")
+    }
+}
+
+/// The helper `prefix` for values of type `ty`, a `void (value)`
+/// function. Its body is emitted by `emit` on first use, without
+/// disturbing the code being generated around the call.
+pub fn helper<'ctx>(
+    cg: &Codegen<'ctx>,
+    prefix: &str,
+    ty: StructType<'ctx>,
+    emit: fn(&Codegen<'ctx>, FunctionValue<'ctx>),
+) -> FunctionValue<'ctx> {
+    keyed_helper(cg, prefix, ty, ty, emit)
+}
+
+/// Like [`helper`], for a function taking a value of type `param` whose
+/// body depends on type `key`.
+pub fn keyed_helper<'ctx>(
+    cg: &Codegen<'ctx>,
+    prefix: &str,
+    key: StructType<'ctx>,
+    param: StructType<'ctx>,
+    emit: impl FnOnce(&Codegen<'ctx>, FunctionValue<'ctx>),
+) -> FunctionValue<'ctx> {
+    let name = format!("{prefix}<{}>", key.print_to_string().to_string_lossy());
+    if let Some(function) = cg.module.get_function(&name) {
+        return function;
+    }
+
+    let fn_type = cg.context.void_type().fn_type(&[param.into()], false);
+    let function = cg
+        .module
+        .add_function(&name, fn_type, Some(Linkage::Private));
+    let entry = cg.context.append_basic_block(function, "entry");
+
+    // Helpers have no debug info of their own.
+    let block = cg.builder.get_insert_block();
+    let location = cg
+        .debug
+        .as_ref()
+        .and_then(|_| cg.builder.get_current_debug_location());
+    cg.builder.unset_current_debug_location();
+    cg.builder.position_at_end(entry);
+
+    emit(cg, function);
+
+    if let Some(block) = block {
+        cg.builder.position_at_end(block);
+    }
+    if let Some(location) = location {
+        cg.builder.set_current_debug_location(location);
+    }
+    function
+}
+
+/// Calls `body` with each element of `list`, inside `function`.
+fn for_each<'ctx>(
+    cg: &Codegen<'ctx>,
+    function: FunctionValue<'ctx>,
+    list: StructValue<'ctx>,
+    body: impl Fn(&Codegen<'ctx>, BasicValueEnum<'ctx>, IntValue<'ctx>),
+) {
+    let elem = list_element(list.get_type()).expect("not a list");
+    let i64_type = cg.context.i64_type();
+    let data = field(cg, list, LIST_DATA).into_pointer_value();
+    let len = field(cg, list, LIST_LEN).into_int_value();
+
+    let before = cg.builder.get_insert_block().expect("no insert block");
+    let head = cg.context.append_basic_block(function, "head");
+    let each = cg.context.append_basic_block(function, "each");
+    let done = cg.context.append_basic_block(function, "done");
+    cg.builder
+        .build_unconditional_branch(head)
+        .expect("branch failed");
+
+    cg.builder.position_at_end(head);
+    let i = cg.builder.build_phi(i64_type, "i").expect("phi failed");
+    let more = cg
+        .builder
+        .build_int_compare(
+            IntPredicate::ULT,
+            i.as_basic_value().into_int_value(),
+            len,
+            "more",
+        )
+        .expect("compare failed");
+    cg.builder
+        .build_conditional_branch(more, each, done)
+        .expect("branch failed");
+
+    cg.builder.position_at_end(each);
+    let index = i.as_basic_value().into_int_value();
+    let ptr = element_ptr(cg, data, elem, index);
+    let value = cg
+        .builder
+        .build_load(elem, ptr, "elem")
+        .expect("load failed");
+    body(cg, value, index);
+    let next = cg
+        .builder
+        .build_int_add(index, i64_type.const_int(1, false), "next")
+        .expect("add failed");
+    let latch = cg.builder.get_insert_block().expect("no insert block");
+    cg.builder
+        .build_unconditional_branch(head)
+        .expect("branch failed");
+
+    i.add_incoming(&[(&i64_type.const_zero(), before), (&next, latch)]);
+    cg.builder.position_at_end(done);
+}
+
+/// Drops a reference to the list's block. The last one releases the
+/// elements and frees it.
+fn emit_release<'ctx>(cg: &Codegen<'ctx>, function: FunctionValue<'ctx>) {
+    let list = function.get_nth_param(0).unwrap().into_struct_value();
+    let data = field(cg, list, LIST_DATA);
+    let dead = cg
+        .builder
+        .build_call(cg.runtime.list_release, &[data.into()], "dead")
+        .expect("list_release call failed")
+        .try_as_basic_value()
+        .unwrap_basic()
+        .into_int_value();
+
+    let free = cg.context.append_basic_block(function, "free");
+    let keep = cg.context.append_basic_block(function, "keep");
+    cg.builder
+        .build_conditional_branch(dead, free, keep)
+        .expect("branch failed");
+
+    cg.builder.position_at_end(free);
+    let elem = list_element(list.get_type()).expect("not a list");
+    if holds_references(elem) {
+        for_each(cg, function, list, |cg, value, _| cg.release(value));
+    }
+    cg.builder
+        .build_call(cg.runtime.list_free, &[data.into()], "")
+        .expect("list_free call failed");
+    cg.builder.build_return(None).expect("return failed");
+
+    cg.builder.position_at_end(keep);
+    cg.builder.build_return(None).expect("return failed");
+}
+
+fn emit_print<'ctx>(cg: &Codegen<'ctx>, function: FunctionValue<'ctx>) {
+    let list = function.get_nth_param(0).unwrap().into_struct_value();
+    cg.printf("[", &[]);
+    for_each(cg, function, list, |cg, value, index| {
+        // A separator before every element but the first.
+        let first = cg
+            .builder
+            .build_int_compare(
+                IntPredicate::EQ,
+                index,
+                cg.context.i64_type().const_zero(),
+                "first",
+            )
+            .expect("compare failed");
+        let separator = cg
+            .builder
+            .build_select(
+                first,
+                cg.global_string(""),
+                cg.global_string(", "),
+                "separator",
+            )
+            .expect("select failed");
+        cg.printf("%s", &[separator]);
+        cg.print_nested(value);
+    });
+    cg.printf("]", &[]);
+    cg.builder.build_return(None).expect("return failed");
+}
@@ -1 +1,48 @@
+use inkwell::{
+    AddressSpace,
+    context::Context,
+    module::{Linkage, Module},
+    types::IntType,
+    values::FunctionValue,
+};
 
+/// C library functions the runtime is built on.
+pub struct Libc<'ctx> {
+    pub malloc: FunctionValue<'ctx>,
+    pub free: FunctionValue<'ctx>,
+    pub strlen: FunctionValue<'ctx>,
+    pub strcmp: FunctionValue<'ctx>,
+    pub printf: FunctionValue<'ctx>,
+    pub fflush: FunctionValue<'ctx>,
+    pub write: FunctionValue<'ctx>,
+    pub exit: FunctionValue<'ctx>,
+}
+
+impl<'ctx> Libc<'ctx> {
+    /// Declares the functions in `module`. `size_type` is the target's `size_t`.
+    pub fn declare(
+        context: &'ctx Context,
+        module: &Module<'ctx>,
+        size_type: IntType<'ctx>,
+    ) -> Self {
+        let ptr = context.ptr_type(AddressSpace::default());
+        let i32_type = context.i32_type();
+        let void = context.void_type();
+
+        let declare = |name: &str, ty| module.add_function(name, ty, Some(Linkage::External));
+
+        Self {
+            malloc: declare("malloc", ptr.fn_type(&[size_type.into()], false)),
+            free: declare("free", void.fn_type(&[ptr.into()], false)),
+            strlen: declare("strlen", size_type.fn_type(&[ptr.into()], false)),
+            strcmp: declare("strcmp", i32_type.fn_type(&[ptr.into(), ptr.into()], false)),
+            printf: declare("printf", i32_type.fn_type(&[ptr.into()], true)),
+            fflush: declare("fflush", i32_type.fn_type(&[ptr.into()], false)),
+            write: declare(
+                "write",
+                size_type.fn_type(&[i32_type.into(), ptr.into(), size_type.into()], false),
+            ),
+            exit: declare("exit", void.fn_type(&[i32_type.into()], false)),
+        }
+    }
+}
@@ -0,0 +1,296 @@
+//! `match`, lowered from the decision tree of its arms: a `switch` on the
+//! tag of a sum type or on an `Int`, and a chain of `strcmp` calls for
+//! strings. Tuples need no test. An arm's body is emitted once for each
+//! leaf that reaches it.
+
+use crate::ast::ast::{Expr, MatchArm};
+use crate::codegen::{
+    context::Codegen,
+    expr::codegen_expr,
+    stmt::{Local, LocalEnv},
+    sum, tuple,
+    types::{STRING_DATA, string_type},
+    unwind::{self, Cleanup},
+};
+use crate::patterns::decision::{self, Decision, Path};
+use crate::patterns::{Ctor, Pat};
+use crate::typechecker::types::Type;
+use inkwell::{
+    IntPredicate,
+    basic_block::BasicBlock,
+    values::{BasicValueEnum, IntValue, PointerValue},
+};
+use std::collections::HashMap;
+
+/// The parts of the matched value loaded so far, by path, and the
+/// variant each sum value on the way is known to be.
+#[derive(Clone)]
+struct Parts<'ctx> {
+    /// The type of the matched value.
+    ty: Type,
+    values: HashMap<Path, BasicValueEnum<'ctx>>,
+    variants: HashMap<Path, usize>,
+}
+
+/// Where the arms' results meet.
+struct Join<'ctx> {
+    block: BasicBlock<'ctx>,
+    incoming: Vec<(BasicValueEnum<'ctx>, BasicBlock<'ctx>)>,
+}
+
+/// `match scrutinee { arms }`. The arms get their own references to what
+/// they bind.
+pub fn build_match<'ctx>(
+    cg: &mut Codegen<'ctx>,
+    env: &mut LocalEnv<'ctx>,
+    scrutinee: &Expr,
+    arms: &[MatchArm],
+) -> BasicValueEnum<'ctx> {
+    let ty = cg.facts.types[&scrutinee.span].clone();
+    let value = codegen_expr(cg, env, scrutinee);
+    let depth = cg.cleanups.len();
+    unwind::push(cg, Cleanup::Value(value));
+    let tag = |name: &str| cg.types.constructor(name).expect("a checked constructor").1;
+    let pats: Vec<_> = arms
+        .iter()
+        .map(|arm| Pat::lower(&arm.pattern, &tag))
+        .collect();
+    let tree = decision::compile(&pats);
+
+    let mut join = Join {
+        block: cg.context.append_basic_block(cg.current_fn, "match_done"),
+        incoming: Vec::new(),
+    };
+    let mut parts = Parts {
+        ty,
+        values: HashMap::new(),
+        variants: HashMap::new(),
+    };
+    parts.values.insert(Vec::new(), value);
+    decide(cg, env, arms, &tree, parts, &mut join);
+    unwind::pop_to(cg, depth);
+
+    cg.builder.position_at_end(join.block);
+    // No arm finishes.
+    let Some(&(first, _)) = join.incoming.first() else {
+        cg.builder.build_unreachable().expect("unreachable failed");
+        let dead = cg.context.append_basic_block(cg.current_fn, "match_never");
+        cg.builder.position_at_end(dead);
+        return cg.unit_value();
+    };
+    let phi = cg
+        .builder
+        .build_phi(first.get_type(), "match")
+        .expect("phi failed");
+    for (result, block) in &join.incoming {
+        phi.add_incoming(&[(result, *block)]);
+    }
+    cg.release(value);
+    phi.as_basic_value()
+}
+
+fn decide<'ctx>(
+    cg: &mut Codegen<'ctx>,
+    env: &mut LocalEnv<'ctx>,
+    arms: &[MatchArm],
+    decision: &Decision,
+    mut parts: Parts<'ctx>,
+    join: &mut Join<'ctx>,
+) {
+    match decision {
+        Decision::Fail => {
+            cg.builder.build_unreachable().expect("unreachable failed");
+        }
+
+        // A part of type `Never` has no value, so nothing gets here.
+        Decision::Leaf { bindings, .. }
+            if bindings
+                .iter()
+                .any(|(_, path)| part_type(cg, &parts, path) == Type::Never) =>
+        {
+            cg.builder.build_unreachable().expect("unreachable failed");
+        }
+        Decision::Switch { path, .. } if part_type(cg, &parts, path) == Type::Never => {
+            cg.builder.build_unreachable().expect("unreachable failed");
+        }
+
+        Decision::Leaf { arm, bindings } => {
+            let depth = cg.cleanups.len();
+            let mut bound = Vec::with_capacity(bindings.len());
+            for (name, path) in bindings {
+                let value = part(cg, &mut parts, path);
+                cg.retain(value);
+                let ptr = cg.alloca(value.get_type(), name);
+                cg.builder.build_store(ptr, value).expect("store failed");
+                let local = Local {
+                    ptr,
+                    ty: value.get_type(),
+                };
+                let shadowed = env.vars.insert(name.clone(), local);
+                unwind::push(cg, Cleanup::Slot(local));
+                bound.push((name, local, shadowed));
+            }
+
+            let result = codegen_expr(cg, env, &arms[*arm].body);
+            unwind::pop_to(cg, depth);
+
+            for (name, local, shadowed) in bound.into_iter().rev() {
+                let value = cg
+                    .builder
+                    .build_load(local.ty, local.ptr, "dead")
+                    .expect("load failed");
+                cg.release(value);
+                match shadowed {
+                    Some(prev) => env.vars.insert(name.clone(), prev),
+                    None => env.vars.remove(name),
+                };
+            }
+            // What an arm that never finishes gives has no type to join.
+            if cg.is_dead() {
+                cg.builder.build_unreachable().expect("unreachable failed");
+                return;
+            }
+            let block = cg.builder.get_insert_block().expect("no insert block");
+            cg.builder
+                .build_unconditional_branch(join.block)
+                .expect("branch failed");
+            join.incoming.push((result, block));
+        }
+
+        // Every value of a tuple type is a tuple.
+        Decision::Switch { cases, .. } if matches!(cases.as_slice(), [(Ctor::Tuple, _)]) => {
+            decide(cg, env, arms, &cases[0].1, parts, join);
+        }
+
+        Decision::Switch {
+            path,
+            cases,
+            default,
+        } => {
+            let value = part(cg, &mut parts, path);
+            let default_block = cg
+                .context
+                .append_basic_block(cg.current_fn, "match_default");
+
+            if let BasicValueEnum::PointerValue(s) = value {
+                // Strings are compared one literal at a time.
+                for (ctor, next) in cases {
+                    let Ctor::String(literal) = ctor else {
+                        unreachable!("patterns are typechecked");
+                    };
+                    let equal = string_equals(cg, s, literal);
+                    let case = cg.context.append_basic_block(cg.current_fn, "match_case");
+                    let other = cg.context.append_basic_block(cg.current_fn, "match_next");
+                    cg.builder
+                        .build_conditional_branch(equal, case, other)
+                        .expect("branch failed");
+                    cg.builder.position_at_end(case);
+                    decide(cg, env, arms, next, parts.clone(), join);
+                    cg.builder.position_at_end(other);
+                }
+                cg.builder
+                    .build_unconditional_branch(default_block)
+                    .expect("branch failed");
+            } else {
+                let scrutinee = match value {
+                    BasicValueEnum::StructValue(v) => sum::tag(cg, v),
+                    other => other.into_int_value(),
+                };
+                let i64_type = cg.context.i64_type();
+                let blocks: Vec<_> = cases
+                    .iter()
+                    .map(|(ctor, _)| {
+                        let key = match ctor {
+                            Ctor::Variant(tag) => i64_type.const_int(*tag as u64, false),
+                            Ctor::Int(n) => i64_type.const_int(*n as u64, true),
+                            Ctor::String(_) | Ctor::Tuple => {
+                                unreachable!("patterns are typechecked")
+                            }
+                        };
+                        let block = cg.context.append_basic_block(cg.current_fn, "match_case");
+                        (key, block)
+                    })
+                    .collect();
+                cg.builder
+                    .build_switch(scrutinee, default_block, &blocks)
+                    .expect("switch failed");
+
+                for ((ctor, next), (_, block)) in cases.iter().zip(blocks) {
+                    cg.builder.position_at_end(block);
+                    let mut parts = parts.clone();
+                    if let Ctor::Variant(tag) = ctor {
+                        parts.variants.insert(path.clone(), *tag);
+                    }
+                    decide(cg, env, arms, next, parts, join);
+                }
+            }
+
+            cg.builder.position_at_end(default_block);
+            decide(cg, env, arms, default, parts, join);
+        }
+    }
+}
+
+/// The part of the matched value at `path`, loading it if this branch has
+/// not yet. Parts are borrowed from the matched value.
+fn part<'ctx>(cg: &Codegen<'ctx>, parts: &mut Parts<'ctx>, path: &Path) -> BasicValueEnum<'ctx> {
+    if let Some(value) = parts.values.get(path) {
+        return *value;
+    }
+    let (&index, parent) = path.split_last().expect("the matched value is loaded");
+    let parent = parent.to_vec();
+    let value = part(cg, parts, &parent).into_struct_value();
+    let ty = part_type(cg, parts, &parent);
+    let field = match ty {
+        Type::Tuple(_) => tuple::item(cg, value, index as u32),
+        _ => sum::field(cg, value, &ty, parts.variants[&parent], index),
+    };
+    parts.values.insert(path.clone(), field);
+    field
+}
+
+/// The type of the part of the matched value at `path`.
+fn part_type(cg: &Codegen<'_>, parts: &Parts<'_>, path: &Path) -> Type {
+    let mut ty = parts.ty.clone();
+    for (depth, &index) in path.iter().enumerate() {
+        if let Type::Tuple(items) = &ty {
+            ty = items[index].clone();
+            continue;
+        }
+        let Type::Sum(sum, args) = &ty else {
+            // Parts of a value of type `Never` are too.
+            return Type::Never;
+        };
+        let tag = parts.variants[&path[..depth]];
+        let variants = cg.types.variants(sum).expect("a declared sum type");
+        ty = variants[tag].fields[index].substitute(args);
+    }
+    ty
+}
+
+/// Whether string `s` holds `literal`.
+fn string_equals<'ctx>(cg: &Codegen<'ctx>, s: PointerValue<'ctx>, literal: &str) -> IntValue<'ctx> {
+    let data = cg
+        .builder
+        .build_struct_gep(string_type(cg.context), s, STRING_DATA, "data")
+        .expect("string data gep failed");
+    let order = cg
+        .builder
+        .build_call(
+            cg.libc.strcmp,
+            &[data.into(), cg.global_string(literal).into()],
+            "order",
+        )
+        .expect("strcmp call failed")
+        .try_as_basic_value()
+        .unwrap_basic()
+        .into_int_value();
+    cg.builder
+        .build_int_compare(
+            IntPredicate::EQ,
+            order,
+            cg.context.i32_type().const_zero(),
+            "equal",
+        )
+        .expect("compare failed")
+}
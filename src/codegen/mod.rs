@@ -1,28 +1,94 @@
 pub mod context;
+pub mod debug;
+pub mod error;
 pub mod expr;
+pub mod function;
+pub mod list;
 pub mod llvm;
+pub mod matching;
+pub mod record;
 pub mod runtime;
+pub mod stages;
 pub mod stmt;
+pub mod sum;
+pub mod target;
+pub mod tuple;
 pub mod types;
+pub mod unwind;
 
 use crate::ast::ast::Ast;
+use crate::typechecker::checker::check_stmt;
+use error::CodegenError;
 use inkwell::context::Context;
 use stmt::{LocalEnv, codegen_stmt};
+use target::CompileTarget;
 
-pub fn codegen(ast: &Ast) {
-    let context = Context::create();
-    let mut cg = context::Codegen::new(&context, "sauce");
+#[derive(Debug, Clone, Default)]
+pub struct CodegenOptions<'a> {
+    /// Emit DWARF debug info (`-g`).
+    pub debug_info: bool,
+    /// Path of the source file, recorded in the debug info.
+    pub filename: &'a str,
+    /// Source text the spans in the `Ast` point into.
+    pub source: &'a str,
+    /// Target to emit an object file for (`--target`).
+    pub target: Option<CompileTarget>,
+    /// Path [`codegen`] writes `<output>.ll` to, and `<output>.o` with a
+    /// target (`-o`).
+    pub output: &'a str,
+}
+
+/// Lowers `ast` into a fresh LLVM module owned by `context`.
+pub fn compile<'ctx>(
+    context: &'ctx Context,
+    ast: &Ast,
+    options: &CodegenOptions,
+) -> Result<context::Codegen<'ctx>, CodegenError> {
+    let machine = options.target.map(|t| t.machine()).transpose()?;
+    let mut cg = context::Codegen::with_target(context, "sauce", machine);
+    if options.debug_info {
+        cg.enable_debug_info(options.filename, options.source);
+    }
     let mut env = LocalEnv::new();
 
+    // The types of what is matched and retossed are only known from
+    // checking, so each statement is checked just before it is compiled.
+    // Nothing after a statement that could not be compiled is lowered.
     for stmt in &ast.items {
+        cg.facts = check_stmt(&mut cg.types, stmt)?;
         codegen_stmt(&mut cg, &mut env, stmt);
+        if let Some(error) = cg.error.take() {
+            return Err(error);
+        }
     }
+    env.release_scope(&cg);
 
     let _ = cg
         .builder
         .build_return(Some(&context.i32_type().const_int(0, false)));
 
+    if let Some(debug) = &cg.debug {
+        debug.finalize();
+    }
+
+    Ok(cg)
+}
+
+pub fn codegen(ast: &Ast, options: &CodegenOptions) -> Result<(), CodegenError> {
+    let context = Context::create();
+    let cg = compile(&context, ast, options)?;
+
+    let ir = format!("{}.ll", options.output);
     cg.module
-        .print_to_file("out.ll")
-        .expect("failed to write LLVM IR");
+        .print_to_file(&ir)
+        .map_err(|e| CodegenError::Emit(format!("{ir}: {}", e.to_string_lossy())))?;
+
+    if let Some(machine) = &cg.target_machine {
+        let object = target::emit_object(&cg.module, machine)?;
+        let path = format!("{}.o", options.output);
+        std::fs::write(&path, object.as_slice())
+            .map_err(|e| CodegenError::Emit(format!("{path}: {e}")))?;
+    }
+
+    Ok(())
 }
@@ -0,0 +1,99 @@
+//! Records, see [`record_type`]. A record value holds its fields, sorted
+//! by name, and owns the references they hold.
+
+use crate::codegen::context::Codegen;
+use crate::codegen::types::{record_fields, record_type};
+use inkwell::values::{BasicValueEnum, StructValue};
+
+/// Builds a record owning the values of `fields`, given in any order.
+pub fn build_record<'ctx>(
+    cg: &Codegen<'ctx>,
+    fields: &[(&str, BasicValueEnum<'ctx>)],
+) -> StructValue<'ctx> {
+    let mut fields = fields.to_vec();
+    fields.sort_by_key(|(name, _)| *name);
+    let types: Vec<_> = fields
+        .iter()
+        .map(|(name, value)| (*name, value.get_type()))
+        .collect();
+
+    let mut record = record_type(cg.context, &types).get_undef();
+    for (i, (_, value)) in fields.iter().enumerate() {
+        record = cg
+            .builder
+            .build_insert_value(record, *value, i as u32, "record")
+            .expect("insert_value failed")
+            .into_struct_value();
+    }
+    record
+}
+
+/// `record.name`. Consumes `record`.
+pub fn build_field<'ctx>(
+    cg: &Codegen<'ctx>,
+    record: StructValue<'ctx>,
+    name: &str,
+) -> BasicValueEnum<'ctx> {
+    let value = field(cg, record, index(record, name));
+    cg.retain(value);
+    release(cg, record);
+    value
+}
+
+/// `{ record with name: value }`, moving each value into its field.
+/// Consumes `record`.
+pub fn build_update<'ctx>(
+    cg: &Codegen<'ctx>,
+    mut record: StructValue<'ctx>,
+    fields: &[(&str, BasicValueEnum<'ctx>)],
+) -> StructValue<'ctx> {
+    for (name, value) in fields {
+        let at = index(record, name);
+        cg.release(field(cg, record, at));
+        record = cg
+            .builder
+            .build_insert_value(record, *value, at, "updated")
+            .expect("insert_value failed")
+            .into_struct_value();
+    }
+    record
+}
+
+pub fn retain<'ctx>(cg: &Codegen<'ctx>, record: StructValue<'ctx>) {
+    for i in 0..record.get_type().count_fields() {
+        cg.retain(field(cg, record, i));
+    }
+}
+
+pub fn release<'ctx>(cg: &Codegen<'ctx>, record: StructValue<'ctx>) {
+    for i in 0..record.get_type().count_fields() {
+        cg.release(field(cg, record, i));
+    }
+}
+
+/// Prints `record` like `{ age: 3, name: "ada" }`, without a newline.
+pub fn print<'ctx>(cg: &Codegen<'ctx>, record: StructValue<'ctx>) {
+    let names = record_fields(record.get_type()).expect("not a record");
+    cg.printf("{ ", &[]);
+    for (i, name) in names.iter().enumerate() {
+        let separator = if i == 0 { "" } else { ", " };
+        cg.printf(&format!("{separator}{name}: "), &[]);
+        cg.print_nested(field(cg, record, i as u32));
+    }
+    cg.printf(" }", &[]);
+}
+
+/// The position of field `name` in `record`.
+fn index(record: StructValue<'_>, name: &str) -> u32 {
+    record_fields(record.get_type())
+        .expect("not a record")
+        .iter()
+        .position(|field| field == name)
+        .expect("fields are typechecked") as u32
+}
+
+fn field<'ctx>(cg: &Codegen<'ctx>, record: StructValue<'ctx>, index: u32) -> BasicValueEnum<'ctx> {
+    cg.builder
+        .build_extract_value(record, index, "field")
+        .expect("extract_value failed")
+}
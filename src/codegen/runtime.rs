@@ -1 +1,866 @@
+//! The Sauce runtime, emitted as LLVM IR into every compiled module.
+//!
+//! All functions use the C ABI and `weak_odr` linkage, so objects built from
+//! several Sauce modules link together and C hosts can call into them.
 
+use crate::codegen::llvm::Libc;
+use crate::codegen::types::{
+    LIST_HEADER_SIZE, STRING_DATA, STRING_HEADER_SIZE, STRING_LEN, STRING_REFCOUNT, string_type,
+};
+use inkwell::{
+    AddressSpace, IntPredicate,
+    builder::Builder,
+    context::Context,
+    module::{Linkage, Module},
+    types::{BasicMetadataTypeEnum, FunctionType, IntType, PointerType, StructType},
+    values::{
+        BasicMetadataValueEnum, BasicValueEnum, FunctionValue, GlobalValue, IntValue, PointerValue,
+    },
+};
+
+/// Payload tag passed to `sauce_rt_toss` and effect handlers.
+pub const TAG_UNIT: u64 = 0;
+pub const TAG_INT: u64 = 1;
+pub const TAG_STRING: u64 = 2;
+
+/// Maximum number of effect handlers `sauce_rt_register_handler` accepts.
+pub const MAX_HANDLERS: u32 = 64;
+
+/// Exit status of a program that hits a runtime error, matching `sauce run`.
+const PANIC_EXIT_CODE: u64 = 1;
+
+/// Strings are pointers to a `{ i64 refcount, i64 len, [0 x i8] data }` header
+/// followed by the NUL-terminated bytes. Literals are constant globals with a
+/// negative refcount; retain and release leave them alone.
+pub struct Runtime<'ctx> {
+    /// `String sauce_rt_str_alloc(i64 len)`, refcount 1
+    pub str_alloc: FunctionValue<'ctx>,
+    /// `String sauce_rt_str_from_cstr(ptr cstr)`, refcount 1
+    pub str_from_cstr: FunctionValue<'ctx>,
+    /// `String sauce_rt_str_concat(String a, String b)`, refcount 1
+    pub str_concat: FunctionValue<'ctx>,
+    /// `void sauce_rt_str_retain(String s)`
+    pub str_retain: FunctionValue<'ctx>,
+    /// `void sauce_rt_str_release(String s)`, frees `s` when the count drops to zero
+    pub str_release: FunctionValue<'ctx>,
+    /// `i64 sauce_rt_strings_live()`, number of heap strings not yet freed
+    pub strings_live: FunctionValue<'ctx>,
+    /// `ptr sauce_rt_list_alloc(i64 cap, i64 elem_size)`, an element block
+    /// with refcount 1
+    pub list_alloc: FunctionValue<'ctx>,
+    /// `void sauce_rt_list_retain(ptr block)`
+    pub list_retain: FunctionValue<'ctx>,
+    /// `bool sauce_rt_list_release(ptr block)`, true when the count drops to
+    /// zero: the caller then releases the elements and calls `list_free`
+    pub list_release: FunctionValue<'ctx>,
+    /// `void sauce_rt_list_free(ptr block)`
+    pub list_free: FunctionValue<'ctx>,
+    /// `i64 sauce_rt_lists_live()`, number of element blocks not yet freed
+    pub lists_live: FunctionValue<'ctx>,
+    /// `void sauce_rt_print_int(i64 value)`
+    pub print_int: FunctionValue<'ctx>,
+    /// `void sauce_rt_print_str(String value)`
+    pub print_str: FunctionValue<'ctx>,
+    /// `void sauce_rt_print_unit()`
+    pub print_unit: FunctionValue<'ctx>,
+    /// `noreturn void sauce_rt_panic(ptr message)`
+    pub panic: FunctionValue<'ctx>,
+    /// `void sauce_rt_register_handler(ptr effect, void (*handler)(i32 tag, i64 payload))`
+    pub register_handler: FunctionValue<'ctx>,
+    /// `void sauce_rt_toss(ptr effect, i32 tag, i64 payload)`, string payloads
+    /// are passed as a pointer to their NUL-terminated bytes
+    pub toss: FunctionValue<'ctx>,
+}
+
+struct Emitter<'a, 'ctx> {
+    context: &'ctx Context,
+    module: &'a Module<'ctx>,
+    builder: Builder<'ctx>,
+    libc: &'a Libc<'ctx>,
+    ptr: PointerType<'ctx>,
+    size: IntType<'ctx>,
+    string: StructType<'ctx>,
+}
+
+impl<'ctx> Runtime<'ctx> {
+    pub fn emit(
+        context: &'ctx Context,
+        module: &Module<'ctx>,
+        libc: &Libc<'ctx>,
+        size_type: IntType<'ctx>,
+    ) -> Self {
+        let e = Emitter {
+            context,
+            module,
+            builder: context.create_builder(),
+            libc,
+            ptr: context.ptr_type(AddressSpace::default()),
+            size: size_type,
+            string: string_type(context),
+        };
+
+        let panic = e.emit_panic();
+        let (live, strings_live) = e.emit_live_counter("strings");
+        let str_alloc = e.emit_str_alloc(panic, live);
+        let str_from_cstr = e.emit_str_from_cstr(str_alloc);
+        let str_concat = e.emit_str_concat(str_alloc);
+        let str_retain = e.emit_str_retain();
+        let str_release = e.emit_str_release(live);
+        let (lists, lists_live) = e.emit_live_counter("lists");
+        let list_alloc = e.emit_list_alloc(panic, lists);
+        let list_retain = e.emit_list_retain();
+        let list_release = e.emit_list_release();
+        let list_free = e.emit_list_free(lists);
+        let print_int = e.emit_print_int();
+        let print_str = e.emit_print_str();
+        let print_unit = e.emit_print_unit();
+        let (handlers, handler_count, handler_type) = e.emit_handler_table();
+        let register_handler =
+            e.emit_register_handler(handlers, handler_count, handler_type, panic);
+        let toss = e.emit_toss(
+            handlers,
+            handler_count,
+            handler_type,
+            str_from_cstr,
+            str_concat,
+            panic,
+        );
+
+        Self {
+            str_alloc,
+            str_from_cstr,
+            str_concat,
+            str_retain,
+            str_release,
+            strings_live,
+            list_alloc,
+            list_retain,
+            list_release,
+            list_free,
+            lists_live,
+            print_int,
+            print_str,
+            print_unit,
+            panic,
+            register_handler,
+            toss,
+        }
+    }
+}
+
+impl<'a, 'ctx> Emitter<'a, 'ctx> {
+    fn function(&self, name: &str, ty: FunctionType<'ctx>) -> FunctionValue<'ctx> {
+        let function = self.module.add_function(name, ty, Some(Linkage::WeakODR));
+        let entry = self.context.append_basic_block(function, "entry");
+        self.builder.position_at_end(entry);
+        function
+    }
+
+    fn string(&self, value: &str, name: &str) -> PointerValue<'ctx> {
+        self.builder
+            .build_global_string_ptr(value, name)
+            .expect("runtime string failed")
+            .as_pointer_value()
+    }
+
+    fn emit_panic(&self) -> FunctionValue<'ctx> {
+        let i32_type = self.context.i32_type();
+        let function = self.function(
+            "sauce_rt_panic",
+            self.context.void_type().fn_type(&[self.ptr.into()], false),
+        );
+        let message = function.get_nth_param(0).unwrap().into_pointer_value();
+
+        // Flush stdout first so output printed before the panic stays in order.
+        self.builder
+            .build_call(self.libc.fflush, &[self.ptr.const_null().into()], "flush")
+            .expect("fflush call failed");
+
+        let stderr = i32_type.const_int(2, false);
+        let prefix = "runtime error: ";
+        let prefix_ptr = self.string(prefix, "panic_prefix");
+        let newline = self.string("\n", "panic_newline");
+        let len = self
+            .builder
+            .build_call(self.libc.strlen, &[message.into()], "len")
+            .expect("strlen call failed")
+            .try_as_basic_value()
+            .unwrap_basic();
+
+        for (ptr, len) in [
+            (prefix_ptr, self.size.const_int(prefix.len() as u64, false)),
+            (message, len.into_int_value()),
+            (newline, self.size.const_int(1, false)),
+        ] {
+            self.builder
+                .build_call(
+                    self.libc.write,
+                    &[stderr.into(), ptr.into(), len.into()],
+                    "write",
+                )
+                .expect("write call failed");
+        }
+
+        self.builder
+            .build_call(
+                self.libc.exit,
+                &[i32_type.const_int(PANIC_EXIT_CODE, false).into()],
+                "",
+            )
+            .expect("exit call failed");
+        self.builder
+            .build_unreachable()
+            .expect("unreachable failed");
+        function
+    }
+
+    fn call(
+        &self,
+        function: FunctionValue<'ctx>,
+        args: &[BasicMetadataValueEnum<'ctx>],
+        name: &str,
+    ) -> BasicValueEnum<'ctx> {
+        self.builder
+            .build_call(function, args, name)
+            .expect("runtime call failed")
+            .try_as_basic_value()
+            .unwrap_basic()
+    }
+
+    fn string_field(&self, s: PointerValue<'ctx>, field: u32, name: &str) -> PointerValue<'ctx> {
+        self.builder
+            .build_struct_gep(self.string, s, field, name)
+            .expect("string field gep failed")
+    }
+
+    fn string_len(&self, s: PointerValue<'ctx>) -> IntValue<'ctx> {
+        let len = self.string_field(s, STRING_LEN, "len_ptr");
+        self.builder
+            .build_load(self.context.i64_type(), len, "len")
+            .unwrap()
+            .into_int_value()
+    }
+
+    /// `sauce_rt_live_<what>` and `sauce_rt_<what>_live()`, which reads it.
+    fn emit_live_counter(&self, what: &str) -> (GlobalValue<'ctx>, FunctionValue<'ctx>) {
+        let i64_type = self.context.i64_type();
+        let live = self
+            .module
+            .add_global(i64_type, None, &format!("sauce_rt_live_{what}"));
+        live.set_linkage(Linkage::WeakODR);
+        live.set_initializer(&i64_type.const_zero());
+
+        let function = self.function(
+            &format!("sauce_rt_{what}_live"),
+            i64_type.fn_type(&[], false),
+        );
+        let count = self
+            .builder
+            .build_load(i64_type, live.as_pointer_value(), "live")
+            .unwrap();
+        self.builder.build_return(Some(&count)).unwrap();
+        (live, function)
+    }
+
+    fn bump_live(&self, live: GlobalValue<'ctx>, delta: i64) {
+        let i64_type = self.context.i64_type();
+        let count = self
+            .builder
+            .build_load(i64_type, live.as_pointer_value(), "live")
+            .unwrap()
+            .into_int_value();
+        let next = self
+            .builder
+            .build_int_add(count, i64_type.const_int(delta as u64, true), "next")
+            .unwrap();
+        self.builder
+            .build_store(live.as_pointer_value(), next)
+            .unwrap();
+    }
+
+    fn emit_str_alloc(
+        &self,
+        panic: FunctionValue<'ctx>,
+        live: GlobalValue<'ctx>,
+    ) -> FunctionValue<'ctx> {
+        let i64_type = self.context.i64_type();
+        let function = self.function(
+            "sauce_rt_str_alloc",
+            self.ptr.fn_type(&[i64_type.into()], false),
+        );
+        let len = function.get_nth_param(0).unwrap().into_int_value();
+
+        // header + bytes + trailing NUL
+        let size = self
+            .builder
+            .build_int_add(
+                len,
+                i64_type.const_int(STRING_HEADER_SIZE + 1, false),
+                "size",
+            )
+            .expect("add failed");
+        let size = self
+            .builder
+            .build_int_cast(size, self.size, "size")
+            .expect("cast failed");
+        let s = self
+            .call(self.libc.malloc, &[size.into()], "s")
+            .into_pointer_value();
+        self.check_alloc(function, s, panic);
+
+        let refcount = self.string_field(s, STRING_REFCOUNT, "refcount");
+        self.builder
+            .build_store(refcount, i64_type.const_int(1, false))
+            .unwrap();
+        let len_ptr = self.string_field(s, STRING_LEN, "len_ptr");
+        self.builder.build_store(len_ptr, len).unwrap();
+        let data = self.string_field(s, STRING_DATA, "data");
+        let end = unsafe {
+            self.builder
+                .build_in_bounds_gep(self.context.i8_type(), data, &[len], "end")
+                .unwrap()
+        };
+        self.builder
+            .build_store(end, self.context.i8_type().const_zero())
+            .unwrap();
+        self.bump_live(live, 1);
+        self.builder.build_return(Some(&s)).unwrap();
+        function
+    }
+
+    fn emit_str_from_cstr(&self, str_alloc: FunctionValue<'ctx>) -> FunctionValue<'ctx> {
+        let function = self.function(
+            "sauce_rt_str_from_cstr",
+            self.ptr.fn_type(&[self.ptr.into()], false),
+        );
+        let cstr = function.get_nth_param(0).unwrap().into_pointer_value();
+
+        let len = self
+            .call(self.libc.strlen, &[cstr.into()], "len")
+            .into_int_value();
+        let len = self
+            .builder
+            .build_int_cast(len, self.context.i64_type(), "len")
+            .unwrap();
+        let s = self
+            .call(str_alloc, &[len.into()], "s")
+            .into_pointer_value();
+        let data = self.string_field(s, STRING_DATA, "data");
+        self.builder.build_memcpy(data, 1, cstr, 1, len).unwrap();
+        self.builder.build_return(Some(&s)).unwrap();
+        function
+    }
+
+    fn emit_str_concat(&self, str_alloc: FunctionValue<'ctx>) -> FunctionValue<'ctx> {
+        let function = self.function(
+            "sauce_rt_str_concat",
+            self.ptr.fn_type(&[self.ptr.into(), self.ptr.into()], false),
+        );
+        let a = function.get_nth_param(0).unwrap().into_pointer_value();
+        let b = function.get_nth_param(1).unwrap().into_pointer_value();
+
+        let a_len = self.string_len(a);
+        let b_len = self.string_len(b);
+        let len = self.builder.build_int_add(a_len, b_len, "len").unwrap();
+
+        let s = self
+            .call(str_alloc, &[len.into()], "s")
+            .into_pointer_value();
+        let data = self.string_field(s, STRING_DATA, "data");
+        let a_data = self.string_field(a, STRING_DATA, "a_data");
+        let b_data = self.string_field(b, STRING_DATA, "b_data");
+        self.builder
+            .build_memcpy(data, 1, a_data, 1, a_len)
+            .unwrap();
+        let tail = unsafe {
+            self.builder
+                .build_in_bounds_gep(self.context.i8_type(), data, &[a_len], "tail")
+                .unwrap()
+        };
+        self.builder
+            .build_memcpy(tail, 1, b_data, 1, b_len)
+            .unwrap();
+        self.builder.build_return(Some(&s)).unwrap();
+        function
+    }
+
+    /// Loads the refcount of `s`, returning early for null and static strings.
+    fn load_managed_refcount(
+        &self,
+        function: FunctionValue<'ctx>,
+        s: PointerValue<'ctx>,
+    ) -> (PointerValue<'ctx>, IntValue<'ctx>) {
+        let i64_type = self.context.i64_type();
+        let check = self.context.append_basic_block(function, "check");
+        let managed = self.context.append_basic_block(function, "managed");
+        let done = self.context.append_basic_block(function, "done");
+
+        let is_null = self.builder.build_is_null(s, "is_null").unwrap();
+        self.builder
+            .build_conditional_branch(is_null, done, check)
+            .unwrap();
+
+        self.builder.position_at_end(check);
+        let refcount_ptr = self.string_field(s, STRING_REFCOUNT, "refcount_ptr");
+        let refcount = self
+            .builder
+            .build_load(i64_type, refcount_ptr, "refcount")
+            .unwrap()
+            .into_int_value();
+        let is_static = self
+            .builder
+            .build_int_compare(
+                IntPredicate::SLT,
+                refcount,
+                i64_type.const_zero(),
+                "is_static",
+            )
+            .unwrap();
+        self.builder
+            .build_conditional_branch(is_static, done, managed)
+            .unwrap();
+
+        self.builder.position_at_end(done);
+        self.builder.build_return(None).unwrap();
+
+        self.builder.position_at_end(managed);
+        (refcount_ptr, refcount)
+    }
+
+    fn emit_str_retain(&self) -> FunctionValue<'ctx> {
+        let function = self.function(
+            "sauce_rt_str_retain",
+            self.context.void_type().fn_type(&[self.ptr.into()], false),
+        );
+        let s = function.get_nth_param(0).unwrap().into_pointer_value();
+
+        let (refcount_ptr, refcount) = self.load_managed_refcount(function, s);
+        let next = self
+            .builder
+            .build_int_add(
+                refcount,
+                self.context.i64_type().const_int(1, false),
+                "next",
+            )
+            .unwrap();
+        self.builder.build_store(refcount_ptr, next).unwrap();
+        self.builder.build_return(None).unwrap();
+        function
+    }
+
+    fn emit_str_release(&self, live: GlobalValue<'ctx>) -> FunctionValue<'ctx> {
+        let function = self.function(
+            "sauce_rt_str_release",
+            self.context.void_type().fn_type(&[self.ptr.into()], false),
+        );
+        let s = function.get_nth_param(0).unwrap().into_pointer_value();
+
+        let (refcount_ptr, refcount) = self.load_managed_refcount(function, s);
+        let i64_type = self.context.i64_type();
+        let next = self
+            .builder
+            .build_int_sub(refcount, i64_type.const_int(1, false), "next")
+            .unwrap();
+        let dead = self
+            .builder
+            .build_int_compare(IntPredicate::EQ, next, i64_type.const_zero(), "dead")
+            .unwrap();
+
+        let free = self.context.append_basic_block(function, "free");
+        let keep = self.context.append_basic_block(function, "keep");
+        self.builder
+            .build_conditional_branch(dead, free, keep)
+            .unwrap();
+
+        self.builder.position_at_end(free);
+        self.builder
+            .build_call(self.libc.free, &[s.into()], "")
+            .expect("free call failed");
+        self.bump_live(live, -1);
+        self.builder.build_return(None).unwrap();
+
+        self.builder.position_at_end(keep);
+        self.builder.build_store(refcount_ptr, next).unwrap();
+        self.builder.build_return(None).unwrap();
+        function
+    }
+
+    /// Panics with "out of memory" if `block` is null, and continues in a
+    /// new block otherwise.
+    fn check_alloc(
+        &self,
+        function: FunctionValue<'ctx>,
+        block: PointerValue<'ctx>,
+        panic: FunctionValue<'ctx>,
+    ) {
+        let oom = self.context.append_basic_block(function, "oom");
+        let ok = self.context.append_basic_block(function, "ok");
+        let is_null = self.builder.build_is_null(block, "is_null").unwrap();
+        self.builder
+            .build_conditional_branch(is_null, oom, ok)
+            .unwrap();
+
+        self.builder.position_at_end(oom);
+        let message = self.string("out of memory", "oom_message");
+        self.builder
+            .build_call(panic, &[message.into()], "")
+            .expect("panic call failed");
+        self.builder.build_unreachable().unwrap();
+
+        self.builder.position_at_end(ok);
+    }
+
+    fn emit_list_alloc(
+        &self,
+        panic: FunctionValue<'ctx>,
+        live: GlobalValue<'ctx>,
+    ) -> FunctionValue<'ctx> {
+        let i64_type = self.context.i64_type();
+        let function = self.function(
+            "sauce_rt_list_alloc",
+            self.ptr.fn_type(&[i64_type.into(), i64_type.into()], false),
+        );
+        let cap = function.get_nth_param(0).unwrap().into_int_value();
+        let elem_size = function.get_nth_param(1).unwrap().into_int_value();
+
+        // refcount + elements
+        let size = self
+            .builder
+            .build_int_mul(cap, elem_size, "elems_size")
+            .unwrap();
+        let size = self
+            .builder
+            .build_int_add(size, i64_type.const_int(LIST_HEADER_SIZE, false), "size")
+            .unwrap();
+        let size = self
+            .builder
+            .build_int_cast(size, self.size, "size")
+            .expect("cast failed");
+        let block = self
+            .call(self.libc.malloc, &[size.into()], "block")
+            .into_pointer_value();
+        self.check_alloc(function, block, panic);
+
+        self.builder
+            .build_store(block, i64_type.const_int(1, false))
+            .unwrap();
+        self.bump_live(live, 1);
+        self.builder.build_return(Some(&block)).unwrap();
+        function
+    }
+
+    fn emit_list_retain(&self) -> FunctionValue<'ctx> {
+        let i64_type = self.context.i64_type();
+        let function = self.function(
+            "sauce_rt_list_retain",
+            self.context.void_type().fn_type(&[self.ptr.into()], false),
+        );
+        let block = function.get_nth_param(0).unwrap().into_pointer_value();
+
+        let refcount = self
+            .builder
+            .build_load(i64_type, block, "refcount")
+            .unwrap()
+            .into_int_value();
+        let next = self
+            .builder
+            .build_int_add(refcount, i64_type.const_int(1, false), "next")
+            .unwrap();
+        self.builder.build_store(block, next).unwrap();
+        self.builder.build_return(None).unwrap();
+        function
+    }
+
+    fn emit_list_release(&self) -> FunctionValue<'ctx> {
+        let i64_type = self.context.i64_type();
+        let function = self.function(
+            "sauce_rt_list_release",
+            self.context.bool_type().fn_type(&[self.ptr.into()], false),
+        );
+        let block = function.get_nth_param(0).unwrap().into_pointer_value();
+
+        let refcount = self
+            .builder
+            .build_load(i64_type, block, "refcount")
+            .unwrap()
+            .into_int_value();
+        let next = self
+            .builder
+            .build_int_sub(refcount, i64_type.const_int(1, false), "next")
+            .unwrap();
+        self.builder.build_store(block, next).unwrap();
+        let dead = self
+            .builder
+            .build_int_compare(IntPredicate::EQ, next, i64_type.const_zero(), "dead")
+            .unwrap();
+        self.builder.build_return(Some(&dead)).unwrap();
+        function
+    }
+
+    fn emit_list_free(&self, live: GlobalValue<'ctx>) -> FunctionValue<'ctx> {
+        let function = self.function(
+            "sauce_rt_list_free",
+            self.context.void_type().fn_type(&[self.ptr.into()], false),
+        );
+        let block = function.get_nth_param(0).unwrap().into_pointer_value();
+        self.builder
+            .build_call(self.libc.free, &[block.into()], "")
+            .expect("free call failed");
+        self.bump_live(live, -1);
+        self.builder.build_return(None).unwrap();
+        function
+    }
+
+    fn emit_printf(&self, name: &str, format: &str, param: Option<BasicMetadataTypeEnum<'ctx>>) {
+        let params: Vec<_> = param.into_iter().collect();
+        let function = self.function(name, self.context.void_type().fn_type(&params, false));
+
+        let mut args = vec![self.string(format, "fmt").into()];
+        if let Some(value) = function.get_first_param() {
+            let arg = match value {
+                // Strings print their data, not the header.
+                BasicValueEnum::PointerValue(s) => self.string_field(s, STRING_DATA, "data").into(),
+                other => other.into(),
+            };
+            args.push(arg);
+        }
+        self.builder
+            .build_call(self.libc.printf, &args, "printf_call")
+            .expect("printf call failed");
+        self.builder.build_return(None).unwrap();
+    }
+
+    fn emit_print_int(&self) -> FunctionValue<'ctx> {
+        let name = "sauce_rt_print_int";
+        self.emit_printf(name, "%lld\n", Some(self.context.i64_type().into()));
+        self.module.get_function(name).unwrap()
+    }
+
+    fn emit_print_str(&self) -> FunctionValue<'ctx> {
+        let name = "sauce_rt_print_str";
+        self.emit_printf(name, "%s\n", Some(self.ptr.into()));
+        self.module.get_function(name).unwrap()
+    }
+
+    fn emit_print_unit(&self) -> FunctionValue<'ctx> {
+        let name = "sauce_rt_print_unit";
+        self.emit_printf(name, "()\n", None);
+        self.module.get_function(name).unwrap()
+    }
+
+    /// `{ ptr effect, ptr handler }` entries plus a count, both `weak_odr`.
+    fn emit_handler_table(&self) -> (GlobalValue<'ctx>, GlobalValue<'ctx>, StructType<'ctx>) {
+        let entry_type = self
+            .context
+            .struct_type(&[self.ptr.into(), self.ptr.into()], false);
+        let table_type = entry_type.array_type(MAX_HANDLERS);
+
+        let handlers = self
+            .module
+            .add_global(table_type, None, "sauce_rt_handlers");
+        handlers.set_linkage(Linkage::WeakODR);
+        handlers.set_initializer(&table_type.const_zero());
+
+        let count = self
+            .module
+            .add_global(self.size, None, "sauce_rt_handler_count");
+        count.set_linkage(Linkage::WeakODR);
+        count.set_initializer(&self.size.const_zero());
+
+        (handlers, count, entry_type)
+    }
+
+    fn handler_slot(
+        &self,
+        handlers: GlobalValue<'ctx>,
+        entry_type: StructType<'ctx>,
+        index: IntValue<'ctx>,
+        field: u64,
+    ) -> PointerValue<'ctx> {
+        let i32_type = self.context.i32_type();
+        unsafe {
+            self.builder
+                .build_in_bounds_gep(
+                    entry_type.array_type(MAX_HANDLERS),
+                    handlers.as_pointer_value(),
+                    &[
+                        self.size.const_zero(),
+                        index,
+                        i32_type.const_int(field, false),
+                    ],
+                    "slot",
+                )
+                .unwrap()
+        }
+    }
+
+    fn emit_register_handler(
+        &self,
+        handlers: GlobalValue<'ctx>,
+        count: GlobalValue<'ctx>,
+        entry_type: StructType<'ctx>,
+        panic: FunctionValue<'ctx>,
+    ) -> FunctionValue<'ctx> {
+        let function = self.function(
+            "sauce_rt_register_handler",
+            self.context
+                .void_type()
+                .fn_type(&[self.ptr.into(), self.ptr.into()], false),
+        );
+        let effect = function.get_nth_param(0).unwrap();
+        let handler = function.get_nth_param(1).unwrap();
+
+        let n = self
+            .builder
+            .build_load(self.size, count.as_pointer_value(), "n")
+            .unwrap()
+            .into_int_value();
+        let full = self
+            .builder
+            .build_int_compare(
+                IntPredicate::UGE,
+                n,
+                self.size.const_int(MAX_HANDLERS as u64, false),
+                "full",
+            )
+            .unwrap();
+
+        let overflow = self.context.append_basic_block(function, "overflow");
+        let store = self.context.append_basic_block(function, "store");
+        self.builder
+            .build_conditional_branch(full, overflow, store)
+            .unwrap();
+
+        self.builder.position_at_end(overflow);
+        let message = self.string("too many effect handlers", "overflow_message");
+        self.builder
+            .build_call(panic, &[message.into()], "")
+            .expect("panic call failed");
+        self.builder.build_unreachable().unwrap();
+
+        self.builder.position_at_end(store);
+        let name_slot = self.handler_slot(handlers, entry_type, n, 0);
+        self.builder.build_store(name_slot, effect).unwrap();
+        let handler_slot = self.handler_slot(handlers, entry_type, n, 1);
+        self.builder.build_store(handler_slot, handler).unwrap();
+        let next = self
+            .builder
+            .build_int_add(n, self.size.const_int(1, false), "next")
+            .unwrap();
+        self.builder
+            .build_store(count.as_pointer_value(), next)
+            .unwrap();
+        self.builder.build_return(None).unwrap();
+        function
+    }
+
+    /// Calls the most recently registered handler for the effect, or panics.
+    fn emit_toss(
+        &self,
+        handlers: GlobalValue<'ctx>,
+        count: GlobalValue<'ctx>,
+        entry_type: StructType<'ctx>,
+        str_from_cstr: FunctionValue<'ctx>,
+        str_concat: FunctionValue<'ctx>,
+        panic: FunctionValue<'ctx>,
+    ) -> FunctionValue<'ctx> {
+        let i32_type = self.context.i32_type();
+        let i64_type = self.context.i64_type();
+        let function = self.function(
+            "sauce_rt_toss",
+            self.context
+                .void_type()
+                .fn_type(&[self.ptr.into(), i32_type.into(), i64_type.into()], false),
+        );
+        let effect = function.get_nth_param(0).unwrap().into_pointer_value();
+        let tag = function.get_nth_param(1).unwrap();
+        let payload = function.get_nth_param(2).unwrap();
+
+        let entry = self.builder.get_insert_block().unwrap();
+        let cond = self.context.append_basic_block(function, "cond");
+        let body = self.context.append_basic_block(function, "body");
+        let found = self.context.append_basic_block(function, "found");
+        let unhandled = self.context.append_basic_block(function, "unhandled");
+
+        let n = self
+            .builder
+            .build_load(self.size, count.as_pointer_value(), "n")
+            .unwrap()
+            .into_int_value();
+        self.builder.build_unconditional_branch(cond).unwrap();
+
+        // Walk the table from the end so later registrations win.
+        self.builder.position_at_end(cond);
+        let i = self.builder.build_phi(self.size, "i").unwrap();
+        i.add_incoming(&[(&n, entry)]);
+        let i_val = i.as_basic_value().into_int_value();
+        let done = self
+            .builder
+            .build_int_compare(IntPredicate::EQ, i_val, self.size.const_zero(), "done")
+            .unwrap();
+        self.builder
+            .build_conditional_branch(done, unhandled, body)
+            .unwrap();
+
+        self.builder.position_at_end(body);
+        let index = self
+            .builder
+            .build_int_sub(i_val, self.size.const_int(1, false), "index")
+            .unwrap();
+        let name_slot = self.handler_slot(handlers, entry_type, index, 0);
+        let name = self
+            .builder
+            .build_load(self.ptr, name_slot, "name")
+            .unwrap();
+        let cmp = self
+            .builder
+            .build_call(self.libc.strcmp, &[name.into(), effect.into()], "cmp")
+            .expect("strcmp call failed")
+            .try_as_basic_value()
+            .unwrap_basic()
+            .into_int_value();
+        let matches = self
+            .builder
+            .build_int_compare(IntPredicate::EQ, cmp, i32_type.const_zero(), "matches")
+            .unwrap();
+        i.add_incoming(&[(&index, body)]);
+        self.builder
+            .build_conditional_branch(matches, found, cond)
+            .unwrap();
+
+        self.builder.position_at_end(found);
+        let handler_slot = self.handler_slot(handlers, entry_type, index, 1);
+        let handler = self
+            .builder
+            .build_load(self.ptr, handler_slot, "handler")
+            .unwrap()
+            .into_pointer_value();
+        let handler_type = self
+            .context
+            .void_type()
+            .fn_type(&[i32_type.into(), i64_type.into()], false);
+        self.builder
+            .build_indirect_call(handler_type, handler, &[tag.into(), payload.into()], "")
+            .expect("handler call failed");
+        self.builder.build_return(None).unwrap();
+
+        self.builder.position_at_end(unhandled);
+        let from_cstr = |cstr: PointerValue<'ctx>| {
+            self.call(str_from_cstr, &[cstr.into()], "part")
+                .into_pointer_value()
+        };
+        let concat = |a: PointerValue<'ctx>, b: PointerValue<'ctx>| {
+            self.call(str_concat, &[a.into(), b.into()], "message")
+                .into_pointer_value()
+        };
+        let prefix = from_cstr(self.string("effect `", "unhandled_prefix"));
+        let suffix = from_cstr(self.string("` was not handled", "unhandled_suffix"));
+        let message = concat(concat(prefix, from_cstr(effect)), suffix);
+        let message = self.string_field(message, STRING_DATA, "message_data");
+        self.builder
+            .build_call(panic, &[message.into()], "")
+            .expect("panic call failed");
+        self.builder.build_unreachable().unwrap();
+        function
+    }
+}
@@ -0,0 +1,284 @@
+//! Built-in pipeline stages. A chain such as
+//! `xs |> map(f) |> filter(g) |> sum` compiles to one loop over `xs`: each
+//! element goes through every stage before the next one is loaded, and the
+//! `fn`s passed to the stages are inlined into the loop, so no list is
+//! built between stages.
+
+use crate::ast::ast::Stage;
+use crate::codegen::{
+    context::Codegen,
+    expr::codegen_expr,
+    function::{Arg, inline},
+    list,
+    stmt::{Local, LocalEnv},
+    types::{STRING_LEN, string_type},
+    unwind::{self, Cleanup},
+};
+use inkwell::{
+    IntPredicate,
+    values::{BasicValueEnum, IntValue, PointerValue},
+};
+use std::{cell::Cell, rc::Rc};
+
+/// Applies a chain of built-in stages, as split by
+/// [`stage_chain`](crate::ast::ast::stage_chain), to a `piped` value.
+/// Consumes `piped`.
+pub fn build_stages<'ctx>(
+    cg: &mut Codegen<'ctx>,
+    env: &mut LocalEnv<'ctx>,
+    piped: BasicValueEnum<'ctx>,
+    stages: &[Stage],
+) -> BasicValueEnum<'ctx> {
+    let list = match piped {
+        // Only `len` takes a string.
+        BasicValueEnum::PointerValue(s) => return string_len(cg, s).into(),
+        BasicValueEnum::StructValue(list) => list,
+        _ => unreachable!("stages are typechecked"),
+    };
+    if let [stage] = stages
+        && stage.name == "len"
+    {
+        return list::build_len(cg, list).into();
+    }
+
+    let i64_type = cg.context.i64_type();
+    let last = stages.last().expect("a chain has at least one stage");
+    let depth = cg.cleanups.len();
+    unwind::push(cg, Cleanup::Value(list.into()));
+
+    // Arguments are evaluated once, before the loop. Each `take` counts
+    // the elements it let through; `len`, `sum` and `fold` accumulate.
+    let mut taken = Vec::with_capacity(stages.len());
+    let mut acc = None;
+    let mut acc_depth = depth;
+    for stage in stages {
+        let mut limit = None;
+        match (stage.name, stage.args) {
+            ("take", [n]) => {
+                let n = codegen_expr(cg, env, n).into_int_value();
+                let count = cg.alloca(i64_type.into(), "taken");
+                store(cg, count, i64_type.const_zero().into());
+                limit = Some((count, n));
+            }
+            ("fold", [init, _]) => {
+                let init = codegen_expr(cg, env, init);
+                let ptr = cg.alloca(init.get_type(), "acc");
+                store(cg, ptr, init);
+                let local = Local {
+                    ptr,
+                    ty: init.get_type(),
+                };
+                acc_depth = cg.cleanups.len();
+                unwind::push(cg, Cleanup::Slot(local));
+                acc = Some(local);
+            }
+            ("len" | "sum", _) => {
+                let ptr = cg.alloca(i64_type.into(), "acc");
+                store(cg, ptr, i64_type.const_zero().into());
+                acc = Some(Local {
+                    ptr,
+                    ty: i64_type.into(),
+                });
+            }
+            _ => {}
+        }
+        taken.push(limit);
+    }
+
+    let len = list::len(cg, list);
+    let index = cg.alloca(i64_type.into(), "i");
+    store(cg, index, i64_type.const_zero().into());
+    let out = cg.alloca(cg.context.ptr_type(Default::default()).into(), "out");
+    let out_len = cg.alloca(i64_type.into(), "out_len");
+    store(cg, out_len, i64_type.const_zero().into());
+    let out_elem = Rc::new(Cell::new(None));
+    if last.is_streaming() {
+        let output = Cleanup::Output {
+            data: out,
+            len: out_len,
+            elem: Rc::clone(&out_elem),
+        };
+        unwind::push(cg, output);
+    }
+
+    let function = cg.current_fn;
+    let alloc = cg.context.append_basic_block(function, "stages_alloc");
+    let head = cg.context.append_basic_block(function, "stages_head");
+    let body = cg.context.append_basic_block(function, "stages_body");
+    let next = cg.context.append_basic_block(function, "stages_next");
+    let done = cg.context.append_basic_block(function, "stages_done");
+    cg.builder
+        .build_unconditional_branch(alloc)
+        .expect("branch failed");
+
+    // Stop at the end of the list, or once any `take` is full.
+    cg.builder.position_at_end(head);
+    let i = load_int(cg, index);
+    let mut more = cg
+        .builder
+        .build_int_compare(IntPredicate::ULT, i, len, "more")
+        .expect("compare failed");
+    for (count, n) in taken.iter().flatten() {
+        let count = load_int(cg, *count);
+        let room = cg
+            .builder
+            .build_int_compare(IntPredicate::SLT, count, *n, "room")
+            .expect("compare failed");
+        more = cg
+            .builder
+            .build_and(more, room, "more")
+            .expect("and failed");
+    }
+    cg.builder
+        .build_conditional_branch(more, body, done)
+        .expect("branch failed");
+
+    // The element going through the stages is held until it is passed on.
+    cg.builder.position_at_end(body);
+    let loop_depth = cg.cleanups.len();
+    let mut item = list::get(cg, list, i);
+    unwind::push(cg, Cleanup::Value(item));
+    for (stage, limit) in stages.iter().zip(&taken) {
+        match (stage.name, stage.args) {
+            ("map", [f]) => {
+                unwind::pop_to(cg, loop_depth);
+                item = inline(cg, env, f, vec![Arg::Value(item)]);
+                unwind::push(cg, Cleanup::Value(item));
+            }
+            ("filter", [f]) => {
+                cg.retain(item);
+                let keep = inline(cg, env, f, vec![Arg::Value(item)]).into_int_value();
+                let kept = cg
+                    .builder
+                    .build_int_compare(IntPredicate::NE, keep, i64_type.const_zero(), "kept")
+                    .expect("compare failed");
+                let drop = cg.context.append_basic_block(function, "stages_drop");
+                let keep = cg.context.append_basic_block(function, "stages_keep");
+                cg.builder
+                    .build_conditional_branch(kept, keep, drop)
+                    .expect("branch failed");
+
+                cg.builder.position_at_end(drop);
+                cg.release(item);
+                cg.builder
+                    .build_unconditional_branch(next)
+                    .expect("branch failed");
+                cg.builder.position_at_end(keep);
+            }
+            ("take", _) => {
+                let (count, _) = limit.expect("`take` has a counter");
+                increment(cg, count);
+            }
+            ("len", _) => {
+                increment(cg, acc.expect("`len` has an accumulator").ptr);
+                cg.release(item);
+                unwind::pop_to(cg, loop_depth);
+            }
+            ("sum", _) => {
+                let acc = acc.expect("`sum` has an accumulator").ptr;
+                let total = cg
+                    .builder
+                    .build_int_add(load_int(cg, acc), item.into_int_value(), "sum")
+                    .expect("add failed");
+                store(cg, acc, total.into());
+            }
+            ("fold", [_, f]) => {
+                // The accumulator is passed on too, until the call is done.
+                let acc = acc.expect("`fold` has an accumulator");
+                let value = cg
+                    .builder
+                    .build_load(acc.ty, acc.ptr, "acc")
+                    .expect("load failed");
+                unwind::pop_to(cg, acc_depth);
+                let value = inline(cg, env, f, vec![Arg::Value(value), Arg::Value(item)]);
+                store(cg, acc.ptr, value);
+                unwind::push(cg, Cleanup::Slot(acc));
+            }
+            (name, _) => unreachable!("`{name}` is typechecked"),
+        }
+    }
+
+    // The element type of the list built is only known now.
+    unwind::pop_to(cg, loop_depth);
+    let elem = item.get_type();
+    out_elem.set(Some(elem));
+    if last.is_streaming() {
+        let data = cg
+            .builder
+            .build_load(cg.context.ptr_type(Default::default()), out, "out")
+            .expect("load failed")
+            .into_pointer_value();
+        let at = load_int(cg, out_len);
+        list::store(cg, data, elem, at, item);
+        increment(cg, out_len);
+    }
+    cg.builder
+        .build_unconditional_branch(next)
+        .expect("branch failed");
+
+    cg.builder.position_at_end(next);
+    increment(cg, index);
+    cg.builder
+        .build_unconditional_branch(head)
+        .expect("branch failed");
+
+    // No stage makes a list longer, so the source's length is enough.
+    cg.builder.position_at_end(alloc);
+    if last.is_streaming() {
+        let data = list::alloc(cg, len, elem);
+        store(cg, out, data.into());
+    }
+    cg.builder
+        .build_unconditional_branch(head)
+        .expect("branch failed");
+
+    cg.builder.position_at_end(done);
+    unwind::pop_to(cg, depth);
+    list::release(cg, list);
+    if last.is_streaming() {
+        let data = cg
+            .builder
+            .build_load(cg.context.ptr_type(Default::default()), out, "out")
+            .expect("load failed")
+            .into_pointer_value();
+        let out_len = load_int(cg, out_len);
+        list::from_parts(cg, elem, data, out_len, len).into()
+    } else {
+        let acc = acc.expect("a final stage that is not streaming accumulates");
+        cg.builder
+            .build_load(acc.ty, acc.ptr, "result")
+            .expect("load failed")
+    }
+}
+
+/// The length of string `s`. Consumes `s`.
+fn string_len<'ctx>(cg: &Codegen<'ctx>, s: PointerValue<'ctx>) -> IntValue<'ctx> {
+    let len_ptr = cg
+        .builder
+        .build_struct_gep(string_type(cg.context), s, STRING_LEN, "len_ptr")
+        .expect("string len gep failed");
+    let len = load_int(cg, len_ptr);
+    cg.release(s.into());
+    len
+}
+
+fn store<'ctx>(cg: &Codegen<'ctx>, ptr: PointerValue<'ctx>, value: BasicValueEnum<'ctx>) {
+    cg.builder.build_store(ptr, value).expect("store failed");
+}
+
+fn load_int<'ctx>(cg: &Codegen<'ctx>, ptr: PointerValue<'ctx>) -> IntValue<'ctx> {
+    cg.builder
+        .build_load(cg.context.i64_type(), ptr, "load")
+        .expect("load failed")
+        .into_int_value()
+}
+
+/// Adds one to the `i64` at `ptr`.
+fn increment<'ctx>(cg: &Codegen<'ctx>, ptr: PointerValue<'ctx>) {
+    let value = load_int(cg, ptr);
+    let value = cg
+        .builder
+        .build_int_add(value, cg.context.i64_type().const_int(1, false), "inc")
+        .expect("add failed");
+    store(cg, ptr, value.into());
+}
@@ -1,68 +1,204 @@
-use crate::ast::ast::Statement;
-use crate::codegen::{context::Codegen, expr::codegen_expr};
-use inkwell::values::PointerValue;
+use crate::ast::ast::{Pattern, PatternKind, Statement, StatementKind, qualify};
+use crate::codegen::{
+    context::Codegen,
+    expr::codegen_expr,
+    function::{Closure, closure},
+    tuple,
+    types::{is_tuple, list_element, record_fields, sum_name},
+};
+use crate::util::span::Span;
+use inkwell::types::BasicTypeEnum;
+use inkwell::values::{BasicValueEnum, PointerValue};
 use std::collections::HashMap;
+use std::rc::Rc;
 
+/// A stack slot holding a binding, with the type stored in it.
+#[derive(Debug, Clone, Copy)]
+pub struct Local<'ctx> {
+    pub ptr: PointerValue<'ctx>,
+    pub ty: BasicTypeEnum<'ctx>,
+}
+
+/// Expressions produce owned values: the caller holds one reference to any
+/// string they return. Bindings take over that reference and give it up
+/// when their scope ends.
+#[derive(Default)]
 pub struct LocalEnv<'ctx> {
-    pub vars: HashMap<String, PointerValue<'ctx>>,
+    pub vars: HashMap<String, Local<'ctx>>,
+    /// Functions bound with `grab`. A name is only in both maps while a
+    /// binding in `vars` hides a function.
+    pub functions: HashMap<String, Rc<Closure<'ctx>>>,
+    /// Every slot bound in this scope, including shadowed ones.
+    pub scope: Vec<Local<'ctx>>,
 }
 
 impl<'ctx> LocalEnv<'ctx> {
     pub fn new() -> Self {
         Self {
             vars: HashMap::new(),
+            functions: HashMap::new(),
+            scope: Vec::new(),
+        }
+    }
+
+    /// Releases the values of all bindings made in this scope.
+    pub fn release_scope(&mut self, cg: &Codegen<'ctx>) {
+        for local in self.scope.drain(..).rev() {
+            let value = cg
+                .builder
+                .build_load(local.ty, local.ptr, "dead")
+                .expect("load failed");
+            cg.release(value);
         }
     }
 }
 
+/// Compiles `stmt` with the facts checking it gave in `cg.facts`.
 pub fn codegen_stmt<'ctx>(cg: &mut Codegen<'ctx>, env: &mut LocalEnv<'ctx>, stmt: &Statement) {
-    match stmt {
-        Statement::Let { name, expr } => {
-            let value = codegen_expr(cg, env, expr);
-
-            let ptr = cg
-                .builder
-                .build_alloca(value.get_type(), name)
-                .expect("alloca failed");
+    if let Some(debug) = &cg.debug {
+        debug.set_location(cg.context, &cg.builder, stmt.span);
+    }
 
-            cg.builder.build_store(ptr, value).expect("store failed");
+    match &stmt.kind {
+        // A function is kept to be inlined where it is called. Its name
+        // hides the slot of an earlier binding, which is still released
+        // with the scope.
+        StatementKind::Let { name, expr, .. } => match closure(cg, env, expr) {
+            Some(function) => {
+                env.vars.remove(name);
+                env.functions.insert(name.clone(), function);
+            }
+            None => {
+                let value = codegen_expr(cg, env, expr);
+                bind(cg, env, name, value, stmt.span);
+            }
+        },
 
-            env.vars.insert(name.clone(), ptr);
+        StatementKind::Destructure { pattern, expr, .. } => {
+            let value = codegen_expr(cg, env, expr);
+            destructure(cg, env, pattern, value);
+            cg.release(value);
         }
 
-        Statement::Yell { expr } => {
+        StatementKind::Yell { expr } => {
             let value = codegen_expr(cg, env, expr);
 
-            let fmt_str = match value {
-                inkwell::values::BasicValueEnum::IntValue(_) => "%ld\n",
-                inkwell::values::BasicValueEnum::PointerValue(_) => "%s\n",
-                _ => "%ld\n",
+            let (print, args) = match value {
+                BasicValueEnum::IntValue(_) => (cg.runtime.print_int, vec![value.into()]),
+                BasicValueEnum::PointerValue(_) => (cg.runtime.print_str, vec![value.into()]),
+                BasicValueEnum::StructValue(v)
+                    if list_element(v.get_type()).is_some()
+                        || record_fields(v.get_type()).is_some()
+                        || sum_name(v.get_type()).is_some()
+                        || is_tuple(v.get_type()) =>
+                {
+                    cg.print_nested(value);
+                    let newline = cg
+                        .builder
+                        .build_global_string_ptr("\n", "newline")
+                        .expect("failed to create newline")
+                        .as_pointer_value();
+                    (cg.libc.printf, vec![newline.into()])
+                }
+                _ => (cg.runtime.print_unit, vec![]),
             };
 
-            let fmt = cg
-                .builder
-                .build_global_string_ptr(fmt_str, "fmt")
-                .expect("format string failed");
-
             cg.builder
-                .build_call(
-                    cg.printf,
-                    &[fmt.as_pointer_value().into(), value.into()],
-                    "printf_call",
-                )
-                .expect("printf call failed");
+                .build_call(print, &args, "yell")
+                .expect("print call failed");
+            cg.release(value);
+        }
+
+        StatementKind::ExprStmt(expr) | StatementKind::Toss { expr } => {
+            let value = codegen_expr(cg, env, expr);
+            cg.release(value);
         }
 
-        Statement::ExprStmt(expr) => {
-            let _ = codegen_expr(cg, env, expr);
+        // The alias shares the slot; the module's binding owns the value.
+        // Imported constructors were aliased in `cg.types` by checking it.
+        StatementKind::Use { path, name, alias } => {
+            let target = qualify(path, name);
+            if let Some(local) = env.vars.get(&target).copied() {
+                env.vars.insert(alias.clone(), local);
+            } else if let Some(function) = env.functions.get(&target).cloned() {
+                env.functions.insert(alias.clone(), function);
+            }
         }
 
-        Statement::Toss { .. } => {
-            eprintln!(
-                "codegen error: `toss` is not supported in the LLVM backend yet.\n\
-Hint: use the interpreter backend to run programs with effects."
-            );
-            std::process::exit(1);
+        // Constructors hide bindings of the same name; their slots are
+        // still released with the scope.
+        StatementKind::SumDecl { variants, .. } => {
+            for variant in variants {
+                env.vars.remove(&variant.name);
+                env.functions.remove(&variant.name);
+            }
+        }
+
+        // Tests are only run by the interpreter; types and effects only
+        // matter to the typechecker.
+        StatementKind::Test { .. }
+        | StatementKind::TypeDecl { .. }
+        | StatementKind::EffectDecl { .. } => {}
+
+        StatementKind::Assert { .. } | StatementKind::AssertEq { .. } => {
+            unreachable!("assertions only parse inside test blocks")
+        }
+    }
+}
+
+/// Binds `name` to a new slot holding `value`, which it takes over.
+fn bind<'ctx>(
+    cg: &Codegen<'ctx>,
+    env: &mut LocalEnv<'ctx>,
+    name: &str,
+    value: BasicValueEnum<'ctx>,
+    span: Span,
+) {
+    let ptr = cg.alloca(value.get_type(), name);
+
+    cg.builder.build_store(ptr, value).expect("store failed");
+
+    if let Some(debug) = &cg.debug {
+        let block = cg.builder.get_insert_block().expect("no insert block");
+        debug.declare_local(cg.context, name, ptr, value, span, block);
+    }
+
+    let local = Local {
+        ptr,
+        ty: value.get_type(),
+    };
+    env.functions.remove(name);
+    env.vars.insert(name.to_string(), local);
+    env.scope.push(local);
+}
+
+/// Binds the names in `pattern` to their own references to the parts of
+/// `value`. A value of type `Never` has no parts, so they get `Unit`.
+fn destructure<'ctx>(
+    cg: &Codegen<'ctx>,
+    env: &mut LocalEnv<'ctx>,
+    pattern: &Pattern,
+    value: BasicValueEnum<'ctx>,
+) {
+    match &pattern.kind {
+        PatternKind::Bind(name) => {
+            cg.retain(value);
+            bind(cg, env, name, value, pattern.span);
+        }
+        PatternKind::Tuple(items) => {
+            for (i, item) in items.iter().enumerate() {
+                let part = match value {
+                    BasicValueEnum::StructValue(t) if is_tuple(t.get_type()) => {
+                        tuple::item(cg, t, i as u32)
+                    }
+                    _ => cg.unit_value(),
+                };
+                destructure(cg, env, item, part);
+            }
+        }
+        PatternKind::Wildcard => {}
+        PatternKind::Int(_) | PatternKind::String(_) | PatternKind::Variant(..) => {
+            unreachable!("`grab` only takes tuple patterns")
         }
     }
 }
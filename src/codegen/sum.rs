@@ -0,0 +1,399 @@
+//! Values of sum types, see [`sum_type`]. Like lists, each sum type gets
+//! its own private helper functions to retain, release and print its
+//! values, which depend on the fields of its variants. The fields of a
+//! prelude type's variants get helpers of their own, which its payloads
+//! point to.
+
+use crate::ast::ast::unqualified;
+use crate::codegen::context::Codegen;
+use crate::codegen::list;
+use crate::codegen::types::{SUM_PAYLOAD, SUM_TAG, llvm_type, sum_name, sum_type};
+use crate::typechecker::types::{Type, VariantType};
+use inkwell::{
+    basic_block::BasicBlock,
+    types::{BasicTypeEnum, StructType},
+    values::{BasicValueEnum, FunctionValue, IntValue, PointerValue, StructValue},
+};
+
+/// Positions in the payload of a prelude type's variant of the pointers to
+/// its helpers, and of its first field.
+const FIELDS_RELEASE: u64 = 0;
+const FIELDS_PRINT: u64 = 1;
+const FIELDS: usize = 2;
+
+/// Builds variant `tag` of sum type `sum`, owning `fields`.
+pub fn build_variant<'ctx>(
+    cg: &Codegen<'ctx>,
+    sum: &str,
+    tag: usize,
+    fields: &[BasicValueEnum<'ctx>],
+) -> StructValue<'ctx> {
+    let payload = if fields.is_empty() {
+        cg.context.ptr_type(Default::default()).const_null()
+    } else {
+        let mut parts = fields.to_vec();
+        let ty = if is_generic(cg, sum) {
+            // A prelude type's fields are whatever it was given.
+            let types: Vec<_> = fields.iter().map(|field| field.get_type()).collect();
+            let ty = generic_payload(cg, &types);
+            let value_type = sum_type(cg.context, sum);
+            let release = format!("sauce_sum_fields_release.{sum}");
+            let release = list::keyed_helper(cg, &release, ty, value_type, |cg, function| {
+                emit_fields_release(cg, function, ty)
+            });
+            let print = format!("sauce_sum_fields_print.{sum}");
+            let print = list::keyed_helper(cg, &print, ty, value_type, |cg, function| {
+                emit_fields_print(cg, function, ty)
+            });
+            for helper in [print, release] {
+                parts.insert(0, helper.as_global_value().as_pointer_value().into());
+            }
+            ty
+        } else {
+            payload_type(cg, &Type::Sum(sum.to_string(), Vec::new()), tag)
+                .expect("a variant with fields")
+        };
+
+        let one = cg.context.i64_type().const_int(1, false);
+        let data = list::alloc(cg, one, ty.into());
+        let mut payload = ty.get_undef();
+        for (i, part) in parts.iter().enumerate() {
+            payload = cg
+                .builder
+                .build_insert_value(payload, *part, i as u32, "payload")
+                .expect("insert_value failed")
+                .into_struct_value();
+        }
+        list::store(cg, data, ty.into(), zero(cg), payload.into());
+        data
+    };
+
+    let tag = cg.context.i64_type().const_int(tag as u64, false);
+    let mut value = sum_type(cg.context, sum).get_undef();
+    for (index, part) in [
+        (SUM_TAG, BasicValueEnum::from(tag)),
+        (SUM_PAYLOAD, payload.into()),
+    ] {
+        value = cg
+            .builder
+            .build_insert_value(value, part, index, "variant")
+            .expect("insert_value failed")
+            .into_struct_value();
+    }
+    value
+}
+
+/// The tag of `value`.
+pub fn tag<'ctx>(cg: &Codegen<'ctx>, value: StructValue<'ctx>) -> IntValue<'ctx> {
+    cg.builder
+        .build_extract_value(value, SUM_TAG, "tag")
+        .expect("extract_value failed")
+        .into_int_value()
+}
+
+/// Field `index` of `value`, of type `ty`, which must be variant `tag`.
+/// The value is borrowed from `value`.
+pub fn field<'ctx>(
+    cg: &Codegen<'ctx>,
+    value: StructValue<'ctx>,
+    ty: &Type,
+    tag: usize,
+    index: usize,
+) -> BasicValueEnum<'ctx> {
+    let payload = payload_type(cg, ty, tag).expect("a variant with fields");
+    extract(
+        cg,
+        load_payload(cg, value, payload),
+        index + first_field(ty),
+    )
+}
+
+fn extract<'ctx>(
+    cg: &Codegen<'ctx>,
+    payload: StructValue<'ctx>,
+    index: usize,
+) -> BasicValueEnum<'ctx> {
+    cg.builder
+        .build_extract_value(payload, index as u32, "field")
+        .expect("extract_value failed")
+}
+
+pub fn retain<'ctx>(cg: &Codegen<'ctx>, value: StructValue<'ctx>) {
+    let retain = list::helper(cg, "sauce_sum_retain", value.get_type(), emit_retain);
+    cg.builder
+        .build_call(retain, &[value.into()], "")
+        .expect("sum retain call failed");
+}
+
+pub fn release<'ctx>(cg: &Codegen<'ctx>, value: StructValue<'ctx>) {
+    let release = list::helper(cg, "sauce_sum_release", value.get_type(), emit_release);
+    cg.builder
+        .build_call(release, &[value.into()], "")
+        .expect("sum release call failed");
+}
+
+/// Prints `value` like `Rect(2, 3)` or `Empty`, without a newline.
+pub fn print<'ctx>(cg: &Codegen<'ctx>, value: StructValue<'ctx>) {
+    let print = list::helper(cg, "sauce_sum_print", value.get_type(), emit_print);
+    cg.builder
+        .build_call(print, &[value.into()], "")
+        .expect("sum print call failed");
+}
+
+/// The variants of the sum type of `value`.
+fn variants<'ctx>(cg: &Codegen<'ctx>, value: StructValue<'ctx>) -> Vec<VariantType> {
+    let sum = sum_name(value.get_type()).expect("not a sum type");
+    cg.types
+        .variants(&sum)
+        .expect("sum types are declared before they are used")
+        .to_vec()
+}
+
+/// Whether `sum` is a prelude type, whose fields depend on its type
+/// arguments.
+fn is_generic(cg: &Codegen<'_>, sum: &str) -> bool {
+    cg.types.params(sum) > 0
+}
+
+/// Where the fields start in a payload of sum type `ty`.
+fn first_field(ty: &Type) -> usize {
+    match ty {
+        Type::Sum(_, args) if !args.is_empty() => FIELDS,
+        _ => 0,
+    }
+}
+
+/// The struct holding the fields of variant `tag` of sum type `ty`, or
+/// `None` if it has none.
+fn payload_type<'ctx>(cg: &Codegen<'ctx>, ty: &Type, tag: usize) -> Option<StructType<'ctx>> {
+    let Type::Sum(sum, args) = ty else {
+        unreachable!("{ty} is not a sum type");
+    };
+    let variant = &cg.types.variants(sum).expect("a declared sum type")[tag];
+    if variant.fields.is_empty() {
+        return None;
+    }
+    let fields: Vec<_> = variant
+        .fields
+        .iter()
+        .map(|field| {
+            llvm_type(cg.context, &field.substitute(args))
+                .unwrap_or_else(|| cg.unit_value().get_type())
+        })
+        .collect();
+    if args.is_empty() {
+        return Some(cg.context.struct_type(&fields, false));
+    }
+    Some(generic_payload(cg, &fields))
+}
+
+/// The payload of a prelude type's variant with `fields`.
+fn generic_payload<'ctx>(cg: &Codegen<'ctx>, fields: &[BasicTypeEnum<'ctx>]) -> StructType<'ctx> {
+    let ptr = cg.context.ptr_type(Default::default()).into();
+    let mut types = vec![ptr, ptr];
+    types.extend_from_slice(fields);
+    cg.context.struct_type(&types, false)
+}
+
+fn payload_ptr<'ctx>(cg: &Codegen<'ctx>, value: StructValue<'ctx>) -> PointerValue<'ctx> {
+    cg.builder
+        .build_extract_value(value, SUM_PAYLOAD, "payload")
+        .expect("extract_value failed")
+        .into_pointer_value()
+}
+
+fn load_payload<'ctx>(
+    cg: &Codegen<'ctx>,
+    value: StructValue<'ctx>,
+    ty: StructType<'ctx>,
+) -> StructValue<'ctx> {
+    let ptr = list::element_ptr(cg, payload_ptr(cg, value), ty.into(), zero(cg));
+    cg.builder
+        .build_load(ty, ptr, "fields")
+        .expect("load failed")
+        .into_struct_value()
+}
+
+fn zero<'ctx>(cg: &Codegen<'ctx>) -> IntValue<'ctx> {
+    cg.context.i64_type().const_zero()
+}
+
+/// Branches to `some` if `value` has a payload, and returns otherwise.
+fn return_unless_payload<'ctx>(
+    cg: &Codegen<'ctx>,
+    function: FunctionValue<'ctx>,
+    value: StructValue<'ctx>,
+) -> BasicBlock<'ctx> {
+    let none = cg
+        .builder
+        .build_is_null(payload_ptr(cg, value), "none")
+        .expect("is_null failed");
+    let some = cg.context.append_basic_block(function, "some");
+    let done = cg.context.append_basic_block(function, "done");
+    cg.builder
+        .build_conditional_branch(none, done, some)
+        .expect("branch failed");
+    cg.builder.position_at_end(done);
+    cg.builder.build_return(None).expect("return failed");
+    cg.builder.position_at_end(some);
+    done
+}
+
+/// Emits a `switch` on the tag of `value`, calling `case` in the block of
+/// each variant, which must end it. Other tags go to `default`.
+fn switch_variants<'ctx>(
+    cg: &Codegen<'ctx>,
+    function: FunctionValue<'ctx>,
+    value: StructValue<'ctx>,
+    default: BasicBlock<'ctx>,
+    case: impl Fn(usize, &VariantType),
+) {
+    let variants = variants(cg, value);
+    let blocks: Vec<_> = variants
+        .iter()
+        .map(|_| cg.context.append_basic_block(function, "variant"))
+        .collect();
+    let cases: Vec<_> = blocks
+        .iter()
+        .enumerate()
+        .map(|(tag, block)| (cg.context.i64_type().const_int(tag as u64, false), *block))
+        .collect();
+    cg.builder
+        .build_switch(tag(cg, value), default, &cases)
+        .expect("switch failed");
+
+    for (tag, (variant, block)) in variants.iter().zip(blocks).enumerate() {
+        cg.builder.position_at_end(block);
+        case(tag, variant);
+    }
+}
+
+fn emit_retain<'ctx>(cg: &Codegen<'ctx>, function: FunctionValue<'ctx>) {
+    let value = function.get_nth_param(0).unwrap().into_struct_value();
+    return_unless_payload(cg, function, value);
+    cg.builder
+        .build_call(cg.runtime.list_retain, &[payload_ptr(cg, value).into()], "")
+        .expect("list_retain call failed");
+    cg.builder.build_return(None).expect("return failed");
+}
+
+/// Drops a reference to the payload. The last one releases the fields
+/// and frees it.
+fn emit_release<'ctx>(cg: &Codegen<'ctx>, function: FunctionValue<'ctx>) {
+    let value = function.get_nth_param(0).unwrap().into_struct_value();
+    let done = return_unless_payload(cg, function, value);
+    let payload = payload_ptr(cg, value);
+    let dead = cg
+        .builder
+        .build_call(cg.runtime.list_release, &[payload.into()], "dead")
+        .expect("list_release call failed")
+        .try_as_basic_value()
+        .unwrap_basic()
+        .into_int_value();
+    let free = cg.context.append_basic_block(function, "free");
+    let fields = cg.context.append_basic_block(function, "fields");
+    cg.builder
+        .build_conditional_branch(dead, fields, done)
+        .expect("branch failed");
+
+    cg.builder.position_at_end(fields);
+    let sum = sum_name(value.get_type()).expect("not a sum type");
+    if is_generic(cg, &sum) {
+        call_fields_helper(cg, value, FIELDS_RELEASE);
+        cg.builder
+            .build_unconditional_branch(free)
+            .expect("branch failed");
+    } else {
+        let ty = Type::Sum(sum, Vec::new());
+        switch_variants(cg, function, value, free, |tag, variant| {
+            for index in 0..variant.fields.len() {
+                cg.release(field(cg, value, &ty, tag, index));
+            }
+            cg.builder
+                .build_unconditional_branch(free)
+                .expect("branch failed");
+        });
+    }
+
+    cg.builder.position_at_end(free);
+    cg.builder
+        .build_call(cg.runtime.list_free, &[payload.into()], "")
+        .expect("list_free call failed");
+    cg.builder.build_return(None).expect("return failed");
+}
+
+fn emit_print<'ctx>(cg: &Codegen<'ctx>, function: FunctionValue<'ctx>) {
+    let value = function.get_nth_param(0).unwrap().into_struct_value();
+    let done = cg.context.append_basic_block(function, "done");
+    let sum = sum_name(value.get_type()).expect("not a sum type");
+    let generic = is_generic(cg, &sum);
+    let ty = Type::Sum(sum, Vec::new());
+    switch_variants(cg, function, value, done, |tag, variant| {
+        cg.printf(unqualified(&variant.name), &[]);
+        if generic && !variant.fields.is_empty() {
+            call_fields_helper(cg, value, FIELDS_PRINT);
+        } else if !variant.fields.is_empty() {
+            for index in 0..variant.fields.len() {
+                cg.printf(if index == 0 { "(" } else { ", " }, &[]);
+                cg.print_nested(field(cg, value, &ty, tag, index));
+            }
+            cg.printf(")", &[]);
+        }
+        cg.builder
+            .build_unconditional_branch(done)
+            .expect("branch failed");
+    });
+
+    cg.builder.position_at_end(done);
+    cg.builder.build_return(None).expect("return failed");
+}
+
+/// Calls the helper at position `helper` of the payload of `value`, a
+/// prelude type's variant with fields.
+fn call_fields_helper<'ctx>(cg: &Codegen<'ctx>, value: StructValue<'ctx>, helper: u64) {
+    let ptr_type = cg.context.ptr_type(Default::default());
+    let index = cg.context.i64_type().const_int(helper, false);
+    let slot = list::element_ptr(cg, payload_ptr(cg, value), ptr_type.into(), index);
+    let function = cg
+        .builder
+        .build_load(ptr_type, slot, "helper")
+        .expect("load failed")
+        .into_pointer_value();
+    let fn_type = cg
+        .context
+        .void_type()
+        .fn_type(&[value.get_type().into()], false);
+    cg.builder
+        .build_indirect_call(fn_type, function, &[value.into()], "")
+        .expect("helper call failed");
+}
+
+/// Releases the fields of a payload of type `payload`.
+fn emit_fields_release<'ctx>(
+    cg: &Codegen<'ctx>,
+    function: FunctionValue<'ctx>,
+    payload: StructType<'ctx>,
+) {
+    let value = function.get_nth_param(0).unwrap().into_struct_value();
+    let fields = load_payload(cg, value, payload);
+    for index in FIELDS..payload.count_fields() as usize {
+        cg.release(extract(cg, fields, index));
+    }
+    cg.builder.build_return(None).expect("return failed");
+}
+
+/// Prints the fields of a payload of type `payload` like `(2, 3)`.
+fn emit_fields_print<'ctx>(
+    cg: &Codegen<'ctx>,
+    function: FunctionValue<'ctx>,
+    payload: StructType<'ctx>,
+) {
+    let value = function.get_nth_param(0).unwrap().into_struct_value();
+    let fields = load_payload(cg, value, payload);
+    for index in FIELDS..payload.count_fields() as usize {
+        cg.printf(if index == FIELDS { "(" } else { ", " }, &[]);
+        cg.print_nested(extract(cg, fields, index));
+    }
+    cg.printf(")", &[]);
+    cg.builder.build_return(None).expect("return failed");
+}
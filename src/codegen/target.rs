@@ -0,0 +1,95 @@
+use crate::codegen::error::CodegenError;
+use inkwell::{
+    OptimizationLevel,
+    memory_buffer::MemoryBuffer,
+    module::Module,
+    targets::{
+        CodeModel, FileType, InitializationConfig, RelocMode, Target, TargetMachine, TargetTriple,
+    },
+};
+use std::{fmt, str::FromStr};
+
+/// A target `sauce build --target` can compile for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompileTarget {
+    X86_64Linux,
+    Aarch64Linux,
+    /// Objects import the C library functions the runtime is built on
+    /// (`malloc`, `write`, ...), so they must be linked against a libc for
+    /// wasm32 such as wasi-libc.
+    Wasm32,
+}
+
+impl CompileTarget {
+    pub const ALL: [CompileTarget; 3] = [
+        CompileTarget::X86_64Linux,
+        CompileTarget::Aarch64Linux,
+        CompileTarget::Wasm32,
+    ];
+
+    pub fn triple(&self) -> &'static str {
+        match self {
+            CompileTarget::X86_64Linux => "x86_64-unknown-linux-gnu",
+            CompileTarget::Aarch64Linux => "aarch64-unknown-linux-gnu",
+            CompileTarget::Wasm32 => "wasm32-unknown-unknown",
+        }
+    }
+
+    fn initialize(&self) {
+        let config = InitializationConfig::default();
+        match self {
+            CompileTarget::X86_64Linux => Target::initialize_x86(&config),
+            CompileTarget::Aarch64Linux => Target::initialize_aarch64(&config),
+            CompileTarget::Wasm32 => Target::initialize_webassembly(&config),
+        }
+    }
+
+    /// Initializes the matching LLVM backend and creates a machine for it.
+    pub fn machine(&self) -> Result<TargetMachine, CodegenError> {
+        self.initialize();
+
+        let triple = TargetTriple::create(self.triple());
+        let target = Target::from_triple(&triple)
+            .map_err(|e| CodegenError::TargetInit(self.triple().to_string(), e.to_string()))?;
+
+        target
+            .create_target_machine(
+                &triple,
+                "generic",
+                "",
+                OptimizationLevel::Default,
+                RelocMode::PIC,
+                CodeModel::Default,
+            )
+            .ok_or_else(|| {
+                CodegenError::TargetInit(
+                    self.triple().to_string(),
+                    "could not create target machine".into(),
+                )
+            })
+    }
+}
+
+impl fmt::Display for CompileTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.triple())
+    }
+}
+
+impl FromStr for CompileTarget {
+    type Err = CodegenError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        CompileTarget::ALL
+            .into_iter()
+            .find(|t| t.triple() == s)
+            .ok_or_else(|| CodegenError::UnsupportedTarget(s.to_string()))
+    }
+}
+
+/// Emits `module` as an object file for `machine`.
+pub fn emit_object(module: &Module, machine: &TargetMachine) -> Result<MemoryBuffer, CodegenError> {
+    machine
+        .write_to_memory_buffer(module, FileType::Object)
+        .map_err(|e| CodegenError::Emit(e.to_string()))
+}
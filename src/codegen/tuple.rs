@@ -0,0 +1,57 @@
+//! Tuples, see [`tuple_type`]. A tuple value holds its items and owns the
+//! references they hold, like a record.
+
+use crate::codegen::context::Codegen;
+use crate::codegen::types::tuple_type;
+use inkwell::values::{BasicValueEnum, StructValue};
+
+/// Builds a tuple owning `items`.
+pub fn build_tuple<'ctx>(cg: &Codegen<'ctx>, items: &[BasicValueEnum<'ctx>]) -> StructValue<'ctx> {
+    let types: Vec<_> = items.iter().map(|item| item.get_type()).collect();
+    let mut tuple = tuple_type(cg.context, &types).get_undef();
+    for (i, item) in items.iter().enumerate() {
+        tuple = cg
+            .builder
+            .build_insert_value(tuple, *item, i as u32, "tuple")
+            .expect("insert_value failed")
+            .into_struct_value();
+    }
+    tuple
+}
+
+pub fn retain<'ctx>(cg: &Codegen<'ctx>, tuple: StructValue<'ctx>) {
+    for i in 0..tuple.get_type().count_fields() {
+        cg.retain(item(cg, tuple, i));
+    }
+}
+
+pub fn release<'ctx>(cg: &Codegen<'ctx>, tuple: StructValue<'ctx>) {
+    for i in 0..tuple.get_type().count_fields() {
+        cg.release(item(cg, tuple, i));
+    }
+}
+
+/// Prints `tuple` like `(1, "a")`, or `(1,)` with one item, without a
+/// newline.
+pub fn print<'ctx>(cg: &Codegen<'ctx>, tuple: StructValue<'ctx>) {
+    let count = tuple.get_type().count_fields();
+    cg.printf("(", &[]);
+    for i in 0..count {
+        if i > 0 {
+            cg.printf(", ", &[]);
+        }
+        cg.print_nested(item(cg, tuple, i));
+    }
+    cg.printf(if count == 1 { ",)" } else { ")" }, &[]);
+}
+
+/// Item `index` of `tuple`, borrowed from it.
+pub fn item<'ctx>(
+    cg: &Codegen<'ctx>,
+    tuple: StructValue<'ctx>,
+    index: u32,
+) -> BasicValueEnum<'ctx> {
+    cg.builder
+        .build_extract_value(tuple, index, "item")
+        .expect("extract_value failed")
+}
@@ -1,13 +1,202 @@
 use crate::typechecker::types::Type;
-use inkwell::types::BasicTypeEnum;
+use inkwell::context::Context;
+use inkwell::types::{BasicType, BasicTypeEnum, StructType};
 
-pub fn llvm_type<'ctx>(
-    ctx: &'ctx inkwell::context::Context,
-    ty: &Type,
-) -> Option<BasicTypeEnum<'ctx>> {
+/// Field indices of the string header, see [`string_type`].
+pub const STRING_REFCOUNT: u32 = 0;
+pub const STRING_LEN: u32 = 1;
+pub const STRING_DATA: u32 = 2;
+
+/// Size in bytes of the refcount and length fields before the data.
+pub const STRING_HEADER_SIZE: u64 = 16;
+
+/// Field indices of a list value, see [`list_type`].
+pub const LIST_DATA: u32 = 0;
+pub const LIST_LEN: u32 = 1;
+pub const LIST_CAP: u32 = 2;
+const LIST_ELEMENT: u32 = 3;
+
+/// Size in bytes of the refcount before a list's elements.
+pub const LIST_HEADER_SIZE: u64 = 8;
+
+pub fn llvm_type<'ctx>(ctx: &'ctx Context, ty: &Type) -> Option<BasicTypeEnum<'ctx>> {
     match ty {
         Type::Int => Some(ctx.i64_type().into()),
         Type::String => Some(ctx.ptr_type(Default::default()).into()),
         Type::Unit => None,
+        Type::List(elem) => {
+            let elem = llvm_type(ctx, elem).unwrap_or_else(|| ctx.struct_type(&[], false).into());
+            Some(list_type(ctx, elem).into())
+        }
+        Type::Record(fields) => {
+            let fields: Vec<_> = fields
+                .iter()
+                .map(|(name, ty)| {
+                    let ty =
+                        llvm_type(ctx, ty).unwrap_or_else(|| ctx.struct_type(&[], false).into());
+                    (name.as_str(), ty)
+                })
+                .collect();
+            Some(record_type(ctx, &fields).into())
+        }
+        Type::Tuple(items) => {
+            let items: Vec<_> = items
+                .iter()
+                .map(|ty| llvm_type(ctx, ty).unwrap_or_else(|| ctx.struct_type(&[], false).into()))
+                .collect();
+            Some(tuple_type(ctx, &items).into())
+        }
+        // Every `Option` shares one type, whatever it holds.
+        Type::Sum(name, _) => Some(sum_type(ctx, name).into()),
+        Type::Never => None,
+        Type::Param(_) => unreachable!("type parameters are substituted before codegen"),
+        Type::Var(_) => unreachable!("type variables are resolved before codegen"),
+        Type::Effects(..) => unreachable!("rows of effects are not the types of values"),
+        // Functions are inlined where they are called. One kept in a value
+        // is a `FunctionValue` error, and stands in as `Unit` until the
+        // statement holding it is done.
+        Type::Function(..) => None,
     }
 }
+
+/// The struct type of a record with `fields`, sorted by name.
+///
+/// Record types are named `record.age.name<i64, ptr>`: the name lists the
+/// fields, so code working on a record value can find them, and the field
+/// types keep records with the same field names apart.
+pub fn record_type<'ctx>(
+    ctx: &'ctx Context,
+    fields: &[(&str, BasicTypeEnum<'ctx>)],
+) -> StructType<'ctx> {
+    let names: Vec<_> = fields.iter().map(|(name, _)| *name).collect();
+    let types: Vec<_> = fields
+        .iter()
+        .map(|(_, ty)| ty.print_to_string().to_string())
+        .collect();
+    let name = format!("record.{}<{}>", names.join("."), types.join(", "));
+    if let Some(ty) = ctx.get_struct_type(&name) {
+        return ty;
+    }
+
+    let ty = ctx.opaque_struct_type(&name);
+    let body: Vec<_> = fields.iter().map(|(_, ty)| *ty).collect();
+    ty.set_body(&body, false);
+    ty
+}
+
+/// The field names of `ty`, in order, if it is a record type.
+pub fn record_fields(ty: StructType<'_>) -> Option<Vec<String>> {
+    let name = ty.get_name()?.to_str().ok()?;
+    let (names, _) = name.strip_prefix("record.")?.split_once('<')?;
+    Some(names.split('.').map(str::to_string).collect())
+}
+
+/// `{ items }`, an anonymous struct of a tuple's items in order.
+///
+/// Unlike a list type, it does not end in an empty array, and unlike the
+/// `{}` of `Unit`, it has fields.
+pub fn tuple_type<'ctx>(ctx: &'ctx Context, items: &[BasicTypeEnum<'ctx>]) -> StructType<'ctx> {
+    ctx.struct_type(items, false)
+}
+
+/// Whether `ty` is a tuple type.
+pub fn is_tuple(ty: StructType<'_>) -> bool {
+    ty.get_name().is_none() && ty.count_fields() > 0 && list_element(ty).is_none()
+}
+
+/// Field indices of a sum type value, see [`sum_type`].
+pub const SUM_TAG: u32 = 0;
+pub const SUM_PAYLOAD: u32 = 1;
+
+/// `{ i64 tag, ptr payload }`, a value of the sum type declared as `name`.
+///
+/// `tag` numbers the variants from 0 in declaration order. `payload` is
+/// null for a variant without fields, and otherwise points to an
+/// `{ i64 refcount, { fields } }` block, shared like a list's. The name,
+/// `sum.Shape`, lets code working on the value find its variants.
+///
+/// The fields of a prelude type's variants depend on its type arguments,
+/// so they come after pointers to the functions that release and print
+/// them: `{ i64 refcount, { ptr release, ptr print, fields } }`.
+pub fn sum_type<'ctx>(ctx: &'ctx Context, name: &str) -> StructType<'ctx> {
+    let name = format!("sum.{name}");
+    if let Some(ty) = ctx.get_struct_type(&name) {
+        return ty;
+    }
+
+    let ty = ctx.opaque_struct_type(&name);
+    ty.set_body(
+        &[
+            ctx.i64_type().into(),
+            ctx.ptr_type(Default::default()).into(),
+        ],
+        false,
+    );
+    ty
+}
+
+/// The name the sum type `ty` was declared with, if it is one.
+pub fn sum_name(ty: StructType<'_>) -> Option<String> {
+    let name = ty.get_name()?.to_str().ok()?;
+    name.strip_prefix("sum.").map(str::to_string)
+}
+
+/// Whether values of type `ty` hold references that must be released.
+/// A sum type may, depending on the variant.
+pub fn holds_references(ty: BasicTypeEnum<'_>) -> bool {
+    match ty {
+        BasicTypeEnum::PointerType(_) => true,
+        BasicTypeEnum::StructType(ty) if list_element(ty).is_some() => true,
+        BasicTypeEnum::StructType(ty) if sum_name(ty).is_some() => true,
+        BasicTypeEnum::StructType(ty) => {
+            (record_fields(ty).is_some() || is_tuple(ty))
+                && ty.get_field_types().into_iter().any(holds_references)
+        }
+        _ => false,
+    }
+}
+
+/// `{ ptr data, i64 len, i64 cap, [0 x T] }`, a `List<T>` value.
+///
+/// `data` points to an `{ i64 refcount, [cap x T] }` block on the heap,
+/// shared by every copy of the list. The empty array holds nothing; it
+/// gives each element type its own list type, so code working on a list
+/// value knows what it holds.
+pub fn list_type<'ctx>(ctx: &'ctx Context, elem: BasicTypeEnum<'ctx>) -> StructType<'ctx> {
+    let i64_type = ctx.i64_type();
+    ctx.struct_type(
+        &[
+            ctx.ptr_type(Default::default()).into(),
+            i64_type.into(),
+            i64_type.into(),
+            elem.array_type(0).into(),
+        ],
+        false,
+    )
+}
+
+/// The element type of `ty`, if it is a list type.
+pub fn list_element(ty: StructType<'_>) -> Option<BasicTypeEnum<'_>> {
+    if ty.count_fields() != LIST_ELEMENT + 1 {
+        return None;
+    }
+    match ty.get_field_type_at_index(LIST_ELEMENT)? {
+        BasicTypeEnum::ArrayType(array) if array.is_empty() => Some(array.get_element_type()),
+        _ => None,
+    }
+}
+
+/// `{ i64 refcount, i64 len, [0 x i8] data }`, the header a `String` points to.
+///
+/// A negative refcount marks a static string that is never freed.
+pub fn string_type(ctx: &Context) -> StructType<'_> {
+    let i64_type = ctx.i64_type();
+    ctx.struct_type(
+        &[
+            i64_type.into(),
+            i64_type.into(),
+            ctx.i8_type().array_type(0).into(),
+        ],
+        false,
+    )
+}
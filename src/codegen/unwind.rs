@@ -0,0 +1,243 @@
+//! `catch` and `retoss`, compiled to jumps. Every `toss` is written out
+//! in the code, since `fn`s are only ever inlined, so a `toss` inside a
+//! `catch` of its effect branches straight to the end of the `catch`. On
+//! the way it releases the references held by the expressions it cuts
+//! short, which codegen keeps on a stack of cleanups as it goes.
+
+use crate::ast::ast::Expr;
+use crate::codegen::{
+    context::Codegen,
+    expr::{build_toss, codegen_expr},
+    list,
+    stmt::{Local, LocalEnv},
+    sum,
+};
+use crate::typechecker::prelude::{ERR, OK, RESULT};
+use crate::typechecker::types::Type;
+use inkwell::{
+    IntPredicate,
+    basic_block::BasicBlock,
+    types::BasicTypeEnum,
+    values::{BasicValueEnum, PointerValue},
+};
+use std::{cell::Cell, rc::Rc};
+
+/// A reference held while the rest of an expression is compiled.
+#[derive(Clone)]
+pub enum Cleanup<'ctx> {
+    /// A value computed, but not yet used.
+    Value(BasicValueEnum<'ctx>),
+    /// A binding made inside the expression, such as a `fn` parameter.
+    Slot(Local<'ctx>),
+    /// The list a chain of stages is building: the first `len` elements of
+    /// block `data`. Their type is only known once the chain is compiled.
+    Output {
+        data: PointerValue<'ctx>,
+        len: PointerValue<'ctx>,
+        elem: Rc<Cell<Option<BasicTypeEnum<'ctx>>>>,
+    },
+}
+
+/// A `catch` being compiled.
+pub struct Catch<'ctx> {
+    effect: String,
+    /// How many cleanups belong to the expressions around it.
+    depth: usize,
+    /// The blocks the `toss`es of its effect jump from, with the cleanups
+    /// to run on the way and what they tossed.
+    tosses: Vec<(BasicBlock<'ctx>, Vec<Cleanup<'ctx>>, BasicValueEnum<'ctx>)>,
+}
+
+/// Holds on to `cleanup` until [`pop_to`] the number of cleanups before
+/// it.
+pub fn push<'ctx>(cg: &mut Codegen<'ctx>, cleanup: Cleanup<'ctx>) {
+    cg.cleanups.push(cleanup);
+}
+
+/// Lets go of the cleanups after the first `depth`.
+pub fn pop_to(cg: &mut Codegen<'_>, depth: usize) {
+    cg.cleanups.truncate(depth);
+}
+
+/// `catch effect expr`: `Ok` of its value, or `Err` of what it tossed.
+pub fn build_catch<'ctx>(
+    cg: &mut Codegen<'ctx>,
+    env: &mut LocalEnv<'ctx>,
+    effect: &str,
+    expr: &Expr,
+) -> BasicValueEnum<'ctx> {
+    cg.catches.push(Catch {
+        effect: effect.to_string(),
+        depth: cg.cleanups.len(),
+        tosses: Vec::new(),
+    });
+    let value = codegen_expr(cg, env, expr);
+    let catch = cg.catches.pop().expect("the catch pushed above");
+
+    let done = cg.context.append_basic_block(cg.current_fn, "catch_done");
+    let ok = sum::build_variant(cg, RESULT, OK, &[value]);
+    let mut incoming = vec![(ok, current_block(cg))];
+    branch(cg, done);
+
+    if !catch.tosses.is_empty() {
+        let caught = cg.context.append_basic_block(cg.current_fn, "caught");
+        let mut payloads = Vec::new();
+        for (block, cleanups, payload) in catch.tosses {
+            cg.builder.position_at_end(block);
+            for cleanup in cleanups.iter().rev() {
+                release(cg, cleanup);
+            }
+            payloads.push((payload, current_block(cg)));
+            branch(cg, caught);
+        }
+
+        cg.builder.position_at_end(caught);
+        let payload = cg
+            .builder
+            .build_phi(payloads[0].0.get_type(), "tossed")
+            .expect("phi failed");
+        for (value, block) in &payloads {
+            payload.add_incoming(&[(value, *block)]);
+        }
+        let err = sum::build_variant(cg, RESULT, ERR, &[payload.as_basic_value()]);
+        incoming.push((err, current_block(cg)));
+        branch(cg, done);
+    }
+
+    cg.builder.position_at_end(done);
+    let result = cg
+        .builder
+        .build_phi(ok.get_type(), "caught")
+        .expect("phi failed");
+    for (value, block) in &incoming {
+        result.add_incoming(&[(value, *block)]);
+    }
+    result.as_basic_value()
+}
+
+/// Jumps from a `toss` of `effect` with `payload` to the innermost
+/// `catch` of it, if there is one, and says whether there was. Codegen
+/// then goes on in a block that is never reached.
+pub fn toss_to_catch<'ctx>(
+    cg: &mut Codegen<'ctx>,
+    effect: &str,
+    payload: BasicValueEnum<'ctx>,
+) -> bool {
+    let Some(at) = cg.catches.iter().rposition(|catch| catch.effect == effect) else {
+        return false;
+    };
+    let block = cg.context.append_basic_block(cg.current_fn, "toss_caught");
+    branch(cg, block);
+    let cleanups = cg.cleanups[cg.catches[at].depth..].to_vec();
+    cg.catches[at].tosses.push((block, cleanups, payload));
+
+    let after = cg.context.append_basic_block(cg.current_fn, "after_toss");
+    cg.builder.position_at_end(after);
+    true
+}
+
+/// `retoss effect value`: the value in an `Ok` or `Some`, or else a `toss`
+/// of what is in the `Err`, or of nothing for a `None`.
+pub fn build_retoss<'ctx>(
+    cg: &mut Codegen<'ctx>,
+    env: &mut LocalEnv<'ctx>,
+    effect: &str,
+    value: &Expr,
+) -> BasicValueEnum<'ctx> {
+    let ty = cg.facts.types[&value.span].clone();
+    let Type::Sum(name, args) = &ty else {
+        unreachable!("retossing {ty} is typechecked");
+    };
+    let tossed = if name == RESULT {
+        args[1].clone()
+    } else {
+        Type::Unit
+    };
+    let value = codegen_expr(cg, env, value).into_struct_value();
+
+    // `Ok` and `Some` share a tag, as do `Err` and `None`.
+    let is_ok = cg
+        .builder
+        .build_int_compare(
+            IntPredicate::EQ,
+            sum::tag(cg, value),
+            cg.context.i64_type().const_int(OK as u64, false),
+            "is_ok",
+        )
+        .expect("compare failed");
+    let ok_block = cg.context.append_basic_block(cg.current_fn, "retoss_ok");
+    let err_block = cg.context.append_basic_block(cg.current_fn, "retoss_err");
+    cg.builder
+        .build_conditional_branch(is_ok, ok_block, err_block)
+        .expect("branch failed");
+
+    // A type argument that is `Never` has no values to take apart.
+    cg.builder.position_at_end(err_block);
+    if tossed != Type::Never {
+        let payload = match tossed {
+            Type::Unit => cg.unit_value(),
+            _ => sum::field(cg, value, &ty, ERR, 0),
+        };
+        cg.retain(payload);
+        cg.release(value.into());
+        if !toss_to_catch(cg, effect, payload) {
+            build_toss(cg, effect, Some(payload));
+            let message = cg.global_string("`retoss` cannot resume");
+            cg.builder
+                .build_call(cg.runtime.panic, &[message.into()], "")
+                .expect("panic call failed");
+        }
+    }
+    cg.builder.build_unreachable().expect("unreachable failed");
+
+    cg.builder.position_at_end(ok_block);
+    if args[0] == Type::Never {
+        cg.builder.build_unreachable().expect("unreachable failed");
+        let dead = cg.context.append_basic_block(cg.current_fn, "retoss_never");
+        cg.builder.position_at_end(dead);
+        return cg.unit_value();
+    }
+    let result = sum::field(cg, value, &ty, OK, 0);
+    cg.retain(result);
+    cg.release(value.into());
+    result
+}
+
+fn release<'ctx>(cg: &Codegen<'ctx>, cleanup: &Cleanup<'ctx>) {
+    match cleanup {
+        Cleanup::Value(value) => cg.release(*value),
+        Cleanup::Slot(local) => {
+            let value = cg
+                .builder
+                .build_load(local.ty, local.ptr, "dead")
+                .expect("load failed");
+            cg.release(value);
+        }
+        Cleanup::Output { data, len, elem } => {
+            let elem = elem
+                .get()
+                .expect("the chain is compiled before its catch ends");
+            let data = cg
+                .builder
+                .build_load(cg.context.ptr_type(Default::default()), *data, "out")
+                .expect("load failed")
+                .into_pointer_value();
+            let len = cg
+                .builder
+                .build_load(cg.context.i64_type(), *len, "out_len")
+                .expect("load failed")
+                .into_int_value();
+            list::release(cg, list::from_parts(cg, elem, data, len, len));
+        }
+    }
+}
+
+fn current_block<'ctx>(cg: &Codegen<'ctx>) -> BasicBlock<'ctx> {
+    cg.builder.get_insert_block().expect("no insert block")
+}
+
+fn branch<'ctx>(cg: &Codegen<'ctx>, to: BasicBlock<'ctx>) {
+    cg.builder
+        .build_unconditional_branch(to)
+        .expect("branch failed");
+}
@@ -0,0 +1,82 @@
+/// A stable error code and its long explanation, shown by `sauce explain`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ErrorCode {
+    pub code: &'static str,
+    pub title: &'static str,
+    pub explanation: &'static str,
+}
+
+macro_rules! codes {
+    ($($code:literal => $title:literal,)*) => {
+        /// Every error and warning code, in order. Codes are never reused.
+        pub const REGISTRY: &[ErrorCode] = &[$(ErrorCode {
+            code: $code,
+            title: $title,
+            explanation: include_str!(concat!("explanations/", $code, ".md")),
+        },)*];
+    };
+}
+
+codes! {
+    "E0001" => "invalid token",
+    "E0002" => "unterminated string",
+    "E0010" => "unexpected token",
+    "E0011" => "unexpected end of input",
+    "E0012" => "misspelled keyword",
+    "E0101" => "unknown identifier",
+    "E0102" => "unknown variable at runtime",
+    "E0201" => "pipeline into literal",
+    "E0301" => "type mismatch",
+    "E0302" => "empty list",
+    "E0303" => "wrong number of arguments",
+    "E0304" => "untyped function parameters",
+    "E0305" => "unknown type",
+    "E0306" => "duplicate field",
+    "E0307" => "unknown field",
+    "E0308" => "non-exhaustive match",
+    "E0309" => "unreachable pattern",
+    "E0310" => "duplicate variant",
+    "E0311" => "duplicate pattern binding",
+    "E0312" => "unapplied constructor",
+    "E0313" => "nothing to catch",
+    "E0314" => "prelude type redeclared",
+    "E0315" => "infinite type",
+    "E0316" => "ambiguous type",
+    "E0317" => "unknown effect",
+    "E0318" => "effect redeclared",
+    "E0401" => "unhandled effect",
+    "E0402" => "retoss cannot resume",
+    "E0501" => "assertion failed",
+    "E0601" => "module not found",
+    "E0602" => "import cycle",
+    "E0603" => "private binding",
+    "E0604" => "unknown export",
+    "E0701" => "invalid manifest",
+    "E0702" => "dependency not found",
+    "E0703" => "conflicting dependencies",
+    "E0704" => "no project",
+    "E0705" => "project already exists",
+    "E0706" => "invalid package name",
+    "E0801" => "index out of bounds",
+    "E0802" => "division by zero",
+    "E0803" => "cannot write output",
+    "E0901" => "unsupported target",
+    "E0902" => "target initialization failed",
+    "E0903" => "object emission failed",
+    "E0904" => "function used as a value",
+    "W0001" => "unused binding",
+    "W0002" => "shadowed binding",
+    "W0003" => "pipeline ignores `_`",
+    "W0004" => "discarded value",
+    "W0005" => "unreachable statement",
+    "W0006" => "unknown lint",
+    "W0007" => "duplicate use",
+}
+
+/// Looks up `code`, accepting `E0201`, `e0201` or `0201`. Bare numbers
+/// name errors; warnings need their `W`.
+pub fn lookup(code: &str) -> Option<&'static ErrorCode> {
+    REGISTRY.iter().find(|c| {
+        c.code.eq_ignore_ascii_case(code) || (c.code.starts_with('E') && &c.code[1..] == code)
+    })
+}
@@ -0,0 +1,411 @@
+use crate::ast::ast::unqualified;
+use crate::codegen::error::CodegenError;
+use crate::errors::{format::FormatError, lex::LexError, parse::ParseError};
+use crate::interpreter::error::{RunError, RuntimeError};
+use crate::lint::warning::Warning;
+use crate::modules::error::{LoadError, ModuleError};
+use crate::project::ProjectError;
+use crate::typechecker::errors::TypeError;
+use crate::util::line_index::LineIndex;
+use crate::util::span::Span;
+use serde_json::{Value, json};
+use std::{fmt, str::FromStr};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Error => f.write_str("error"),
+            Severity::Warning => f.write_str("warning"),
+        }
+    }
+}
+
+/// A secondary span with a message, shown alongside the primary one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Label {
+    pub span: Span,
+    pub message: String,
+}
+
+/// One error or warning, in a form every output format can render.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// Compiler phase that produced it: `lex`, `parse`, `typecheck`, ...
+    pub phase: &'static str,
+    pub code: Option<&'static str>,
+    pub message: String,
+    pub span: Option<Span>,
+    pub labels: Vec<Label>,
+    pub notes: Vec<String>,
+}
+
+/// How diagnostics are printed (`--error-format`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ErrorFormat {
+    #[default]
+    Human,
+    /// One JSON object per line.
+    Json,
+}
+
+impl Diagnostic {
+    pub fn error(phase: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Error,
+            phase,
+            code: None,
+            message: message.into(),
+            span: None,
+            labels: Vec::new(),
+            notes: Vec::new(),
+        }
+    }
+
+    pub fn warning(phase: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Warning,
+            ..Self::error(phase, message)
+        }
+    }
+
+    pub fn with_code(mut self, code: &'static str) -> Self {
+        self.code = Some(code);
+        self
+    }
+
+    pub fn with_span(mut self, span: Span) -> Self {
+        self.span = Some(span);
+        self
+    }
+
+    pub fn with_label(mut self, span: Span, message: impl Into<String>) -> Self {
+        self.labels.push(Label {
+            span,
+            message: message.into(),
+        });
+        self
+    }
+
+    pub fn with_note(mut self, note: impl Into<String>) -> Self {
+        self.notes.push(note.into());
+        self
+    }
+
+    pub fn render(&self, format: ErrorFormat, filename: &str, src: &str) -> String {
+        match format {
+            ErrorFormat::Human => self.render_human(filename, src),
+            ErrorFormat::Json => self.to_json(filename, src).to_string(),
+        }
+    }
+
+    /// `typecheck error: ...` followed by the location, labels and notes.
+    pub fn render_human(&self, filename: &str, src: &str) -> String {
+        let lines = LineIndex::new(src);
+        let mut out = match self.severity {
+            Severity::Error => format!("{} error", self.phase),
+            Severity::Warning => "warning".to_string(),
+        };
+        if let Some(code) = self.code {
+            out.push_str(&format!("[{code}]"));
+        }
+        out.push_str(&format!(": {}", self.message));
+
+        if let Some(span) = self.span {
+            let (line, col) = lines.line_col(span.start);
+            out.push_str(&format!("\n  --> {filename}:{line}:{col}"));
+        }
+        for label in &self.labels {
+            let (line, col) = lines.line_col(label.span.start);
+            out.push_str(&format!("\n  {line}:{col}: {}", label.message));
+        }
+        for note in &self.notes {
+            out.push_str(&format!("\n  = note: {note}"));
+        }
+        out
+    }
+
+    pub fn to_json(&self, filename: &str, src: &str) -> Value {
+        let lines = LineIndex::new(src);
+        let span = |span: Span| {
+            let (line, column) = lines.line_col(span.start);
+            let (end_line, end_column) = lines.line_col(span.end);
+            json!({
+                "file": filename,
+                "start": span.start,
+                "end": span.end,
+                "line": line,
+                "column": column,
+                "end_line": end_line,
+                "end_column": end_column,
+            })
+        };
+
+        json!({
+            "severity": self.severity.to_string(),
+            "phase": self.phase,
+            "code": self.code,
+            "message": self.message,
+            "span": self.span.map(span),
+            "labels": self
+                .labels
+                .iter()
+                .map(|label| json!({ "span": span(label.span), "message": label.message }))
+                .collect::<Vec<_>>(),
+            "notes": self.notes,
+        })
+    }
+}
+
+impl FromStr for ErrorFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "human" => Ok(ErrorFormat::Human),
+            "json" => Ok(ErrorFormat::Json),
+            _ => Err(format!(
+                "unknown error format `{s}`, expected `human` or `json`"
+            )),
+        }
+    }
+}
+
+impl From<&LexError> for Diagnostic {
+    fn from(e: &LexError) -> Self {
+        Diagnostic::error("lex", e.to_string())
+            .with_code(e.code())
+            .with_span(e.span())
+    }
+}
+
+impl From<&ParseError> for Diagnostic {
+    fn from(e: &ParseError) -> Self {
+        let diagnostic = Diagnostic::error("parse", e.to_string())
+            .with_code(e.code())
+            .with_span(e.span());
+        match e {
+            ParseError::Incomplete(_) => diagnostic.with_note("statements end with `;`"),
+            ParseError::MisspelledKeyword(_, _, keyword) => {
+                diagnostic.with_note(format!("did you mean `{keyword}`?"))
+            }
+            ParseError::UnexpectedToken(..) => diagnostic,
+        }
+    }
+}
+
+impl From<&TypeError> for Diagnostic {
+    fn from(e: &TypeError) -> Self {
+        let diagnostic = Diagnostic::error("typecheck", e.to_string())
+            .with_code(e.code())
+            .with_span(e.span());
+        match e {
+            TypeError::InvalidPipeline(span) => {
+                diagnostic.with_label(*span, "a literal ignores the value piped into it")
+            }
+            TypeError::UnknownIdent(_, name, _) if name == "_" => diagnostic
+                .with_note("`_` is only bound on the right side of a pipeline, as in `x |> _`"),
+            TypeError::UnknownIdent(_, _, Some(suggestion)) => {
+                diagnostic.with_note(format!("did you mean `{suggestion}`?"))
+            }
+            TypeError::EmptyList(span) => {
+                diagnostic.with_label(*span, "add an element so its type is known")
+            }
+            TypeError::Conflict(_, expected, _, origin) => {
+                diagnostic.with_label(*origin, format!("expected {expected} because of this"))
+            }
+            TypeError::Ambiguous(_) => {
+                diagnostic.with_note("annotate what it comes from, as in `fn(p: Person) p.name`")
+            }
+            TypeError::InfiniteType(..) => {
+                diagnostic.with_note("a value cannot be passed to or put inside itself")
+            }
+            TypeError::UnknownType(_, _, Some(suggestion))
+            | TypeError::UnknownField(_, _, _, Some(suggestion)) => {
+                diagnostic.with_note(format!("did you mean `{suggestion}`?"))
+            }
+            TypeError::DuplicateField(span, _) => {
+                diagnostic.with_label(*span, "a record has one value per field")
+            }
+            TypeError::NonExhaustive(..) => {
+                diagnostic.with_note("add arms for the missing values, or a `_` arm")
+            }
+            TypeError::UnreachablePattern(span) => {
+                diagnostic.with_label(*span, "the arms before this one match everything it does")
+            }
+            TypeError::UnappliedConstructor(_, name, arity) => {
+                let params: Vec<_> = ('a'..='z').take(*arity).map(String::from).collect();
+                let params = params.join(", ");
+                diagnostic.with_note(format!(
+                    "wrap it in a function, as in `fn({params}) {}({params})`",
+                    unqualified(name)
+                ))
+            }
+            TypeError::NothingToCatch(_, _, Some(suggestion)) => {
+                diagnostic.with_note(format!("did you mean `{suggestion}`?"))
+            }
+            TypeError::NothingToCatch(..) => diagnostic.with_note(
+                "`catch` only sees what its expression, and the functions it calls, are known to toss",
+            ),
+            TypeError::UnknownEffect(_, _, Some(suggestion)) => {
+                diagnostic.with_note(format!("did you mean `{suggestion}`?"))
+            }
+            TypeError::UnknownEffect(_, name, None) => {
+                diagnostic.with_note(format!("declare it first, as in `effect {name}(String);`"))
+            }
+            TypeError::EffectRedeclared(..) => {
+                diagnostic.with_note("give the new effect another name")
+            }
+            TypeError::PreludeType(..) => {
+                diagnostic.with_note("pick another name, or use the prelude's type")
+            }
+            TypeError::UnknownIdent(..)
+            | TypeError::Mismatch(..)
+            | TypeError::ArgumentCount(..)
+            | TypeError::ParamCount(..)
+            | TypeError::UnknownType(..)
+            | TypeError::UnknownField(..)
+            | TypeError::DuplicateVariant(..)
+            | TypeError::DuplicateBinding(..) => diagnostic,
+        }
+    }
+}
+
+impl From<&RuntimeError> for Diagnostic {
+    fn from(e: &RuntimeError) -> Self {
+        let diagnostic = Diagnostic::error("runtime", e.to_string())
+            .with_code(e.code())
+            .with_span(e.span());
+        match e {
+            RuntimeError::UnhandledEffect(..) => {
+                diagnostic.with_note("`sauce run` has no effect handlers")
+            }
+            RuntimeError::CannotResume(..) => {
+                diagnostic.with_note("catch the effect to turn what was tossed into a value")
+            }
+            RuntimeError::AssertionFailed(_) => {
+                diagnostic.with_note("`assert` fails when its value is 0")
+            }
+            RuntimeError::IndexOutOfBounds(..) => diagnostic.with_note("list indices start at 0"),
+            RuntimeError::UnknownVariable(..)
+            | RuntimeError::NotEqual(..)
+            | RuntimeError::DivisionByZero(_)
+            | RuntimeError::Output(..) => diagnostic,
+        }
+    }
+}
+
+impl From<&Warning> for Diagnostic {
+    fn from(w: &Warning) -> Self {
+        let diagnostic = Diagnostic::warning("lint", w.to_string())
+            .with_code(w.code())
+            .with_span(w.span());
+        let diagnostic = match w {
+            Warning::Shadowed(_, _, earlier) => {
+                diagnostic.with_label(*earlier, "previously bound here")
+            }
+            Warning::IgnoredPlaceholder(_) => {
+                diagnostic.with_note("the value on the left of `|>` is thrown away")
+            }
+            Warning::UnusedValue(..) => {
+                diagnostic.with_note("bind it with `grab`, or print it with `yell`")
+            }
+            Warning::DuplicateUse(_, _, first) => {
+                diagnostic.with_label(*first, "first imported here")
+            }
+            Warning::Unreachable(_, toss) => {
+                diagnostic.with_label(*toss, "the program stops at this `toss`")
+            }
+            Warning::UnknownLint(_, _, Some(lint)) => {
+                diagnostic.with_note(format!("did you mean `{lint}`?"))
+            }
+            Warning::UnusedBinding(..) | Warning::UnknownLint(..) => diagnostic,
+        };
+        diagnostic.with_note(format!("silence this with `#allow({})`", w.lint()))
+    }
+}
+
+impl From<&ModuleError> for Diagnostic {
+    fn from(e: &ModuleError) -> Self {
+        let diagnostic = Diagnostic::error("module", e.to_string())
+            .with_code(e.code())
+            .with_span(e.span());
+        match e {
+            ModuleError::Private(..) => diagnostic.with_note("mark it `pub` to export it"),
+            ModuleError::UnknownExport(_, _, _, Some(suggestion)) => {
+                diagnostic.with_note(format!("did you mean `{suggestion}`?"))
+            }
+            ModuleError::NotFound(..) => {
+                diagnostic.with_note("module paths are relative to the entry file")
+            }
+            ModuleError::Cycle(..) | ModuleError::UnknownExport(..) => diagnostic,
+        }
+    }
+}
+
+impl From<&ProjectError> for Diagnostic {
+    fn from(e: &ProjectError) -> Self {
+        let diagnostic = Diagnostic::error("project", e.to_string()).with_code(e.code());
+        match e {
+            ProjectError::NoProject => {
+                diagnostic.with_note("pass a file name, or create a project with `sauce new`")
+            }
+            ProjectError::InvalidName(..) => {
+                diagnostic.with_note("package names are identifiers, like `my_app`")
+            }
+            ProjectError::Invalid(..)
+            | ProjectError::MissingDependency(..)
+            | ProjectError::ConflictingDependency(..)
+            | ProjectError::AlreadyExists(..) => diagnostic,
+        }
+    }
+}
+
+impl From<&RunError> for Diagnostic {
+    fn from(e: &RunError) -> Self {
+        match e {
+            RunError::Type(e) => e.into(),
+            RunError::Runtime(e) => e.into(),
+        }
+    }
+}
+
+impl From<&LoadError> for Diagnostic {
+    fn from(e: &LoadError) -> Self {
+        match e {
+            LoadError::Lex(e) => e.into(),
+            LoadError::Parse(e) => e.into(),
+            LoadError::Module(e) => e.into(),
+        }
+    }
+}
+
+impl From<&CodegenError> for Diagnostic {
+    fn from(e: &CodegenError) -> Self {
+        if let CodegenError::Type(e) = e {
+            return e.into();
+        }
+        let diagnostic = Diagnostic::error("codegen", e.to_string()).with_code(e.code());
+        match e {
+            CodegenError::FunctionValue(span) => diagnostic.with_span(*span).with_note(
+                "compiled functions are inlined where they are called; `sauce run` runs this",
+            ),
+            _ => diagnostic,
+        }
+    }
+}
+
+impl From<&FormatError> for Diagnostic {
+    fn from(e: &FormatError) -> Self {
+        match e {
+            FormatError::Lex(e) => e.into(),
+            FormatError::Parse(e) => e.into(),
+        }
+    }
+}
@@ -0,0 +1,11 @@
+use crate::errors::{lex::LexError, parse::ParseError};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum FormatError {
+    #[error("lex error: {0}")]
+    Lex(#[from] LexError),
+
+    #[error("parse error: {0}")]
+    Parse(#[from] ParseError),
+}
@@ -3,9 +3,24 @@ use thiserror::Error;
 
 #[derive(Debug, Error, PartialEq)]
 pub enum LexError {
-    #[error("invalid token at {0:?}")]
+    #[error("invalid token")]
     InvalidToken(Span),
 
-    #[error("unterminated string starting at {0:?}")]
+    #[error("unterminated string")]
     UnterminatedString(Span),
 }
+
+impl LexError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            LexError::InvalidToken(_) => "E0001",
+            LexError::UnterminatedString(_) => "E0002",
+        }
+    }
+
+    pub fn span(&self) -> Span {
+        match self {
+            LexError::InvalidToken(span) | LexError::UnterminatedString(span) => *span,
+        }
+    }
+}
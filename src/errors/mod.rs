@@ -1,2 +1,5 @@
+pub mod codes;
+pub mod diagnostic;
+pub mod format;
 pub mod lex;
 pub mod parse;
@@ -3,12 +3,32 @@ use thiserror::Error;
 
 #[derive(Debug, Error)]
 pub enum ParseError {
-    #[error("unexpected token at {0:?}")]
-    UnexpectedToken(Span),
+    #[error("unexpected {1}")]
+    UnexpectedToken(Span, String),
 
-    #[error("Incomplete input at {0:?}")]
+    #[error("unexpected end of input")]
     Incomplete(Span),
 
-    #[error("generic parse error: {0}")]
-    Generic(String),
+    /// An identifier starting a statement that is one typo away from a
+    /// keyword, e.g. `yel x;`.
+    #[error("`{1}` is not a keyword")]
+    MisspelledKeyword(Span, String, &'static str),
+}
+
+impl ParseError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            ParseError::UnexpectedToken(..) => "E0010",
+            ParseError::Incomplete(_) => "E0011",
+            ParseError::MisspelledKeyword(..) => "E0012",
+        }
+    }
+
+    pub fn span(&self) -> Span {
+        match self {
+            ParseError::UnexpectedToken(span, _)
+            | ParseError::Incomplete(span)
+            | ParseError::MisspelledKeyword(span, _, _) => *span,
+        }
+    }
 }
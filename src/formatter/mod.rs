@@ -0,0 +1,24 @@
+pub mod printer;
+
+pub use printer::{MAX_WIDTH, Printer};
+
+use crate::ast::ast::Ast;
+use crate::errors::format::FormatError;
+use crate::lexer::{Lexer, SpannedToken, Token};
+use crate::parser::SauceParser;
+
+/// Formats `src` into canonical Sauce, keeping its comments.
+pub fn format_source(src: &str) -> Result<String, FormatError> {
+    let tokens = Lexer::lossless(src).collect::<Result<Vec<_>, _>>()?;
+    let (comments, code): (Vec<SpannedToken>, Vec<SpannedToken>) = tokens
+        .into_iter()
+        .partition(|t| matches!(t.token, Token::Comment(_)));
+
+    let ast = SauceParser::new().parse(&code)?;
+    Ok(Printer::new(src, comments).print(&ast))
+}
+
+/// Prints `ast` as canonical Sauce.
+pub fn print_ast(ast: &Ast) -> String {
+    Printer::new("", Vec::new()).print(ast)
+}
@@ -0,0 +1,678 @@
+use crate::ast::ast::{
+    Ast, BinOp, Expr, ExprKind, Param, Pattern, PatternKind, Statement, StatementKind, TypeExpr,
+    TypeExprKind, Variant,
+};
+use crate::lexer::{SpannedToken, Token};
+use std::collections::VecDeque;
+
+/// Statements longer than this are broken across lines.
+pub const MAX_WIDTH: usize = 80;
+
+const INDENT: &str = "    ";
+
+/// Prints an `Ast` as canonical Sauce.
+///
+/// Comments are not part of the `Ast`; they are passed in as tokens and
+/// placed back by comparing their spans with the statements around them.
+pub struct Printer<'src> {
+    src: &'src str,
+    comments: VecDeque<SpannedToken>,
+    out: String,
+    /// End of the last statement or comment written, in the source.
+    last_end: Option<usize>,
+}
+
+impl<'src> Printer<'src> {
+    pub fn new(src: &'src str, comments: Vec<SpannedToken>) -> Self {
+        Self {
+            src,
+            comments: comments.into(),
+            out: String::new(),
+            last_end: None,
+        }
+    }
+
+    pub fn print(mut self, ast: &Ast) -> String {
+        for stmt in &ast.items {
+            self.statement(stmt, "");
+        }
+        self.own_line_comments(usize::MAX, "");
+        self.out
+    }
+
+    fn statement(&mut self, stmt: &Statement, indent: &str) {
+        // Attributes come before the statement's own span.
+        let start = stmt.allow.first().map_or(stmt.span.start, |a| a.span.start);
+        self.own_line_comments(start, indent);
+        self.blank_line_before(start);
+
+        if !stmt.allow.is_empty() {
+            let lints: Vec<_> = stmt.allow.iter().map(|a| a.lint.as_str()).collect();
+            self.out
+                .push_str(&format!("{indent}#allow({})\n", lints.join(", ")));
+        }
+
+        match &stmt.kind {
+            StatementKind::Let { name, ty, expr } => {
+                let head = if stmt.public { "pub grab" } else { "grab" };
+                let ty = annotation_to_string(ty.as_ref());
+                self.expr_statement(&format!("{head} {name}{ty} = "), expr, indent)
+            }
+            StatementKind::Destructure { pattern, ty, expr } => {
+                let head = if stmt.public { "pub grab" } else { "grab" };
+                let pattern = pattern_to_string(pattern);
+                let ty = annotation_to_string(ty.as_ref());
+                self.expr_statement(&format!("{head} {pattern}{ty} = "), expr, indent)
+            }
+            StatementKind::TypeDecl { name, ty } => {
+                self.own_line_comments(stmt.span.end, indent);
+                let head = if stmt.public { "pub type" } else { "type" };
+                let line = format!("{indent}{head} {name} = {};", type_to_string(ty));
+                match &ty.kind {
+                    TypeExprKind::Record(fields) if line.len() > MAX_WIDTH => {
+                        self.out.push_str(&format!("{indent}{head} {name} = {{\n"));
+                        for (field, ty) in fields {
+                            self.out.push_str(&format!(
+                                "{indent}{INDENT}{field}: {},\n",
+                                type_to_string(ty)
+                            ));
+                        }
+                        self.out.push_str(&format!("{indent}}};"));
+                    }
+                    _ => self.out.push_str(&line),
+                }
+            }
+            StatementKind::SumDecl { name, variants } => {
+                self.own_line_comments(stmt.span.end, indent);
+                let head = if stmt.public { "pub type" } else { "type" };
+                self.out.push_str(&format!(
+                    "{indent}{head} {name} = {};",
+                    variants_to_string(variants)
+                ));
+            }
+            StatementKind::EffectDecl { name, payload } => {
+                self.own_line_comments(stmt.span.end, indent);
+                let payload = payload
+                    .as_ref()
+                    .map_or_else(String::new, |ty| format!("({})", type_to_string(ty)));
+                self.out
+                    .push_str(&format!("{indent}effect {name}{payload};"));
+            }
+            StatementKind::Use { path, name, .. } => {
+                self.own_line_comments(stmt.span.end, indent);
+                self.out
+                    .push_str(&format!("{indent}use {}::{name};", path.join("::")));
+            }
+            StatementKind::Yell { expr } => self.expr_statement("yell ", expr, indent),
+            StatementKind::Assert { expr } => self.expr_statement("assert ", expr, indent),
+            StatementKind::ExprStmt(expr) | StatementKind::Toss { expr } => {
+                self.expr_statement("", expr, indent)
+            }
+            StatementKind::AssertEq { left, right } => {
+                self.own_line_comments(stmt.span.end, indent);
+                self.out.push_str(&format!(
+                    "{indent}assert_eq {}, {};",
+                    expr_to_string(left),
+                    expr_to_string(right)
+                ));
+            }
+            StatementKind::Test { name, body } => self.test(stmt, name, body, indent),
+        }
+
+        let line_end = self.line_end(stmt.span.end);
+        self.trailing_comment(stmt.span.end, line_end);
+        self.out.push('\n');
+        // Anything left inside the statement, e.g. before its `;`.
+        self.own_line_comments(stmt.span.end, indent);
+        self.last_end = Some(self.last_end.unwrap_or(0).max(stmt.span.end));
+    }
+
+    /// A statement made of `head` and one expression, without its newline.
+    fn expr_statement(&mut self, head: &str, expr: &Expr, indent: &str) {
+        self.own_line_comments(expr.span.start, indent);
+        self.out.push_str(indent);
+        self.out.push_str(head);
+        self.layout(expr, indent, 1);
+        self.out.push(';');
+    }
+
+    /// Writes `expr` where the current line has got to: on that line if
+    /// it fits there with `tail` more characters after it, and otherwise
+    /// broken across lines indented from `indent`. Only what comes last
+    /// breaks, so the rest stays on the first line, as in
+    /// `xs |> map(fn(x) match x {`.
+    ///
+    /// Comments inside `expr` stay where they are: it breaks around them,
+    /// or is copied from the source where it cannot.
+    fn layout(&mut self, expr: &Expr, indent: &str, tail: usize) {
+        let flat = expr_to_string(expr);
+        if !self.next_comment_before(expr.span.end)
+            && self.column() + flat.len() + tail <= MAX_WIDTH
+        {
+            self.out.push_str(&flat);
+            return;
+        }
+
+        match &expr.kind {
+            ExprKind::Match { scrutinee, arms }
+                if !self.next_comment_before(scrutinee.span.end) =>
+            {
+                let arms: Vec<_> = arms
+                    .iter()
+                    .map(|arm| {
+                        let pattern = format!("{} => ", pattern_to_string(&arm.pattern));
+                        (arm.pattern.span.start, pattern, &arm.body)
+                    })
+                    .collect();
+                let open = format!("match {} {{", expr_to_string(scrutinee));
+                self.items(&open, scrutinee.span.end, &arms, "}", expr.span.end, indent);
+            }
+            ExprKind::Record(fields) => {
+                let fields = field_items(fields);
+                self.items("{", expr.span.start, &fields, "}", expr.span.end, indent);
+            }
+            ExprKind::Update { record, fields } if !self.next_comment_before(record.span.end) => {
+                let fields = field_items(fields);
+                let open = format!("{{ {} with", expr_to_string(record));
+                self.items(&open, record.span.end, &fields, "}", expr.span.end, indent);
+            }
+            ExprKind::List(items) => {
+                let items: Vec<_> = items
+                    .iter()
+                    .map(|item| (item.span.start, String::new(), item))
+                    .collect();
+                self.items("[", expr.span.start, &items, "]", expr.span.end, indent);
+            }
+            ExprKind::Pipeline(..) => self.pipeline(expr, indent, tail),
+            ExprKind::Lambda { params, ret, body }
+                if !self.next_comment_before(body.span.start) =>
+            {
+                self.out.push_str(&lambda_head(params, ret.as_ref()));
+                self.layout(body, indent, tail);
+            }
+            ExprKind::Call(callee, args)
+                if args
+                    .last()
+                    .is_some_and(|last| !self.next_comment_before(last.span.start)) =>
+            {
+                let (last, rest) = args.split_last().expect("checked above");
+                self.out.push_str(&target_to_string(callee));
+                self.out.push('(');
+                for arg in rest {
+                    self.out.push_str(&expr_to_string(arg));
+                    self.out.push_str(", ");
+                }
+                self.layout(last, indent, tail + 1);
+                self.out.push(')');
+            }
+            ExprKind::Toss {
+                effect,
+                arg: Some(arg),
+            } if !self.next_comment_before(arg.span.start) => {
+                self.out.push_str(&format!("toss {effect} "));
+                self.layout(arg, indent, tail);
+            }
+            ExprKind::Catch { effect, expr } if !self.next_comment_before(expr.span.start) => {
+                self.out.push_str(&format!("catch {effect} "));
+                self.layout(expr, indent, tail);
+            }
+            ExprKind::Retoss { effect, expr } if !self.next_comment_before(expr.span.start) => {
+                self.out.push_str(&format!("retoss {effect} "));
+                self.layout(expr, indent, tail);
+            }
+            _ if self.next_comment_before(expr.span.end) => self.verbatim(expr),
+            _ => self.out.push_str(&flat),
+        }
+    }
+
+    /// Copies `expr` from the source with the comments inside it, so that
+    /// no line with a comment gets joined to the next.
+    fn verbatim(&mut self, expr: &Expr) {
+        self.out.push_str(&self.src[expr.span.start..expr.span.end]);
+        while self.next_comment_before(expr.span.end) {
+            self.comments.pop_front();
+        }
+        self.last_end = Some(expr.span.end);
+    }
+
+    /// Writes `open`, then each item on a line of its own as its prefix and
+    /// expression with a trailing comma, then `close` back at `indent`.
+    /// Items start at their offset in the source; `start` and `end` are
+    /// where the first one can begin and the last one must end.
+    fn items(
+        &mut self,
+        open: &str,
+        start: usize,
+        items: &[(usize, String, &Expr)],
+        close: &str,
+        end: usize,
+        indent: &str,
+    ) {
+        let inner = format!("{indent}{INDENT}");
+        self.out.push_str(open);
+        self.out.push('\n');
+        self.last_end = Some(start);
+        for (i, (item_start, prefix, expr)) in items.iter().enumerate() {
+            self.own_line_comments(expr.span.start, &inner);
+            self.blank_line_before(*item_start);
+            self.out.push_str(&inner);
+            self.out.push_str(prefix);
+            self.layout(expr, &inner, 1);
+            self.out.push(',');
+            let next = items.get(i + 1).map_or(end, |(next, ..)| *next);
+            self.trailing_comment(expr.span.end, next);
+            self.out.push('\n');
+            self.last_end = Some(self.last_end.unwrap_or(0).max(expr.span.end));
+        }
+        self.own_line_comments(end, &inner);
+        self.out.push_str(indent);
+        self.out.push_str(close);
+    }
+
+    /// Writes a pipeline that does not fit on one line. Its last stage
+    /// hangs off the line if that is enough, and otherwise each stage
+    /// after the first goes on a line of its own.
+    fn pipeline(&mut self, expr: &Expr, indent: &str, tail: usize) {
+        let stages = pipeline_stages(expr);
+        let (last, rest) = stages.split_last().expect("a pipeline has stages");
+
+        if !self.next_comment_before(last.span.start) {
+            let saved = (self.out.len(), self.comments.clone(), self.last_end);
+            let line_start = self.out.rfind('\n').map_or(0, |i| i + 1);
+            for stage in rest {
+                self.out.push_str(&operand(stage, false));
+                self.out.push_str(" |> ");
+            }
+            self.operand(last, indent, tail, true);
+            let first_line = self.out[line_start..].lines().next().unwrap_or_default();
+            if first_line.len() <= MAX_WIDTH && self.out[saved.0..].contains('\n') {
+                return;
+            }
+            (self.comments, self.last_end) = (saved.1, saved.2);
+            self.out.truncate(saved.0);
+        }
+
+        let stage_indent = format!("{indent}{INDENT}");
+        self.operand(stages[0], indent, 0, stages.len() == 1);
+        let mut prev_end = stages[0].span.end;
+
+        for (i, stage) in stages.iter().enumerate().skip(1) {
+            let last = i == stages.len() - 1;
+            self.last_end = Some(prev_end);
+            self.trailing_comment(prev_end, stage.span.start);
+            self.out.push('\n');
+            self.own_line_comments(stage.span.start, &stage_indent);
+            self.out.push_str(&stage_indent);
+            self.out.push_str("|> ");
+            self.operand(stage, &stage_indent, if last { tail } else { 0 }, last);
+            prev_end = stage.span.end;
+        }
+    }
+
+    /// Writes a pipeline stage with [`layout`](Self::layout), adding the
+    /// parentheses the parser needs.
+    fn operand(&mut self, expr: &Expr, indent: &str, tail: usize, last: bool) {
+        if operand_parens(expr, last) {
+            self.out.push('(');
+            self.layout(expr, indent, tail + 1);
+            self.out.push(')');
+        } else {
+            self.layout(expr, indent, tail);
+        }
+    }
+
+    /// How far the current line has got.
+    fn column(&self) -> usize {
+        self.out.len() - self.out.rfind('\n').map_or(0, |i| i + 1)
+    }
+
+    /// `test "name" { ... }` with its body indented, without its newline.
+    fn test(&mut self, stmt: &Statement, name: &str, body: &[Statement], indent: &str) {
+        // The closing `}` is the last character of the block.
+        let close = stmt.span.end - 1;
+        self.out.push_str(&format!("{indent}test \"{name}\" {{"));
+
+        if body.is_empty() && !self.next_comment_before(close) {
+            self.out.push('}');
+            return;
+        }
+
+        self.out.push('\n');
+        self.last_end = Some(stmt.span.start);
+        let inner = format!("{indent}{INDENT}");
+        for stmt in body {
+            self.statement(stmt, &inner);
+        }
+        self.own_line_comments(close, &inner);
+        self.out.push_str(indent);
+        self.out.push('}');
+    }
+
+    fn next_comment_before(&self, pos: usize) -> bool {
+        self.comments.front().is_some_and(|c| c.span.start < pos)
+    }
+
+    /// Writes every comment before `pos` on a line of its own.
+    fn own_line_comments(&mut self, pos: usize, indent: &str) {
+        while self.next_comment_before(pos) {
+            let comment = self.comments.pop_front().expect("checked above");
+            self.blank_line_before(comment.span.start);
+            self.out.push_str(indent);
+            self.out.push_str(comment_text(&comment));
+            self.out.push('\n');
+            self.last_end = Some(comment.span.end);
+        }
+    }
+
+    /// Appends the next comment to the current line if it starts after
+    /// `from` on the same source line and before `until`.
+    fn trailing_comment(&mut self, from: usize, until: usize) {
+        let Some(comment) = self.comments.front() else {
+            return;
+        };
+        let same_line = self
+            .src
+            .get(from..comment.span.start)
+            .is_some_and(|gap| !gap.contains('\n'));
+
+        if comment.span.start < until && same_line {
+            let comment = self.comments.pop_front().expect("checked above");
+            self.out.push(' ');
+            self.out.push_str(comment_text(&comment));
+            self.last_end = Some(comment.span.end);
+        }
+    }
+
+    /// Keeps one blank line where the source had at least one.
+    fn blank_line_before(&mut self, start: usize) {
+        let Some(end) = self.last_end else {
+            return;
+        };
+        let blank = self
+            .src
+            .get(end..start)
+            .is_some_and(|gap| gap.matches('\n').count() >= 2);
+
+        // Never directly inside a `{` or `[`.
+        let opened = self.out.ends_with("{\n") || self.out.ends_with("[\n");
+        if blank && !self.out.ends_with("\n\n") && !opened {
+            self.out.push('\n');
+        }
+    }
+
+    /// Offset of the end of the source line containing `pos`.
+    fn line_end(&self, pos: usize) -> usize {
+        self.src
+            .get(pos..)
+            .and_then(|rest| rest.find('\n'))
+            .map_or(self.src.len().max(pos), |i| pos + i)
+    }
+}
+
+fn comment_text(comment: &SpannedToken) -> &str {
+    match &comment.token {
+        Token::Comment(text) => text,
+        other => unreachable!("expected a comment token, got {other:?}"),
+    }
+}
+
+/// Prints `expr` on a single line.
+pub fn expr_to_string(expr: &Expr) -> String {
+    match &expr.kind {
+        ExprKind::Int(n) => n.to_string(),
+        ExprKind::Ident(name) => name.clone(),
+        ExprKind::String(s) => format!("\"{s}\""),
+        ExprKind::Toss { effect, arg } => match arg {
+            Some(arg) => format!("toss {effect} {}", expr_to_string(arg)),
+            None => format!("toss {effect}"),
+        },
+        ExprKind::Catch { effect, expr } => format!("catch {effect} {}", expr_to_string(expr)),
+        ExprKind::Retoss { effect, expr } => format!("retoss {effect} {}", expr_to_string(expr)),
+        ExprKind::List(items) => {
+            let items: Vec<_> = items.iter().map(expr_to_string).collect();
+            format!("[{}]", items.join(", "))
+        }
+        ExprKind::Tuple(items) => tuple_to_string(items.iter().map(expr_to_string).collect()),
+        ExprKind::Index(target, index) => {
+            format!("{}[{}]", target_to_string(target), expr_to_string(index))
+        }
+        ExprKind::Call(callee, args) => {
+            let args: Vec<_> = args.iter().map(expr_to_string).collect();
+            format!("{}({})", target_to_string(callee), args.join(", "))
+        }
+        ExprKind::Lambda { params, ret, body } => {
+            format!(
+                "{}{}",
+                lambda_head(params, ret.as_ref()),
+                expr_to_string(body)
+            )
+        }
+        ExprKind::Binary(op, left, right) => {
+            let level = precedence(expr);
+            // Comparisons do not chain, the others are left-associative.
+            let left_parens = precedence(left) < level
+                || (op.is_comparison() && precedence(left) == level)
+                || greedy(left);
+            let right_parens = precedence(right) <= level || greedy(right);
+            format!(
+                "{} {} {}",
+                parenthesize(left, left_parens),
+                op.symbol(),
+                parenthesize(right, right_parens)
+            )
+        }
+        ExprKind::Record(fields) => format!("{{ {} }}", fields_to_string(fields)),
+        ExprKind::Field(record, name) => format!("{}.{name}", target_to_string(record)),
+        ExprKind::Update { record, fields } => format!(
+            "{{ {} with {} }}",
+            expr_to_string(record),
+            fields_to_string(fields)
+        ),
+        ExprKind::Match { scrutinee, arms } => {
+            let arms: Vec<_> = arms
+                .iter()
+                .map(|arm| {
+                    format!(
+                        "{} => {}",
+                        pattern_to_string(&arm.pattern),
+                        expr_to_string(&arm.body)
+                    )
+                })
+                .collect();
+            format!(
+                "match {} {{ {} }}",
+                expr_to_string(scrutinee),
+                arms.join(", ")
+            )
+        }
+        ExprKind::Pipeline(..) => {
+            let stages = pipeline_stages(expr);
+            let last = stages.len() - 1;
+            stages
+                .iter()
+                .enumerate()
+                .map(|(i, stage)| operand(stage, i == last))
+                .collect::<Vec<_>>()
+                .join(" |> ")
+        }
+    }
+}
+
+/// `fn(params) -> ret `, what comes before a `fn`'s body.
+fn lambda_head(params: &[Param], ret: Option<&TypeExpr>) -> String {
+    let params: Vec<_> = params
+        .iter()
+        .map(|param| format!("{}{}", param.name, annotation_to_string(param.ty.as_ref())))
+        .collect();
+    let ret = match ret {
+        Some(ret) => format!(" -> {}", type_to_string(ret)),
+        None => String::new(),
+    };
+    format!("fn({}){ret} ", params.join(", "))
+}
+
+/// `(a, b)`, or `(a,)` for a single item, telling it from parentheses.
+fn tuple_to_string(items: Vec<String>) -> String {
+    match items.as_slice() {
+        [item] => format!("({item},)"),
+        items => format!("({})", items.join(", ")),
+    }
+}
+
+/// A record's fields as [`items`](Printer::items). A field starts at its
+/// value, as the `Ast` keeps no span for its name.
+fn field_items(fields: &[(String, Expr)]) -> Vec<(usize, String, &Expr)> {
+    fields
+        .iter()
+        .map(|(name, value)| (value.span.start, format!("{name}: "), value))
+        .collect()
+}
+
+/// `name: value, ...`, the fields of a record or an update.
+fn fields_to_string(fields: &[(String, Expr)]) -> String {
+    let fields: Vec<_> = fields
+        .iter()
+        .map(|(name, value)| format!("{name}: {}", expr_to_string(value)))
+        .collect();
+    fields.join(", ")
+}
+
+/// Prints a type as written in the source.
+pub fn type_to_string(ty: &TypeExpr) -> String {
+    match &ty.kind {
+        TypeExprKind::Named(name, args) if args.is_empty() => name.clone(),
+        TypeExprKind::Named(name, args) => {
+            let args: Vec<_> = args.iter().map(type_to_string).collect();
+            format!("{name}<{}>", args.join(", "))
+        }
+        TypeExprKind::Record(fields) => {
+            let fields: Vec<_> = fields
+                .iter()
+                .map(|(name, ty)| format!("{name}: {}", type_to_string(ty)))
+                .collect();
+            format!("{{ {} }}", fields.join(", "))
+        }
+        TypeExprKind::Tuple(items) => tuple_to_string(items.iter().map(type_to_string).collect()),
+        TypeExprKind::Function(params, ret) => {
+            let params: Vec<_> = params.iter().map(type_to_string).collect();
+            format!("fn({}) -> {}", params.join(", "), type_to_string(ret))
+        }
+    }
+}
+
+/// `: ty` after a name, or nothing without an annotation.
+fn annotation_to_string(ty: Option<&TypeExpr>) -> String {
+    ty.map(|ty| format!(": {}", type_to_string(ty)))
+        .unwrap_or_default()
+}
+
+/// Prints the variants of a sum type. A lone variant without fields
+/// keeps its leading `|`, which tells it from a type name.
+fn variants_to_string(variants: &[Variant]) -> String {
+    let printed: Vec<_> = variants
+        .iter()
+        .map(|variant| {
+            if variant.fields.is_empty() {
+                return variant.name.clone();
+            }
+            let fields: Vec<_> = variant.fields.iter().map(type_to_string).collect();
+            format!("{}({})", variant.name, fields.join(", "))
+        })
+        .collect();
+    match variants {
+        [only] if only.fields.is_empty() => format!("| {}", only.name),
+        _ => printed.join(" | "),
+    }
+}
+
+/// Prints a pattern as written in the source.
+pub fn pattern_to_string(pattern: &Pattern) -> String {
+    match &pattern.kind {
+        PatternKind::Wildcard => "_".to_string(),
+        PatternKind::Bind(name) => name.clone(),
+        PatternKind::Int(n) => n.to_string(),
+        PatternKind::String(s) => format!("\"{s}\""),
+        PatternKind::Variant(name, args) if args.is_empty() => name.clone(),
+        PatternKind::Variant(name, args) => {
+            let args: Vec<_> = args.iter().map(pattern_to_string).collect();
+            format!("{name}({})", args.join(", "))
+        }
+        PatternKind::Tuple(items) => tuple_to_string(items.iter().map(pattern_to_string).collect()),
+    }
+}
+
+/// The stages of a left-nested pipeline, `a |> b |> c` gives `[a, b, c]`.
+fn pipeline_stages(expr: &Expr) -> Vec<&Expr> {
+    match &expr.kind {
+        ExprKind::Pipeline(lhs, rhs) => {
+            let mut stages = pipeline_stages(lhs);
+            stages.push(rhs);
+            stages
+        }
+        _ => vec![expr],
+    }
+}
+
+/// Prints a pipeline stage, adding the parentheses the parser needs.
+///
+/// A nested pipeline or a comparison needs them to stay a single stage,
+/// and a `toss` with an argument or a `fn` would otherwise swallow the
+/// stages after it.
+fn operand(expr: &Expr, last: bool) -> String {
+    parenthesize(expr, operand_parens(expr, last))
+}
+
+fn operand_parens(expr: &Expr, last: bool) -> bool {
+    precedence(expr) <= PIPE || (greedy(expr) && !last)
+}
+
+/// Prints what is indexed, called or has a field read. `toss e xs[0]`
+/// would index the payload, and `a |> b[0]` the stage.
+fn target_to_string(expr: &Expr) -> String {
+    let needs_parens = matches!(
+        expr.kind,
+        ExprKind::Pipeline(..)
+            | ExprKind::Toss { .. }
+            | ExprKind::Catch { .. }
+            | ExprKind::Retoss { .. }
+            | ExprKind::Lambda { .. }
+            | ExprKind::Binary(..)
+    );
+    parenthesize(expr, needs_parens)
+}
+
+fn parenthesize(expr: &Expr, needs_parens: bool) -> String {
+    if needs_parens {
+        format!("({})", expr_to_string(expr))
+    } else {
+        expr_to_string(expr)
+    }
+}
+
+/// Precedence of `|>`, between comparisons and arithmetic.
+const PIPE: u8 = 2;
+
+/// How tightly `expr` holds together; tighter expressions can be operands
+/// of looser ones without parentheses.
+fn precedence(expr: &Expr) -> u8 {
+    match &expr.kind {
+        ExprKind::Binary(op, ..) if op.is_comparison() => 1,
+        ExprKind::Pipeline(..) => PIPE,
+        ExprKind::Binary(BinOp::Add | BinOp::Sub, ..) => 3,
+        ExprKind::Binary(..) => 4,
+        _ => 5,
+    }
+}
+
+/// Whether `expr` ends in a `toss` argument, a `fn` body or what a
+/// `catch` or `retoss` wraps, which extend as far right as they can.
+fn greedy(expr: &Expr) -> bool {
+    match &expr.kind {
+        ExprKind::Toss { arg: Some(_), .. }
+        | ExprKind::Catch { .. }
+        | ExprKind::Retoss { .. }
+        | ExprKind::Lambda { .. } => true,
+        ExprKind::Pipeline(_, rhs) => greedy(rhs),
+        _ => false,
+    }
+}
@@ -1,7 +1,10 @@
 use super::value::Value;
+use crate::util::span::Span;
 
 #[derive(Debug)]
 pub struct Effect {
     pub name: String,
     pub payload: Option<Value>,
+    /// The `toss` that raised it.
+    pub span: Span,
 }
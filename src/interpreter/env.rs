@@ -1,16 +1,25 @@
 use super::value::Value;
+use crate::typechecker::prelude;
 use std::collections::HashMap;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default, PartialEq)]
 pub struct RuntimeEnv {
     vars: HashMap<String, Value>,
 }
 
 impl RuntimeEnv {
+    /// An environment holding only the prelude's constructors.
     pub fn new() -> Self {
-        Self {
+        let mut env = Self {
             vars: HashMap::new(),
+        };
+        for (_, _, variants) in prelude::sums() {
+            for (tag, variant) in variants.into_iter().enumerate() {
+                let value = Value::constructor(variant.name.clone(), tag, variant.fields.len());
+                env.set(variant.name, value);
+            }
         }
+        env
     }
 
     pub fn get(&self, name: &str) -> Option<Value> {
@@ -20,4 +29,9 @@ impl RuntimeEnv {
     pub fn set(&mut self, name: impl Into<String>, value: Value) {
         self.vars.insert(name.into(), value);
     }
+
+    /// Every binding, in no particular order.
+    pub fn bindings(&self) -> impl Iterator<Item = (&str, &Value)> {
+        self.vars.iter().map(|(name, value)| (name.as_str(), value))
+    }
 }
@@ -1,10 +1,74 @@
+use crate::typechecker::TypeError;
+use crate::util::span::Span;
 use thiserror::Error;
 
 #[derive(Debug, Error)]
 pub enum RuntimeError {
-    #[error("unknown variable `{0}`")]
-    UnknownVariable(String),
+    #[error("unknown variable `{1}`")]
+    UnknownVariable(Span, String),
 
-    #[error("effect `{0}` was not handled")]
-    UnhandledEffect(String),
+    #[error("effect `{1}` was not handled")]
+    UnhandledEffect(Span, String),
+
+    /// A `retoss` a host's handler would handle, which has no value to give.
+    #[error("`retoss` of effect `{1}` cannot resume")]
+    CannotResume(Span, String),
+
+    /// `assert` on zero.
+    #[error("assertion failed")]
+    AssertionFailed(Span),
+
+    /// `assert_eq` on two different values, shown as written by `yell`.
+    #[error("assertion failed: `{1}` is not equal to `{2}`")]
+    NotEqual(Span, String, String),
+
+    /// The index, and the length of the list.
+    #[error("index {1} is out of bounds for a list of length {2}")]
+    IndexOutOfBounds(Span, i64, usize),
+
+    /// `/` or `%` by zero.
+    #[error("division by zero")]
+    DivisionByZero(Span),
+
+    /// A `yell` whose output could not be written, with the I/O error.
+    #[error("cannot write `yell` output: {1}")]
+    Output(Span, String),
+}
+
+impl RuntimeError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            RuntimeError::UnknownVariable(..) => "E0102",
+            RuntimeError::UnhandledEffect(..) => "E0401",
+            RuntimeError::CannotResume(..) => "E0402",
+            RuntimeError::AssertionFailed(_) | RuntimeError::NotEqual(..) => "E0501",
+            RuntimeError::IndexOutOfBounds(..) => "E0801",
+            RuntimeError::DivisionByZero(_) => "E0802",
+            RuntimeError::Output(..) => "E0803",
+        }
+    }
+
+    pub fn span(&self) -> Span {
+        match self {
+            RuntimeError::UnknownVariable(span, _)
+            | RuntimeError::UnhandledEffect(span, _)
+            | RuntimeError::CannotResume(span, _)
+            | RuntimeError::Output(span, _)
+            | RuntimeError::AssertionFailed(span)
+            | RuntimeError::NotEqual(span, _, _)
+            | RuntimeError::IndexOutOfBounds(span, _, _)
+            | RuntimeError::DivisionByZero(span) => *span,
+        }
+    }
+}
+
+/// Anything that stops a host's [`Interpreter`](super::Interpreter) from
+/// running a program.
+#[derive(Debug, Error)]
+pub enum RunError {
+    #[error(transparent)]
+    Type(#[from] TypeError),
+
+    #[error(transparent)]
+    Runtime(#[from] RuntimeError),
 }
@@ -1,5 +1,13 @@
-use super::{effects::Effect, env::RuntimeEnv, error::RuntimeError, value::Value};
-use crate::ast::ast::{Ast, Expr, Statement};
+use super::{effects::Effect, env::RuntimeEnv, error::RuntimeError, host::Host, value::Value};
+use crate::ast::ast::{
+    Ast, BinOp, Expr, ExprKind, Stage, Statement, StatementKind, qualify, stage_chain,
+};
+use crate::patterns::decision::{self, Decision};
+use crate::patterns::{Ctor, Pat};
+use crate::typechecker::prelude::{ERR, OK};
+use crate::util::span::Span;
+use std::collections::HashMap;
+use std::io;
 
 type EvalResult<T> = Result<T, Control>;
 
@@ -15,76 +23,506 @@ impl From<RuntimeError> for Control {
     }
 }
 
-fn eval_expr(expr: &Expr, env: &mut RuntimeEnv) -> EvalResult<Value> {
-    match expr {
-        Expr::Int(n) => Ok(Value::Int(*n)),
-        Expr::String(s) => Ok(Value::String(s.clone())),
+fn eval_expr(expr: &Expr, env: &mut RuntimeEnv, host: &mut Host) -> EvalResult<Value> {
+    match &expr.kind {
+        ExprKind::Int(n) => Ok(Value::Int(*n)),
+        ExprKind::String(s) => Ok(Value::String(s.clone())),
 
-        Expr::Ident(name) => env
+        ExprKind::Ident(name) => env
             .get(name)
-            .ok_or_else(|| RuntimeError::UnknownVariable(name.clone()).into()),
+            .ok_or_else(|| RuntimeError::UnknownVariable(expr.span, name.clone()).into()),
 
-        Expr::Pipeline(lhs, rhs) => {
-            let value = eval_expr(lhs, env)?;
+        ExprKind::List(items) => Ok(Value::List(
+            items
+                .iter()
+                .map(|item| eval_expr(item, env, host))
+                .collect::<EvalResult<_>>()?,
+        )),
+
+        ExprKind::Tuple(items) => Ok(Value::Tuple(
+            items
+                .iter()
+                .map(|item| eval_expr(item, env, host))
+                .collect::<EvalResult<_>>()?,
+        )),
+
+        ExprKind::Index(target, index) => {
+            let (Value::List(items), Value::Int(i)) =
+                (eval_expr(target, env, host)?, eval_expr(index, env, host)?)
+            else {
+                unreachable!("indexing is typechecked");
+            };
+            usize::try_from(i)
+                .ok()
+                .and_then(|at| items.get(at).cloned())
+                .ok_or_else(|| RuntimeError::IndexOutOfBounds(expr.span, i, items.len()).into())
+        }
+
+        ExprKind::Pipeline(lhs, rhs) => {
+            if let Some((source, stages)) = stage_chain(expr, |name| env.get(name).is_some()) {
+                let value = eval_expr(source, env, host)?;
+                return apply_stages(value, &stages, env, host);
+            }
+
+            let value = eval_expr(lhs, env, host)?;
 
             // pipeline creates a new scope with `_`
             let mut next_env = env.clone();
             next_env.set("_", value);
 
-            eval_expr(rhs, &mut next_env)
+            eval_expr(rhs, &mut next_env, host)
+        }
+
+        ExprKind::Lambda { params, body, .. } => Ok(Value::Function {
+            params: params.iter().map(|param| param.name.clone()).collect(),
+            body: body.clone(),
+            env: env.clone(),
+        }),
+
+        ExprKind::Call(callee, args) => {
+            let function = eval_expr(callee, env, host)?;
+            let args = args
+                .iter()
+                .map(|arg| eval_expr(arg, env, host))
+                .collect::<EvalResult<_>>()?;
+            call(&function, args, host)
+        }
+
+        ExprKind::Binary(op, left, right) => {
+            let left = eval_expr(left, env, host)?;
+            let right = eval_expr(right, env, host)?;
+            Ok(binary(*op, left, right, expr.span)?)
         }
 
-        Expr::Toss { effect, arg } => {
+        ExprKind::Record(fields) => {
+            let mut fields = fields
+                .iter()
+                .map(|(name, value)| Ok((name.clone(), eval_expr(value, env, host)?)))
+                .collect::<EvalResult<Vec<_>>>()?;
+            fields.sort_by(|(a, _), (b, _)| a.cmp(b));
+            Ok(Value::Record(fields))
+        }
+
+        ExprKind::Field(record, name) => {
+            let Value::Record(fields) = eval_expr(record, env, host)? else {
+                unreachable!("field access is typechecked");
+            };
+            Ok(fields
+                .into_iter()
+                .find(|(field, _)| field == name)
+                .map(|(_, value)| value)
+                .expect("fields are typechecked"))
+        }
+
+        ExprKind::Update { record, fields } => {
+            let Value::Record(mut record) = eval_expr(record, env, host)? else {
+                unreachable!("updates are typechecked");
+            };
+            for (name, value) in fields {
+                let value = eval_expr(value, env, host)?;
+                let (_, slot) = record
+                    .iter_mut()
+                    .find(|(field, _)| field == name)
+                    .expect("fields are typechecked");
+                *slot = value;
+            }
+            Ok(Value::Record(record))
+        }
+
+        ExprKind::Match { scrutinee, arms } => {
+            let value = eval_expr(scrutinee, env, host)?;
+            let tag = |name: &str| match env.get(name) {
+                Some(Value::Variant { tag, .. } | Value::Constructor { tag, .. }) => tag,
+                _ => unreachable!("constructors are typechecked"),
+            };
+            let pats: Vec<_> = arms
+                .iter()
+                .map(|arm| Pat::lower(&arm.pattern, &tag))
+                .collect();
+            let (arm, bindings) = decide(&decision::compile(&pats), &value);
+
+            let mut arm_env = env.clone();
+            for (name, value) in bindings {
+                arm_env.set(name, value);
+            }
+            eval_expr(&arms[arm].body, &mut arm_env, host)
+        }
+
+        ExprKind::Toss { effect, arg } => {
             let payload = if let Some(expr) = arg {
-                Some(eval_expr(expr, env)?)
+                Some(eval_expr(expr, env, host)?)
             } else {
                 None
             };
 
-            Err(Control::Effect(Effect {
+            let effect = Effect {
                 name: effect.clone(),
                 payload,
-            }))
+                span: expr.span,
+            };
+            match host.handler(&effect.name) {
+                Some(handler) => {
+                    handler(&effect);
+                    Ok(Value::Unit)
+                }
+                None => Err(Control::Effect(effect)),
+            }
+        }
+
+        ExprKind::Catch { effect, expr } => {
+            host.catching.push(effect.clone());
+            let result = eval_expr(expr, env, host);
+            host.catching.pop();
+            match result {
+                Ok(value) => Ok(variant("Ok", OK, value)),
+                Err(Control::Effect(caught)) if caught.name == *effect => {
+                    Ok(variant("Err", ERR, caught.payload.unwrap_or(Value::Unit)))
+                }
+                Err(other) => Err(other),
+            }
+        }
+
+        // `Ok` and `Some` share a tag, as do `Err` and `None`. A handler
+        // can only give a `toss` its `Unit`, so a `retoss` one would handle
+        // stops the program without calling it.
+        ExprKind::Retoss {
+            effect,
+            expr: value,
+        } => match eval_expr(value, env, host)? {
+            Value::Variant {
+                tag: OK, fields, ..
+            } => Ok(fields
+                .into_iter()
+                .next()
+                .expect("`Ok` and `Some` hold a value")),
+            Value::Variant { fields, .. } => {
+                let effect = Effect {
+                    name: effect.clone(),
+                    payload: Some(fields.into_iter().next().unwrap_or(Value::Unit)),
+                    span: expr.span,
+                };
+                if host.handler(&effect.name).is_some() {
+                    return Err(RuntimeError::CannotResume(effect.span, effect.name).into());
+                }
+                Err(Control::Effect(effect))
+            }
+            other => unreachable!("retossing {} is typechecked", other.type_name()),
+        },
+    }
+}
+
+/// A value of the prelude's `Result` type.
+fn variant(name: &str, tag: usize, value: Value) -> Value {
+    Value::Variant {
+        name: name.to_string(),
+        tag,
+        fields: vec![value],
+    }
+}
+
+/// Applies a chain of built-in stages to a `piped` value. Elements go
+/// through every stage one at a time, so `xs |> map(f) |> take(2)` calls
+/// `f` at most twice.
+fn apply_stages(
+    piped: Value,
+    stages: &[Stage],
+    env: &mut RuntimeEnv,
+    host: &mut Host,
+) -> EvalResult<Value> {
+    // Arguments are evaluated once, before any element.
+    let args = stages
+        .iter()
+        .map(|stage| {
+            stage
+                .args
+                .iter()
+                .map(|arg| eval_expr(arg, env, host))
+                .collect::<EvalResult<Vec<_>>>()
+        })
+        .collect::<EvalResult<Vec<_>>>()?;
+
+    let items = match piped {
+        Value::List(items) => items,
+        // Only `len` takes a string.
+        Value::String(s) => return Ok(Value::Int(s.len() as i64)),
+        other => unreachable!("stages on {} are typechecked", other.type_name()),
+    };
+    let last = stages.last().expect("a chain has at least one stage");
+    let mut acc = match (last.name, args.last().map(Vec::as_slice)) {
+        ("fold", Some([init, _])) => init.clone(),
+        _ => Value::Int(0),
+    };
+    let mut out = Vec::new();
+    let mut taken = vec![0; stages.len()];
+
+    'items: for mut item in items {
+        // A full `take` ends the chain before another element is computed.
+        for ((stage, args), taken) in stages.iter().zip(&args).zip(&taken) {
+            if stage.name == "take" && *taken >= int(&args[0]) {
+                break 'items;
+            }
+        }
+
+        for ((stage, args), taken) in stages.iter().zip(&args).zip(&mut taken) {
+            item = match (stage.name, args.as_slice()) {
+                ("map", [f]) => call(f, vec![item], host)?,
+                ("filter", [f]) => {
+                    if call(f, vec![item.clone()], host)? == Value::Int(0) {
+                        continue 'items;
+                    }
+                    item
+                }
+                ("take", _) => {
+                    *taken += 1;
+                    item
+                }
+                ("len", _) => Value::Int(int(&acc) + 1),
+                ("sum", _) => Value::Int(int(&acc).wrapping_add(int(&item))),
+                ("fold", [_, f]) => call(
+                    f,
+                    vec![std::mem::replace(&mut acc, Value::Unit), item],
+                    host,
+                )?,
+                (name, _) => unreachable!("`{name}` is typechecked"),
+            };
+        }
+        if last.is_streaming() {
+            out.push(item);
+        } else {
+            acc = item;
+        }
+    }
+
+    Ok(if last.is_streaming() {
+        Value::List(out)
+    } else {
+        acc
+    })
+}
+
+/// Follows `decision` for `value`: the arm it takes, and the values of
+/// the names that arm binds.
+fn decide(mut decision: &Decision, value: &Value) -> (usize, Vec<(String, Value)>) {
+    loop {
+        match decision {
+            Decision::Leaf { arm, bindings } => {
+                let bindings = bindings
+                    .iter()
+                    .map(|(name, path)| (name.clone(), part(value, path).clone()))
+                    .collect();
+                return (*arm, bindings);
+            }
+            Decision::Fail => unreachable!("matches are checked to be exhaustive"),
+            Decision::Switch {
+                path,
+                cases,
+                default,
+            } => {
+                let part = part(value, path);
+                decision = cases
+                    .iter()
+                    .find(|(ctor, _)| is(part, ctor))
+                    .map_or(&**default, |(_, next)| next);
+            }
+        }
+    }
+}
+
+/// The part of `value` at `path`, following the fields of variants and
+/// the items of tuples.
+fn part<'a>(value: &'a Value, path: &[usize]) -> &'a Value {
+    path.iter().fold(value, |value, &field| match value {
+        Value::Variant { fields, .. } | Value::Tuple(fields) => &fields[field],
+        other => unreachable!("{} has no fields to match", other.type_name()),
+    })
+}
+
+/// Whether `value` is built by `ctor`.
+fn is(value: &Value, ctor: &Ctor) -> bool {
+    match (value, ctor) {
+        (Value::Variant { tag, .. }, Ctor::Variant(expected)) => tag == expected,
+        (Value::Int(n), Ctor::Int(expected)) => n == expected,
+        (Value::String(s), Ctor::String(expected)) => s == expected,
+        (Value::Tuple(_), Ctor::Tuple) => true,
+        _ => false,
+    }
+}
+
+fn int(value: &Value) -> i64 {
+    match value {
+        Value::Int(n) => *n,
+        other => unreachable!("expected an Int, found {}", other.type_name()),
+    }
+}
+
+/// Calls `function` with `args`, in the scope it was created in.
+fn call(function: &Value, args: Vec<Value>, host: &mut Host) -> EvalResult<Value> {
+    let (params, body, env) = match function {
+        Value::Function { params, body, env } => (params, body, env),
+        Value::Constructor { name, tag } => {
+            return Ok(Value::Variant {
+                name: name.clone(),
+                tag: *tag,
+                fields: args,
+            });
         }
+        _ => unreachable!("calls are typechecked"),
+    };
+    let mut env = env.clone();
+    for (param, arg) in params.iter().zip(args) {
+        env.set(param.clone(), arg);
     }
+    eval_expr(body, &mut env, host)
 }
 
-fn eval_stmt(stmt: &Statement, env: &mut RuntimeEnv) -> EvalResult<()> {
-    match stmt {
-        Statement::Let { name, expr } => {
-            let val = eval_expr(expr, env)?;
+/// `left op right`. Integer arithmetic wraps around on overflow.
+fn binary(op: BinOp, left: Value, right: Value, span: Span) -> Result<Value, RuntimeError> {
+    let (a, b) = match (left, right) {
+        (Value::String(a), Value::String(b)) => return Ok(Value::String(a + &b)),
+        (Value::Int(a), Value::Int(b)) => (a, b),
+        _ => unreachable!("operands are typechecked"),
+    };
+    Ok(Value::Int(match op {
+        BinOp::Add => a.wrapping_add(b),
+        BinOp::Sub => a.wrapping_sub(b),
+        BinOp::Mul => a.wrapping_mul(b),
+        BinOp::Div | BinOp::Rem if b == 0 => return Err(RuntimeError::DivisionByZero(span)),
+        BinOp::Div => a.wrapping_div(b),
+        BinOp::Rem => a.wrapping_rem(b),
+        BinOp::Eq => (a == b) as i64,
+        BinOp::Ne => (a != b) as i64,
+        BinOp::Lt => (a < b) as i64,
+        BinOp::Le => (a <= b) as i64,
+        BinOp::Gt => (a > b) as i64,
+        BinOp::Ge => (a >= b) as i64,
+    }))
+}
+
+fn eval_stmt(stmt: &Statement, env: &mut RuntimeEnv, host: &mut Host) -> EvalResult<()> {
+    match &stmt.kind {
+        StatementKind::Let { name, expr, .. } => {
+            let val = eval_expr(expr, env, host)?;
             env.set(name.clone(), val);
             Ok(())
         }
 
-        Statement::Yell { expr } => {
-            let val = eval_expr(expr, env)?;
-            println!("{:?}", val);
+        // The pattern matches every tuple of its type, so it is a match
+        // with one arm.
+        StatementKind::Destructure { pattern, expr, .. } => {
+            let value = eval_expr(expr, env, host)?;
+            let pat = Pat::lower(pattern, &|_: &str| -> usize {
+                unreachable!("tuple patterns name no variants")
+            });
+            let (_, bindings) = decide(&decision::compile(&[pat]), &value);
+            for (name, value) in bindings {
+                env.set(name, value);
+            }
             Ok(())
         }
 
-        Statement::ExprStmt(expr) => {
-            eval_expr(expr, env)?;
+        StatementKind::Yell { expr } => {
+            let val = eval_expr(expr, env, host)?;
+            writeln!(host.out, "{:?}", val)
+                .map_err(|err| RuntimeError::Output(stmt.span, err.to_string()).into())
+        }
+
+        StatementKind::ExprStmt(expr) => {
+            eval_expr(expr, env, host)?;
             Ok(())
         }
 
-        Statement::Toss { expr } => {
-            eval_expr(expr, env)?;
+        StatementKind::Toss { expr } => {
+            eval_expr(expr, env, host)?;
+            Ok(())
+        }
+
+        StatementKind::Use { path, name, alias } => {
+            let target = qualify(path, name);
+            let value = env
+                .get(&target)
+                .ok_or(RuntimeError::UnknownVariable(stmt.span, target))?;
+            env.set(alias.clone(), value);
+            Ok(())
+        }
+
+        StatementKind::SumDecl { variants, .. } => {
+            for (tag, variant) in variants.iter().enumerate() {
+                let name = variant.name.clone();
+                env.set(
+                    name.clone(),
+                    Value::constructor(name, tag, variant.fields.len()),
+                );
+            }
+            Ok(())
+        }
+
+        // Tests only run through `run_tests`; types and effects only
+        // matter to the typechecker.
+        StatementKind::Test { .. }
+        | StatementKind::TypeDecl { .. }
+        | StatementKind::EffectDecl { .. } => Ok(()),
+
+        StatementKind::Assert { expr } => match eval_expr(expr, env, host)? {
+            Value::Int(0) => Err(RuntimeError::AssertionFailed(stmt.span).into()),
+            _ => Ok(()),
+        },
+
+        StatementKind::AssertEq { left, right } => {
+            let left = eval_expr(left, env, host)?;
+            let right = eval_expr(right, env, host)?;
+            if left != right {
+                return Err(
+                    RuntimeError::NotEqual(stmt.span, left.to_string(), right.to_string()).into(),
+                );
+            }
             Ok(())
         }
     }
 }
 
+/// Turns control flow that escaped to the top level into an error.
+fn settle<T>(result: EvalResult<T>) -> Result<T, RuntimeError> {
+    match result {
+        Ok(value) => Ok(value),
+        Err(Control::Error(e)) => Err(e),
+        Err(Control::Effect(e)) => Err(RuntimeError::UnhandledEffect(e.span, e.name)),
+    }
+}
+
+/// Evaluates a top-level statement in an existing environment, writing
+/// what it `yell`s to stdout.
+pub fn eval_statement(stmt: &Statement, env: &mut RuntimeEnv) -> Result<(), RuntimeError> {
+    with_stdout(|host| eval_statement_in(stmt, env, host))
+}
+
+/// Evaluates a top-level expression in an existing environment.
+pub fn eval_expression(expr: &Expr, env: &mut RuntimeEnv) -> Result<Value, RuntimeError> {
+    with_stdout(|host| settle(eval_expr(expr, env, host)))
+}
+
+/// Like [`eval_statement`], through `host`.
+pub fn eval_statement_in(
+    stmt: &Statement,
+    env: &mut RuntimeEnv,
+    host: &mut Host,
+) -> Result<(), RuntimeError> {
+    settle(eval_stmt(stmt, env, host))
+}
+
+/// Runs `f` with a host that writes to stdout and handles no effects.
+fn with_stdout<T>(f: impl FnOnce(&mut Host) -> T) -> T {
+    let mut out = io::stdout();
+    let mut handlers = HashMap::new();
+    f(&mut Host::new(&mut out, &mut handlers))
+}
+
+/// Runs a program that has already been typechecked.
 pub fn eval_program(ast: &Ast) -> Result<(), RuntimeError> {
     let mut env = RuntimeEnv::new();
-
-    for stmt in &ast.items {
-        match eval_stmt(stmt, &mut env) {
-            Ok(_) => {}
-            Err(Control::Error(e)) => return Err(e),
-            Err(Control::Effect(e)) => return Err(RuntimeError::UnhandledEffect(e.name)),
+    with_stdout(|host| {
+        for stmt in &ast.items {
+            eval_statement_in(stmt, &mut env, host)?;
         }
-    }
-
-    Ok(())
+        Ok(())
+    })
 }
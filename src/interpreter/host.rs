@@ -0,0 +1,37 @@
+//! What evaluation reaches outside the program through: where `yell`
+//! writes, and the handlers a host gives the effects no `catch` catches.
+
+use super::effects::Effect;
+use std::collections::HashMap;
+use std::io::Write;
+
+/// A host's handler for an effect. The `toss` it handles gives `Unit`
+/// once it returns, as in compiled programs.
+pub type Handler = Box<dyn FnMut(&Effect)>;
+
+pub struct Host<'a> {
+    /// Where `yell` writes each value, on a line of its own.
+    pub out: &'a mut dyn Write,
+    pub handlers: &'a mut HashMap<String, Handler>,
+    /// The effects of the `catch`es being evaluated, innermost last.
+    pub catching: Vec<String>,
+}
+
+impl<'a> Host<'a> {
+    pub fn new(out: &'a mut dyn Write, handlers: &'a mut HashMap<String, Handler>) -> Self {
+        Self {
+            out,
+            handlers,
+            catching: Vec::new(),
+        }
+    }
+
+    /// The handler for effect `name`, unless a `catch` being evaluated
+    /// catches it first.
+    pub fn handler(&mut self, name: &str) -> Option<&mut Handler> {
+        if self.catching.iter().any(|caught| caught == name) {
+            return None;
+        }
+        self.handlers.get_mut(name)
+    }
+}
@@ -0,0 +1,89 @@
+//! Running Sauce from a Rust host.
+
+use super::{
+    effects::Effect,
+    env::RuntimeEnv,
+    error::RunError,
+    eval::eval_statement_in,
+    host::{Handler, Host},
+    value::Value,
+};
+use crate::ast::ast::Ast;
+use crate::typechecker::checker::{TypeEnv, typecheck_stmt};
+use crate::typechecker::types::Type;
+use std::collections::HashMap;
+use std::io::Write;
+
+/// An interpreter a host runs programs in. The bindings one program makes
+/// stay for the next, and the host reads them back once it is done.
+pub struct Interpreter<W: Write> {
+    env: RuntimeEnv,
+    /// The types of the bindings in `env`, which programs are checked
+    /// against.
+    types: TypeEnv,
+    /// Where `yell` writes.
+    out: W,
+    handlers: HashMap<String, Handler>,
+}
+
+impl<W: Write> Interpreter<W> {
+    /// An interpreter with only the prelude bound, writing what programs
+    /// `yell` to `out`.
+    pub fn new(out: W) -> Self {
+        Self {
+            env: RuntimeEnv::new(),
+            types: TypeEnv::new(),
+            out,
+            handlers: HashMap::new(),
+        }
+    }
+
+    /// Handles effect `name` with `handler` wherever no `catch` catches it,
+    /// replacing any handler it had. The `toss` then gives `Unit`.
+    pub fn handle(&mut self, name: impl Into<String>, handler: impl FnMut(&Effect) + 'static) {
+        self.handlers.insert(name.into(), Box::new(handler));
+    }
+
+    /// Binds `name` to `value` of type `ty` for the programs run after.
+    /// Programs are checked against `ty`, so it must be the type of
+    /// `value`.
+    pub fn set(&mut self, name: impl Into<String>, value: Value, ty: Type) {
+        let name = name.into();
+        self.types.insert(name.clone(), ty);
+        self.env.set(name, value);
+    }
+
+    pub fn get(&self, name: &str) -> Option<Value> {
+        self.env.get(name)
+    }
+
+    /// Every binding, including the prelude's constructors.
+    pub fn bindings(&self) -> impl Iterator<Item = (&str, &Value)> {
+        self.env.bindings()
+    }
+
+    /// Typechecks `ast`, then runs it until it ends or a statement fails.
+    /// A program that does not typecheck does not run at all; the
+    /// bindings made before a failure stay.
+    pub fn run(&mut self, ast: &Ast) -> Result<(), RunError> {
+        let mut types = self.types.clone();
+        for stmt in &ast.items {
+            typecheck_stmt(&mut types, stmt)?;
+        }
+        self.types = types;
+
+        let mut host = Host::new(&mut self.out, &mut self.handlers);
+        for stmt in &ast.items {
+            eval_statement_in(stmt, &mut self.env, &mut host)?;
+        }
+        Ok(())
+    }
+
+    pub fn output(&self) -> &W {
+        &self.out
+    }
+
+    pub fn into_output(self) -> W {
+        self.out
+    }
+}
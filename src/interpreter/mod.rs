@@ -2,4 +2,9 @@ pub mod effects;
 pub mod env;
 pub mod error;
 pub mod eval;
+pub mod host;
+#[allow(clippy::module_inception)]
+pub mod interpreter;
+pub mod runner;
 pub mod value;
+pub use interpreter::Interpreter;
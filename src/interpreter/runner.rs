@@ -0,0 +1,65 @@
+use super::{env::RuntimeEnv, error::RuntimeError, eval::eval_statement};
+use crate::ast::ast::{Ast, StatementKind};
+use crate::util::span::Span;
+
+/// The result of one `test` block.
+#[derive(Debug)]
+pub struct TestOutcome {
+    pub name: String,
+    /// The whole `test` block.
+    pub span: Span,
+    pub result: Result<(), RuntimeError>,
+}
+
+/// Runs every test whose name contains `filter`, each in a fresh
+/// environment with only the constructors of the top-level sum types.
+/// Other top-level statements are not run.
+pub fn run_tests(ast: &Ast, filter: Option<&str>) -> Vec<TestOutcome> {
+    let constructors = constructors(ast);
+    ast.items
+        .iter()
+        .filter_map(|stmt| match &stmt.kind {
+            StatementKind::Test { name, body } => Some((name, body, stmt.span)),
+            _ => None,
+        })
+        .filter(|(name, _, _)| filter.is_none_or(|filter| name.contains(filter)))
+        .map(|(name, body, span)| {
+            let mut env = constructors.clone();
+            let result = body
+                .iter()
+                .try_for_each(|stmt| eval_statement(stmt, &mut env));
+            TestOutcome {
+                name: name.clone(),
+                span,
+                result,
+            }
+        })
+        .collect()
+}
+
+/// The constructors bound by top-level sum types and imports of them.
+fn constructors(ast: &Ast) -> RuntimeEnv {
+    let mut env = RuntimeEnv::new();
+    for stmt in &ast.items {
+        match &stmt.kind {
+            StatementKind::SumDecl { .. } => {
+                eval_statement(stmt, &mut env).expect("declaring a sum type cannot fail");
+            }
+            // Only constructors are bound here, so importing anything
+            // else fails and binds nothing.
+            StatementKind::Use { .. } => {
+                let _ = eval_statement(stmt, &mut env);
+            }
+            _ => {}
+        }
+    }
+    env
+}
+
+/// The number of `test` blocks in `ast`.
+pub fn count_tests(ast: &Ast) -> usize {
+    ast.items
+        .iter()
+        .filter(|stmt| matches!(stmt.kind, StatementKind::Test { .. }))
+        .count()
+}
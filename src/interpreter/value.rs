@@ -1,16 +1,117 @@
+use super::env::RuntimeEnv;
+use crate::ast::ast::{Expr, unqualified};
+use std::fmt;
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum Value {
     Int(i64),
     String(String),
     Unit,
+    List(Vec<Value>),
+    Tuple(Vec<Value>),
+    /// Field names and values, sorted by name.
+    Record(Vec<(String, Value)>),
+    /// A value of a sum type: variant `tag`, built by constructor `name`.
+    Variant {
+        name: String,
+        tag: usize,
+        fields: Vec<Value>,
+    },
+    /// The constructor of a variant with fields, waiting to be called.
+    Constructor {
+        name: String,
+        tag: usize,
+    },
+    /// A `fn`, with the bindings it captured.
+    Function {
+        params: Vec<String>,
+        body: Box<Expr>,
+        env: RuntimeEnv,
+    },
 }
 
 impl Value {
+    /// What constructor `name` of variant `tag` evaluates to: the variant
+    /// itself when it has no fields.
+    pub fn constructor(name: String, tag: usize, fields: usize) -> Self {
+        if fields == 0 {
+            Value::Variant {
+                name,
+                tag,
+                fields: Vec::new(),
+            }
+        } else {
+            Value::Constructor { name, tag }
+        }
+    }
+
     pub fn type_name(&self) -> &'static str {
         match self {
             Value::Int(_) => "Int",
             Value::String(_) => "String",
             Value::Unit => "Unit",
+            Value::List(_) => "List",
+            Value::Tuple(_) => "Tuple",
+            Value::Record(_) => "Record",
+            Value::Variant { .. } => "Variant",
+            Value::Constructor { .. } => "Constructor",
+            Value::Function { .. } => "Function",
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Int(n) => write!(f, "{n}"),
+            Value::String(s) => write!(f, "{s:?}"),
+            Value::Unit => f.write_str("()"),
+            Value::List(items) => {
+                f.write_str("[")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{item}")?;
+                }
+                f.write_str("]")
+            }
+            Value::Tuple(items) => {
+                f.write_str("(")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{item}")?;
+                }
+                f.write_str(if items.len() == 1 { ",)" } else { ")" })
+            }
+            Value::Record(fields) => {
+                f.write_str("{ ")?;
+                for (i, (name, value)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{name}: {value}")?;
+                }
+                f.write_str(" }")
+            }
+            Value::Variant { name, fields, .. } => {
+                f.write_str(unqualified(name))?;
+                if fields.is_empty() {
+                    return Ok(());
+                }
+                f.write_str("(")?;
+                for (i, field) in fields.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{field}")?;
+                }
+                f.write_str(")")
+            }
+            Value::Constructor { name, .. } => f.write_str(unqualified(name)),
+            Value::Function { params, .. } => write!(f, "fn({})", params.join(", ")),
         }
     }
 }
@@ -17,12 +17,35 @@ impl SpannedToken {
 
 pub struct Lexer<'input> {
     inner: logos::Lexer<'input, Token>,
+    keep_comments: bool,
+    /// Added to every span, to place the source in a larger `SourceMap`.
+    offset: usize,
 }
 
 impl<'input> Lexer<'input> {
     pub fn new(src: &'input str) -> Self {
         Self {
             inner: Token::lexer(src),
+            keep_comments: false,
+            offset: 0,
+        }
+    }
+
+    /// A lexer whose spans start at `offset` instead of 0.
+    pub fn with_offset(src: &'input str, offset: usize) -> Self {
+        Self {
+            offset,
+            ..Self::new(src)
+        }
+    }
+
+    /// A lexer that also yields comments. Together with the token spans
+    /// this is enough to rebuild the source exactly.
+    pub fn lossless(src: &'input str) -> Self {
+        Self {
+            inner: Token::lexer(src),
+            keep_comments: true,
+            offset: 0,
         }
     }
 }
@@ -31,13 +54,16 @@ impl<'input> Iterator for Lexer<'input> {
     type Item = Result<SpannedToken, LexError>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        let token_result = self.inner.next()?;
-        let range = self.inner.span();
-        let span = Span::new(range.start, range.end);
+        loop {
+            let token_result = self.inner.next()?;
+            let range = self.inner.span();
+            let span = Span::new(self.offset + range.start, self.offset + range.end);
 
-        match token_result {
-            Ok(token) => Some(Ok(SpannedToken::new(token, span))),
-            Err(_) => Some(Err(LexError::InvalidToken(span))),
+            return match token_result {
+                Ok(Token::Comment(_)) if !self.keep_comments => continue,
+                Ok(token) => Some(Ok(SpannedToken::new(token, span))),
+                Err(_) => Some(Err(LexError::InvalidToken(span))),
+            };
         }
     }
 }
@@ -1,4 +1,5 @@
 use logos::Logos;
+use std::fmt;
 
 #[derive(Logos, Debug, Clone, PartialEq)]
 #[logos(skip r"[ \t\r\n\f]+")]
@@ -9,11 +10,70 @@ pub enum Token {
     Yell,
     #[token("toss")]
     Toss,
+    #[token("catch")]
+    Catch,
+    #[token("retoss")]
+    Retoss,
+    #[token("test")]
+    Test,
+    #[token("assert")]
+    Assert,
+    #[token("assert_eq")]
+    AssertEq,
+    #[token("pub")]
+    Pub,
+    #[token("use")]
+    Use,
+    #[token("fn")]
+    Fn,
+    #[token("type")]
+    Type,
+    #[token("effect")]
+    Effect,
+    #[token("with")]
+    With,
+    #[token("match")]
+    Match,
     #[token("|>")]
     Pipe,
 
     #[token("=")]
     Equals,
+    #[token("::")]
+    PathSep,
+    #[token(":")]
+    Colon,
+    #[token(".")]
+    Dot,
+    #[token("=>")]
+    FatArrow,
+    #[token("->")]
+    Arrow,
+    #[token("|")]
+    Bar,
+
+    #[token("+")]
+    Plus,
+    #[token("-")]
+    Minus,
+    #[token("*")]
+    Star,
+    #[token("/")]
+    Slash,
+    #[token("%")]
+    Percent,
+    #[token("==")]
+    EqEq,
+    #[token("!=")]
+    NotEq,
+    #[token("<")]
+    Lt,
+    #[token("<=")]
+    Le,
+    #[token(">")]
+    Gt,
+    #[token(">=")]
+    Ge,
 
     #[regex(r"[a-zA-Z_][a-zA-Z0-9_]*", |lex| lex.slice().to_string())]
     Ident(String),
@@ -34,8 +94,73 @@ pub enum Token {
     LBrace,
     #[token("}")]
     RBrace,
+    #[token("[")]
+    LBracket,
+    #[token("]")]
+    RBracket,
     #[token(",")]
     Comma,
     #[token(";")]
     Semicolon,
+    #[token("#")]
+    Hash,
+
+    /// A `//` line comment, including the slashes. Only produced by
+    /// [`Lexer::lossless`](crate::lexer::Lexer::lossless).
+    #[regex(r"//[^\n]*", |lex| lex.slice().trim_end().to_string())]
+    Comment(String),
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Grab => f.write_str("`grab`"),
+            Token::Yell => f.write_str("`yell`"),
+            Token::Toss => f.write_str("`toss`"),
+            Token::Catch => f.write_str("`catch`"),
+            Token::Retoss => f.write_str("`retoss`"),
+            Token::Test => f.write_str("`test`"),
+            Token::Assert => f.write_str("`assert`"),
+            Token::AssertEq => f.write_str("`assert_eq`"),
+            Token::Pub => f.write_str("`pub`"),
+            Token::Use => f.write_str("`use`"),
+            Token::Fn => f.write_str("`fn`"),
+            Token::Type => f.write_str("`type`"),
+            Token::Effect => f.write_str("`effect`"),
+            Token::With => f.write_str("`with`"),
+            Token::Match => f.write_str("`match`"),
+            Token::PathSep => f.write_str("`::`"),
+            Token::Colon => f.write_str("`:`"),
+            Token::Dot => f.write_str("`.`"),
+            Token::FatArrow => f.write_str("`=>`"),
+            Token::Arrow => f.write_str("`->`"),
+            Token::Bar => f.write_str("`|`"),
+            Token::Pipe => f.write_str("`|>`"),
+            Token::Equals => f.write_str("`=`"),
+            Token::Plus => f.write_str("`+`"),
+            Token::Minus => f.write_str("`-`"),
+            Token::Star => f.write_str("`*`"),
+            Token::Slash => f.write_str("`/`"),
+            Token::Percent => f.write_str("`%`"),
+            Token::EqEq => f.write_str("`==`"),
+            Token::NotEq => f.write_str("`!=`"),
+            Token::Lt => f.write_str("`<`"),
+            Token::Le => f.write_str("`<=`"),
+            Token::Gt => f.write_str("`>`"),
+            Token::Ge => f.write_str("`>=`"),
+            Token::Ident(name) => write!(f, "identifier `{name}`"),
+            Token::Int(n) => write!(f, "integer `{n}`"),
+            Token::String(s) => write!(f, "string \"{s}\""),
+            Token::LParen => f.write_str("`(`"),
+            Token::RParen => f.write_str("`)`"),
+            Token::LBrace => f.write_str("`{`"),
+            Token::RBrace => f.write_str("`}`"),
+            Token::LBracket => f.write_str("`[`"),
+            Token::RBracket => f.write_str("`]`"),
+            Token::Comma => f.write_str("`,`"),
+            Token::Semicolon => f.write_str("`;`"),
+            Token::Hash => f.write_str("`#`"),
+            Token::Comment(_) => f.write_str("comment"),
+        }
+    }
 }
@@ -1,8 +1,15 @@
 pub mod ast;
 pub mod codegen;
 pub mod errors;
+pub mod formatter;
 pub mod interpreter;
 pub mod lexer;
+pub mod lint;
+pub mod lsp;
+pub mod modules;
 pub mod parser;
+pub mod patterns;
+pub mod project;
+pub mod repl;
 pub mod typechecker;
 pub mod util;
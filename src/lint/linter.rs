@@ -0,0 +1,380 @@
+use crate::ast::ast::{
+    Allow, Ast, Expr, ExprKind, Pattern, PatternKind, Stage, Statement, StatementKind, qualify,
+    unqualified,
+};
+use crate::lint::warning::Warning;
+use crate::typechecker::checker::{TypeEnv, typecheck_expr, typecheck_stmt};
+use crate::typechecker::types::Type;
+use crate::util::span::Span;
+use crate::util::suggest::best_match;
+use std::collections::HashMap;
+
+/// Every lint `#allow(...)` accepts.
+pub const LINTS: [&str; 7] = [
+    "unused_binding",
+    "shadowing",
+    "ignored_placeholder",
+    "unused_value",
+    "unreachable",
+    "unknown_lint",
+    "duplicate_use",
+];
+
+/// A `grab` that is still in scope.
+struct Binding {
+    span: Span,
+    used: bool,
+    /// Whether the `grab` allows `unused_binding`.
+    allowed: bool,
+    /// What a `use` bound, by its qualified name.
+    import: Option<String>,
+}
+
+/// Lints one scope: the top level, or the body of a test.
+#[derive(Default)]
+struct Linter<'a> {
+    types: TypeEnv,
+    bindings: HashMap<String, Binding>,
+    /// Lints allowed on every statement, by an enclosing `test`.
+    outer: Vec<&'a Allow>,
+    warnings: Vec<Warning>,
+    /// The `toss` that ends the scope, once one is seen.
+    tossed: Option<Span>,
+    reported_unreachable: bool,
+}
+
+/// Lints a program that has already passed the typechecker.
+///
+/// Warnings come back in source order, without those silenced by `#allow`.
+pub fn lint_program(ast: &Ast) -> Vec<Warning> {
+    let mut warnings = Linter::default().scope(&ast.items);
+    warnings.sort_by_key(|w| w.span().start);
+    warnings
+}
+
+impl<'a> Linter<'a> {
+    fn scope(mut self, stmts: &'a [Statement]) -> Vec<Warning> {
+        for stmt in stmts {
+            self.statement(stmt);
+        }
+
+        let mut unused: Vec<_> = self.bindings.drain().collect();
+        unused.sort_by_key(|(_, binding)| binding.span.start);
+        for (name, binding) in unused {
+            self.unused(name, binding);
+        }
+        self.warnings
+    }
+
+    fn statement(&mut self, stmt: &'a Statement) {
+        for allow in &stmt.allow {
+            if !LINTS.contains(&allow.lint.as_str()) {
+                let suggestion = best_match(&allow.lint, LINTS);
+                self.warn(
+                    stmt,
+                    Warning::UnknownLint(allow.span, allow.lint.clone(), suggestion),
+                );
+            }
+        }
+
+        // Tests run on their own, so a `toss` before them does not matter.
+        if let Some(toss) = self.tossed
+            && !self.reported_unreachable
+            && !matches!(stmt.kind, StatementKind::Test { .. })
+        {
+            self.reported_unreachable = true;
+            self.warn(stmt, Warning::Unreachable(stmt.span, toss));
+        }
+
+        let exprs = match &stmt.kind {
+            StatementKind::Let { expr, .. }
+            | StatementKind::Destructure { expr, .. }
+            | StatementKind::Yell { expr }
+            | StatementKind::ExprStmt(expr)
+            | StatementKind::Toss { expr }
+            | StatementKind::Assert { expr } => vec![expr],
+            StatementKind::AssertEq { left, right } => vec![left, right],
+            StatementKind::TypeDecl { .. }
+            | StatementKind::SumDecl { .. }
+            | StatementKind::EffectDecl { .. } => vec![],
+            StatementKind::Use { path, name, .. } => {
+                if let Some(binding) = self.bindings.get_mut(&qualify(path, name)) {
+                    binding.used = true;
+                }
+                vec![]
+            }
+            StatementKind::Test { body, .. } => {
+                // A test has its own scope, and never runs with the program.
+                let mut outer = self.outer.clone();
+                outer.extend(&stmt.allow);
+                let test = Linter {
+                    types: self.types.scope(),
+                    outer,
+                    ..Linter::default()
+                };
+                self.warnings.extend(test.scope(body));
+                vec![]
+            }
+        };
+        for expr in exprs {
+            self.expr(stmt, expr);
+            if self.tossed.is_none() {
+                self.tossed = first_toss(expr);
+            }
+        }
+
+        match &stmt.kind {
+            StatementKind::Let { name, .. } => self.bind(stmt, name, stmt.span),
+            StatementKind::Use { path, name, alias } => {
+                self.import(stmt, alias, qualify(path, name))
+            }
+            StatementKind::Destructure { pattern, .. } => {
+                for (name, span) in pattern.bindings() {
+                    self.bind(stmt, name, span);
+                }
+            }
+            StatementKind::ExprStmt(expr) => {
+                if let Ok(ty) = typecheck_expr(&self.types, expr)
+                    && ty != Type::Unit
+                {
+                    self.warn(stmt, Warning::UnusedValue(expr.span, ty));
+                }
+            }
+            _ => {}
+        }
+
+        // Only for the types of later statements; errors were reported by
+        // the typechecker.
+        let _ = typecheck_stmt(&mut self.types, stmt);
+    }
+
+    fn expr(&mut self, stmt: &Statement, expr: &Expr) {
+        match &expr.kind {
+            ExprKind::Ident(name) => {
+                if let Some(binding) = self.bindings.get_mut(name) {
+                    binding.used = true;
+                }
+            }
+            ExprKind::Pipeline(lhs, rhs) => {
+                if !uses_placeholder(rhs) {
+                    self.warn(stmt, Warning::IgnoredPlaceholder(rhs.span));
+                }
+                self.expr(stmt, lhs);
+                self.expr(stmt, rhs);
+            }
+            ExprKind::List(items) | ExprKind::Tuple(items) => {
+                for item in items {
+                    self.expr(stmt, item);
+                }
+            }
+            ExprKind::Index(target, index) => {
+                self.expr(stmt, target);
+                self.expr(stmt, index);
+            }
+            ExprKind::Lambda { params, body, .. } => {
+                // Parameters hide bindings of the same name in the body.
+                let hidden: Vec<_> = params
+                    .iter()
+                    .filter_map(|param| self.bindings.remove_entry(&param.name))
+                    .collect();
+                self.expr(stmt, body);
+                self.bindings.extend(hidden);
+            }
+            ExprKind::Call(callee, args) => {
+                self.expr(stmt, callee);
+                for arg in args {
+                    self.expr(stmt, arg);
+                }
+            }
+            ExprKind::Binary(_, left, right) => {
+                self.expr(stmt, left);
+                self.expr(stmt, right);
+            }
+            ExprKind::Record(fields) => {
+                for (_, value) in fields {
+                    self.expr(stmt, value);
+                }
+            }
+            ExprKind::Field(record, _) => self.expr(stmt, record),
+            ExprKind::Update { record, fields } => {
+                self.expr(stmt, record);
+                for (_, value) in fields {
+                    self.expr(stmt, value);
+                }
+            }
+            ExprKind::Match { scrutinee, arms } => {
+                self.expr(stmt, scrutinee);
+                for arm in arms {
+                    self.pattern(&arm.pattern);
+                    // Like parameters, the names a pattern binds hide
+                    // bindings in its arm.
+                    let hidden: Vec<_> = arm
+                        .pattern
+                        .bindings()
+                        .into_iter()
+                        .filter_map(|(name, _)| self.bindings.remove_entry(name))
+                        .collect();
+                    self.expr(stmt, &arm.body);
+                    self.bindings.extend(hidden);
+                }
+            }
+            ExprKind::Toss { arg: Some(arg), .. } => self.expr(stmt, arg),
+            ExprKind::Catch { expr, .. } | ExprKind::Retoss { expr, .. } => self.expr(stmt, expr),
+            ExprKind::Int(_) | ExprKind::String(_) | ExprKind::Toss { arg: None, .. } => {}
+        }
+    }
+
+    /// Starts tracking `name`, bound by `stmt` at `span`.
+    fn bind(&mut self, stmt: &Statement, name: &str, span: Span) {
+        if is_underscored(name) {
+            return;
+        }
+        if let Some(earlier) = self.bindings.remove(name) {
+            self.warn(
+                stmt,
+                Warning::Shadowed(stmt.span, unqualified(name).to_string(), earlier.span),
+            );
+            self.unused(name.to_string(), earlier);
+        }
+        // Exports are used by other modules, or meant to be.
+        let allowed = stmt.public || self.allowed(stmt, "unused_binding");
+        self.bindings.insert(
+            name.to_string(),
+            Binding {
+                span,
+                used: false,
+                allowed,
+                import: None,
+            },
+        );
+    }
+
+    /// Starts tracking `alias`, bound by a `use` of `target`, unless an
+    /// earlier `use` already bound it to the same thing.
+    fn import(&mut self, stmt: &Statement, alias: &str, target: String) {
+        if let Some(earlier) = self.bindings.get(alias)
+            && earlier.import.as_ref() == Some(&target)
+        {
+            let warning =
+                Warning::DuplicateUse(stmt.span, unqualified(alias).to_string(), earlier.span);
+            self.warn(stmt, warning);
+            return;
+        }
+        self.bind(stmt, alias, stmt.span);
+        if let Some(binding) = self.bindings.get_mut(alias) {
+            binding.import = Some(target);
+        }
+    }
+
+    /// Marks the imported constructors `pattern` names as used.
+    fn pattern(&mut self, pattern: &Pattern) {
+        match &pattern.kind {
+            PatternKind::Variant(name, args) => {
+                if let Some(binding) = self.bindings.get_mut(name) {
+                    binding.used = true;
+                }
+                for arg in args {
+                    self.pattern(arg);
+                }
+            }
+            PatternKind::Tuple(items) => {
+                for item in items {
+                    self.pattern(item);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Reports a binding that went out of scope, if nobody read it, by
+    /// the name it has in its own module.
+    fn unused(&mut self, name: String, binding: Binding) {
+        if !binding.used && !binding.allowed {
+            let name = unqualified(&name).to_string();
+            self.warnings
+                .push(Warning::UnusedBinding(binding.span, name));
+        }
+    }
+
+    fn allowed(&self, stmt: &Statement, lint: &str) -> bool {
+        stmt.allow
+            .iter()
+            .chain(self.outer.iter().copied())
+            .any(|allow| allow.lint == lint)
+    }
+
+    fn warn(&mut self, stmt: &Statement, warning: Warning) {
+        if !self.allowed(stmt, warning.lint()) {
+            self.warnings.push(warning);
+        }
+    }
+}
+
+/// Whether a binding's own name starts with `_`, so `math::_x` counts.
+fn is_underscored(name: &str) -> bool {
+    name.rsplit("::").next().is_some_and(|n| n.starts_with('_'))
+}
+
+/// Whether `expr` reads the `_` bound by the pipeline around it. A nested
+/// pipeline binds its own `_` on its right side, and a `fn` with a `_`
+/// parameter its own in its body. A built-in stage reads the piped value
+/// itself.
+fn uses_placeholder(expr: &Expr) -> bool {
+    if Stage::of(expr).is_some() {
+        return true;
+    }
+    match &expr.kind {
+        ExprKind::Ident(name) => name == "_",
+        ExprKind::Pipeline(lhs, _) => uses_placeholder(lhs),
+        ExprKind::List(items) | ExprKind::Tuple(items) => items.iter().any(uses_placeholder),
+        ExprKind::Index(target, index) | ExprKind::Binary(_, target, index) => {
+            uses_placeholder(target) || uses_placeholder(index)
+        }
+        ExprKind::Lambda { params, body, .. } => {
+            !params.iter().any(|p| p.name == "_") && uses_placeholder(body)
+        }
+        ExprKind::Call(callee, args) => {
+            uses_placeholder(callee) || args.iter().any(uses_placeholder)
+        }
+        ExprKind::Record(fields) => fields.iter().any(|(_, value)| uses_placeholder(value)),
+        ExprKind::Field(record, _) => uses_placeholder(record),
+        ExprKind::Update { record, fields } => {
+            uses_placeholder(record) || fields.iter().any(|(_, value)| uses_placeholder(value))
+        }
+        ExprKind::Match { scrutinee, arms } => {
+            uses_placeholder(scrutinee) || arms.iter().any(|arm| uses_placeholder(&arm.body))
+        }
+        ExprKind::Toss { arg: Some(arg), .. } => uses_placeholder(arg),
+        ExprKind::Catch { expr, .. } | ExprKind::Retoss { expr, .. } => uses_placeholder(expr),
+        ExprKind::Int(_) | ExprKind::String(_) | ExprKind::Toss { arg: None, .. } => false,
+    }
+}
+
+/// The first `toss` evaluating `expr` always reaches. The body of a `fn`
+/// may never run.
+fn first_toss(expr: &Expr) -> Option<Span> {
+    match &expr.kind {
+        ExprKind::Toss { arg, .. } => arg.as_deref().and_then(first_toss).or(Some(expr.span)),
+        ExprKind::Pipeline(lhs, rhs)
+        | ExprKind::Index(lhs, rhs)
+        | ExprKind::Binary(_, lhs, rhs) => first_toss(lhs).or_else(|| first_toss(rhs)),
+        ExprKind::List(items) | ExprKind::Tuple(items) => items.iter().find_map(first_toss),
+        ExprKind::Call(callee, args) => {
+            first_toss(callee).or_else(|| args.iter().find_map(first_toss))
+        }
+        ExprKind::Record(fields) => fields.iter().find_map(|(_, value)| first_toss(value)),
+        ExprKind::Field(record, _) => first_toss(record),
+        // A `catch` may stop what is tossed inside it, and a `retoss` only
+        // tosses an `Err` or `None`.
+        ExprKind::Retoss { expr, .. } => first_toss(expr),
+        ExprKind::Catch { .. } => None,
+        // Only the scrutinee is always evaluated.
+        ExprKind::Match { scrutinee, .. } => first_toss(scrutinee),
+        ExprKind::Update { record, fields } => {
+            first_toss(record).or_else(|| fields.iter().find_map(|(_, value)| first_toss(value)))
+        }
+        ExprKind::Ident(_) | ExprKind::Int(_) | ExprKind::String(_) | ExprKind::Lambda { .. } => {
+            None
+        }
+    }
+}
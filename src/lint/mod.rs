@@ -0,0 +1,4 @@
+pub mod linter;
+pub mod warning;
+pub use linter::{LINTS, lint_program};
+pub use warning::Warning;
@@ -0,0 +1,70 @@
+use crate::typechecker::types::Type;
+use crate::util::span::Span;
+use thiserror::Error;
+
+#[derive(Debug, Error, PartialEq)]
+pub enum Warning {
+    #[error("unused binding `{1}`")]
+    UnusedBinding(Span, String),
+
+    /// The shadowing statement, the name, and the earlier binding.
+    #[error("`{1}` shadows an earlier binding")]
+    Shadowed(Span, String, Span),
+
+    #[error("the right side of this pipeline ignores `_`")]
+    IgnoredPlaceholder(Span),
+
+    #[error("value of type `{1}` is discarded")]
+    UnusedValue(Span, Type),
+
+    /// The first unreachable statement, and the `toss` before it.
+    #[error("unreachable statement")]
+    Unreachable(Span, Span),
+
+    /// The name in `#allow(...)`, and the closest known lint.
+    #[error("unknown lint `{1}`")]
+    UnknownLint(Span, String, Option<&'static str>),
+
+    /// The repeated `use`, the name, and the first `use` of it.
+    #[error("`{1}` is already imported")]
+    DuplicateUse(Span, String, Span),
+}
+
+impl Warning {
+    pub fn code(&self) -> &'static str {
+        match self {
+            Warning::UnusedBinding(..) => "W0001",
+            Warning::Shadowed(..) => "W0002",
+            Warning::IgnoredPlaceholder(_) => "W0003",
+            Warning::UnusedValue(..) => "W0004",
+            Warning::Unreachable(..) => "W0005",
+            Warning::UnknownLint(..) => "W0006",
+            Warning::DuplicateUse(..) => "W0007",
+        }
+    }
+
+    /// The name `#allow(...)` silences this warning with.
+    pub fn lint(&self) -> &'static str {
+        match self {
+            Warning::UnusedBinding(..) => "unused_binding",
+            Warning::Shadowed(..) => "shadowing",
+            Warning::IgnoredPlaceholder(_) => "ignored_placeholder",
+            Warning::UnusedValue(..) => "unused_value",
+            Warning::Unreachable(..) => "unreachable",
+            Warning::UnknownLint(..) => "unknown_lint",
+            Warning::DuplicateUse(..) => "duplicate_use",
+        }
+    }
+
+    pub fn span(&self) -> Span {
+        match self {
+            Warning::UnusedBinding(span, _)
+            | Warning::Shadowed(span, _, _)
+            | Warning::IgnoredPlaceholder(span)
+            | Warning::UnusedValue(span, _)
+            | Warning::Unreachable(span, _)
+            | Warning::UnknownLint(span, _, _)
+            | Warning::DuplicateUse(span, _, _) => *span,
+        }
+    }
+}
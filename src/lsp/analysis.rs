@@ -0,0 +1,319 @@
+use crate::ast::ast::{Expr, ExprKind, Statement, StatementKind};
+use crate::errors::diagnostic::{Diagnostic, Severity};
+use crate::lexer::{Lexer, SpannedToken, Token};
+use crate::lint::lint_program;
+use crate::modules::Loader;
+use crate::project;
+use crate::typechecker::checker::{
+    TypeEnv, pattern_bindings, resolve, typecheck_expr, typecheck_stmt,
+};
+use crate::typechecker::types::Type;
+use crate::util::source_map::SourceMap;
+use crate::util::span::Span;
+use std::collections::HashMap;
+
+/// The type of one expression or binding name in the document.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypedSpan {
+    pub span: Span,
+    pub ty: Type,
+    /// For `_`, the pipeline stage it is bound to.
+    pub bound_to: Option<Span>,
+}
+
+/// A top-level `grab` binding.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Symbol {
+    pub name: String,
+    /// The whole statement.
+    pub span: Span,
+    pub name_span: Span,
+    pub ty: Option<Type>,
+}
+
+/// Everything the server knows about one version of a document.
+#[derive(Debug, Default)]
+pub struct Analysis {
+    pub diagnostics: Vec<Diagnostic>,
+    pub types: Vec<TypedSpan>,
+    /// Pairs of (use, definition).
+    pub definitions: Vec<(Span, Span)>,
+    pub symbols: Vec<Symbol>,
+}
+
+impl Analysis {
+    /// Analyzes `src`, the text of `filename`, together with the modules
+    /// it uses.
+    pub fn new(src: &str, filename: &str) -> Self {
+        let mut analysis = Self::default();
+
+        // A broken manifest is reported by `sauce check`; modules beside
+        // the file still resolve without it.
+        let mut loader =
+            project::loader_for(filename).unwrap_or_else(|_| Loader::for_file(filename));
+        let ast = match loader.load(filename, src) {
+            Ok(ast) => ast,
+            Err(e) => {
+                analysis.diagnostics.push((&e).into());
+                return analysis.in_document(src, &loader.sources);
+            }
+        };
+        // The document is the first file, so its tokens need no offset.
+        let tokens = Lexer::new(src)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap_or_default();
+
+        analysis.statements(&tokens, &ast.items, TypeEnv::new(), true);
+
+        // Lints assume a program that typechecks.
+        if analysis.diagnostics.is_empty() {
+            let warnings = lint_program(&ast);
+            analysis.diagnostics.extend(warnings.iter().map(Into::into));
+        }
+
+        analysis.in_document(src, &loader.sources)
+    }
+
+    /// Drops what other modules contributed, except their errors, which
+    /// lose their span and name their file instead.
+    fn in_document(mut self, src: &str, sources: &SourceMap) -> Self {
+        let inside = |span: Span| span.end <= src.len();
+
+        self.types.retain(|t| inside(t.span));
+        self.definitions
+            .retain(|(usage, def)| inside(*usage) && inside(*def));
+        self.symbols.retain(|s| inside(s.span));
+
+        self.diagnostics.retain_mut(|d| match d.span {
+            Some(span) if !inside(span) => {
+                if let Some(file) = sources.file(span.start) {
+                    d.message = format!("{}: {}", file.name, d.message);
+                }
+                d.span = None;
+                d.labels.clear();
+                d.severity == Severity::Error
+            }
+            _ => true,
+        });
+        self
+    }
+
+    /// Walks one scope: the top level, or the body of a test. Only
+    /// top-level bindings become symbols.
+    fn statements(
+        &mut self,
+        tokens: &[SpannedToken],
+        stmts: &[Statement],
+        mut env: TypeEnv,
+        top_level: bool,
+    ) {
+        let mut defs = HashMap::new();
+
+        for stmt in stmts {
+            // Walk the statement before checking it, so a `grab` does not
+            // see its own binding.
+            match &stmt.kind {
+                StatementKind::Let { expr, .. }
+                | StatementKind::Destructure { expr, .. }
+                | StatementKind::Yell { expr }
+                | StatementKind::ExprStmt(expr)
+                | StatementKind::Toss { expr }
+                | StatementKind::Assert { expr } => self.expr(&env, &defs, expr),
+                StatementKind::AssertEq { left, right } => {
+                    self.expr(&env, &defs, left);
+                    self.expr(&env, &defs, right);
+                }
+                StatementKind::Test { body, .. } => {
+                    self.statements(tokens, body, env.scope(), false)
+                }
+                StatementKind::Use { .. }
+                | StatementKind::TypeDecl { .. }
+                | StatementKind::SumDecl { .. }
+                | StatementKind::EffectDecl { .. } => {}
+            }
+
+            // A test reports its own errors as it is walked.
+            let checked = match &stmt.kind {
+                StatementKind::Test { .. } => Ok(()),
+                _ => typecheck_stmt(&mut env, stmt),
+            };
+            if let Err(e) = &checked {
+                self.diagnostics.push(e.into());
+            }
+
+            let bound = match &stmt.kind {
+                StatementKind::Let { name, .. } => {
+                    vec![(name.as_str(), binding_name_span(tokens, stmt.span))]
+                }
+                StatementKind::Destructure { pattern, .. } => pattern.bindings(),
+                _ => Vec::new(),
+            };
+            for (name, name_span) in bound {
+                let ty = checked.as_ref().ok().and_then(|_| env.get(name).cloned());
+                if let Some(ty) = &ty {
+                    self.types.push(TypedSpan {
+                        span: name_span,
+                        ty: ty.clone(),
+                        bound_to: None,
+                    });
+                    defs.insert(name.to_string(), name_span);
+                }
+
+                if top_level {
+                    self.symbols.push(Symbol {
+                        name: name.to_string(),
+                        span: stmt.span,
+                        name_span,
+                        ty,
+                    });
+                }
+            }
+        }
+    }
+
+    /// The innermost typed span containing `offset`.
+    pub fn type_at(&self, offset: usize) -> Option<&TypedSpan> {
+        self.types
+            .iter()
+            .filter(|t| contains(t.span, offset))
+            .min_by_key(|t| t.span.len())
+    }
+
+    /// Where the identifier at `offset` was bound.
+    pub fn definition_at(&self, offset: usize) -> Option<Span> {
+        self.definitions
+            .iter()
+            .find(|(usage, _)| contains(*usage, offset))
+            .map(|(_, def)| *def)
+    }
+
+    fn expr(&mut self, env: &TypeEnv, defs: &HashMap<String, Span>, expr: &Expr) {
+        let bound_to = match &expr.kind {
+            ExprKind::Ident(name) if name == "_" => defs.get(name).copied(),
+            _ => None,
+        };
+        if let Ok(ty) = typecheck_expr(env, expr) {
+            self.types.push(TypedSpan {
+                span: expr.span,
+                ty,
+                bound_to,
+            });
+        }
+
+        match &expr.kind {
+            ExprKind::Ident(name) => {
+                if let Some(def) = defs.get(name) {
+                    self.definitions.push((expr.span, *def));
+                }
+            }
+            ExprKind::Pipeline(lhs, rhs) => {
+                self.expr(env, defs, lhs);
+
+                // Mirrors the checker: `rhs` sees `_` bound to `lhs`.
+                let mut env = env.clone();
+                if let Ok(ty) = typecheck_expr(&env, lhs) {
+                    env.insert("_".to_string(), ty);
+                }
+                let mut defs = defs.clone();
+                defs.insert("_".to_string(), lhs.span);
+                self.expr(&env, &defs, rhs);
+            }
+            ExprKind::List(items) | ExprKind::Tuple(items) => {
+                for item in items {
+                    self.expr(env, defs, item);
+                }
+            }
+            ExprKind::Index(target, index) => {
+                self.expr(env, defs, target);
+                self.expr(env, defs, index);
+            }
+            ExprKind::Lambda { params, body, .. } => {
+                // Parameters hide outer names; their types come from their
+                // annotations or the stage the `fn` is passed to.
+                let mut env = env.clone();
+                let mut defs = defs.clone();
+                for param in params {
+                    match param.ty.as_ref().map(|ty| resolve(&env, ty)) {
+                        Some(Ok(ty)) => env.insert(param.name.clone(), ty),
+                        _ => env.remove(&param.name),
+                    }
+                    defs.insert(param.name.clone(), param.span);
+                }
+                self.expr(&env, &defs, body);
+            }
+            ExprKind::Call(callee, args) => {
+                self.expr(env, defs, callee);
+                for arg in args {
+                    self.expr(env, defs, arg);
+                }
+            }
+            ExprKind::Binary(_, left, right) => {
+                self.expr(env, defs, left);
+                self.expr(env, defs, right);
+            }
+            ExprKind::Record(fields) => {
+                for (_, value) in fields {
+                    self.expr(env, defs, value);
+                }
+            }
+            ExprKind::Field(record, _) => self.expr(env, defs, record),
+            ExprKind::Update { record, fields } => {
+                self.expr(env, defs, record);
+                for (_, value) in fields {
+                    self.expr(env, defs, value);
+                }
+            }
+            ExprKind::Match { scrutinee, arms } => {
+                self.expr(env, defs, scrutinee);
+                let scrutinee_ty = typecheck_expr(env, scrutinee).ok();
+                for arm in arms {
+                    // Mirrors the checker: each arm sees what its pattern
+                    // binds.
+                    let mut env = env.clone();
+                    let mut defs = defs.clone();
+                    let bound = scrutinee_ty
+                        .as_ref()
+                        .and_then(|ty| pattern_bindings(&env, &arm.pattern, ty).ok());
+                    for (name, span) in arm.pattern.bindings() {
+                        let ty = bound
+                            .iter()
+                            .flatten()
+                            .find(|(bound, _)| bound == name)
+                            .map(|(_, ty)| ty.clone());
+                        match ty {
+                            Some(ty) => {
+                                self.types.push(TypedSpan {
+                                    span,
+                                    ty: ty.clone(),
+                                    bound_to: None,
+                                });
+                                env.insert(name.to_string(), ty);
+                            }
+                            None => env.remove(name),
+                        }
+                        defs.insert(name.to_string(), span);
+                    }
+                    self.expr(&env, &defs, &arm.body);
+                }
+            }
+            ExprKind::Toss { arg: Some(arg), .. } => self.expr(env, defs, arg),
+            ExprKind::Catch { expr, .. } | ExprKind::Retoss { expr, .. } => {
+                self.expr(env, defs, expr)
+            }
+            ExprKind::Int(_) | ExprKind::String(_) | ExprKind::Toss { arg: None, .. } => {}
+        }
+    }
+}
+
+fn contains(span: Span, offset: usize) -> bool {
+    span.start <= offset && offset <= span.end
+}
+
+/// The name token of the `grab` statement at `stmt`.
+fn binding_name_span(tokens: &[SpannedToken], stmt: Span) -> Span {
+    tokens
+        .iter()
+        .skip_while(|t| t.span.start < stmt.start)
+        .find(|t| matches!(t.token, Token::Ident(_)))
+        .map_or(stmt, |t| t.span)
+}
@@ -0,0 +1,12 @@
+pub mod analysis;
+pub mod server;
+pub mod transport;
+
+pub use server::Server;
+
+use std::io::{self, BufRead, Write};
+
+/// Serves the language server protocol on `input` and `output`.
+pub fn run(input: impl BufRead, output: impl Write) -> io::Result<()> {
+    Server::new(output).run(input)
+}
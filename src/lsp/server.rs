@@ -0,0 +1,268 @@
+use crate::errors::diagnostic::Severity;
+use crate::lsp::analysis::Analysis;
+use crate::lsp::transport::{read_body, write_message};
+use crate::util::line_index::LineIndex;
+use crate::util::span::Span;
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+/// `SymbolKind.Variable` in the LSP spec.
+const SYMBOL_VARIABLE: u32 = 13;
+/// `DiagnosticSeverity` in the LSP spec.
+fn severity(severity: Severity) -> u32 {
+    match severity {
+        Severity::Error => 1,
+        Severity::Warning => 2,
+    }
+}
+
+/// An open document and the analysis of its current text.
+struct Document {
+    text: String,
+    lines: LineIndex,
+    analysis: Analysis,
+}
+
+impl Document {
+    fn new(uri: &str, text: String) -> Self {
+        // Modules are found next to the file, so this needs a path.
+        let filename = uri.strip_prefix("file://").unwrap_or(uri);
+        Self {
+            lines: LineIndex::new(&text),
+            analysis: Analysis::new(&text, filename),
+            text,
+        }
+    }
+
+    /// LSP positions are 0-based and count UTF-16 code units.
+    fn position(&self, offset: usize) -> Value {
+        let (line, _) = self.lines.line_col(offset);
+        let start = self.lines.line_start(line as usize - 1);
+        let character = self.text[start..offset].encode_utf16().count();
+        json!({ "line": line - 1, "character": character })
+    }
+
+    fn range(&self, span: Span) -> Value {
+        json!({ "start": self.position(span.start), "end": self.position(span.end) })
+    }
+
+    fn offset(&self, position: &Value) -> Option<usize> {
+        let line = position["line"].as_u64()? as usize;
+        let character = position["character"].as_u64()? as usize;
+
+        let start = self.lines.line_start(line);
+        let mut units = 0;
+        for (i, c) in self.text[start..].char_indices() {
+            if units >= character || c == '\n' {
+                return Some(start + i);
+            }
+            units += c.len_utf16();
+        }
+        Some(self.text.len())
+    }
+}
+
+/// A language server speaking JSON-RPC over a pair of streams.
+pub struct Server<W: Write> {
+    out: W,
+    documents: HashMap<String, Document>,
+    shutdown: bool,
+}
+
+impl<W: Write> Server<W> {
+    pub fn new(out: W) -> Self {
+        Self {
+            out,
+            documents: HashMap::new(),
+            shutdown: false,
+        }
+    }
+
+    /// Serves requests until `exit` or end of input.
+    pub fn run(&mut self, mut input: impl BufRead) -> io::Result<()> {
+        while let Some(body) = read_body(&mut input)? {
+            // A body that is not JSON has no id to answer, so the reply to
+            // it has a null one.
+            let message: Value = match serde_json::from_slice(&body) {
+                Ok(message) => message,
+                Err(e) => {
+                    let error = json!({
+                        "jsonrpc": "2.0",
+                        "id": null,
+                        "error": { "code": PARSE_ERROR, "message": e.to_string() },
+                    });
+                    write_message(&mut self.out, &error)?;
+                    continue;
+                }
+            };
+            if message["method"] == "exit" {
+                break;
+            }
+            self.handle(message)?;
+        }
+        Ok(())
+    }
+
+    fn handle(&mut self, message: Value) -> io::Result<()> {
+        let method = message["method"].as_str().unwrap_or_default();
+        let params = &message["params"];
+
+        let result = match method {
+            _ if self.shutdown => Err((INVALID_REQUEST, "server is shutting down".to_string())),
+            "initialize" => Ok(capabilities()),
+            "shutdown" => {
+                self.shutdown = true;
+                Ok(Value::Null)
+            }
+            "textDocument/didOpen" => {
+                let doc = &params["textDocument"];
+                let text = doc["text"].as_str().unwrap_or_default();
+                return self.update(doc["uri"].as_str().unwrap_or_default(), text);
+            }
+            "textDocument/didChange" => {
+                // We ask for full syncs, so the last change is the whole text.
+                let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+                let changes = params["contentChanges"].as_array();
+                return match changes.and_then(|c| c.last()) {
+                    Some(change) => self.update(uri, change["text"].as_str().unwrap_or_default()),
+                    None => Ok(()),
+                };
+            }
+            "textDocument/didClose" => {
+                let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+                self.documents.remove(uri);
+                return self.publish_diagnostics(uri, Vec::new());
+            }
+            "textDocument/hover" => self.with_document(params, hover),
+            "textDocument/definition" => self.with_document(params, definition),
+            "textDocument/documentSymbol" => {
+                self.with_document(params, |doc, _, _| Ok(document_symbols(doc)))
+            }
+            _ => Err((METHOD_NOT_FOUND, format!("unknown method `{method}`"))),
+        };
+
+        // Notifications have no id and get no response.
+        let Some(id) = message.get("id") else {
+            return Ok(());
+        };
+        let response = match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err((code, message)) => json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": { "code": code, "message": message },
+            }),
+        };
+        write_message(&mut self.out, &response)
+    }
+
+    fn update(&mut self, uri: &str, text: &str) -> io::Result<()> {
+        let doc = Document::new(uri, text.to_string());
+        let diagnostics = doc
+            .analysis
+            .diagnostics
+            .iter()
+            .map(|d| {
+                let mut message = d.message.clone();
+                for note in &d.notes {
+                    message.push_str(&format!("\nnote: {note}"));
+                }
+                json!({
+                    "range": doc.range(d.span.unwrap_or(Span::new(0, 0))),
+                    "severity": severity(d.severity),
+                    "code": d.code,
+                    "source": "sauce",
+                    "message": message,
+                })
+            })
+            .collect();
+
+        self.documents.insert(uri.to_string(), doc);
+        self.publish_diagnostics(uri, diagnostics)
+    }
+
+    fn publish_diagnostics(&mut self, uri: &str, diagnostics: Vec<Value>) -> io::Result<()> {
+        let notification = json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/publishDiagnostics",
+            "params": { "uri": uri, "diagnostics": diagnostics },
+        });
+        write_message(&mut self.out, &notification)
+    }
+
+    /// Runs a request against the document and position named in `params`.
+    fn with_document(
+        &self,
+        params: &Value,
+        f: impl FnOnce(&Document, &str, usize) -> Result<Value, (i64, String)>,
+    ) -> Result<Value, (i64, String)> {
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+        let doc = self
+            .documents
+            .get(uri)
+            .ok_or_else(|| (INVALID_PARAMS, format!("unknown document `{uri}`")))?;
+        let offset = doc.offset(&params["position"]).unwrap_or(0);
+        f(doc, uri, offset)
+    }
+}
+
+fn capabilities() -> Value {
+    json!({
+        "capabilities": {
+            "textDocumentSync": 1,
+            "hoverProvider": true,
+            "definitionProvider": true,
+            "documentSymbolProvider": true,
+        },
+        "serverInfo": { "name": "sauce", "version": env!("CARGO_PKG_VERSION") },
+    })
+}
+
+fn hover(doc: &Document, _uri: &str, offset: usize) -> Result<Value, (i64, String)> {
+    let Some(typed) = doc.analysis.type_at(offset) else {
+        return Ok(Value::Null);
+    };
+
+    let source = &doc.text[typed.span.start..typed.span.end];
+    let mut value = format!("```sauce\n{source}: {}\n```", typed.ty);
+    if let Some(bound) = typed.bound_to {
+        value.push_str(&format!(
+            "\n\n`_` is bound to `{}`",
+            &doc.text[bound.start..bound.end]
+        ));
+    }
+
+    Ok(json!({
+        "contents": { "kind": "markdown", "value": value },
+        "range": doc.range(typed.span),
+    }))
+}
+
+fn definition(doc: &Document, uri: &str, offset: usize) -> Result<Value, (i64, String)> {
+    Ok(match doc.analysis.definition_at(offset) {
+        Some(span) => json!({ "uri": uri, "range": doc.range(span) }),
+        None => Value::Null,
+    })
+}
+
+fn document_symbols(doc: &Document) -> Value {
+    doc.analysis
+        .symbols
+        .iter()
+        .map(|symbol| {
+            json!({
+                "name": symbol.name,
+                "detail": symbol.ty.as_ref().map(|ty| ty.to_string()),
+                "kind": SYMBOL_VARIABLE,
+                "range": doc.range(symbol.span),
+                "selectionRange": doc.range(symbol.name_span),
+            })
+        })
+        .collect()
+}
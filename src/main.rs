@@ -1,10 +1,18 @@
 use clap::{CommandFactory, Parser, Subcommand};
 
-use sauce::codegen::codegen;
+use sauce::codegen::{CodegenOptions, codegen, target::CompileTarget};
+use sauce::errors::codes;
+use sauce::errors::diagnostic::{Diagnostic, ErrorFormat};
+use sauce::formatter::format_source;
 use sauce::interpreter::eval::eval_program;
+use sauce::interpreter::runner::{count_tests, run_tests};
 use sauce::lexer::Lexer;
-use sauce::parser::SauceParser;
+use sauce::lint::lint_program;
+use sauce::modules::Loader;
+use sauce::project::{self, Project, ProjectError, scaffold};
 use sauce::typechecker::checker::typecheck_program;
+use sauce::util::source_map::SourceMap;
+use sauce::{lsp, repl};
 
 #[derive(Parser)]
 #[command(
@@ -28,15 +36,85 @@ struct Cli {
 
     #[command(flatten)]
     args: Args,
+
+    /// How to print diagnostics: `human` or `json`
+    #[arg(long, global = true, default_value = "human")]
+    error_format: ErrorFormat,
+
+    /// Treat warnings as errors
+    #[arg(long, global = true)]
+    deny_warnings: bool,
 }
 
 #[derive(Subcommand)]
 enum Command {
-    Run { filename: String },
-
-    Check { filename: String },
-
-    Build { filename: String },
+    /// Run a file, or the current project
+    Run { filename: Option<String> },
+
+    /// Typecheck a file, or the current project
+    Check { filename: Option<String> },
+
+    /// Compile a file, or the current project, to LLVM IR
+    Build {
+        filename: Option<String>,
+
+        /// Emit DWARF debug info
+        #[arg(short = 'g')]
+        debug_info: bool,
+
+        /// Target triple to emit an object file for
+        #[arg(long)]
+        target: Option<CompileTarget>,
+
+        /// Path to write `<output>.ll` and `<output>.o` to [default: the
+        /// input file's name, in the current directory]
+        #[arg(short, long)]
+        output: Option<String>,
+    },
+
+    /// Start an interactive session
+    Repl,
+
+    /// Run the language server on stdin and stdout
+    Lsp,
+
+    /// Print the long explanation of an error code, e.g. `E0201`
+    Explain { code: String },
+
+    /// Run the `test` blocks in files, or in every `.sauce` file here
+    Test {
+        filenames: Vec<String>,
+
+        /// Only run tests whose name contains this
+        #[arg(long)]
+        filter: Option<String>,
+    },
+
+    /// Create a project in a new directory
+    New {
+        path: String,
+
+        /// Package name; defaults to the directory's name
+        #[arg(long)]
+        name: Option<String>,
+    },
+
+    /// Create a project in the current directory
+    Init {
+        /// Package name; defaults to the directory's name
+        #[arg(long)]
+        name: Option<String>,
+    },
+
+    /// Rewrite files in the canonical style
+    Fmt {
+        #[arg(required = true)]
+        filenames: Vec<String>,
+
+        /// Report unformatted files instead of rewriting them
+        #[arg(long)]
+        check: bool,
+    },
 }
 
 #[derive(Parser)]
@@ -63,21 +141,89 @@ struct Args {
     /// Run the program using the interpreter
     #[arg(long)]
     run: bool,
+
+    /// Emit DWARF debug info when compiling
+    #[arg(short = 'g')]
+    debug_info: bool,
+
+    /// Target triple to emit an object file for
+    #[arg(long)]
+    target: Option<CompileTarget>,
 }
 
 fn main() {
     let cli = Cli::parse();
+    let format = cli.error_format;
+    let deny_warnings = cli.deny_warnings;
 
     if let Some(cmd) = &cli.command {
         match cmd {
             Command::Run { filename } => {
-                run_pipeline(filename, Mode::Run);
+                let filename = entry_file(filename.as_deref(), format);
+                run_pipeline(&filename, Mode::Run, format, deny_warnings);
             }
             Command::Check { filename } => {
-                run_pipeline(filename, Mode::Check);
+                let filename = entry_file(filename.as_deref(), format);
+                run_pipeline(&filename, Mode::Check, format, deny_warnings);
+            }
+            Command::Build {
+                filename,
+                debug_info,
+                target,
+                output,
+            } => {
+                let filename = entry_file(filename.as_deref(), format);
+                let output = output_path(&filename, output.as_deref());
+                run_pipeline(
+                    &filename,
+                    Mode::Build {
+                        debug_info: *debug_info,
+                        target: *target,
+                        output,
+                    },
+                    format,
+                    deny_warnings,
+                );
+            }
+            Command::Repl => {
+                println!("Sauce v{} — :help for commands", env!("CARGO_PKG_VERSION"));
+                let stdin = std::io::stdin();
+                repl::run(stdin.lock(), std::io::stdout(), format).unwrap_or_else(|e| {
+                    eprintln!("repl error: {e}");
+                    std::process::exit(1);
+                });
+            }
+            Command::Lsp => {
+                let stdin = std::io::stdin();
+                lsp::run(stdin.lock(), std::io::stdout()).unwrap_or_else(|e| {
+                    eprintln!("lsp error: {e}");
+                    std::process::exit(1);
+                });
             }
-            Command::Build { filename } => {
-                run_pipeline(filename, Mode::Build);
+            Command::Explain { code } => {
+                explain(code, format);
+            }
+            Command::Test { filenames, filter } => {
+                test(filenames, filter.as_deref(), format, deny_warnings);
+            }
+            Command::New { path, name } => {
+                let dir = std::path::Path::new(path);
+                let name = package_name(dir, name.as_deref());
+                let files =
+                    scaffold::new_project(dir, &name).unwrap_or_else(|e| project_error(&e, format));
+                create(&files, format);
+                println!("Created project `{name}` in {path}");
+            }
+            Command::Init { name } => {
+                let dir = std::env::current_dir().unwrap_or_default();
+                let name = package_name(&dir, name.as_deref());
+                let files = scaffold::init_project(std::path::Path::new(""), &name)
+                    .unwrap_or_else(|e| project_error(&e, format));
+                create(&files, format);
+                println!("Created project `{name}`");
+            }
+            Command::Fmt { filenames, check } => {
+                fmt(filenames, *check, format);
             }
         }
         return;
@@ -90,17 +236,28 @@ fn main() {
         std::process::exit(0);
     });
 
-    let src = read_file(filename);
-    let tokens = lex(&src);
+    let src = read_file(filename, format);
+    let mut loader = loader_for(filename, format);
+    let loaded = loader.load(filename, &src);
+    let report = Report {
+        format,
+        sources: &loader.sources,
+        deny_warnings,
+    };
 
     if args.tokens {
+        // The entry file comes first in the source map, so its spans need
+        // no offset.
+        let tokens = Lexer::new(&src)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap_or_else(|e| report.fail(&e));
         output("Tokens", &format!("{:#?}", &tokens), &args.output, "tokens");
         if !args.ast && !args.check && !args.run {
             return;
         }
     }
 
-    let ast = parse(&tokens);
+    let ast = loaded.unwrap_or_else(|e| report.fail(&e));
 
     if args.ast {
         output("AST", &format!("{:#?}", &ast), &args.output, "ast");
@@ -109,79 +266,323 @@ fn main() {
         }
     }
 
-    typecheck_program(&ast).unwrap_or_else(|e| {
-        eprintln!("typecheck error: {e}");
-        std::process::exit(1);
-    });
+    typecheck_program(&ast).unwrap_or_else(|e| report.fail(&e));
+    report.lint(&ast);
 
     if args.check {
         return;
     }
 
     if args.run {
-        eval_program(&ast).unwrap_or_else(|e| {
-            eprintln!("runtime error: {e}");
-            std::process::exit(1);
-        });
+        eval_program(&ast).unwrap_or_else(|e| report.fail(&e));
         return;
     }
 
-    codegen(&ast);
+    build(
+        &report,
+        &ast,
+        &CodegenOptions {
+            debug_info: args.debug_info,
+            filename,
+            source: &src,
+            target: args.target,
+            output: &output_path(filename, args.output.as_deref()),
+        },
+    );
 }
 
 enum Mode {
     Run,
     Check,
-    Build,
+    Build {
+        debug_info: bool,
+        target: Option<CompileTarget>,
+        output: String,
+    },
 }
 
-fn run_pipeline(filename: &str, mode: Mode) {
-    let src = read_file(filename);
-    let tokens = lex(&src);
-    let ast = parse(&tokens);
+/// Where diagnostics about a program go, and in which format.
+struct Report<'a> {
+    format: ErrorFormat,
+    sources: &'a SourceMap,
+    deny_warnings: bool,
+}
+
+impl Report<'_> {
+    fn print(&self, diagnostic: &Diagnostic) {
+        eprintln!("{}", self.sources.render(diagnostic, self.format));
+    }
 
-    typecheck_program(&ast).unwrap_or_else(|e| {
-        eprintln!("typecheck error: {e}");
+    fn fail<'e, E>(&self, error: &'e E) -> !
+    where
+        Diagnostic: From<&'e E>,
+    {
+        self.print(&Diagnostic::from(error));
         std::process::exit(1);
-    });
+    }
+
+    /// Prints the program's warnings, and stops if they are denied.
+    fn lint(&self, ast: &sauce::ast::ast::Ast) {
+        let warnings = lint_program(ast);
+        for warning in &warnings {
+            self.print(&warning.into());
+        }
+
+        if self.deny_warnings && !warnings.is_empty() {
+            let count = match warnings.len() {
+                1 => "1 warning".to_string(),
+                n => format!("{n} warnings"),
+            };
+            self.print(&Diagnostic::error(
+                "lint",
+                format!("aborting due to {count} (--deny-warnings)"),
+            ));
+            std::process::exit(1);
+        }
+    }
+}
+
+fn run_pipeline(filename: &str, mode: Mode, format: ErrorFormat, deny_warnings: bool) {
+    let src = read_file(filename, format);
+    let mut loader = loader_for(filename, format);
+    let loaded = loader.load(filename, &src);
+    let report = Report {
+        format,
+        sources: &loader.sources,
+        deny_warnings,
+    };
+    let ast = loaded.unwrap_or_else(|e| report.fail(&e));
+
+    typecheck_program(&ast).unwrap_or_else(|e| report.fail(&e));
+    report.lint(&ast);
 
     match mode {
         Mode::Check => {}
         Mode::Run => {
-            eval_program(&ast).unwrap_or_else(|e| {
-                eprintln!("runtime error: {e}");
-                std::process::exit(1);
-            });
+            eval_program(&ast).unwrap_or_else(|e| report.fail(&e));
         }
-        Mode::Build => {
-            codegen(&ast);
+        Mode::Build {
+            debug_info,
+            target,
+            output,
+        } => {
+            build(
+                &report,
+                &ast,
+                &CodegenOptions {
+                    debug_info,
+                    filename,
+                    source: &src,
+                    target,
+                    output: &output,
+                },
+            );
         }
     }
 }
 
-fn read_file(filename: &str) -> String {
-    std::fs::read_to_string(filename).unwrap_or_else(|e| {
-        eprintln!("Error reading {}: {}", filename, e);
-        std::process::exit(1);
-    })
+/// `output`, or the name of `filename` without its extension, so that
+/// `src/app.sauce` builds `app.ll` in the current directory.
+fn output_path(filename: &str, output: Option<&str>) -> String {
+    output.map_or_else(
+        || {
+            std::path::Path::new(filename).file_stem().map_or_else(
+                || "out".to_string(),
+                |stem| stem.to_string_lossy().into_owned(),
+            )
+        },
+        str::to_string,
+    )
+}
+
+fn build(report: &Report, ast: &sauce::ast::ast::Ast, options: &CodegenOptions) {
+    codegen(ast, options).unwrap_or_else(|e| report.fail(&e));
 }
 
-fn lex(src: &str) -> Vec<sauce::lexer::SpannedToken> {
-    let lexer = Lexer::new(src);
-    lexer.collect::<Result<_, _>>().unwrap_or_else(|e| {
-        eprintln!("lex error: {e}");
+fn test(filenames: &[String], filter: Option<&str>, format: ErrorFormat, deny_warnings: bool) {
+    let filenames = if filenames.is_empty() {
+        // In a project, its sources; else everything here.
+        let dir = match Project::find(std::path::Path::new("")) {
+            Ok(Some(project)) => project.source_root(),
+            Ok(None) => std::path::PathBuf::new(),
+            Err(e) => project_error(&e, format),
+        };
+        let dir = if dir.as_os_str().is_empty() {
+            std::path::Path::new(".")
+        } else {
+            &dir
+        };
+        let mut found = Vec::new();
+        find_sources(dir, &mut found);
+        found.sort();
+        found
+    } else {
+        filenames.to_vec()
+    };
+
+    let (mut passed, mut failed, mut filtered) = (0, 0, 0);
+    for filename in &filenames {
+        let src = read_file(filename, format);
+        let mut loader = loader_for(filename, format);
+        let loaded = loader.load(filename, &src);
+        let report = Report {
+            format,
+            sources: &loader.sources,
+            deny_warnings,
+        };
+        let ast = loaded.unwrap_or_else(|e| report.fail(&e));
+        typecheck_program(&ast).unwrap_or_else(|e| report.fail(&e));
+        report.lint(&ast);
+
+        let outcomes = run_tests(&ast, filter);
+        filtered += count_tests(&ast) - outcomes.len();
+        if outcomes.is_empty() {
+            continue;
+        }
+
+        let count = match outcomes.len() {
+            1 => "1 test".to_string(),
+            n => format!("{n} tests"),
+        };
+        println!("running {count} in {filename}");
+        for outcome in &outcomes {
+            match &outcome.result {
+                Ok(()) => {
+                    println!("test {} ... ok", outcome.name);
+                    passed += 1;
+                }
+                Err(e) => {
+                    println!("test {} ... FAILED", outcome.name);
+                    report.print(&e.into());
+                    failed += 1;
+                }
+            }
+        }
+        println!();
+    }
+
+    let status = if failed == 0 { "ok" } else { "FAILED" };
+    println!("test result: {status}. {passed} passed; {failed} failed; {filtered} filtered out");
+    if failed > 0 {
         std::process::exit(1);
-    })
+    }
+}
+
+/// Every `.sauce` file under `dir`, skipping hidden directories and
+/// `target`.
+fn find_sources(dir: &std::path::Path, found: &mut Vec<String>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        if path.is_dir() {
+            if !name.starts_with('.') && name != "target" {
+                find_sources(&path, found);
+            }
+        } else if path.extension().is_some_and(|ext| ext == "sauce") {
+            let path = path.strip_prefix(".").unwrap_or(&path);
+            found.push(path.to_string_lossy().into_owned());
+        }
+    }
 }
 
-fn parse(tokens: &[sauce::lexer::SpannedToken]) -> sauce::ast::ast::Ast {
-    let parser = SauceParser::new();
-    parser.parse(tokens).unwrap_or_else(|e| {
-        eprintln!("parse error: {e}");
+fn fmt(filenames: &[String], check: bool, format: ErrorFormat) {
+    let mut unformatted = false;
+
+    for filename in filenames {
+        let src = read_file(filename, format);
+        let mut sources = SourceMap::new();
+        sources.add(filename.as_str(), src.as_str());
+        let report = Report {
+            format,
+            sources: &sources,
+            deny_warnings: false,
+        };
+        let formatted = format_source(&src).unwrap_or_else(|e| report.fail(&e));
+
+        if formatted == src {
+            continue;
+        }
+        if check {
+            println!("would reformat {filename}");
+            unformatted = true;
+        } else if let Err(e) = std::fs::write(filename, formatted) {
+            io_error(format!("Error writing to {filename}: {e}"), format);
+        }
+    }
+
+    if unformatted {
         std::process::exit(1);
+    }
+}
+
+fn explain(code: &str, format: ErrorFormat) {
+    match codes::lookup(code) {
+        Some(entry) => print!("{}: {}\n\n{}", entry.code, entry.title, entry.explanation),
+        None => {
+            let diagnostic = Diagnostic::error("explain", format!("unknown error code `{code}`"));
+            eprintln!("{}", diagnostic.render(format, "", ""));
+            std::process::exit(1);
+        }
+    }
+}
+
+/// `filename`, or the entry file of the project we are in.
+fn entry_file(filename: Option<&str>, format: ErrorFormat) -> String {
+    if let Some(filename) = filename {
+        return filename.to_string();
+    }
+    match Project::find(std::path::Path::new("")) {
+        Ok(Some(project)) => project.entry().to_string_lossy().into_owned(),
+        Ok(None) => project_error(&ProjectError::NoProject, format),
+        Err(e) => project_error(&e, format),
+    }
+}
+
+fn loader_for(filename: &str, format: ErrorFormat) -> Loader {
+    project::loader_for(filename).unwrap_or_else(|e| project_error(&e, format))
+}
+
+/// `name`, or the name of the directory `dir`.
+fn package_name(dir: &std::path::Path, name: Option<&str>) -> String {
+    name.map(str::to_string).unwrap_or_else(|| {
+        dir.file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default()
     })
 }
 
+fn create(files: &[(std::path::PathBuf, String)], format: ErrorFormat) {
+    for (path, content) in files {
+        let written = path
+            .parent()
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .and_then(|()| std::fs::write(path, content));
+        if let Err(e) = written {
+            io_error(format!("Error writing to {}: {e}", path.display()), format);
+        }
+    }
+}
+
+fn project_error(e: &ProjectError, format: ErrorFormat) -> ! {
+    eprintln!("{}", Diagnostic::from(e).render(format, "", ""));
+    std::process::exit(1);
+}
+
+fn io_error(message: String, format: ErrorFormat) -> ! {
+    let diagnostic = Diagnostic::error("io", message);
+    eprintln!("{}", diagnostic.render(format, "", ""));
+    std::process::exit(1);
+}
+
+fn read_file(filename: &str, format: ErrorFormat) -> String {
+    std::fs::read_to_string(filename)
+        .unwrap_or_else(|e| io_error(format!("Error reading {filename}: {e}"), format))
+}
+
 fn output(label: &str, content: &str, prefix: &Option<String>, suffix: &str) {
     if let Some(p) = prefix {
         let filename = format!("{}.{}", p, suffix);
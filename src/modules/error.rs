@@ -0,0 +1,55 @@
+use crate::errors::{lex::LexError, parse::ParseError};
+use crate::util::span::Span;
+use thiserror::Error;
+
+#[derive(Debug, Error, PartialEq)]
+pub enum ModuleError {
+    /// The `use`, the module, and the file it was looked for in.
+    #[error("cannot find module `{1}` at `{2}`")]
+    NotFound(Span, String, String),
+
+    /// The `use` closing the cycle, and the modules in it, starting and
+    /// ending with the same one.
+    #[error("import cycle: {}", .1.join(" -> "))]
+    Cycle(Span, Vec<String>),
+
+    #[error("`{2}` is private to module `{1}`")]
+    Private(Span, String, String),
+
+    /// The closest exported name, if one looks like a typo of it.
+    #[error("module `{1}` has no binding `{2}`")]
+    UnknownExport(Span, String, String, Option<String>),
+}
+
+impl ModuleError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            ModuleError::NotFound(..) => "E0601",
+            ModuleError::Cycle(..) => "E0602",
+            ModuleError::Private(..) => "E0603",
+            ModuleError::UnknownExport(..) => "E0604",
+        }
+    }
+
+    pub fn span(&self) -> Span {
+        match self {
+            ModuleError::NotFound(span, _, _)
+            | ModuleError::Cycle(span, _)
+            | ModuleError::Private(span, _, _)
+            | ModuleError::UnknownExport(span, _, _, _) => *span,
+        }
+    }
+}
+
+/// Anything that stops a program's modules from being loaded.
+#[derive(Debug, Error)]
+pub enum LoadError {
+    #[error(transparent)]
+    Lex(#[from] LexError),
+
+    #[error(transparent)]
+    Parse(#[from] ParseError),
+
+    #[error(transparent)]
+    Module(#[from] ModuleError),
+}
@@ -0,0 +1,404 @@
+use crate::ast::ast::{
+    Ast, Expr, ExprKind, Pattern, PatternKind, Statement, StatementKind, TypeExpr, TypeExprKind,
+    qualify,
+};
+use crate::lexer::Lexer;
+use crate::modules::error::{LoadError, ModuleError};
+use crate::parser::SauceParser;
+use crate::util::source_map::SourceMap;
+use crate::util::span::Span;
+use crate::util::suggest::best_match;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+/// Loads a program's modules and links them into one `Ast`.
+///
+/// `use a::b::name;` reads `a/b.sauce` under the root, the directory of
+/// the entry file. A path starting with a dependency's name is resolved
+/// in that dependency instead: `use util::shout;` reads its entry file and
+/// `use util::text::shout;` reads `text.sauce` beside it. Each top-level name of a used module is renamed to its
+/// qualified form, `a::b::name`, so the typechecker, interpreter and
+/// codegen all see one program with no clashing names. A `use` stays in
+/// the program as an alias of that qualified name; one of a type becomes
+/// a `type` declaration naming it.
+#[derive(Debug, Default)]
+pub struct Loader {
+    /// Every file read so far. Spans in loaded programs point into it.
+    pub sources: SourceMap,
+    root: PathBuf,
+    /// The entry file of each dependency, by name.
+    dependencies: HashMap<String, PathBuf>,
+    /// Exports of each linked module: whether each top-level name is `pub`.
+    modules: HashMap<Vec<String>, HashMap<String, bool>>,
+    /// Qualified names of the types linked modules declare.
+    types: HashSet<String>,
+    /// Modules being loaded, outermost first.
+    loading: Vec<Vec<String>>,
+    /// Statements of used modules, dependencies first.
+    linked: Vec<Statement>,
+}
+
+impl Loader {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            sources: SourceMap::new(),
+            root: root.into(),
+            dependencies: HashMap::new(),
+            modules: HashMap::new(),
+            types: HashSet::new(),
+            loading: Vec::new(),
+            linked: Vec::new(),
+        }
+    }
+
+    /// A loader for the program whose entry file is `filename`.
+    pub fn for_file(filename: &str) -> Self {
+        // `main.sauce` has the empty parent, so module paths stay relative.
+        Self::new(Path::new(filename).parent().unwrap_or(Path::new("")))
+    }
+
+    /// Resolves paths starting with `name` in the project whose entry
+    /// file is `entry`.
+    pub fn add_dependency(&mut self, name: impl Into<String>, entry: impl Into<PathBuf>) {
+        self.dependencies.insert(name.into(), entry.into());
+    }
+
+    /// Loads the entry module `src`, read from `filename`, and links in
+    /// every module it uses. Modules linked by an earlier call are not
+    /// repeated, so a loader can be fed one input at a time.
+    pub fn load(&mut self, filename: &str, src: &str) -> Result<Ast, LoadError> {
+        let stem = Path::new(filename)
+            .file_stem()
+            .map_or(filename.into(), |s| s.to_string_lossy());
+        let entry = vec![stem.into_owned()];
+
+        let known: Vec<_> = self.modules.keys().cloned().collect();
+        let result = self
+            .parse(filename, src)
+            .and_then(|items| self.module(&entry, items, false));
+        let linked = std::mem::take(&mut self.linked);
+        self.loading.clear();
+
+        match result {
+            Ok(items) => Ok(Ast {
+                items: linked.into_iter().chain(items).collect(),
+            }),
+            Err(e) => {
+                // Forget modules whose statements were thrown away.
+                self.modules.retain(|path, _| known.contains(path));
+                Err(e)
+            }
+        }
+    }
+
+    fn parse(&mut self, filename: &str, src: &str) -> Result<Vec<Statement>, LoadError> {
+        let base = self.sources.add(filename, src);
+        let tokens = Lexer::with_offset(src, base).collect::<Result<Vec<_>, _>>()?;
+        Ok(SauceParser::new().parse(&tokens)?.items)
+    }
+
+    /// Resolves the names in one module. Used modules are loaded on the
+    /// way. With `qualified`, top-level names get their module's prefix.
+    fn module(
+        &mut self,
+        path: &[String],
+        items: Vec<Statement>,
+        qualified: bool,
+    ) -> Result<Vec<Statement>, LoadError> {
+        self.loading.push(path.to_vec());
+        // What each top-level name in this module is called once linked.
+        let mut names: HashMap<String, String> = HashMap::new();
+        let mut exports = HashMap::new();
+        let mut out = Vec::with_capacity(items.len());
+
+        for mut stmt in items {
+            match &mut stmt.kind {
+                StatementKind::Use {
+                    path: used,
+                    name,
+                    alias,
+                } => {
+                    self.import(used, name, stmt.span)?;
+                    let local = alias.clone();
+                    if qualified {
+                        *alias = qualify(path, &local);
+                    }
+                    names.insert(local, alias.clone());
+
+                    let target = qualify(used, name);
+                    if self.types.contains(&target) {
+                        let ty = TypeExpr::new(TypeExprKind::Named(target, Vec::new()), stmt.span);
+                        stmt.kind = StatementKind::TypeDecl {
+                            name: alias.clone(),
+                            ty,
+                        };
+                    }
+                }
+                StatementKind::TypeDecl { name, ty } => {
+                    rename_type(ty, &names);
+                    let local = name.clone();
+                    if qualified {
+                        *name = qualify(path, &local);
+                        self.types.insert(name.clone());
+                    }
+                    names.insert(local.clone(), name.clone());
+                    exports.insert(local, stmt.public);
+                }
+                // Constructors are exported along with their type.
+                StatementKind::SumDecl { name, variants } => {
+                    let local = name.clone();
+                    if qualified {
+                        *name = qualify(path, &local);
+                        self.types.insert(name.clone());
+                    }
+                    names.insert(local.clone(), name.clone());
+                    exports.insert(local, stmt.public);
+                    for variant in variants {
+                        for field in &mut variant.fields {
+                            rename_type(field, &names);
+                        }
+                        let local = variant.name.clone();
+                        if qualified {
+                            variant.name = qualify(path, &local);
+                        }
+                        names.insert(local.clone(), variant.name.clone());
+                        exports.insert(local, stmt.public);
+                    }
+                }
+                StatementKind::Let { name, ty, expr } => {
+                    if let Some(ty) = ty {
+                        rename_type(ty, &names);
+                    }
+                    rename(expr, &names);
+                    let local = name.clone();
+                    if qualified && local != "_" {
+                        *name = qualify(path, &local);
+                        names.insert(local.clone(), name.clone());
+                    } else {
+                        names.remove(&local);
+                    }
+                    exports.insert(local, stmt.public);
+                }
+                StatementKind::Destructure { pattern, ty, expr } => {
+                    if let Some(ty) = ty {
+                        rename_type(ty, &names);
+                    }
+                    rename(expr, &names);
+                    for name in bound_names(pattern) {
+                        let local = name.clone();
+                        if qualified {
+                            *name = qualify(path, &local);
+                            names.insert(local.clone(), name.clone());
+                        } else {
+                            names.remove(&local);
+                        }
+                        exports.insert(local, stmt.public);
+                    }
+                }
+                StatementKind::Yell { expr }
+                | StatementKind::ExprStmt(expr)
+                | StatementKind::Toss { expr }
+                | StatementKind::Assert { expr } => rename(expr, &names),
+                StatementKind::AssertEq { left, right } => {
+                    rename(left, &names);
+                    rename(right, &names);
+                }
+                // Effects are not bound to names, so they are not
+                // qualified: one declared in any module is declared in all.
+                StatementKind::EffectDecl { payload, .. } => {
+                    if let Some(payload) = payload {
+                        rename_type(payload, &names);
+                    }
+                }
+                // Only the entry module's tests run. Tests see none of the
+                // module's names, so there is nothing to rename.
+                StatementKind::Test { .. } if qualified => continue,
+                StatementKind::Test { .. } => {}
+            }
+            out.push(stmt);
+        }
+
+        self.loading.pop();
+        self.modules.insert(path.to_vec(), exports);
+        Ok(out)
+    }
+
+    /// Makes sure module `path` is linked and exports `name`.
+    fn import(&mut self, path: &[String], name: &str, span: Span) -> Result<(), LoadError> {
+        let module = path.join("::");
+
+        if let Some(start) = self.loading.iter().position(|m| m == path) {
+            let mut cycle: Vec<_> = self.loading[start..].iter().map(|m| m.join("::")).collect();
+            cycle.push(module);
+            return Err(ModuleError::Cycle(span, cycle).into());
+        }
+
+        if !self.modules.contains_key(path) {
+            let file = self.file(path);
+            let filename = file.to_string_lossy().into_owned();
+            let src = std::fs::read_to_string(&file)
+                .map_err(|_| ModuleError::NotFound(span, module.clone(), filename.clone()))?;
+
+            let items = self.parse(&filename, &src)?;
+            let stmts = self.module(path, items, true)?;
+            self.linked.extend(stmts);
+        }
+
+        let exports = &self.modules[path];
+        match exports.get(name) {
+            Some(true) => Ok(()),
+            Some(false) => Err(ModuleError::Private(span, module, name.to_string()).into()),
+            None => {
+                let public = exports
+                    .iter()
+                    .filter(|(_, public)| **public)
+                    .map(|(name, _)| name.as_str());
+                let suggestion = best_match(name, public).map(str::to_string);
+                Err(ModuleError::UnknownExport(span, module, name.to_string(), suggestion).into())
+            }
+        }
+    }
+
+    /// The file module `path` is read from.
+    fn file(&self, path: &[String]) -> PathBuf {
+        match self.dependencies.get(&path[0]) {
+            Some(entry) if path.len() == 1 => entry.clone(),
+            Some(entry) => {
+                let root = entry.parent().unwrap_or(Path::new(""));
+                root.join(path[1..].join("/")).with_extension("sauce")
+            }
+            None => self.root.join(path.join("/")).with_extension("sauce"),
+        }
+    }
+}
+
+/// Renames the module's top-level names in `expr`. A pipeline's `_` is
+/// never one of them, and a `fn`'s parameters and the names a pattern
+/// binds hide them.
+fn rename(expr: &mut Expr, names: &HashMap<String, String>) {
+    match &mut expr.kind {
+        ExprKind::Ident(name) => {
+            if let Some(linked) = names.get(name.as_str()) {
+                *name = linked.clone();
+            }
+        }
+        ExprKind::Pipeline(lhs, rhs) | ExprKind::Index(lhs, rhs) => {
+            rename(lhs, names);
+            rename(rhs, names);
+        }
+        ExprKind::List(items) | ExprKind::Tuple(items) => {
+            for item in items {
+                rename(item, names);
+            }
+        }
+        ExprKind::Lambda { params, ret, body } => {
+            for ty in params
+                .iter_mut()
+                .filter_map(|param| param.ty.as_mut())
+                .chain(ret)
+            {
+                rename_type(ty, names);
+            }
+            let mut names = names.clone();
+            names.retain(|name, _| !params.iter().any(|param| param.name == *name));
+            rename(body, &names);
+        }
+        ExprKind::Call(callee, args) => {
+            rename(callee, names);
+            for arg in args {
+                rename(arg, names);
+            }
+        }
+        ExprKind::Binary(_, left, right) => {
+            rename(left, names);
+            rename(right, names);
+        }
+        ExprKind::Record(fields) => {
+            for (_, value) in fields {
+                rename(value, names);
+            }
+        }
+        ExprKind::Field(record, _) => rename(record, names),
+        ExprKind::Update { record, fields } => {
+            rename(record, names);
+            for (_, value) in fields {
+                rename(value, names);
+            }
+        }
+        ExprKind::Match { scrutinee, arms } => {
+            rename(scrutinee, names);
+            for arm in arms {
+                rename_pattern(&mut arm.pattern, names);
+                let mut names = names.clone();
+                for (bound, _) in arm.pattern.bindings() {
+                    names.remove(bound);
+                }
+                rename(&mut arm.body, &names);
+            }
+        }
+        ExprKind::Toss { arg: Some(arg), .. } => rename(arg, names),
+        ExprKind::Catch { expr, .. } | ExprKind::Retoss { expr, .. } => rename(expr, names),
+        ExprKind::Int(_) | ExprKind::String(_) | ExprKind::Toss { arg: None, .. } => {}
+    }
+}
+
+/// Renames the module's constructors in `pattern`.
+fn rename_pattern(pattern: &mut Pattern, names: &HashMap<String, String>) {
+    match &mut pattern.kind {
+        PatternKind::Variant(name, args) => {
+            if let Some(linked) = names.get(name.as_str()) {
+                *name = linked.clone();
+            }
+            for arg in args {
+                rename_pattern(arg, names);
+            }
+        }
+        PatternKind::Tuple(items) => {
+            for item in items {
+                rename_pattern(item, names);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// The names `pattern` binds, to qualify them.
+fn bound_names(pattern: &mut Pattern) -> Vec<&mut String> {
+    match &mut pattern.kind {
+        PatternKind::Bind(name) => vec![name],
+        PatternKind::Variant(_, items) | PatternKind::Tuple(items) => {
+            items.iter_mut().flat_map(bound_names).collect()
+        }
+        PatternKind::Wildcard | PatternKind::Int(_) | PatternKind::String(_) => Vec::new(),
+    }
+}
+
+/// Renames the module's type names in `ty`.
+fn rename_type(ty: &mut TypeExpr, names: &HashMap<String, String>) {
+    match &mut ty.kind {
+        TypeExprKind::Named(name, args) => {
+            if let Some(linked) = names.get(name.as_str()) {
+                *name = linked.clone();
+            }
+            for arg in args {
+                rename_type(arg, names);
+            }
+        }
+        TypeExprKind::Record(fields) => {
+            for (_, field) in fields {
+                rename_type(field, names);
+            }
+        }
+        TypeExprKind::Tuple(items) => {
+            for item in items {
+                rename_type(item, names);
+            }
+        }
+        TypeExprKind::Function(params, ret) => {
+            for param in params {
+                rename_type(param, names);
+            }
+            rename_type(ret, names);
+        }
+    }
+}
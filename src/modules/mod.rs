@@ -0,0 +1,4 @@
+pub mod error;
+pub mod loader;
+pub use error::{LoadError, ModuleError};
+pub use loader::Loader;
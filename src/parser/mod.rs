@@ -1,3 +1,4 @@
+#[allow(clippy::module_inception)]
 pub mod parser;
 pub use parser::{
     SauceParser, parser_expr, parser_ident, parser_integer, parser_name, parser_statement,
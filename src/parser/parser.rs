@@ -1,10 +1,33 @@
-use crate::ast::ast::{Ast, Expr, Statement};
+use crate::ast::ast::{
+    Allow, Ast, BinOp, Expr, ExprKind, MatchArm, Param, Pattern, PatternKind, Statement,
+    StatementKind, TypeExpr, TypeExprKind, Variant,
+};
 use crate::errors::parse::ParseError;
 use crate::lexer::{SpannedToken, Token};
+use crate::util::span::Span;
+use crate::util::suggest::best_match;
 
 use chumsky::prelude::*;
 use chumsky::select;
 
+/// Keywords that can start a statement.
+const KEYWORDS: [&str; 10] = [
+    "grab",
+    "yell",
+    "toss",
+    "test",
+    "assert",
+    "assert_eq",
+    "pub",
+    "use",
+    "type",
+    "effect",
+];
+
+/// Parser state and error type shared by every parser in this module.
+pub type Extra<'src> = extra::Err<Rich<'src, SpannedToken>>;
+
+#[derive(Default)]
 pub struct SauceParser;
 
 impl SauceParser {
@@ -29,120 +52,996 @@ impl SauceParser {
                     .next()
                     .expect("parser reported no errors but returned Err");
 
-                Err(Self::map_error(first))
+                Err(Self::map_error(tokens, first))
             }
         }
     }
 
-    fn map_error<E: std::fmt::Display>(err: E) -> ParseError {
-        ParseError::Generic(err.to_string())
+    /// Parses a single expression with no trailing `;`.
+    pub fn parse_expr(&self, tokens: &[SpannedToken]) -> Result<Expr, ParseError> {
+        parser_expr()
+            .then_ignore(end())
+            .parse(tokens)
+            .into_result()
+            .map_err(|errors| {
+                let first = errors
+                    .into_iter()
+                    .next()
+                    .expect("parser reported no errors but returned Err");
+                Self::map_error(tokens, first)
+            })
+    }
+
+    /// Chumsky reports positions as token indices; this maps them back to
+    /// the source.
+    fn map_error(tokens: &[SpannedToken], err: Rich<'_, SpannedToken>) -> ParseError {
+        if let Some(e) = Self::misspelled_keyword(tokens, err.span().start) {
+            return e;
+        }
+
+        match err.found() {
+            Some(found) => ParseError::UnexpectedToken(found.span, found.token.to_string()),
+            None => {
+                let end = tokens.last().map_or(0, |t| t.span.end);
+                ParseError::Incomplete(Span::new(end, end))
+            }
+        }
+    }
+
+    /// Checks whether the statement holding the error at token `index`
+    /// starts with an identifier that looks like a keyword.
+    fn misspelled_keyword(tokens: &[SpannedToken], index: usize) -> Option<ParseError> {
+        let mut start = tokens[..index.min(tokens.len())]
+            .iter()
+            .rposition(|t| matches!(t.token, Token::Semicolon | Token::LBrace | Token::RBrace))
+            .map_or(0, |end| end + 1);
+        // Skip attributes: `#allow(...)` ends at its `)`.
+        while tokens.get(start).is_some_and(|t| t.token == Token::Hash) {
+            start += tokens[start..]
+                .iter()
+                .position(|t| t.token == Token::RParen)?
+                + 1;
+        }
+
+        let (first, second) = (tokens.get(start)?, tokens.get(start + 1)?);
+        let Token::Ident(name) = &first.token else {
+            return None;
+        };
+        if index != start + 1 || !matches!(second.token, Token::Ident(_)) {
+            return None;
+        }
+
+        let keyword = best_match(name, KEYWORDS)?;
+        Some(ParseError::MisspelledKeyword(
+            first.span,
+            name.clone(),
+            keyword,
+        ))
     }
 }
 
-pub fn parser_integer<'src>() -> impl Parser<'src, &'src [SpannedToken], Expr> + Clone {
+pub fn parser_integer<'src>() -> impl Parser<'src, &'src [SpannedToken], Expr, Extra<'src>> + Clone
+{
     select! {
-        SpannedToken { token: Token::Int(value), .. } => Expr::Int(value),
+        SpannedToken { token: Token::Int(value), span } => Expr::new(ExprKind::Int(value), span),
     }
 }
 
-pub fn parser_ident<'src>() -> impl Parser<'src, &'src [SpannedToken], Expr> + Clone {
+pub fn parser_ident<'src>() -> impl Parser<'src, &'src [SpannedToken], Expr, Extra<'src>> + Clone {
     select! {
-        SpannedToken { token: Token::Ident(name), .. } => Expr::Ident(name),
+        SpannedToken { token: Token::Ident(name), span } => Expr::new(ExprKind::Ident(name), span),
     }
 }
 
-pub fn parser_string<'src>() -> impl Parser<'src, &'src [SpannedToken], Expr> + Clone {
-    select! { SpannedToken { token: Token::String(value), ..} => Expr::String(value) }
+pub fn parser_string<'src>() -> impl Parser<'src, &'src [SpannedToken], Expr, Extra<'src>> + Clone {
+    select! {
+        SpannedToken { token: Token::String(value), span } => Expr::new(ExprKind::String(value), span),
+    }
 }
-pub fn parser_expr<'src>() -> impl Parser<'src, &'src [SpannedToken], Expr> + Clone {
+
+pub fn parser_expr<'src>() -> impl Parser<'src, &'src [SpannedToken], Expr, Extra<'src>> + Clone {
     recursive(|expr| {
         let toss_kw = select! {
-            SpannedToken { token: Token::Toss, .. } => (),
+            SpannedToken { token: Token::Toss, span } => span,
         };
 
         let toss_expr = toss_kw
-            .ignore_then(parser_name())
+            .then(parser_name_spanned())
             .then(expr.clone().or_not())
-            .map(|(effect, arg)| Expr::Toss {
-                effect,
-                arg: arg.map(Box::new),
+            .map(|((kw_span, (effect, name_span)), arg): (_, Option<Expr>)| {
+                let span = match &arg {
+                    Some(arg) => kw_span.union(arg.span),
+                    None => kw_span.union(name_span),
+                };
+                Expr::new(
+                    ExprKind::Toss {
+                        effect,
+                        arg: arg.map(Box::new),
+                    },
+                    span,
+                )
+            });
+
+        // `catch effect expr` and `retoss effect expr` take the rest of
+        // the expression, like a `toss` with an argument.
+        let catch_kw = select! {
+            SpannedToken { token: Token::Catch, span } => span,
+        };
+        let catch_expr = catch_kw.then(parser_name()).then(expr.clone()).map(
+            |((kw, effect), expr): ((Span, String), Expr)| {
+                let span = kw.union(expr.span);
+                let kind = ExprKind::Catch {
+                    effect,
+                    expr: Box::new(expr),
+                };
+                Expr::new(kind, span)
+            },
+        );
+        let retoss_kw = select! {
+            SpannedToken { token: Token::Retoss, span } => span,
+        };
+        let retoss_expr = retoss_kw.then(parser_name()).then(expr.clone()).map(
+            |((kw, effect), expr): ((Span, String), Expr)| {
+                let span = kw.union(expr.span);
+                let kind = ExprKind::Retoss {
+                    effect,
+                    expr: Box::new(expr),
+                };
+                Expr::new(kind, span)
+            },
+        );
+
+        let lparen = select! {
+            SpannedToken { token: Token::LParen, span } => span,
+        };
+        let rparen = select! {
+            SpannedToken { token: Token::RParen, span } => span,
+        };
+        let comma = select! {
+            SpannedToken { token: Token::Comma, .. } => (),
+        };
+
+        let fn_kw = select! {
+            SpannedToken { token: Token::Fn, span } => span,
+        };
+
+        let colon = select! {
+            SpannedToken { token: Token::Colon, .. } => (),
+        };
+        let arrow = select! {
+            SpannedToken { token: Token::Arrow, .. } => (),
+        };
+        let param = parser_name_spanned()
+            .then(colon.ignore_then(parser_type()).or_not())
+            .map(|((name, span), ty): ((String, Span), Option<TypeExpr>)| {
+                let span = ty.as_ref().map_or(span, |ty| span.union(ty.span));
+                Param { name, ty, span }
+            });
+
+        // `fn(x) body`: the body extends as far right as it can.
+        let lambda = fn_kw
+            .then(
+                param
+                    .separated_by(comma)
+                    .allow_trailing()
+                    .collect::<Vec<_>>()
+                    .delimited_by(lparen, rparen),
+            )
+            .then(arrow.ignore_then(parser_type()).or_not())
+            .then(expr.clone())
+            .map(|(((kw, params), ret), body)| {
+                let span = kw.union(body.span);
+                Expr::new(
+                    ExprKind::Lambda {
+                        params,
+                        ret,
+                        body: Box::new(body),
+                    },
+                    span,
+                )
             });
 
         let atom_base = parser_integer()
             .or(parser_ident())
             .or(parser_string())
-            .or(toss_expr);
+            .or(toss_expr)
+            .or(catch_expr)
+            .or(retoss_expr)
+            .or(lambda);
+
+        let paren_expr = parenthesized(expr.clone()).map(|(parens, span)| match parens {
+            Parens::One(inner) => Expr::new(inner.kind, span),
+            Parens::Tuple(items) => Expr::new(ExprKind::Tuple(items), span),
+        });
+
+        let lbracket = select! {
+            SpannedToken { token: Token::LBracket, span } => span,
+        };
+        let rbracket = select! {
+            SpannedToken { token: Token::RBracket, span } => span,
+        };
+
+        let list_expr = lbracket
+            .then(
+                expr.clone()
+                    .separated_by(comma)
+                    .allow_trailing()
+                    .collect::<Vec<_>>(),
+            )
+            .then(rbracket)
+            .map(|((open, items), close): ((Span, Vec<Expr>), Span)| {
+                Expr::new(ExprKind::List(items), open.union(close))
+            });
+
+        let lbrace = select! {
+            SpannedToken { token: Token::LBrace, span } => span,
+        };
+        let rbrace = select! {
+            SpannedToken { token: Token::RBrace, span } => span,
+        };
+        let colon = select! {
+            SpannedToken { token: Token::Colon, .. } => (),
+        };
+        let with_kw = select! {
+            SpannedToken { token: Token::With, .. } => (),
+        };
+
+        let fields = parser_name()
+            .then_ignore(colon)
+            .then(expr.clone())
+            .separated_by(comma)
+            .allow_trailing()
+            .at_least(1)
+            .collect::<Vec<_>>();
+        let record_expr = lbrace.then(fields.clone()).then(rbrace).map(
+            |((open, fields), close): ((Span, Vec<(String, Expr)>), Span)| {
+                Expr::new(ExprKind::Record(fields), open.union(close))
+            },
+        );
+        let update_expr = lbrace
+            .then(expr.clone())
+            .then_ignore(with_kw)
+            .then(fields)
+            .then(rbrace)
+            .map(|(((open, record), fields), close)| {
+                let kind = ExprKind::Update {
+                    record: Box::new(record),
+                    fields,
+                };
+                Expr::new(kind, open.union(close))
+            });
+
+        let match_kw = select! {
+            SpannedToken { token: Token::Match, span } => span,
+        };
+        let fat_arrow = select! {
+            SpannedToken { token: Token::FatArrow, .. } => (),
+        };
+        let arm = parser_pattern()
+            .then_ignore(fat_arrow)
+            .then(expr.clone())
+            .map(|(pattern, body)| MatchArm { pattern, body });
+        let match_expr = match_kw
+            .then(expr.clone())
+            .then_ignore(lbrace)
+            .then(
+                arm.separated_by(comma)
+                    .allow_trailing()
+                    .at_least(1)
+                    .collect::<Vec<_>>(),
+            )
+            .then(rbrace)
+            .map(|(((kw, scrutinee), arms), close)| {
+                let kind = ExprKind::Match {
+                    scrutinee: Box::new(scrutinee),
+                    arms,
+                };
+                Expr::new(kind, kw.union(close))
+            });
+
+        // `xs[0][1]`, `f(a)(b)` and `p.name`: indexing, calls and field
+        // access bind tightest.
+        let index = lbracket
+            .ignore_then(expr.clone())
+            .then(rbracket)
+            .map(|(index, close)| (Postfix::Index(index), close));
+        let call = lparen
+            .ignore_then(
+                expr.clone()
+                    .separated_by(comma)
+                    .allow_trailing()
+                    .collect::<Vec<_>>(),
+            )
+            .then(rparen)
+            .map(|(args, close)| (Postfix::Call(args), close));
+        let dot = select! {
+            SpannedToken { token: Token::Dot, .. } => (),
+        };
+        let field = dot
+            .ignore_then(parser_name_spanned())
+            .map(|(name, span)| (Postfix::Field(name), span));
+        let atom = atom_base
+            .or(paren_expr)
+            .or(list_expr)
+            .or(record_expr)
+            .or(update_expr)
+            .or(match_expr)
+            .foldl(
+                index.or(call).or(field).repeated(),
+                |target, (postfix, close)| {
+                    let span = target.span.union(close);
+                    let kind = match postfix {
+                        Postfix::Index(index) => ExprKind::Index(Box::new(target), Box::new(index)),
+                        Postfix::Call(args) => ExprKind::Call(Box::new(target), args),
+                        Postfix::Field(name) => ExprKind::Field(Box::new(target), name),
+                    };
+                    Expr::new(kind, span)
+                },
+            );
+
+        // From tightest to loosest: `* / %`, `+ -`, `|>`, comparisons.
+        let product = binary(
+            atom,
+            select! {
+                SpannedToken { token: Token::Star, .. } => BinOp::Mul,
+                SpannedToken { token: Token::Slash, .. } => BinOp::Div,
+                SpannedToken { token: Token::Percent, .. } => BinOp::Rem,
+            },
+        );
+        let sum = binary(
+            product,
+            select! {
+                SpannedToken { token: Token::Plus, .. } => BinOp::Add,
+                SpannedToken { token: Token::Minus, .. } => BinOp::Sub,
+            },
+        );
+
+        let pipe = select! {
+            SpannedToken { token: Token::Pipe, .. } => (),
+        };
+        let pipeline = sum
+            .clone()
+            .foldl(pipe.ignore_then(sum).repeated(), |left, right| {
+                let span = left.span.union(right.span);
+                Expr::new(ExprKind::Pipeline(Box::new(left), Box::new(right)), span)
+            });
+
+        // Comparisons do not chain: `a < b < c` is an error.
+        let comparison = select! {
+            SpannedToken { token: Token::EqEq, .. } => BinOp::Eq,
+            SpannedToken { token: Token::NotEq, .. } => BinOp::Ne,
+            SpannedToken { token: Token::Lt, .. } => BinOp::Lt,
+            SpannedToken { token: Token::Le, .. } => BinOp::Le,
+            SpannedToken { token: Token::Gt, .. } => BinOp::Gt,
+            SpannedToken { token: Token::Ge, .. } => BinOp::Ge,
+        };
+        pipeline
+            .clone()
+            .then(comparison.then(pipeline).or_not())
+            .map(|(left, right)| match right {
+                Some((op, right)) => binary_expr(op, left, right),
+                None => left,
+            })
+    })
+}
+
+/// What follows an atom: `[index]`, `(args)` or `.field`.
+enum Postfix {
+    Index(Expr),
+    Call(Vec<Expr>),
+    Field(String),
+}
+
+/// What [`parenthesized`] finds between the parentheses.
+enum Parens<T> {
+    /// `(a)`
+    One(T),
+    /// `(a, b)` or `(a,)`: a comma makes a tuple.
+    Tuple(Vec<T>),
+}
+
+/// `item` in parentheses, or a tuple of them, with the span of both
+/// parentheses.
+fn parenthesized<'src, T>(
+    item: impl Parser<'src, &'src [SpannedToken], T, Extra<'src>> + Clone,
+) -> impl Parser<'src, &'src [SpannedToken], (Parens<T>, Span), Extra<'src>> + Clone {
+    let lparen = select! {
+        SpannedToken { token: Token::LParen, span } => span,
+    };
+    let rparen = select! {
+        SpannedToken { token: Token::RParen, span } => span,
+    };
+    let comma = select! {
+        SpannedToken { token: Token::Comma, .. } => (),
+    };
+
+    lparen
+        .then(item.clone())
+        .then(comma.ignore_then(item).repeated().collect::<Vec<_>>())
+        .then(comma.or_not())
+        .then(rparen)
+        .map(|((((open, first), rest), trailing), close)| {
+            let parens = if rest.is_empty() && trailing.is_none() {
+                Parens::One(first)
+            } else {
+                Parens::Tuple(std::iter::once(first).chain(rest).collect())
+            };
+            (parens, open.union(close))
+        })
+}
+
+/// Left-associative `operand (op operand)*`.
+fn binary<'src>(
+    operand: impl Parser<'src, &'src [SpannedToken], Expr, Extra<'src>> + Clone,
+    op: impl Parser<'src, &'src [SpannedToken], BinOp, Extra<'src>> + Clone,
+) -> impl Parser<'src, &'src [SpannedToken], Expr, Extra<'src>> + Clone {
+    operand
+        .clone()
+        .foldl(op.then(operand).repeated(), |left, (op, right)| {
+            binary_expr(op, left, right)
+        })
+}
+
+fn binary_expr(op: BinOp, left: Expr, right: Expr) -> Expr {
+    let span = left.span.union(right.span);
+    Expr::new(ExprKind::Binary(op, Box::new(left), Box::new(right)), span)
+}
+
+/// A type: `Int`, `List<String>`, `Person`, `{ name: String }`,
+/// `(Int, String)` or `fn(Int) -> Int`.
+pub fn parser_type<'src>() -> impl Parser<'src, &'src [SpannedToken], TypeExpr, Extra<'src>> + Clone
+{
+    recursive(|ty| {
+        let lt = select! {
+            SpannedToken { token: Token::Lt, .. } => (),
+        };
+        let gt = select! {
+            SpannedToken { token: Token::Gt, span } => span,
+        };
+        let lbrace = select! {
+            SpannedToken { token: Token::LBrace, span } => span,
+        };
+        let rbrace = select! {
+            SpannedToken { token: Token::RBrace, span } => span,
+        };
+        let colon = select! {
+            SpannedToken { token: Token::Colon, .. } => (),
+        };
+        let comma = select! {
+            SpannedToken { token: Token::Comma, .. } => (),
+        };
+
+        let args = lt
+            .ignore_then(
+                ty.clone()
+                    .separated_by(comma)
+                    .at_least(1)
+                    .collect::<Vec<_>>(),
+            )
+            .then(gt);
+        let named = parser_name_spanned().then(args.or_not()).map(
+            |((name, span), args): ((String, Span), Option<_>)| {
+                let (args, end) = args.unwrap_or((Vec::new(), span));
+                TypeExpr::new(TypeExprKind::Named(name, args), span.union(end))
+            },
+        );
+
+        let tuple = parenthesized(ty.clone()).map(|(parens, span)| match parens {
+            Parens::One(inner) => TypeExpr::new(inner.kind, span),
+            Parens::Tuple(items) => TypeExpr::new(TypeExprKind::Tuple(items), span),
+        });
+
+        let record = lbrace
+            .then(
+                parser_name()
+                    .then_ignore(colon)
+                    .then(ty.clone())
+                    .separated_by(comma)
+                    .allow_trailing()
+                    .at_least(1)
+                    .collect::<Vec<_>>(),
+            )
+            .then(rbrace)
+            .map(|((open, fields), close)| {
+                TypeExpr::new(TypeExprKind::Record(fields), open.union(close))
+            });
 
+        let fn_kw = select! {
+            SpannedToken { token: Token::Fn, span } => span,
+        };
         let lparen = select! {
             SpannedToken { token: Token::LParen, .. } => (),
         };
         let rparen = select! {
             SpannedToken { token: Token::RParen, .. } => (),
         };
+        let arrow = select! {
+            SpannedToken { token: Token::Arrow, .. } => (),
+        };
+        let function = fn_kw
+            .then(
+                ty.clone()
+                    .separated_by(comma)
+                    .allow_trailing()
+                    .collect::<Vec<_>>()
+                    .delimited_by(lparen, rparen),
+            )
+            .then_ignore(arrow)
+            .then(ty.clone())
+            .map(|((kw, params), ret): ((Span, Vec<TypeExpr>), TypeExpr)| {
+                let span = kw.union(ret.span);
+                TypeExpr::new(TypeExprKind::Function(params, Box::new(ret)), span)
+            });
 
-        let paren_expr = lparen.ignore_then(expr.clone()).then_ignore(rparen);
+        named.or(record).or(tuple).or(function)
+    })
+}
 
-        let atom = atom_base.or(paren_expr);
-        let atom_for_pipe = atom.clone();
+/// A pattern in a `match` arm: `_`, `x`, `1`, `"a"`, `Circle(r)` or
+/// `(x, y)`.
+pub fn parser_pattern<'src>()
+-> impl Parser<'src, &'src [SpannedToken], Pattern, Extra<'src>> + Clone {
+    recursive(|pattern| {
+        let lparen = select! {
+            SpannedToken { token: Token::LParen, .. } => (),
+        };
+        let rparen = select! {
+            SpannedToken { token: Token::RParen, span } => span,
+        };
+        let comma = select! {
+            SpannedToken { token: Token::Comma, .. } => (),
+        };
 
-        let pipe = select! {
-            SpannedToken { token: Token::Pipe, .. } => (),
+        let literal = select! {
+            SpannedToken { token: Token::Int(n), span } => Pattern::new(PatternKind::Int(n), span),
+            SpannedToken { token: Token::String(s), span } => {
+                Pattern::new(PatternKind::String(s), span)
+            },
         };
+        let minus = select! {
+            SpannedToken { token: Token::Minus, span } => span,
+        };
+        let negative = minus.then(select! {
+            SpannedToken { token: Token::Int(n), span } => (n, span),
+        });
+        let negative = negative
+            .map(|(minus, (n, span))| Pattern::new(PatternKind::Int(-n), minus.union(span)));
 
-        atom.foldl(pipe.ignore_then(atom_for_pipe).repeated(), |left, right| {
-            Expr::Pipeline(Box::new(left), Box::new(right))
-        })
+        let name = select! {
+            SpannedToken { token: Token::Ident(name), span } if name == "_" => {
+                Pattern::new(PatternKind::Wildcard, span)
+            },
+            SpannedToken { token: Token::Ident(name), span } if !is_constructor(&name) => {
+                Pattern::new(PatternKind::Bind(name), span)
+            },
+        };
+
+        let args = lparen
+            .ignore_then(
+                pattern
+                    .clone()
+                    .separated_by(comma)
+                    .allow_trailing()
+                    .at_least(1)
+                    .collect::<Vec<_>>(),
+            )
+            .then(rparen);
+        let variant = parser_constructor().then(args.or_not()).map(
+            |((name, span), args): ((String, Span), Option<_>)| {
+                let (args, end) = args.unwrap_or((Vec::new(), span));
+                Pattern::new(PatternKind::Variant(name, args), span.union(end))
+            },
+        );
+
+        let tuple = parenthesized(pattern).map(|(parens, span)| match parens {
+            Parens::One(inner) => Pattern::new(inner.kind, span),
+            Parens::Tuple(items) => Pattern::new(PatternKind::Tuple(items), span),
+        });
+
+        literal.or(negative).or(name).or(variant).or(tuple)
     })
 }
 
-pub fn parser_name<'src>() -> impl Parser<'src, &'src [SpannedToken], String> + Clone {
+/// The tuple pattern of a destructuring `grab`, `(x, (y, _))`, which
+/// matches every value of its type.
+fn parser_tuple_pattern<'src>()
+-> impl Parser<'src, &'src [SpannedToken], Pattern, Extra<'src>> + Clone {
+    let name = select! {
+        SpannedToken { token: Token::Ident(name), span } if name == "_" => {
+            Pattern::new(PatternKind::Wildcard, span)
+        },
+        SpannedToken { token: Token::Ident(name), span } if !is_constructor(&name) => {
+            Pattern::new(PatternKind::Bind(name), span)
+        },
+    };
+    let part = recursive(
+        |part: Recursive<dyn Parser<'src, _, Pattern, Extra<'src>>>| {
+            name.or(parenthesized(part).map(|(parens, span)| match parens {
+                Parens::One(inner) => Pattern::new(inner.kind, span),
+                Parens::Tuple(items) => Pattern::new(PatternKind::Tuple(items), span),
+            }))
+        },
+    );
+
+    let lparen = select! {
+        SpannedToken { token: Token::LParen, span } => span,
+    };
+    let rparen = select! {
+        SpannedToken { token: Token::RParen, span } => span,
+    };
+    let comma = select! {
+        SpannedToken { token: Token::Comma, .. } => (),
+    };
+
+    // The comma after the first item is what makes it a tuple.
+    lparen
+        .then(part.clone())
+        .then_ignore(comma)
+        .then(
+            part.separated_by(comma)
+                .allow_trailing()
+                .collect::<Vec<_>>(),
+        )
+        .then(rparen)
+        .map(|(((open, first), rest), close)| {
+            let items = std::iter::once(first).chain(rest).collect();
+            Pattern::new(PatternKind::Tuple(items), open.union(close))
+        })
+}
+
+/// Whether `name` names a constructor rather than a binding: `Circle`.
+pub fn is_constructor(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_uppercase())
+}
+
+/// A constructor name, which starts with a capital letter.
+fn parser_constructor<'src>()
+-> impl Parser<'src, &'src [SpannedToken], (String, Span), Extra<'src>> + Clone {
     select! {
-        SpannedToken { token: Token::Ident(name), .. } => name,
+        SpannedToken { token: Token::Ident(name), span } if is_constructor(&name) => (name, span),
     }
 }
 
-pub fn parser_yell<'src>() -> impl Parser<'src, &'src [SpannedToken], Statement> + Clone {
-    let yell_kw = select! {
-        SpannedToken { token: Token::Yell, .. } => (),
-    };
+pub fn parser_name<'src>() -> impl Parser<'src, &'src [SpannedToken], String, Extra<'src>> + Clone {
+    parser_name_spanned().map(|(name, _)| name)
+}
+
+fn parser_name_spanned<'src>()
+-> impl Parser<'src, &'src [SpannedToken], (String, Span), Extra<'src>> + Clone {
+    select! {
+        SpannedToken { token: Token::Ident(name), span } => (name, span),
+    }
+}
+
+fn parser_semi<'src>() -> impl Parser<'src, &'src [SpannedToken], Span, Extra<'src>> + Clone {
+    select! {
+        SpannedToken { token: Token::Semicolon, span } => span,
+    }
+}
 
-    let semi = select! {
-        SpannedToken { token: Token::Semicolon, .. } => (),
+pub fn parser_yell<'src>() -> impl Parser<'src, &'src [SpannedToken], Statement, Extra<'src>> + Clone
+{
+    let yell_kw = select! {
+        SpannedToken { token: Token::Yell, span } => span,
     };
 
     yell_kw
-        .ignore_then(parser_expr())
-        .then_ignore(semi)
-        .map(|expr| Statement::Yell { expr })
+        .then(parser_expr())
+        .then(parser_semi())
+        .map(|((kw, expr), semi)| Statement::new(StatementKind::Yell { expr }, kw.union(semi)))
 }
 
-fn parser_let<'src>() -> impl Parser<'src, &'src [SpannedToken], Statement> + Clone {
+fn parser_let<'src>() -> impl Parser<'src, &'src [SpannedToken], Statement, Extra<'src>> + Clone {
     let grab_kw = select! {
-        SpannedToken { token: Token::Grab, .. } => (),
+        SpannedToken { token: Token::Grab, span } => span,
     };
     let equals = select! {
         SpannedToken { token: Token::Equals, .. } => (),
     };
-    let semi = select! {
-        SpannedToken { token: Token::Semicolon, .. } => (),
+
+    let colon = select! {
+        SpannedToken { token: Token::Colon, .. } => (),
     };
 
+    let target = parser_name()
+        .map(Grab::Name)
+        .or(parser_tuple_pattern().map(Grab::Tuple));
     grab_kw
-        .ignore_then(parser_name())
+        .then(target)
+        .then(colon.ignore_then(parser_type()).or_not())
         .then_ignore(equals)
         .then(parser_expr())
-        .then_ignore(semi)
-        .map(|(name, expr)| Statement::Let { name, expr })
+        .then(parser_semi())
+        .map(|((((kw, target), ty), expr), semi)| {
+            let kind = match target {
+                Grab::Name(name) => StatementKind::Let { name, ty, expr },
+                Grab::Tuple(pattern) => StatementKind::Destructure { pattern, ty, expr },
+            };
+            Statement::new(kind, kw.union(semi))
+        })
+}
+
+/// What a `grab` binds.
+enum Grab {
+    Name(String),
+    Tuple(Pattern),
 }
 
-fn parser_expr_stmt<'src>() -> impl Parser<'src, &'src [SpannedToken], Statement> + Clone {
-    let semi = select! {
-        SpannedToken { token: Token::Semicolon, .. } => (),
+fn parser_expr_stmt<'src>()
+-> impl Parser<'src, &'src [SpannedToken], Statement, Extra<'src>> + Clone {
+    parser_expr().then(parser_semi()).map(|(expr, semi)| {
+        let span = expr.span.union(semi);
+        Statement::new(StatementKind::ExprStmt(expr), span)
+    })
+}
+
+/// `#allow(lint, ...)`, silencing lints on the statement that follows.
+fn parser_allow<'src>() -> impl Parser<'src, &'src [SpannedToken], Vec<Allow>, Extra<'src>> + Clone
+{
+    let hash = select! {
+        SpannedToken { token: Token::Hash, .. } => (),
+    };
+    let allow = select! {
+        SpannedToken { token: Token::Ident(name), .. } if name == "allow" => (),
+    };
+    let lparen = select! {
+        SpannedToken { token: Token::LParen, .. } => (),
+    };
+    let rparen = select! {
+        SpannedToken { token: Token::RParen, .. } => (),
+    };
+    let comma = select! {
+        SpannedToken { token: Token::Comma, .. } => (),
     };
 
-    parser_expr().then_ignore(semi).map(Statement::ExprStmt)
+    let lint = parser_name_spanned().map(|(lint, span)| Allow { lint, span });
+
+    hash.ignore_then(allow)
+        .ignore_then(lparen)
+        .ignore_then(
+            lint.separated_by(comma)
+                .allow_trailing()
+                .at_least(1)
+                .collect(),
+        )
+        .then_ignore(rparen)
+}
+
+fn parser_assert<'src>() -> impl Parser<'src, &'src [SpannedToken], Statement, Extra<'src>> + Clone
+{
+    let assert_kw = select! {
+        SpannedToken { token: Token::Assert, span } => span,
+    };
+
+    assert_kw
+        .then(parser_expr())
+        .then(parser_semi())
+        .map(|((kw, expr), semi)| Statement::new(StatementKind::Assert { expr }, kw.union(semi)))
+}
+
+fn parser_assert_eq<'src>()
+-> impl Parser<'src, &'src [SpannedToken], Statement, Extra<'src>> + Clone {
+    let assert_eq_kw = select! {
+        SpannedToken { token: Token::AssertEq, span } => span,
+    };
+    let comma = select! {
+        SpannedToken { token: Token::Comma, .. } => (),
+    };
+
+    assert_eq_kw
+        .then(parser_expr())
+        .then_ignore(comma)
+        .then(parser_expr())
+        .then(parser_semi())
+        .map(|(((kw, left), right), semi)| {
+            Statement::new(StatementKind::AssertEq { left, right }, kw.union(semi))
+        })
+}
+
+/// `type Name = ...;`, naming a type or declaring a sum type.
+fn parser_type_decl<'src>()
+-> impl Parser<'src, &'src [SpannedToken], Statement, Extra<'src>> + Clone {
+    let type_kw = select! {
+        SpannedToken { token: Token::Type, span } => span,
+    };
+    let equals = select! {
+        SpannedToken { token: Token::Equals, .. } => (),
+    };
+
+    let body = parser_sum()
+        .map(TypeBody::Sum)
+        .or(parser_type().map(TypeBody::Alias));
+    type_kw
+        .then(parser_name())
+        .then_ignore(equals)
+        .then(body)
+        .then(parser_semi())
+        .map(|(((kw, name), body), semi)| {
+            let kind = match body {
+                TypeBody::Alias(ty) => StatementKind::TypeDecl { name, ty },
+                TypeBody::Sum(variants) => StatementKind::SumDecl { name, variants },
+            };
+            Statement::new(kind, kw.union(semi))
+        })
+}
+
+/// What follows the `=` of a type declaration.
+enum TypeBody {
+    Alias(TypeExpr),
+    Sum(Vec<Variant>),
+}
+
+/// The variants of a sum type: `Circle(Int) | Rect(Int, Int)`. A lone
+/// variant without fields needs a leading `|`, `| Empty`, since `Empty`
+/// alone names a type.
+fn parser_sum<'src>() -> impl Parser<'src, &'src [SpannedToken], Vec<Variant>, Extra<'src>> + Clone
+{
+    let bar = select! {
+        SpannedToken { token: Token::Bar, .. } => (),
+    };
+    let lparen = select! {
+        SpannedToken { token: Token::LParen, .. } => (),
+    };
+    let rparen = select! {
+        SpannedToken { token: Token::RParen, span } => span,
+    };
+    let comma = select! {
+        SpannedToken { token: Token::Comma, .. } => (),
+    };
+
+    let fields = lparen
+        .ignore_then(
+            parser_type()
+                .separated_by(comma)
+                .allow_trailing()
+                .at_least(1)
+                .collect::<Vec<_>>(),
+        )
+        .then(rparen);
+    let variant = parser_constructor().then(fields.or_not()).map(
+        |((name, span), fields): ((String, Span), Option<_>)| {
+            let (fields, end) = fields.unwrap_or((Vec::new(), span));
+            Variant {
+                name,
+                fields,
+                span: span.union(end),
+            }
+        },
+    );
+
+    bar.or_not()
+        .then(variant.separated_by(bar).at_least(1).collect::<Vec<_>>())
+        .try_map(|(leading, variants), span| {
+            let is_sum = leading.is_some() || variants.len() > 1 || !variants[0].fields.is_empty();
+            if is_sum {
+                Ok(variants)
+            } else {
+                Err(Rich::custom(span, "a type name, not a sum type"))
+            }
+        })
+}
+
+/// `effect name(Payload);`, or `effect name;` for an effect tossed with
+/// nothing.
+fn parser_effect_decl<'src>()
+-> impl Parser<'src, &'src [SpannedToken], Statement, Extra<'src>> + Clone {
+    let effect_kw = select! {
+        SpannedToken { token: Token::Effect, span } => span,
+    };
+    let lparen = select! {
+        SpannedToken { token: Token::LParen, .. } => (),
+    };
+    let rparen = select! {
+        SpannedToken { token: Token::RParen, .. } => (),
+    };
+
+    effect_kw
+        .then(parser_name())
+        .then(parser_type().delimited_by(lparen, rparen).or_not())
+        .then(parser_semi())
+        .map(|(((kw, name), payload), semi)| {
+            Statement::new(StatementKind::EffectDecl { name, payload }, kw.union(semi))
+        })
+}
+
+/// `pub grab ...;` or `pub type ...;`, which other modules can `use`.
+fn parser_pub<'src>() -> impl Parser<'src, &'src [SpannedToken], Statement, Extra<'src>> + Clone {
+    let pub_kw = select! {
+        SpannedToken { token: Token::Pub, span } => span,
+    };
+
+    pub_kw
+        .then(parser_let().or(parser_type_decl()))
+        .map(|(kw, mut stmt)| {
+            stmt.public = true;
+            stmt.span = kw.union(stmt.span);
+            stmt
+        })
+}
+
+/// `use path::to::module::name;`
+fn parser_use<'src>() -> impl Parser<'src, &'src [SpannedToken], Statement, Extra<'src>> + Clone {
+    let use_kw = select! {
+        SpannedToken { token: Token::Use, span } => span,
+    };
+    let sep = select! {
+        SpannedToken { token: Token::PathSep, .. } => (),
+    };
+
+    use_kw
+        .then(
+            parser_name()
+                .separated_by(sep)
+                .at_least(2)
+                .collect::<Vec<_>>(),
+        )
+        .then(parser_semi())
+        .map(|((kw, mut path), semi)| {
+            let name = path.pop().expect("at least two segments");
+            let kind = StatementKind::Use {
+                path,
+                alias: name.clone(),
+                name,
+            };
+            Statement::new(kind, kw.union(semi))
+        })
+}
+
+/// `test "name" { ... }`. Assertions are only allowed in here.
+fn parser_test<'src>() -> impl Parser<'src, &'src [SpannedToken], Statement, Extra<'src>> + Clone {
+    let test_kw = select! {
+        SpannedToken { token: Token::Test, span } => span,
+    };
+    let name = select! {
+        SpannedToken { token: Token::String(name), .. } => name,
+    };
+    let lbrace = select! {
+        SpannedToken { token: Token::LBrace, .. } => (),
+    };
+    let rbrace = select! {
+        SpannedToken { token: Token::RBrace, span } => span,
+    };
+
+    let body_stmt = parser_let()
+        .or(parser_yell())
+        .or(parser_assert())
+        .or(parser_assert_eq())
+        .or(parser_expr_stmt());
+
+    test_kw
+        .then(name)
+        .then_ignore(lbrace)
+        .then(with_allow(body_stmt).repeated().collect())
+        .then(rbrace)
+        .map(|(((kw, name), body), close)| {
+            Statement::new(StatementKind::Test { name, body }, kw.union(close))
+        })
+}
+
+/// Attaches any `#allow(...)` attributes in front of a statement.
+fn with_allow<'src>(
+    stmt: impl Parser<'src, &'src [SpannedToken], Statement, Extra<'src>> + Clone,
+) -> impl Parser<'src, &'src [SpannedToken], Statement, Extra<'src>> + Clone {
+    parser_allow()
+        .repeated()
+        .collect::<Vec<_>>()
+        .then(stmt)
+        .map(|(allow, mut stmt)| {
+            stmt.allow = allow.concat();
+            stmt
+        })
 }
 
-pub fn parser_statement<'src>() -> impl Parser<'src, &'src [SpannedToken], Statement> + Clone {
-    parser_let().or(parser_yell()).or(parser_expr_stmt())
+pub fn parser_statement<'src>()
+-> impl Parser<'src, &'src [SpannedToken], Statement, Extra<'src>> + Clone {
+    with_allow(
+        parser_let()
+            .or(parser_pub())
+            .or(parser_type_decl())
+            .or(parser_effect_decl())
+            .or(parser_use())
+            .or(parser_yell())
+            .or(parser_test())
+            .or(parser_expr_stmt()),
+    )
 }
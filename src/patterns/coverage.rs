@@ -0,0 +1,193 @@
+//! Exhaustiveness and redundancy of `match` arms, by the usefulness
+//! algorithm of Maranget's "Warnings for pattern matching": a pattern is
+//! useful after some others if it matches a value none of them do.
+
+use crate::patterns::{Ctor, Pat};
+use crate::typechecker::Type;
+
+/// At most this many missing values are worked out.
+const LIMIT: usize = 16;
+
+/// What [`check`] finds.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Coverage {
+    /// Values no arm matches, as patterns.
+    pub missing: Vec<Pat>,
+    /// The arms every value they match is matched by an earlier arm, by
+    /// index.
+    pub unreachable: Vec<usize>,
+}
+
+/// Checks `arms` matching a value of type `ty`. `variants` gives the
+/// field types of each variant of a sum type, and `None` for other types
+/// but tuples, whose values are too many to list.
+pub fn check(
+    arms: &[Pat],
+    ty: &Type,
+    variants: &impl Fn(&Type) -> Option<Vec<Vec<Type>>>,
+) -> Coverage {
+    let tys = std::slice::from_ref(ty);
+    let mut rows = Vec::new();
+    let mut unreachable = Vec::new();
+    for (i, arm) in arms.iter().enumerate() {
+        let row = vec![arm.clone()];
+        if useful(&rows, &row, tys, variants).is_empty() {
+            unreachable.push(i);
+        }
+        rows.push(row);
+    }
+
+    let missing = useful(&rows, &[Pat::Any(None)], tys, variants)
+        .into_iter()
+        .map(|mut row| row.remove(0))
+        .collect();
+    Coverage {
+        missing,
+        unreachable,
+    }
+}
+
+/// The values `row` matches that none of `rows` do, as rows of patterns.
+/// `tys` holds the type of each column.
+fn useful(
+    rows: &[Vec<Pat>],
+    row: &[Pat],
+    tys: &[Type],
+    variants: &impl Fn(&Type) -> Option<Vec<Vec<Type>>>,
+) -> Vec<Vec<Pat>> {
+    let Some((head, rest)) = row.split_first() else {
+        return if rows.is_empty() {
+            vec![Vec::new()]
+        } else {
+            Vec::new()
+        };
+    };
+
+    if let Pat::Ctor(ctor, args) = head {
+        return specialized(rows, ctor, args, rest, tys, variants);
+    }
+
+    let mut heads: Vec<&Ctor> = Vec::new();
+    for row in rows {
+        if let Pat::Ctor(ctor, _) = &row[0]
+            && !heads.contains(&ctor)
+        {
+            heads.push(ctor);
+        }
+    }
+
+    let all = signature(&tys[0], variants);
+    if let Some(all) = &all
+        && all.iter().all(|(ctor, _)| heads.contains(&ctor))
+    {
+        // Every constructor is tested for, so look under each.
+        let mut found = Vec::new();
+        for (ctor, fields) in all {
+            let args = vec![Pat::Any(None); fields.len()];
+            found.extend(specialized(rows, ctor, &args, rest, tys, variants));
+            if found.len() >= LIMIT {
+                break;
+            }
+        }
+        found.truncate(LIMIT);
+        return found;
+    }
+
+    // Some values of the column match no constructor tested for, so only
+    // the rows matching anything there can match them.
+    let defaults: Vec<_> = rows
+        .iter()
+        .filter(|row| matches!(row[0], Pat::Any(_)))
+        .map(|row| row[1..].to_vec())
+        .collect();
+    let found = useful(&defaults, rest, &tys[1..], variants);
+    let missing_heads = match all {
+        Some(all) if !heads.is_empty() => all
+            .into_iter()
+            .filter(|(ctor, _)| !heads.contains(&ctor))
+            .map(|(ctor, fields)| Pat::Ctor(ctor, vec![Pat::Any(None); fields.len()]))
+            .collect(),
+        _ => vec![Pat::Any(None)],
+    };
+    found
+        .iter()
+        .flat_map(|row| {
+            missing_heads.iter().map(move |head| {
+                let mut row = row.clone();
+                row.insert(0, head.clone());
+                row
+            })
+        })
+        .take(LIMIT)
+        .collect()
+}
+
+/// Every constructor of `ty` with the types of its fields, if there are
+/// few enough to list: the variants of a sum type, or a tuple.
+fn signature(
+    ty: &Type,
+    variants: &impl Fn(&Type) -> Option<Vec<Vec<Type>>>,
+) -> Option<Vec<(Ctor, Vec<Type>)>> {
+    if let Type::Tuple(items) = ty {
+        return Some(vec![(Ctor::Tuple, items.clone())]);
+    }
+    let all = variants(ty)?;
+    Some(
+        all.into_iter()
+            .enumerate()
+            .map(|(tag, fields)| (Ctor::Variant(tag), fields))
+            .collect(),
+    )
+}
+
+/// [`useful`] for a `row` starting with `ctor(args)`: the rows that can
+/// match it have their first column replaced by its fields.
+fn specialized(
+    rows: &[Vec<Pat>],
+    ctor: &Ctor,
+    args: &[Pat],
+    rest: &[Pat],
+    tys: &[Type],
+    variants: &impl Fn(&Type) -> Option<Vec<Vec<Type>>>,
+) -> Vec<Vec<Pat>> {
+    let mut field_tys = match ctor {
+        // A column of type `Never` has no values, nor fields to look at.
+        Ctor::Variant(tag) => match variants(&tys[0]) {
+            Some(all) => all[*tag].clone(),
+            None => vec![Type::Never; args.len()],
+        },
+        Ctor::Tuple => match &tys[0] {
+            Type::Tuple(items) => items.clone(),
+            _ => vec![Type::Never; args.len()],
+        },
+        Ctor::Int(_) | Ctor::String(_) => Vec::new(),
+    };
+    field_tys.extend_from_slice(&tys[1..]);
+
+    let rows: Vec<_> = rows
+        .iter()
+        .filter_map(|row| specialize(row, ctor, args.len()))
+        .collect();
+    let row = [args, rest].concat();
+    useful(&rows, &row, &field_tys, variants)
+        .into_iter()
+        .map(|mut row| {
+            let fields = row.drain(..args.len()).collect();
+            row.insert(0, Pat::Ctor(ctor.clone(), fields));
+            row
+        })
+        .collect()
+}
+
+/// `row` with its first pattern replaced by the `arity` patterns it
+/// matches the fields of `ctor` with, or `None` if it cannot match `ctor`.
+fn specialize(row: &[Pat], ctor: &Ctor, arity: usize) -> Option<Vec<Pat>> {
+    let (head, rest) = row.split_first()?;
+    let mut specialized = match head {
+        Pat::Any(_) => vec![Pat::Any(None); arity],
+        Pat::Ctor(head, args) if head == ctor => args.clone(),
+        Pat::Ctor(..) => return None,
+    };
+    specialized.extend_from_slice(rest);
+    Some(specialized)
+}
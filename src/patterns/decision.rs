@@ -0,0 +1,133 @@
+//! Decision trees: `match` arms compiled into tests that look at each
+//! part of the matched value at most once on the way to an arm.
+
+use crate::patterns::{Ctor, Pat};
+
+/// Where a part of the matched value is: the field indices to follow from
+/// the value itself, which is the empty path.
+pub type Path = Vec<usize>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Decision {
+    /// Arm `arm` matches, binding each name to the part of the value at
+    /// its path.
+    Leaf {
+        arm: usize,
+        bindings: Vec<(String, Path)>,
+    },
+    /// No arm matches. Exhaustive matches never get here.
+    Fail,
+    /// Tests the part of the value at `path` against each constructor in
+    /// turn, taking `default` if none is it.
+    Switch {
+        path: Path,
+        cases: Vec<(Ctor, Decision)>,
+        default: Box<Decision>,
+    },
+}
+
+/// A row of the matrix being compiled: the tests left for one arm, and
+/// the names bound so far.
+#[derive(Debug, Clone)]
+struct Row {
+    tests: Vec<(Path, Pat)>,
+    bindings: Vec<(String, Path)>,
+    arm: usize,
+}
+
+/// Compiles `arms`, tried in order.
+pub fn compile(arms: &[Pat]) -> Decision {
+    let rows = arms
+        .iter()
+        .enumerate()
+        .map(|(arm, pat)| Row {
+            tests: vec![(Vec::new(), pat.clone())],
+            bindings: Vec::new(),
+            arm,
+        })
+        .collect();
+    decide(rows)
+}
+
+fn decide(mut rows: Vec<Row>) -> Decision {
+    // Patterns matching anything test nothing; they only bind.
+    for row in &mut rows {
+        let tests = std::mem::take(&mut row.tests);
+        for (path, pat) in tests {
+            match pat {
+                Pat::Any(Some(name)) => row.bindings.push((name, path)),
+                Pat::Any(None) => {}
+                pat @ Pat::Ctor(..) => row.tests.push((path, pat)),
+            }
+        }
+    }
+
+    let Some(first) = rows.first() else {
+        return Decision::Fail;
+    };
+    let Some((path, _)) = first.tests.first() else {
+        return Decision::Leaf {
+            arm: first.arm,
+            bindings: first.bindings.clone(),
+        };
+    };
+    let path = path.clone();
+
+    let mut ctors: Vec<(&Ctor, usize)> = Vec::new();
+    for row in &rows {
+        for (at, pat) in &row.tests {
+            if let Pat::Ctor(ctor, args) = pat
+                && *at == path
+                && !ctors.iter().any(|(seen, _)| *seen == ctor)
+            {
+                ctors.push((ctor, args.len()));
+            }
+        }
+    }
+
+    let cases = ctors
+        .iter()
+        .map(|(ctor, arity)| {
+            let rows = rows
+                .iter()
+                .filter_map(|row| specialize(row, &path, ctor, *arity))
+                .collect();
+            ((*ctor).clone(), decide(rows))
+        })
+        .collect();
+    let default = rows
+        .iter()
+        .filter(|row| row.tests.iter().all(|(at, _)| *at != path))
+        .cloned()
+        .collect();
+    Decision::Switch {
+        cases,
+        default: Box::new(decide(default)),
+        path,
+    }
+}
+
+/// `row` once the value at `path` is known to be `ctor`, with the test
+/// there replaced by tests of its `arity` fields, or `None` if the row
+/// needs another constructor there.
+fn specialize(row: &Row, path: &Path, ctor: &Ctor, arity: usize) -> Option<Row> {
+    let Some(i) = row.tests.iter().position(|(at, _)| at == path) else {
+        return Some(row.clone());
+    };
+    let Pat::Ctor(found, args) = &row.tests[i].1 else {
+        unreachable!("only constructors are left to test");
+    };
+    if found != ctor {
+        return None;
+    }
+
+    let mut row = row.clone();
+    let fields = (0..arity).map(|field| {
+        let mut at = path.clone();
+        at.push(field);
+        at
+    });
+    let tests: Vec<_> = fields.zip(args.iter().cloned()).collect();
+    row.tests.splice(i..=i, tests);
+    Some(row)
+}
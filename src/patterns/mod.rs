@@ -0,0 +1,47 @@
+//! Patterns as `match` needs them: [`coverage`] finds the values no arm
+//! matches and the arms no value reaches, and [`decision`] turns the arms
+//! into a tree of tests that looks at each part of a value once.
+
+pub mod coverage;
+pub mod decision;
+
+use crate::ast::ast::{Pattern, PatternKind};
+
+/// A pattern with its constructors resolved to tags.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Pat {
+    /// `_`, or the name it binds.
+    Any(Option<String>),
+    Ctor(Ctor, Vec<Pat>),
+}
+
+/// What a pattern tests a value for.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Ctor {
+    /// Variant `tag` of a sum type, counting from 0 in declaration order.
+    Variant(usize),
+    Int(i64),
+    String(String),
+    /// A tuple, which every value of a tuple type is.
+    Tuple,
+}
+
+impl Pat {
+    /// Lowers `pattern`. `tag` gives the tag of a constructor name.
+    pub fn lower(pattern: &Pattern, tag: &impl Fn(&str) -> usize) -> Self {
+        match &pattern.kind {
+            PatternKind::Wildcard => Pat::Any(None),
+            PatternKind::Bind(name) => Pat::Any(Some(name.clone())),
+            PatternKind::Int(n) => Pat::Ctor(Ctor::Int(*n), Vec::new()),
+            PatternKind::String(s) => Pat::Ctor(Ctor::String(s.clone()), Vec::new()),
+            PatternKind::Variant(name, args) => {
+                let args = args.iter().map(|arg| Pat::lower(arg, tag)).collect();
+                Pat::Ctor(Ctor::Variant(tag(name)), args)
+            }
+            PatternKind::Tuple(items) => {
+                let items = items.iter().map(|item| Pat::lower(item, tag)).collect();
+                Pat::Ctor(Ctor::Tuple, items)
+            }
+        }
+    }
+}
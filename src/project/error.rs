@@ -0,0 +1,39 @@
+use thiserror::Error;
+
+#[derive(Debug, Error, PartialEq)]
+pub enum ProjectError {
+    /// The manifest's file name, and what is wrong with it.
+    #[error("invalid manifest `{0}`: {1}")]
+    Invalid(String, String),
+
+    /// The dependency, and the directory that lacks a `Sauce.toml`.
+    #[error("cannot find dependency `{0}` at `{1}`")]
+    MissingDependency(String, String),
+
+    /// The dependency, and the two entry files it names.
+    #[error("dependency `{0}` is both `{1}` and `{2}`")]
+    ConflictingDependency(String, String, String),
+
+    #[error("no `Sauce.toml` here or in any parent directory")]
+    NoProject,
+
+    /// The path that `sauce new` or `sauce init` would overwrite.
+    #[error("`{0}` already exists")]
+    AlreadyExists(String),
+
+    #[error("`{0}` is not a valid package name")]
+    InvalidName(String),
+}
+
+impl ProjectError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            ProjectError::Invalid(..) => "E0701",
+            ProjectError::MissingDependency(..) => "E0702",
+            ProjectError::ConflictingDependency(..) => "E0703",
+            ProjectError::NoProject => "E0704",
+            ProjectError::AlreadyExists(..) => "E0705",
+            ProjectError::InvalidName(..) => "E0706",
+        }
+    }
+}
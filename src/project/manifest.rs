@@ -0,0 +1,83 @@
+use crate::lexer::{Lexer, Token};
+use crate::project::error::ProjectError;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+/// The file name of a project manifest.
+pub const MANIFEST: &str = "Sauce.toml";
+
+/// Editions this compiler understands, oldest first.
+pub const EDITIONS: &[&str] = &["2026"];
+
+/// A parsed `Sauce.toml`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Manifest {
+    pub package: Package,
+    #[serde(default)]
+    pub dependencies: BTreeMap<String, Dependency>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Package {
+    pub name: String,
+    pub version: String,
+    #[serde(default = "latest_edition")]
+    pub edition: String,
+    /// The entry file, relative to the manifest.
+    #[serde(default = "default_entry")]
+    pub entry: String,
+}
+
+/// A dependency on another project on disk.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Dependency {
+    /// The dependency's directory, relative to the manifest.
+    pub path: String,
+}
+
+impl Manifest {
+    /// Parses `src`, read from `filename`.
+    pub fn parse(filename: &str, src: &str) -> Result<Self, ProjectError> {
+        let invalid = |message: String| ProjectError::Invalid(filename.to_string(), message);
+        let manifest: Manifest = toml::from_str(src).map_err(|e| {
+            let line = e
+                .span()
+                .map_or(1, |span| src[..span.start].lines().count().max(1));
+            invalid(format!("{} (line {line})", e.message()))
+        })?;
+
+        if !is_identifier(&manifest.package.name) {
+            let name = &manifest.package.name;
+            return Err(invalid(format!(
+                "package name `{name}` is not an identifier"
+            )));
+        }
+        if !EDITIONS.contains(&manifest.package.edition.as_str()) {
+            let edition = &manifest.package.edition;
+            return Err(invalid(format!("unknown edition `{edition}`")));
+        }
+        if let Some(name) = manifest.dependencies.keys().find(|n| !is_identifier(n)) {
+            return Err(invalid(format!(
+                "dependency name `{name}` is not an identifier"
+            )));
+        }
+        Ok(manifest)
+    }
+}
+
+/// Whether `name` can start a `use` path: a name that is not a keyword.
+pub fn is_identifier(name: &str) -> bool {
+    let tokens: Vec<_> = Lexer::new(name).collect();
+    matches!(tokens.as_slice(), [Ok(t)] if matches!(&t.token, Token::Ident(n) if n == name))
+}
+
+pub fn latest_edition() -> String {
+    EDITIONS[EDITIONS.len() - 1].to_string()
+}
+
+fn default_entry() -> String {
+    "src/main.sauce".to_string()
+}
@@ -0,0 +1,157 @@
+pub mod error;
+pub mod manifest;
+pub mod scaffold;
+pub use error::ProjectError;
+pub use manifest::{Dependency, MANIFEST, Manifest, Package};
+
+use crate::modules::Loader;
+use std::collections::BTreeMap;
+use std::path::{Component, Path, PathBuf};
+
+/// A directory with a `Sauce.toml`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Project {
+    /// The directory holding the manifest, relative to where we started.
+    pub root: PathBuf,
+    pub manifest: Manifest,
+}
+
+impl Project {
+    /// Reads the project whose manifest is in `root`.
+    pub fn open(root: impl Into<PathBuf>) -> Result<Self, ProjectError> {
+        let root = root.into();
+        let file = root.join(MANIFEST);
+        let filename = file.to_string_lossy().into_owned();
+        let src = std::fs::read_to_string(&file)
+            .map_err(|e| ProjectError::Invalid(filename.clone(), e.to_string()))?;
+        Ok(Self {
+            manifest: Manifest::parse(&filename, &src)?,
+            root,
+        })
+    }
+
+    /// The project `dir` is in: the nearest directory at or above it with
+    /// a `Sauce.toml`.
+    pub fn find(dir: &Path) -> Result<Option<Self>, ProjectError> {
+        let start = if dir.as_os_str().is_empty() {
+            Path::new(".")
+        } else {
+            dir
+        };
+        let Ok(absolute) = start.canonicalize() else {
+            return Ok(None);
+        };
+
+        // Walk up with `..` rather than the absolute path, so file names
+        // in diagnostics stay relative.
+        let mut root = dir.to_path_buf();
+        for ancestor in absolute.ancestors() {
+            if ancestor.join(MANIFEST).is_file() {
+                return Self::open(normalize(&root)).map(Some);
+            }
+            root.push("..");
+        }
+        Ok(None)
+    }
+
+    /// The file `sauce run` starts from.
+    pub fn entry(&self) -> PathBuf {
+        normalize(&self.root.join(&self.manifest.package.entry))
+    }
+
+    /// The directory module paths are resolved in: the entry file's.
+    pub fn source_root(&self) -> PathBuf {
+        self.entry()
+            .parent()
+            .map_or_else(PathBuf::new, Path::to_path_buf)
+    }
+
+    /// The entry file of every dependency, direct or not, by name.
+    pub fn dependencies(&self) -> Result<BTreeMap<String, PathBuf>, ProjectError> {
+        let mut found: BTreeMap<String, PathBuf> = BTreeMap::new();
+        let mut pending = vec![self.clone()];
+
+        while let Some(project) = pending.pop() {
+            for (name, dependency) in &project.manifest.dependencies {
+                let root = normalize(&project.root.join(&dependency.path));
+                if !root.join(MANIFEST).is_file() {
+                    let path = root.to_string_lossy().into_owned();
+                    return Err(ProjectError::MissingDependency(name.clone(), path));
+                }
+
+                let dependency = Self::open(&root)?;
+                let entry = dependency.entry();
+                match found.get(name) {
+                    Some(seen) if same_file(seen, &entry) => {}
+                    Some(seen) => {
+                        return Err(ProjectError::ConflictingDependency(
+                            name.clone(),
+                            seen.to_string_lossy().into_owned(),
+                            entry.to_string_lossy().into_owned(),
+                        ));
+                    }
+                    None => {
+                        found.insert(name.clone(), entry);
+                        pending.push(dependency);
+                    }
+                }
+            }
+        }
+        Ok(found)
+    }
+
+    /// A loader for the project's files, which knows its dependencies.
+    pub fn loader(&self) -> Result<Loader, ProjectError> {
+        let mut loader = Loader::new(self.source_root());
+        for (name, entry) in self.dependencies()? {
+            loader.add_dependency(name, entry);
+        }
+        Ok(loader)
+    }
+
+    /// Whether `filename` is one of the project's own source files.
+    pub fn contains(&self, filename: &Path) -> bool {
+        match (filename.canonicalize(), self.source_root().canonicalize()) {
+            (Ok(file), Ok(root)) => file.starts_with(root),
+            _ => normalize(filename).starts_with(self.source_root()),
+        }
+    }
+}
+
+/// A loader for the program whose entry file is `filename`: the loader of
+/// its project if it is part of one, else one resolving modules next to
+/// the file.
+pub fn loader_for(filename: &str) -> Result<Loader, ProjectError> {
+    let dir = Path::new(filename).parent().unwrap_or(Path::new(""));
+    match Project::find(dir)? {
+        Some(project) if project.contains(Path::new(filename)) => project.loader(),
+        _ => Ok(Loader::for_file(filename)),
+    }
+}
+
+/// Folds `.` and `..` out of `path` without touching the file system.
+/// Leading `..` are kept.
+fn normalize(path: &Path) -> PathBuf {
+    let mut out = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if matches!(out.components().next_back(), Some(Component::Normal(_))) {
+                    out.pop();
+                } else {
+                    out.push("..");
+                }
+            }
+            other => out.push(other),
+        }
+    }
+    out
+}
+
+fn same_file(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
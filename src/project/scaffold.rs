@@ -0,0 +1,44 @@
+use crate::project::error::ProjectError;
+use crate::project::manifest::{MANIFEST, is_identifier, latest_edition};
+use std::path::{Path, PathBuf};
+
+/// The files `sauce new` creates: a project `name` in the new directory
+/// `dir`.
+pub fn new_project(dir: &Path, name: &str) -> Result<Vec<(PathBuf, String)>, ProjectError> {
+    if dir.exists() {
+        return Err(ProjectError::AlreadyExists(dir.to_string_lossy().into()));
+    }
+    files(dir, name)
+}
+
+/// The files `sauce init` creates: a project `name` in the existing
+/// directory `dir`. An existing entry file is kept.
+pub fn init_project(dir: &Path, name: &str) -> Result<Vec<(PathBuf, String)>, ProjectError> {
+    let manifest = dir.join(MANIFEST);
+    if manifest.exists() {
+        return Err(ProjectError::AlreadyExists(
+            manifest.to_string_lossy().into(),
+        ));
+    }
+    let mut files = files(dir, name)?;
+    files.retain(|(path, _)| !path.exists());
+    Ok(files)
+}
+
+fn files(dir: &Path, name: &str) -> Result<Vec<(PathBuf, String)>, ProjectError> {
+    if !is_identifier(name) {
+        return Err(ProjectError::InvalidName(name.to_string()));
+    }
+
+    let manifest = format!(
+        "[package]\nname = \"{name}\"\nversion = \"0.1.0\"\nedition = \"{}\"\nentry = \"src/main.sauce\"\n\n[dependencies]\n",
+        latest_edition()
+    );
+    let main = format!(
+        "grab greeting = \"Hello from {name}!\";\nyell greeting;\n\ntest \"doubling\" {{\n    grab n = 2 |> _ * 2;\n    assert_eq n, 4;\n}}\n"
+    );
+    Ok(vec![
+        (dir.join(MANIFEST), manifest),
+        (dir.join("src").join("main.sauce"), main),
+    ])
+}
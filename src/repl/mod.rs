@@ -0,0 +1,39 @@
+pub mod session;
+
+pub use session::{Reply, Session};
+
+use crate::errors::diagnostic::ErrorFormat;
+use std::io::{self, BufRead, Write};
+
+const PROMPT: &str = "sauce> ";
+const CONTINUE: &str = "  ...> ";
+
+/// Reads lines from `input` until EOF or `:quit`, writing replies to `output`.
+pub fn run(input: impl BufRead, mut output: impl Write, format: ErrorFormat) -> io::Result<()> {
+    let mut session = Session::with_error_format(format);
+
+    write!(output, "{PROMPT}")?;
+    output.flush()?;
+
+    for line in input.lines() {
+        match session.feed(&line?) {
+            Reply::More => {}
+            Reply::Lines(lines) => {
+                for line in lines {
+                    writeln!(output, "{line}")?;
+                }
+            }
+            Reply::Quit => return Ok(()),
+        }
+
+        let prompt = if session.is_pending() {
+            CONTINUE
+        } else {
+            PROMPT
+        };
+        write!(output, "{prompt}")?;
+        output.flush()?;
+    }
+
+    writeln!(output)
+}
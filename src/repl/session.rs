@@ -0,0 +1,278 @@
+use crate::ast::ast::{Expr, Statement, StatementKind};
+use crate::errors::diagnostic::{Diagnostic, ErrorFormat};
+use crate::interpreter::env::RuntimeEnv;
+use crate::interpreter::eval::{eval_expression, eval_statement};
+use crate::lexer::{Lexer, SpannedToken, Token};
+use crate::modules::Loader;
+use crate::parser::SauceParser;
+use crate::typechecker::checker::{TypeEnv, typecheck_expr, typecheck_stmt};
+
+pub const HELP: &str = "\
+:type <expr>    show the type of an expression
+:ast <expr>     show the parsed AST of an expression
+:tokens <src>   show the tokens of some source
+:load <file>    run a file in this session
+:reset          forget all bindings
+:help           show this message
+:quit           leave the REPL";
+
+/// What the REPL should do after a line of input.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Reply {
+    /// The input so far is not a complete statement yet.
+    More,
+    /// Lines to show the user, possibly none.
+    Lines(Vec<String>),
+    Quit,
+}
+
+/// A REPL session: bindings made by one input stay visible to the next.
+#[derive(Debug, Default)]
+pub struct Session {
+    types: TypeEnv,
+    values: RuntimeEnv,
+    /// Links the modules inputs `use`, relative to the working directory.
+    loader: Loader,
+    buffer: String,
+    format: ErrorFormat,
+}
+
+/// The file name diagnostics report for REPL input.
+const INPUT_NAME: &str = "<repl>";
+
+impl Session {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_error_format(format: ErrorFormat) -> Self {
+        Self {
+            format,
+            ..Self::default()
+        }
+    }
+
+    /// Whether earlier lines are waiting for the rest of a statement.
+    pub fn is_pending(&self) -> bool {
+        !self.buffer.is_empty()
+    }
+
+    pub fn reset(&mut self) {
+        self.types = TypeEnv::new();
+        self.values = RuntimeEnv::new();
+        self.loader = Loader::default();
+        self.buffer.clear();
+    }
+
+    /// Feeds one line of input to the session.
+    pub fn feed(&mut self, line: &str) -> Reply {
+        if !self.is_pending() {
+            let trimmed = line.trim();
+            if trimmed.is_empty() {
+                return Reply::Lines(Vec::new());
+            }
+            if let Some(command) = trimmed.strip_prefix(':') {
+                return self.command(command);
+            }
+        }
+
+        self.buffer.push_str(line);
+        self.buffer.push('\n');
+
+        if !is_complete(&self.buffer) {
+            return Reply::More;
+        }
+
+        let src = std::mem::take(&mut self.buffer);
+        Reply::Lines(self.eval_source(INPUT_NAME, &src).0)
+    }
+
+    fn command(&mut self, command: &str) -> Reply {
+        let (name, arg) = match command.split_once(char::is_whitespace) {
+            Some((name, arg)) => (name, arg.trim()),
+            None => (command, ""),
+        };
+
+        let lines = match name {
+            "type" | "t" => self.type_of(arg),
+            "ast" => self.ast_of(arg),
+            "tokens" => self.tokens_of(arg),
+            "load" | "l" => self.load(arg),
+            "reset" => {
+                self.reset();
+                vec!["cleared all bindings".to_string()]
+            }
+            "help" | "h" | "?" => vec![HELP.to_string()],
+            "quit" | "q" => return Reply::Quit,
+            _ => vec![format!("unknown command `:{name}`, try :help")],
+        };
+
+        Reply::Lines(lines)
+    }
+
+    fn type_of(&self, src: &str) -> Vec<String> {
+        let expr = match parse_expr(src) {
+            Ok(expr) => expr,
+            Err(e) => return vec![self.render(&e, src)],
+        };
+
+        match typecheck_expr(&self.types, &expr) {
+            Ok(ty) => vec![ty.to_string()],
+            Err(e) => vec![self.render(&(&e).into(), src)],
+        }
+    }
+
+    fn ast_of(&self, src: &str) -> Vec<String> {
+        match parse_expr(src) {
+            Ok(expr) => vec![format!("{expr:#?}")],
+            Err(e) => vec![self.render(&e, src)],
+        }
+    }
+
+    fn tokens_of(&self, src: &str) -> Vec<String> {
+        match lex(src) {
+            Ok(tokens) => tokens
+                .iter()
+                .map(|t| format!("{:?} {}..{}", t.token, t.span.start, t.span.end))
+                .collect(),
+            Err(e) => vec![self.render(&e, src)],
+        }
+    }
+
+    fn render(&self, diagnostic: &Diagnostic, src: &str) -> String {
+        diagnostic.render(self.format, INPUT_NAME, src)
+    }
+
+    fn load(&mut self, filename: &str) -> Vec<String> {
+        if filename.is_empty() {
+            return vec!["usage: :load <file>".to_string()];
+        }
+
+        match std::fs::read_to_string(filename) {
+            Ok(src) => {
+                let (mut lines, ok) = self.eval_source(filename, &src);
+                if ok {
+                    lines.push(format!("loaded {filename}"));
+                }
+                lines
+            }
+            Err(e) => vec![format!("Error reading {filename}: {e}")],
+        }
+    }
+
+    /// Runs every statement in `src`, and in the modules it uses for the
+    /// first time, stopping at the first error. Also says whether all of
+    /// them ran.
+    fn eval_source(&mut self, filename: &str, src: &str) -> (Vec<String>, bool) {
+        let base = self.loader.sources.next_base();
+        let ast = match self.loader.load(filename, src) {
+            Ok(ast) => ast,
+            Err(e) => {
+                let error = self.loader.sources.render(&(&e).into(), self.format);
+                return (vec![error], false);
+            }
+        };
+
+        let mut lines = Vec::new();
+        for stmt in &ast.items {
+            // Used modules run quietly; only the input's bindings are shown.
+            let input = (base..=base + src.len()).contains(&stmt.span.start);
+            match self.eval_stmt(stmt) {
+                Ok(Some(line)) if input => lines.push(line),
+                Ok(_) => {}
+                Err(e) => {
+                    lines.push(self.loader.sources.render(&e, self.format));
+                    return (lines, false);
+                }
+            }
+        }
+        (lines, true)
+    }
+
+    /// Checks and runs one statement. The session only sees its bindings if
+    /// both steps succeed.
+    fn eval_stmt(&mut self, stmt: &Statement) -> Result<Option<String>, Box<Diagnostic>> {
+        // An expression statement binds nothing, so checking its expression
+        // checks the statement.
+        if let StatementKind::ExprStmt(expr) = &stmt.kind {
+            let ty = typecheck_expr(&self.types, expr).map_err(|e| failed(&e))?;
+            let value = eval_expression(expr, &mut self.values).map_err(|e| failed(&e))?;
+            return Ok(Some(format!("{value} : {ty}")));
+        }
+
+        let mut types = self.types.clone();
+        typecheck_stmt(&mut types, stmt).map_err(|e| failed(&e))?;
+
+        let line = match &stmt.kind {
+            StatementKind::Let { name, .. } => {
+                eval_statement(stmt, &mut self.values).map_err(|e| failed(&e))?;
+                types.get(name).map(|ty| format!("{name} : {ty}"))
+            }
+            StatementKind::Destructure { pattern, .. } => {
+                eval_statement(stmt, &mut self.values).map_err(|e| failed(&e))?;
+                let lines: Vec<_> = pattern
+                    .bindings()
+                    .into_iter()
+                    .filter_map(|(name, _)| types.get(name).map(|ty| format!("{name} : {ty}")))
+                    .collect();
+                (!lines.is_empty()).then(|| lines.join("\n"))
+            }
+            _ => {
+                eval_statement(stmt, &mut self.values).map_err(|e| failed(&e))?;
+                None
+            }
+        };
+
+        self.types = types;
+        Ok(line)
+    }
+}
+
+/// Whether `src` ends in a `;` with every bracket closed.
+///
+/// Input that does not lex is complete so the error gets reported.
+pub fn is_complete(src: &str) -> bool {
+    let Ok(tokens) = Lexer::new(src).collect::<Result<Vec<_>, _>>() else {
+        return true;
+    };
+
+    let depth: i64 = tokens
+        .iter()
+        .map(|t| match t.token {
+            Token::LParen | Token::LBrace | Token::LBracket => 1,
+            Token::RParen | Token::RBrace | Token::RBracket => -1,
+            _ => 0,
+        })
+        .sum();
+
+    depth <= 0 && matches!(tokens.last(), Some(t) if t.token == Token::Semicolon)
+}
+
+/// Boxed, as diagnostics are large and travel through several `Result`s.
+fn failed<'e, E>(error: &'e E) -> Box<Diagnostic>
+where
+    Diagnostic: From<&'e E>,
+{
+    Box::new(Diagnostic::from(error))
+}
+
+fn lex(src: &str) -> Result<Vec<SpannedToken>, Box<Diagnostic>> {
+    Lexer::new(src)
+        .collect::<Result<_, _>>()
+        .map_err(|e| failed(&e))
+}
+
+fn parse_expr(src: &str) -> Result<Expr, Box<Diagnostic>> {
+    let src = src.trim().trim_end_matches(';');
+    if src.is_empty() {
+        return Err(Box::new(Diagnostic::error(
+            "parse",
+            "expected an expression",
+        )));
+    }
+
+    let tokens = lex(src)?;
+    SauceParser::new()
+        .parse_expr(&tokens)
+        .map_err(|e| failed(&e))
+}
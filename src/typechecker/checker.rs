@@ -1,59 +1,989 @@
-use crate::ast::ast::{Ast, Expr, Statement};
-use crate::typechecker::{errors::TypeError, types::Type};
+use crate::ast::ast::{
+    Ast, BinOp, Expr, ExprKind, MatchArm, Pattern, PatternKind, Stage, Statement, StatementKind,
+    TypeExpr, TypeExprKind, qualify, stage_arity, unqualified,
+};
+use crate::patterns::{Ctor, Pat, coverage};
+use crate::typechecker::errors::TypeError;
+use crate::typechecker::infer::{Conflict, Subst};
+use crate::typechecker::prelude::{self, OPTION, RESULT};
+use crate::typechecker::types::{Type, VariantType};
+use crate::util::span::Span;
+use crate::util::suggest::best_match;
 use std::collections::HashMap;
 
-#[derive(Debug)]
+/// Types every program can name.
+const BUILTIN_TYPES: [&str; 4] = ["Int", "String", "Unit", "List"];
+
+#[derive(Debug, Clone, Default)]
 pub struct TypeEnv {
     vars: HashMap<String, Type>,
+    /// Types named by `type` declarations.
+    types: HashMap<String, Type>,
+    /// The number of type parameters of each sum type, and its variants
+    /// in declaration order.
+    sums: HashMap<String, (usize, Vec<VariantType>)>,
+    /// Constructors in scope, with the sum type each builds and its tag.
+    constructors: HashMap<String, (String, usize)>,
+    /// Declared effects, with the type of what is tossed with each.
+    effects: HashMap<String, Type>,
 }
 
 impl TypeEnv {
+    /// An environment with the prelude's types and constructors.
     pub fn new() -> Self {
+        let mut env = Self::default();
+        for (name, params, variants) in prelude::sums() {
+            env.declare_sum(name, params, variants);
+        }
+        env
+    }
+    /// An environment with the types, constructors and effects declared
+    /// in this one, but none of its other bindings.
+    pub fn scope(&self) -> Self {
+        let vars = self
+            .vars
+            .iter()
+            .filter(|(name, _)| self.constructors.contains_key(*name))
+            .map(|(name, ty)| (name.clone(), ty.clone()))
+            .collect();
         Self {
-            vars: HashMap::new(),
+            vars,
+            types: self.types.clone(),
+            sums: self.sums.clone(),
+            constructors: self.constructors.clone(),
+            effects: self.effects.clone(),
         }
     }
+    /// Binds `name`, hiding any constructor of that name.
     pub fn insert(&mut self, name: String, ty: Type) {
+        self.constructors.remove(&name);
         self.vars.insert(name, ty);
     }
     pub fn get(&self, name: &str) -> Option<&Type> {
         self.vars.get(name)
     }
+    pub fn remove(&mut self, name: &str) {
+        self.vars.remove(name);
+    }
+    /// The type declared as `name`.
+    pub fn get_type(&self, name: &str) -> Option<&Type> {
+        self.types.get(name)
+    }
+    pub fn declare(&mut self, name: String, ty: Type) {
+        self.types.insert(name, ty);
+    }
+    /// Declares sum type `name` with `params` type parameters and
+    /// `variants`, binding a constructor for each: a function of its
+    /// fields, or a value if it has none.
+    pub fn declare_sum(&mut self, name: &str, params: usize, variants: Vec<VariantType>) {
+        let ty = Type::Sum(name.to_string(), (0..params).map(Type::Param).collect());
+        for (tag, variant) in variants.iter().enumerate() {
+            let constructor = if variant.fields.is_empty() {
+                ty.clone()
+            } else {
+                Type::Function(
+                    variant.fields.clone(),
+                    Box::new(ty.clone()),
+                    Box::new(Type::Never),
+                )
+            };
+            self.insert(variant.name.clone(), constructor);
+            self.constructors
+                .insert(variant.name.clone(), (name.to_string(), tag));
+        }
+        self.types.insert(name.to_string(), ty);
+        self.sums.insert(name.to_string(), (params, variants));
+    }
+    /// The variants of sum type `name`. Their fields refer to its type
+    /// parameters as [`Type::Param`].
+    pub fn variants(&self, name: &str) -> Option<&[VariantType]> {
+        self.sums.get(name).map(|(_, variants)| variants.as_slice())
+    }
+    /// The number of type parameters of sum type `name`.
+    pub fn params(&self, name: &str) -> usize {
+        self.sums.get(name).map_or(0, |(params, _)| *params)
+    }
+    /// The sum type constructor `name` builds, and its tag.
+    pub fn constructor(&self, name: &str) -> Option<(&str, usize)> {
+        self.constructors
+            .get(name)
+            .map(|(sum, tag)| (sum.as_str(), *tag))
+    }
+    /// The type of what is tossed with effect `name`, if it is declared.
+    pub fn effect(&self, name: &str) -> Option<&Type> {
+        self.effects.get(name)
+    }
+    /// Names in scope, except `_`.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.vars
+            .keys()
+            .map(String::as_str)
+            .filter(|name| *name != "_")
+    }
 }
 
+/// What checking finds besides types: what the code being checked
+/// tosses, the types of values later passes look inside, and what
+/// inference has worked out so far.
+#[derive(Debug, Default)]
+pub struct Facts {
+    /// The type of each matched or re-tossed value, and what each called
+    /// function returns, by its span.
+    pub types: HashMap<Span, Type>,
+    /// The types each use of a binding with type parameters gives them,
+    /// by the span of the use.
+    pub instances: HashMap<Span, Vec<Type>>,
+    /// The row of effects the `fn` or `catch` being checked tosses, which
+    /// each `toss` and call in it adds to. Nothing needs to know outside
+    /// of one.
+    row: Option<Type>,
+    subst: Subst,
+}
+
+impl Facts {
+    /// Replaces the variables in what was found with what they were
+    /// solved to, once a statement is checked.
+    fn resolve(&mut self) {
+        for ty in self.types.values_mut() {
+            *ty = self.subst.resolve(ty);
+        }
+        for args in self.instances.values_mut() {
+            for arg in args {
+                *arg = self.subst.resolve(arg);
+            }
+        }
+    }
+}
+
+/// The type of `expr`, with what it leaves unknown as type parameters:
+/// `fn(x) x` is a `fn(A) -> A`.
 pub fn typecheck_expr(env: &TypeEnv, expr: &Expr) -> Result<Type, TypeError> {
-    match expr {
-        Expr::Int(_) => Ok(Type::Int),
+    let mut facts = Facts::default();
+    let ty = check(env, expr, &mut facts)?;
+    Ok(facts.subst.generalize(&ty, &mut 0))
+}
+
+fn check(env: &TypeEnv, expr: &Expr, facts: &mut Facts) -> Result<Type, TypeError> {
+    match &expr.kind {
+        ExprKind::Int(_) => Ok(Type::Int),
+
+        ExprKind::String(_) => Ok(Type::String),
+
+        ExprKind::Ident(name) => {
+            match (env.get(name), env.constructor(name)) {
+                (Some(Type::Function(fields, _, _)), Some(_)) => Err(
+                    TypeError::UnappliedConstructor(expr.span, name.clone(), fields.len()),
+                ),
+                (Some(_), _) => Ok(instantiate(env, name, expr.span, facts)),
+                (None, _) => {
+                    let suggestion = best_match(name, env.names()).map(str::to_string);
+                    Err(TypeError::UnknownIdent(expr.span, name.clone(), suggestion))
+                }
+            }
+        }
 
-        Expr::String(_) => Ok(Type::String),
+        ExprKind::Toss { effect, arg } => {
+            let payload = declared(env, effect, expr.span)?;
+            match arg {
+                Some(arg) => expect(env, arg, &payload, facts)?,
+                None if payload != Type::Unit => {
+                    return Err(TypeError::Mismatch(
+                        expr.span,
+                        payload.to_string(),
+                        "nothing".to_string(),
+                    ));
+                }
+                None => {}
+            }
+            let row = Type::effects(vec![effect.clone()], facts.subst.fresh());
+            toss(facts, &row, expr.span)?;
+            Ok(Type::Unit)
+        }
+
+        ExprKind::Catch { effect, expr: body } => {
+            let err = declared(env, effect, expr.span)?;
+            let inner = facts.subst.fresh();
+            let outer = facts.row.replace(inner.clone());
+            let ok = check(env, body, facts);
+            facts.row = outer;
+            let ok = ok?;
+            let (mut tossed, rest) = facts.subst.row(&inner);
+            let Some(at) = tossed.iter().position(|name| name == effect) else {
+                let suggestion = best_match(effect, tossed.iter().map(String::as_str));
+                return Err(TypeError::NothingToCatch(
+                    expr.span,
+                    effect.clone(),
+                    suggestion.map(str::to_string),
+                ));
+            };
+            tossed.remove(at);
+            toss(facts, &Type::effects(tossed, rest), expr.span)?;
+            Ok(Type::Sum(RESULT.to_string(), vec![ok, err]))
+        }
 
-        Expr::Ident(name) => match env.get(name) {
-            Some(ty) => Ok(ty.clone()),
-            None => Err(TypeError::Generic(format!("unknown identifier `{}`", name))),
-        },
+        ExprKind::Retoss {
+            effect,
+            expr: value,
+        } => {
+            let declared = declared(env, effect, expr.span)?;
+            let ty = check(env, value, facts)?;
+            facts.types.insert(value.span, ty.clone());
+            let (ok, payload) = match facts.subst.apply(&ty) {
+                Type::Sum(name, args) if name == RESULT => (args[0].clone(), args[1].clone()),
+                Type::Sum(name, args) if name == OPTION => (args[0].clone(), Type::Unit),
+                Type::Var(_) => return Err(TypeError::Ambiguous(value.span)),
+                other => {
+                    return Err(TypeError::Mismatch(
+                        value.span,
+                        "Option or Result".to_string(),
+                        other.to_string(),
+                    ));
+                }
+            };
+            unify(facts, &declared, &payload, value.span)?;
+            let row = Type::effects(vec![effect.clone()], facts.subst.fresh());
+            toss(facts, &row, expr.span)?;
+            Ok(ok)
+        }
 
-        Expr::Toss { .. } => Ok(Type::Unit),
+        ExprKind::List(items) => {
+            if items.is_empty() {
+                return Err(TypeError::EmptyList(expr.span));
+            }
+            // The first item solves the variable, so a later item that
+            // differs points back to it.
+            let elem = facts.subst.fresh();
+            for item in items {
+                expect(env, item, &elem, facts)?;
+            }
+            Ok(Type::List(Box::new(elem)))
+        }
 
-        Expr::Pipeline(left, right) => {
-            let left_ty = typecheck_expr(env, left)?;
+        ExprKind::Tuple(items) => {
+            let items = items
+                .iter()
+                .map(|item| check(env, item, facts))
+                .collect::<Result<_, _>>()?;
+            Ok(Type::Tuple(items))
+        }
 
-            let mut pipeline_env = TypeEnv {
-                vars: env.vars.clone(),
+        ExprKind::Index(target, index) => {
+            let ty = check(env, target, facts)?;
+            let elem = match facts.subst.apply(&ty) {
+                Type::List(elem) => *elem,
+                Type::Var(_) => {
+                    let elem = facts.subst.fresh();
+                    unify(facts, &ty, &Type::List(Box::new(elem.clone())), target.span)?;
+                    elem
+                }
+                other => {
+                    return Err(TypeError::Mismatch(
+                        target.span,
+                        "List".to_string(),
+                        other.to_string(),
+                    ));
+                }
             };
+            expect(env, index, &Type::Int, facts)?;
+            Ok(elem)
+        }
+
+        ExprKind::Pipeline(left, right) => {
+            let left_ty = check(env, left, facts)?;
+
+            if let Some(stage) = Stage::of(right)
+                && env.get(stage.name).is_none()
+            {
+                return stage_type(env, stage, left_ty, left.span, right.span, facts);
+            }
+
+            let mut pipeline_env = env.clone();
             pipeline_env.insert("_".to_string(), left_ty);
 
-            match **right {
-                Expr::Int(_) | Expr::String(_) => {
-                    return Err(TypeError::Generic(
-                        "right side of pipeline cannot be a literal".into(),
-                    ));
+            match right.kind {
+                ExprKind::Int(_) | ExprKind::String(_) => {
+                    return Err(TypeError::InvalidPipeline(right.span));
                 }
                 _ => {}
             }
 
-            let right_ty = typecheck_expr(&pipeline_env, right)?;
+            let right_ty = check(&pipeline_env, right, facts)?;
             Ok(right_ty)
         }
+
+        // A parameter without an annotation has the type its uses give it.
+        // A function tosses what its body does when it is called, not
+        // where it is written.
+        ExprKind::Lambda { params, ret, body } => {
+            let mut body_env = env.clone();
+            let mut types = Vec::new();
+            for param in params {
+                let ty = match &param.ty {
+                    Some(ty) => written(env, ty, facts)?,
+                    None => facts.subst.fresh(),
+                };
+                body_env.insert(param.name.clone(), ty.clone());
+                types.push(ty);
+            }
+            let outer = facts.row.replace(facts.subst.fresh());
+            let ret = returned(env, &body_env, ret.as_ref(), body, facts);
+            let row = std::mem::replace(&mut facts.row, outer).expect("a row for the body");
+            Ok(Type::Function(types, Box::new(ret?), Box::new(row)))
+        }
+
+        ExprKind::Call(callee, args) => {
+            if let ExprKind::Ident(name) = &callee.kind
+                && let Some((sum, tag)) = env.constructor(name)
+            {
+                return construct(env, expr.span, sum, tag, args, facts);
+            }
+            // Only the top of the type is looked through, so parameters an
+            // earlier call solved still point to the argument that did.
+            let ty = check(env, callee, facts)?;
+            let (params, ret, row) = match facts.subst.head(&ty).0 {
+                Type::Function(params, ret, row) => (params, ret, row),
+                Type::Var(_) => {
+                    let params: Vec<_> = args.iter().map(|_| facts.subst.fresh()).collect();
+                    let ret = Box::new(facts.subst.fresh());
+                    let row = Box::new(facts.subst.fresh());
+                    let function = Type::Function(params.clone(), ret.clone(), row.clone());
+                    unify(facts, &ty, &function, callee.span)?;
+                    (params, ret, row)
+                }
+                other => {
+                    return Err(TypeError::Mismatch(
+                        callee.span,
+                        "a function".to_string(),
+                        facts.subst.apply(&other).to_string(),
+                    ));
+                }
+            };
+            if params.len() != args.len() {
+                return Err(TypeError::ArgumentCount(
+                    expr.span,
+                    params.len(),
+                    args.len(),
+                ));
+            }
+            for (param, arg) in params.iter().zip(args) {
+                expect(env, arg, param, facts)?;
+            }
+            toss(facts, &row, expr.span)?;
+            facts.types.insert(callee.span, (*ret).clone());
+            Ok(*ret)
+        }
+
+        ExprKind::Binary(op, left, right) => {
+            let left_ty = check(env, left, facts)?;
+            match (op, facts.subst.apply(&left_ty)) {
+                (BinOp::Add, Type::String) => {
+                    expect(env, right, &Type::String, facts)?;
+                    Ok(Type::String)
+                }
+                (_, Type::Int) => {
+                    expect(env, right, &Type::Int, facts)?;
+                    Ok(Type::Int)
+                }
+                // Strings add too, but numbers are what two values nothing
+                // else gives a type to add as.
+                (BinOp::Add, Type::Var(_)) => {
+                    let right_ty = check(env, right, facts)?;
+                    let ty = match facts.subst.apply(&right_ty) {
+                        ty @ Type::String => ty,
+                        Type::Int | Type::Var(_) => Type::Int,
+                        other => {
+                            return Err(TypeError::Mismatch(
+                                right.span,
+                                "Int or String".to_string(),
+                                other.to_string(),
+                            ));
+                        }
+                    };
+                    unify(facts, &ty, &right_ty, right.span)?;
+                    unify(facts, &ty, &left_ty, left.span)?;
+                    Ok(ty)
+                }
+                (_, Type::Var(_)) => {
+                    unify(facts, &Type::Int, &left_ty, left.span)?;
+                    expect(env, right, &Type::Int, facts)?;
+                    Ok(Type::Int)
+                }
+                (BinOp::Add, other) => Err(TypeError::Mismatch(
+                    left.span,
+                    "Int or String".to_string(),
+                    other.to_string(),
+                )),
+                (_, other) => Err(TypeError::Mismatch(
+                    left.span,
+                    Type::Int.to_string(),
+                    other.to_string(),
+                )),
+            }
+        }
+
+        ExprKind::Record(fields) => {
+            unique_fields(fields.iter().map(|(name, value)| (name, value.span)))?;
+            let fields = fields
+                .iter()
+                .map(|(name, value)| Ok((name.clone(), check(env, value, facts)?)))
+                .collect::<Result<_, TypeError>>()?;
+            Ok(Type::record(fields))
+        }
+
+        ExprKind::Field(record, name) => {
+            let ty = record_type(env, record, facts)?;
+            match ty.field(name) {
+                Some(field) => Ok(field.clone()),
+                None => Err(unknown_field(expr.span, &ty, name)),
+            }
+        }
+
+        ExprKind::Update { record, fields } => {
+            let ty = record_type(env, record, facts)?;
+            unique_fields(fields.iter().map(|(name, value)| (name, value.span)))?;
+            for (name, value) in fields {
+                let Some(field) = ty.field(name) else {
+                    return Err(unknown_field(value.span, &ty, name));
+                };
+                expect(env, value, field, facts)?;
+            }
+            Ok(ty)
+        }
+
+        ExprKind::Match { scrutinee, arms } => {
+            let ty = check(env, scrutinee, facts)?;
+            facts.types.insert(scrutinee.span, ty.clone());
+            let result = facts.subst.fresh();
+            for arm in arms {
+                let mut arm_env = env.clone();
+                let mut bindings = Vec::new();
+                check_pattern(env, &arm.pattern, &ty, &mut bindings, facts)?;
+                for (name, bound) in bindings {
+                    arm_env.insert(name, bound);
+                }
+                expect(&arm_env, &arm.body, &result, facts)?;
+            }
+            check_coverage(env, scrutinee.span, arms, &facts.subst.apply(&ty))?;
+            Ok(result)
+        }
+    }
+}
+
+/// The type of variant `tag` of sum type `sum` built from `args`. Its type
+/// parameters are what the arguments give them.
+fn construct(
+    env: &TypeEnv,
+    span: Span,
+    sum: &str,
+    tag: usize,
+    args: &[Expr],
+    facts: &mut Facts,
+) -> Result<Type, TypeError> {
+    let fields = &env.variants(sum).expect("a declared sum type")[tag].fields;
+    if fields.len() != args.len() {
+        return Err(TypeError::ArgumentCount(span, fields.len(), args.len()));
+    }
+    let params: Vec<_> = (0..env.params(sum)).map(|_| facts.subst.fresh()).collect();
+    for (field, arg) in fields.iter().zip(args) {
+        expect(env, arg, &field.substitute(&params), facts)?;
+    }
+    Ok(Type::Sum(sum.to_string(), params))
+}
+
+/// The names `pattern` binds when it matches a value of type `ty`, with
+/// their types.
+pub fn pattern_bindings(
+    env: &TypeEnv,
+    pattern: &Pattern,
+    ty: &Type,
+) -> Result<Vec<(String, Type)>, TypeError> {
+    let mut facts = Facts::default();
+    let mut bindings = Vec::new();
+    check_pattern(env, pattern, ty, &mut bindings, &mut facts)?;
+    Ok(bindings
+        .into_iter()
+        .map(|(name, ty)| (name, facts.subst.resolve(&ty)))
+        .collect())
+}
+
+fn check_pattern(
+    env: &TypeEnv,
+    pattern: &Pattern,
+    ty: &Type,
+    bindings: &mut Vec<(String, Type)>,
+    facts: &mut Facts,
+) -> Result<(), TypeError> {
+    match &pattern.kind {
+        PatternKind::Wildcard => Ok(()),
+        PatternKind::Bind(name) => {
+            if bindings.iter().any(|(bound, _)| bound == name) {
+                return Err(TypeError::DuplicateBinding(pattern.span, name.clone()));
+            }
+            bindings.push((name.clone(), ty.clone()));
+            Ok(())
+        }
+        PatternKind::Int(_) => unify(facts, ty, &Type::Int, pattern.span),
+        PatternKind::String(_) => unify(facts, ty, &Type::String, pattern.span),
+        PatternKind::Variant(name, args) => {
+            let Some((sum, tag)) = env.constructor(name) else {
+                let known = env.constructors.keys().map(String::as_str);
+                let suggestion = best_match(name, known).map(str::to_string);
+                return Err(TypeError::UnknownIdent(
+                    pattern.span,
+                    name.clone(),
+                    suggestion,
+                ));
+            };
+            let params: Vec<_> = (0..env.params(sum)).map(|_| facts.subst.fresh()).collect();
+            let variant = Type::Sum(sum.to_string(), params.clone());
+            unify(facts, ty, &variant, pattern.span)?;
+            let fields = &env.variants(sum).expect("a declared sum type")[tag].fields;
+            if fields.len() != args.len() {
+                return Err(TypeError::ArgumentCount(
+                    pattern.span,
+                    fields.len(),
+                    args.len(),
+                ));
+            }
+            for (arg, field) in args.iter().zip(fields) {
+                check_pattern(env, arg, &field.substitute(&params), bindings, facts)?;
+            }
+            Ok(())
+        }
+        PatternKind::Tuple(items) => {
+            let types: Vec<_> = items.iter().map(|_| facts.subst.fresh()).collect();
+            unify(facts, ty, &Type::Tuple(types.clone()), pattern.span)?;
+            for (item, ty) in items.iter().zip(&types) {
+                check_pattern(env, item, ty, bindings, facts)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+/// The field types of each variant of `ty`, if it is a sum type.
+fn variant_fields(env: &TypeEnv, ty: &Type) -> Option<Vec<Vec<Type>>> {
+    let Type::Sum(name, params) = ty else {
+        return None;
+    };
+    let variants = env.variants(name)?;
+    let fields = variants
+        .iter()
+        .map(|variant| {
+            variant
+                .fields
+                .iter()
+                .map(|field| field.substitute(params))
+                .collect()
+        })
+        .collect();
+    Some(fields)
+}
+
+/// Checks that `arms` match every value of type `ty`, and that each
+/// matches some value the arms before it do not.
+fn check_coverage(
+    env: &TypeEnv,
+    span: Span,
+    arms: &[MatchArm],
+    ty: &Type,
+) -> Result<(), TypeError> {
+    let tag = |name: &str| env.constructor(name).expect("a checked constructor").1;
+    let pats: Vec<_> = arms
+        .iter()
+        .map(|arm| Pat::lower(&arm.pattern, &tag))
+        .collect();
+
+    let coverage = coverage::check(&pats, ty, &|ty: &Type| variant_fields(env, ty));
+    if let Some(&arm) = coverage.unreachable.first() {
+        return Err(TypeError::UnreachablePattern(arms[arm].pattern.span));
+    }
+    if !coverage.missing.is_empty() {
+        let missing = coverage
+            .missing
+            .iter()
+            .map(|pat| show_pattern(env, pat, ty))
+            .collect();
+        return Err(TypeError::NonExhaustive(span, missing));
+    }
+    Ok(())
+}
+
+/// `pat`, matching values of type `ty`, as it would be written.
+fn show_pattern(env: &TypeEnv, pat: &Pat, ty: &Type) -> String {
+    match (pat, ty) {
+        (Pat::Any(_), _) => "_".to_string(),
+        (Pat::Ctor(Ctor::Int(n), _), _) => n.to_string(),
+        (Pat::Ctor(Ctor::String(s), _), _) => format!("{s:?}"),
+        (Pat::Ctor(Ctor::Variant(tag), args), Type::Sum(sum, _)) => {
+            let variant = &env.variants(sum).expect("a declared sum type")[*tag];
+            let name = unqualified(&variant.name);
+            if args.is_empty() {
+                return name.to_string();
+            }
+            let fields = &variant_fields(env, ty).expect("a sum type")[*tag];
+            let args: Vec<_> = args
+                .iter()
+                .zip(fields)
+                .map(|(arg, field)| show_pattern(env, arg, field))
+                .collect();
+            format!("{name}({})", args.join(", "))
+        }
+        (Pat::Ctor(Ctor::Variant(_), _), _) => unreachable!("only sum types have variants"),
+        (Pat::Ctor(Ctor::Tuple, items), _) => {
+            let types = match ty {
+                Type::Tuple(types) => types.clone(),
+                _ => vec![Type::Never; items.len()],
+            };
+            let items: Vec<_> = items
+                .iter()
+                .zip(&types)
+                .map(|(item, ty)| show_pattern(env, item, ty))
+                .collect();
+            match items.as_slice() {
+                [item] => format!("({item},)"),
+                items => format!("({})", items.join(", ")),
+            }
+        }
+    }
+}
+
+/// The type `ty` stands for.
+pub fn resolve(env: &TypeEnv, ty: &TypeExpr) -> Result<Type, TypeError> {
+    let (name, args) = match &ty.kind {
+        TypeExprKind::Named(name, args) => (name, args.as_slice()),
+        TypeExprKind::Record(fields) => {
+            unique_fields(fields.iter().map(|(name, field)| (name, field.span)))?;
+            let fields = fields
+                .iter()
+                .map(|(name, field)| Ok((name.clone(), resolve(env, field)?)))
+                .collect::<Result<_, TypeError>>()?;
+            return Ok(Type::record(fields));
+        }
+        TypeExprKind::Tuple(items) => {
+            let items = items
+                .iter()
+                .map(|item| resolve(env, item))
+                .collect::<Result<_, _>>()?;
+            return Ok(Type::Tuple(items));
+        }
+        TypeExprKind::Function(params, ret) => {
+            let params = params
+                .iter()
+                .map(|param| resolve(env, param))
+                .collect::<Result<_, _>>()?;
+            // What a function tosses is not written out: see `written`.
+            let ret = resolve(env, ret)?;
+            return Ok(Type::Function(params, Box::new(ret), Box::new(Type::Never)));
+        }
+    };
+
+    // Declared types hide the built-in ones. A sum type is given its type
+    // arguments; other names take none.
+    if let Some(declared) = env.get_type(name) {
+        let params = match declared {
+            Type::Sum(sum, _) if sum == name => env.params(sum),
+            _ => 0,
+        };
+        if args.len() != params {
+            return Err(TypeError::ArgumentCount(ty.span, params, args.len()));
+        }
+        if params == 0 {
+            return Ok(declared.clone());
+        }
+        let args = args
+            .iter()
+            .map(|arg| resolve(env, arg))
+            .collect::<Result<_, _>>()?;
+        return Ok(Type::Sum(name.clone(), args));
+    }
+    match (name.as_str(), args) {
+        ("Int", []) => Ok(Type::Int),
+        ("String", []) => Ok(Type::String),
+        ("Unit", []) => Ok(Type::Unit),
+        ("List", [elem]) => Ok(Type::List(Box::new(resolve(env, elem)?))),
+        ("Int" | "String" | "Unit", args) => Err(TypeError::ArgumentCount(ty.span, 0, args.len())),
+        ("List", args) => Err(TypeError::ArgumentCount(ty.span, 1, args.len())),
+        _ => {
+            let known = BUILTIN_TYPES
+                .into_iter()
+                .chain(env.types.keys().map(String::as_str));
+            let suggestion = best_match(name, known).map(str::to_string);
+            Err(TypeError::UnknownType(ty.span, name.clone(), suggestion))
+        }
+    }
+}
+
+/// Checks that no field is given twice.
+fn unique_fields<'a>(fields: impl Iterator<Item = (&'a String, Span)>) -> Result<(), TypeError> {
+    let mut seen = Vec::new();
+    for (name, span) in fields {
+        if seen.contains(&name) {
+            return Err(TypeError::DuplicateField(span, name.clone()));
+        }
+        seen.push(name);
+    }
+    Ok(())
+}
+
+/// The type of `expr`, which must be a record. Its fields must be known
+/// by then: a record type is not inferred from the fields read.
+fn record_type(env: &TypeEnv, expr: &Expr, facts: &mut Facts) -> Result<Type, TypeError> {
+    let ty = check(env, expr, facts)?;
+    match facts.subst.apply(&ty) {
+        ty @ Type::Record(_) => Ok(ty),
+        Type::Var(_) => Err(TypeError::Ambiguous(expr.span)),
+        other => Err(TypeError::Mismatch(
+            expr.span,
+            "a record".to_string(),
+            other.to_string(),
+        )),
+    }
+}
+
+fn unknown_field(span: Span, record: &Type, name: &str) -> TypeError {
+    let Type::Record(fields) = record else {
+        unreachable!("only records have fields");
+    };
+    let suggestion = best_match(name, fields.iter().map(|(field, _)| field.as_str()));
+    TypeError::UnknownField(
+        span,
+        record.to_string(),
+        name.to_string(),
+        suggestion.map(str::to_string),
+    )
+}
+
+/// Solves variables so that `found`, the type of what is at `span`, is
+/// `expected`. A mismatch points to what made it expected, if that was
+/// inferred from something else.
+fn unify(facts: &mut Facts, expected: &Type, found: &Type, span: Span) -> Result<(), TypeError> {
+    let conflict = match facts.subst.unify(expected, found, span) {
+        Ok(()) => return Ok(()),
+        Err(conflict) => conflict,
+    };
+    let expected = facts.subst.apply(expected).to_string();
+    let found = facts.subst.apply(found).to_string();
+    Err(match conflict {
+        Conflict::Differ(Some(origin)) if origin != span => {
+            TypeError::Conflict(span, expected, found, origin)
+        }
+        Conflict::Differ(_) => TypeError::Mismatch(span, expected, found),
+        Conflict::Infinite(ty) => TypeError::InfiniteType(span, ty.to_string()),
+    })
+}
+
+/// The type `ty` stands for, with a variable for what each function type
+/// in it tosses: that is not written out, so inference works it out.
+fn written(env: &TypeEnv, ty: &TypeExpr, facts: &mut Facts) -> Result<Type, TypeError> {
+    Ok(open(&resolve(env, ty)?, facts))
+}
+
+fn open(ty: &Type, facts: &mut Facts) -> Type {
+    ty.map(&mut |ty| match ty {
+        Type::Function(params, ret, row) if **row == Type::Never => Some(Type::Function(
+            params.iter().map(|param| open(param, facts)).collect(),
+            Box::new(open(ret, facts)),
+            Box::new(facts.subst.fresh()),
+        )),
+        _ => None,
+    })
+}
+
+/// Records that the code being checked tosses the effects of `row`,
+/// which the expression at `span` does.
+fn toss(facts: &mut Facts, row: &Type, span: Span) -> Result<(), TypeError> {
+    match facts.row.clone() {
+        Some(current) => unify(facts, &current, row, span),
+        None => Ok(()),
+    }
+}
+
+/// The payload type of effect `name`, tossed or caught at `span`.
+fn declared(env: &TypeEnv, name: &str, span: Span) -> Result<Type, TypeError> {
+    match env.effect(name) {
+        Some(payload) => Ok(payload.clone()),
+        None => {
+            let suggestion = best_match(name, env.effects.keys().map(String::as_str));
+            Err(TypeError::UnknownEffect(
+                span,
+                name.to_string(),
+                suggestion.map(str::to_string),
+            ))
+        }
+    }
+}
+
+/// Checks that `expr` has type `expected`.
+fn expect(env: &TypeEnv, expr: &Expr, expected: &Type, facts: &mut Facts) -> Result<(), TypeError> {
+    let found = check(env, expr, facts)?;
+    unify(facts, expected, &found, expr.span)
+}
+
+/// The type of a use of `name` at `span`: its type, with fresh variables
+/// for its type parameters. Those of a binding that is not a constructor
+/// are recorded, so codegen knows which types the use is at.
+fn instantiate(env: &TypeEnv, name: &str, span: Span, facts: &mut Facts) -> Type {
+    let ty = env.get(name).expect("a bound name");
+    let params = ty.params();
+    if params == 0 {
+        return ty.clone();
+    }
+    let args: Vec<_> = (0..params).map(|_| facts.subst.fresh()).collect();
+    let ty = ty.substitute(&args);
+    if env.constructor(name).is_none() {
+        facts.instances.insert(span, args);
+    }
+    ty
+}
+
+/// The return type of function `expr` called with arguments of type
+/// `params`, and the span of what it returns. A `fn` written in place
+/// gets its parameter types from `params`.
+fn function_type(
+    env: &TypeEnv,
+    expr: &Expr,
+    params: &[Type],
+    facts: &mut Facts,
+) -> Result<(Type, Span), TypeError> {
+    let ExprKind::Lambda {
+        params: names,
+        ret,
+        body,
+    } = &expr.kind
+    else {
+        let found = check(env, expr, facts)?;
+        let ret = facts.subst.fresh();
+        let row = facts.subst.fresh();
+        let expected = Type::Function(
+            params.to_vec(),
+            Box::new(ret.clone()),
+            Box::new(row.clone()),
+        );
+        unify(facts, &expected, &found, expr.span)?;
+        toss(facts, &row, expr.span)?;
+        facts.types.insert(expr.span, ret.clone());
+        return Ok((ret, expr.span));
+    };
+
+    if names.len() != params.len() {
+        return Err(TypeError::ParamCount(expr.span, params.len(), names.len()));
+    }
+    let mut body_env = env.clone();
+    for (param, given) in names.iter().zip(params) {
+        let ty = match &param.ty {
+            Some(annotation) => {
+                let ty = written(env, annotation, facts)?;
+                unify(facts, &ty, given, annotation.span)?;
+                ty
+            }
+            None => given.clone(),
+        };
+        body_env.insert(param.name.clone(), ty);
+    }
+    let at = ret.as_ref().map_or(body.span, |ret| ret.span);
+    Ok((returned(env, &body_env, ret.as_ref(), body, facts)?, at))
+}
+
+/// The type a function with body `body` returns, checked against its
+/// annotation `ret` if it has one. The annotation is resolved in `env`,
+/// the body in `body_env`.
+fn returned(
+    env: &TypeEnv,
+    body_env: &TypeEnv,
+    ret: Option<&TypeExpr>,
+    body: &Expr,
+    facts: &mut Facts,
+) -> Result<Type, TypeError> {
+    let Some(ret) = ret else {
+        return check(body_env, body, facts);
+    };
+    let ret = written(env, ret, facts)?;
+    expect(body_env, body, &ret, facts)?;
+    Ok(ret)
+}
+
+/// The type `expr` is annotated with, or what it has if it has none.
+fn annotated(
+    env: &TypeEnv,
+    ty: Option<&TypeExpr>,
+    expr: &Expr,
+    facts: &mut Facts,
+) -> Result<Type, TypeError> {
+    let Some(ty) = ty else {
+        return check(env, expr, facts);
+    };
+    let ty = written(env, ty, facts)?;
+    expect(env, expr, &ty, facts)?;
+    Ok(ty)
+}
+
+/// The type of built-in `stage` applied to a value of type `piped`.
+/// `span` is the piped value's, `stage_span` the stage's.
+fn stage_type(
+    env: &TypeEnv,
+    stage: Stage,
+    piped: Type,
+    span: Span,
+    stage_span: Span,
+    facts: &mut Facts,
+) -> Result<Type, TypeError> {
+    let arity = stage.args.len();
+    let expected = stage_arity(stage.name).expect("a built-in stage");
+    if arity != expected {
+        return Err(TypeError::ArgumentCount(stage_span, expected, arity));
+    }
+
+    let elem = match (stage.name, facts.subst.apply(&piped)) {
+        ("len", Type::List(_) | Type::String) => return Ok(Type::Int),
+        ("len", Type::Var(_)) => return Err(TypeError::Ambiguous(span)),
+        ("len", other) => {
+            return Err(TypeError::Mismatch(
+                span,
+                "List or String".to_string(),
+                other.to_string(),
+            ));
+        }
+        ("sum", _) => {
+            unify(facts, &Type::List(Box::new(Type::Int)), &piped, span)?;
+            return Ok(Type::Int);
+        }
+        (_, Type::List(elem)) => *elem,
+        (_, Type::Var(_)) => {
+            let elem = facts.subst.fresh();
+            unify(facts, &Type::List(Box::new(elem.clone())), &piped, span)?;
+            elem
+        }
+        (_, other) => {
+            return Err(TypeError::Mismatch(
+                span,
+                "List".to_string(),
+                other.to_string(),
+            ));
+        }
+    };
+
+    match (stage.name, stage.args) {
+        ("map", [f]) => {
+            let (ret, _) = function_type(env, f, &[elem], facts)?;
+            Ok(Type::List(Box::new(ret)))
+        }
+        ("filter", [f]) => {
+            let (keep, at) = function_type(env, f, std::slice::from_ref(&elem), facts)?;
+            unify(facts, &Type::Int, &keep, at)?;
+            Ok(Type::List(Box::new(elem)))
+        }
+        ("take", [n]) => {
+            expect(env, n, &Type::Int, facts)?;
+            Ok(Type::List(Box::new(elem)))
+        }
+        ("fold", [init, f]) => {
+            let acc = check(env, init, facts)?;
+            let (ret, at) = function_type(env, f, &[acc.clone(), elem], facts)?;
+            unify(facts, &acc, &ret, at)?;
+            Ok(acc)
+        }
+        _ => unreachable!("`{}` is not a built-in stage", stage.name),
     }
 }
 
@@ -68,26 +998,150 @@ pub fn typecheck_program(ast: &Ast) -> Result<(), TypeError> {
 }
 
 pub fn typecheck_stmt(env: &mut TypeEnv, stmt: &Statement) -> Result<(), TypeError> {
-    match stmt {
-        Statement::Let { name, expr } => {
-            let ty = typecheck_expr(env, expr)?;
+    check_stmt(env, stmt).map(|_| ())
+}
+
+/// Like [`typecheck_stmt`], also returning the facts later passes need.
+pub fn check_stmt(env: &mut TypeEnv, stmt: &Statement) -> Result<Facts, TypeError> {
+    let mut facts = Facts::default();
+    statement(env, stmt, &mut facts)?;
+    facts.resolve();
+    Ok(facts)
+}
+
+fn statement(env: &mut TypeEnv, stmt: &Statement, facts: &mut Facts) -> Result<(), TypeError> {
+    match &stmt.kind {
+        // What is still unknown about the type once the value is checked
+        // can be anything: each use picks its own.
+        StatementKind::Let { name, ty, expr } => {
+            let ty = annotated(env, ty.as_ref(), expr, facts)?;
+            let ty = facts.subst.generalize(&ty, &mut 0);
             env.insert(name.clone(), ty);
             Ok(())
         }
 
-        Statement::Yell { expr } => {
-            typecheck_expr(env, expr)?;
+        StatementKind::Destructure { pattern, ty, expr } => {
+            let ty = annotated(env, ty.as_ref(), expr, facts)?;
+            let mut bindings = Vec::new();
+            check_pattern(env, pattern, &ty, &mut bindings, facts)?;
+            for (name, bound) in bindings {
+                let bound = facts.subst.generalize(&bound, &mut 0);
+                env.insert(name, bound);
+            }
+            Ok(())
+        }
+
+        StatementKind::Yell { expr } => {
+            check(env, expr, facts)?;
+            Ok(())
+        }
+
+        StatementKind::ExprStmt(expr) => {
+            check(env, expr, facts)?;
             Ok(())
         }
 
-        Statement::ExprStmt(expr) => {
-            typecheck_expr(env, expr)?;
+        StatementKind::Toss { expr } => {
+            check(env, expr, facts)?;
             Ok(())
         }
 
-        Statement::Toss { expr } => {
-            typecheck_expr(env, expr)?;
+        // Each test starts with no bindings, but sees the types declared
+        // before it.
+        StatementKind::Test { body, .. } => {
+            let mut test_env = env.scope();
+            for stmt in body {
+                statement(&mut test_env, stmt, facts)?;
+            }
+            Ok(())
+        }
+
+        StatementKind::Assert { expr } => {
+            expect(env, expr, &Type::Int, facts)?;
+            Ok(())
+        }
+
+        StatementKind::TypeDecl { name, ty } => {
+            prelude_name(stmt.span, name)?;
+            let ty = resolve(env, ty)?;
+            env.declare(name.clone(), ty);
+            Ok(())
+        }
+
+        // Declared before its variants, which may hold values of the type
+        // itself.
+        StatementKind::SumDecl { name, variants } => {
+            prelude_name(stmt.span, name)?;
+            env.declare(name.clone(), Type::Sum(name.clone(), Vec::new()));
+            let mut resolved: Vec<VariantType> = Vec::new();
+            for variant in variants {
+                if resolved.iter().any(|v| v.name == variant.name) {
+                    return Err(TypeError::DuplicateVariant(
+                        variant.span,
+                        name.clone(),
+                        variant.name.clone(),
+                    ));
+                }
+                let fields = variant
+                    .fields
+                    .iter()
+                    .map(|field| resolve(env, field))
+                    .collect::<Result<_, _>>()?;
+                resolved.push(VariantType {
+                    name: variant.name.clone(),
+                    fields,
+                });
+            }
+            env.declare_sum(name, 0, resolved);
+            Ok(())
+        }
+
+        // Code checked before a redeclaration relies on what the effect
+        // was declared with, so only the same declaration may be repeated.
+        StatementKind::EffectDecl { name, payload } => {
+            let payload = match payload {
+                Some(payload) => resolve(env, payload)?,
+                None => Type::Unit,
+            };
+            if let Some(earlier) = env.effect(name)
+                && *earlier != payload
+            {
+                return Err(TypeError::EffectRedeclared(
+                    stmt.span,
+                    name.clone(),
+                    earlier.to_string(),
+                ));
+            }
+            env.effects.insert(name.clone(), payload);
             Ok(())
         }
+
+        StatementKind::Use { path, name, alias } => {
+            let target = qualify(path, name);
+            match env.get(&target) {
+                Some(ty) => {
+                    let constructor = env.constructors.get(&target).cloned();
+                    env.insert(alias.clone(), ty.clone());
+                    if let Some(constructor) = constructor {
+                        env.constructors.insert(alias.clone(), constructor);
+                    }
+                    Ok(())
+                }
+                None => Err(TypeError::UnknownIdent(stmt.span, target, None)),
+            }
+        }
+
+        StatementKind::AssertEq { left, right } => {
+            let left_ty = check(env, left, facts)?;
+            expect(env, right, &left_ty, facts)
+        }
     }
 }
+
+/// Checks that a type declaration does not take a prelude type's name.
+fn prelude_name(span: Span, name: &str) -> Result<(), TypeError> {
+    if prelude::sums().iter().any(|(sum, _, _)| *sum == name) {
+        return Err(TypeError::PreludeType(span, name.to_string()));
+    }
+    Ok(())
+}
@@ -3,15 +3,157 @@ use thiserror::Error;
 
 #[derive(Debug, Error, PartialEq)]
 pub enum TypeError {
-    #[error("unknown identifier `{1}` at {0:?}")]
-    UnknownIdent(Span, String),
+    /// The unknown name, and the closest name in scope if one looks like a
+    /// typo of it.
+    #[error("unknown identifier `{1}`")]
+    UnknownIdent(Span, String, Option<String>),
 
-    #[error("type mismatch at {0:?}: expected {1}, found {2}")]
+    #[error("type mismatch: expected {1}, found {2}")]
     Mismatch(Span, String, String),
 
-    #[error("invalid pipeline at {0:?}: right side must be callable")]
+    /// A mismatch with a type inferred from elsewhere: the span of what
+    /// gave it.
+    #[error("type mismatch: expected {1}, found {2}")]
+    Conflict(Span, String, String, Span),
+
+    #[error("right side of pipeline cannot be a literal")]
     InvalidPipeline(Span),
 
-    #[error("generic type error: {0}")]
-    Generic(String),
+    #[error("cannot infer the element type of an empty list")]
+    EmptyList(Span),
+
+    /// The number of arguments expected, and the number passed.
+    #[error("wrong number of arguments: expected {1}, found {2}")]
+    ArgumentCount(Span, usize, usize),
+
+    /// A function passed where one of another arity is expected: the
+    /// parameters expected, and the parameters it has.
+    #[error("expected a function of {1} parameter(s), found one of {2}")]
+    ParamCount(Span, usize, usize),
+
+    /// A value used where its type must already be known, such as a
+    /// record whose field is read, before anything gives it one.
+    #[error("cannot infer the type of this value")]
+    Ambiguous(Span),
+
+    /// The unknown type name, and the closest known one if one looks like
+    /// a typo of it.
+    #[error("unknown type `{1}`")]
+    UnknownType(Span, String, Option<String>),
+
+    #[error("field `{1}` is given more than once")]
+    DuplicateField(Span, String),
+
+    /// The record type, the missing field, and the closest field it has.
+    #[error("{1} has no field `{2}`")]
+    UnknownField(Span, String, String, Option<String>),
+
+    /// Values no arm of a `match` covers, written as patterns.
+    #[error("non-exhaustive match: {} not covered", list(.1))]
+    NonExhaustive(Span, Vec<String>),
+
+    #[error("unreachable pattern")]
+    UnreachablePattern(Span),
+
+    /// The sum type, and the variant it declares twice.
+    #[error("`{1}` declares variant `{2}` more than once")]
+    DuplicateVariant(Span, String, String),
+
+    #[error("`{1}` is bound more than once in this pattern")]
+    DuplicateBinding(Span, String),
+
+    /// A constructor used as a value instead of called, and the number
+    /// of fields it takes.
+    #[error("constructor `{1}` must be called with its fields")]
+    UnappliedConstructor(Span, String, usize),
+
+    /// A `catch` of an effect nothing in it tosses, and the closest effect
+    /// tossed there if one looks like a typo of it.
+    #[error("nothing here tosses `{1}`")]
+    NothingToCatch(Span, String, Option<String>),
+
+    #[error("`{1}` is already declared by the prelude")]
+    PreludeType(Span, String),
+
+    /// A `toss`, `retoss` or `catch` of an effect no `effect` declares,
+    /// and the closest declared effect if one looks like a typo of it.
+    #[error("unknown effect `{1}`")]
+    UnknownEffect(Span, String, Option<String>),
+
+    /// An effect declared again with another payload type: the payload
+    /// type it was declared with first.
+    #[error("effect `{1}` is already declared with payload {2}")]
+    EffectRedeclared(Span, String, String),
+
+    /// A value whose type would have to contain itself, and the type it
+    /// would have.
+    #[error("infinite type: a value would have to be a {1} that contains itself")]
+    InfiniteType(Span, String),
+}
+
+/// `` `A` ``, `` `A` and `B` `` or `` `A`, `B`, `C` and 2 more ``.
+fn list(patterns: &[String]) -> String {
+    const SHOWN: usize = 3;
+    let quoted: Vec<_> = patterns.iter().map(|p| format!("`{p}`")).collect();
+    match quoted.as_slice() {
+        [] => String::new(),
+        [one] => one.clone(),
+        all if all.len() <= SHOWN => {
+            let (last, rest) = all.split_last().expect("more than one pattern");
+            format!("{} and {last}", rest.join(", "))
+        }
+        all => format!("{} and {} more", all[..SHOWN].join(", "), all.len() - SHOWN),
+    }
+}
+
+impl TypeError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            TypeError::UnknownIdent(..) => "E0101",
+            TypeError::Mismatch(..) | TypeError::Conflict(..) => "E0301",
+            TypeError::InvalidPipeline(_) => "E0201",
+            TypeError::EmptyList(_) => "E0302",
+            TypeError::ArgumentCount(..) | TypeError::ParamCount(..) => "E0303",
+            TypeError::Ambiguous(_) => "E0316",
+            TypeError::UnknownType(..) => "E0305",
+            TypeError::DuplicateField(..) => "E0306",
+            TypeError::UnknownField(..) => "E0307",
+            TypeError::NonExhaustive(..) => "E0308",
+            TypeError::UnreachablePattern(_) => "E0309",
+            TypeError::DuplicateVariant(..) => "E0310",
+            TypeError::DuplicateBinding(..) => "E0311",
+            TypeError::UnappliedConstructor(..) => "E0312",
+            TypeError::NothingToCatch(..) => "E0313",
+            TypeError::PreludeType(..) => "E0314",
+            TypeError::InfiniteType(..) => "E0315",
+            TypeError::UnknownEffect(..) => "E0317",
+            TypeError::EffectRedeclared(..) => "E0318",
+        }
+    }
+
+    pub fn span(&self) -> Span {
+        match self {
+            TypeError::UnknownIdent(span, _, _)
+            | TypeError::Mismatch(span, _, _)
+            | TypeError::Conflict(span, _, _, _)
+            | TypeError::InvalidPipeline(span)
+            | TypeError::EmptyList(span)
+            | TypeError::ArgumentCount(span, _, _)
+            | TypeError::ParamCount(span, _, _)
+            | TypeError::Ambiguous(span)
+            | TypeError::UnknownType(span, _, _)
+            | TypeError::DuplicateField(span, _)
+            | TypeError::UnknownField(span, _, _, _)
+            | TypeError::NonExhaustive(span, _)
+            | TypeError::UnreachablePattern(span)
+            | TypeError::DuplicateVariant(span, _, _)
+            | TypeError::DuplicateBinding(span, _)
+            | TypeError::UnappliedConstructor(span, _, _)
+            | TypeError::NothingToCatch(span, _, _)
+            | TypeError::PreludeType(span, _)
+            | TypeError::InfiniteType(span, _)
+            | TypeError::UnknownEffect(span, _, _)
+            | TypeError::EffectRedeclared(span, _, _) => *span,
+        }
+    }
 }
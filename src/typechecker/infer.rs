@@ -0,0 +1,203 @@
+//! Type variables, and the substitution unification solves them with.
+
+use crate::typechecker::types::Type;
+use crate::util::span::Span;
+
+/// Each type variable made so far: unsolved, or solved with a type and
+/// the span of what solved it, if an expression did.
+#[derive(Debug, Default)]
+pub struct Subst {
+    solved: Vec<Option<(Type, Option<Span>)>>,
+}
+
+/// Why two types do not unify.
+#[derive(Debug, PartialEq)]
+pub enum Conflict {
+    /// They differ. The span is what solved the variable the expected
+    /// type came from, if it came from one.
+    Differ(Option<Span>),
+    /// Solving a variable would make it contain itself: the type it
+    /// would be solved with.
+    Infinite(Type),
+}
+
+impl Subst {
+    pub fn fresh(&mut self) -> Type {
+        self.solved.push(None);
+        Type::Var(self.solved.len() - 1)
+    }
+
+    /// `ty` with the solved variables at its top replaced, and the span
+    /// that solved the last of them.
+    pub fn head(&self, ty: &Type) -> (Type, Option<Span>) {
+        let mut ty = ty.clone();
+        let mut origin = None;
+        while let Type::Var(var) = ty
+            && let Some((solved, span)) = &self.solved[var]
+        {
+            ty = solved.clone();
+            origin = *span;
+        }
+        (ty, origin)
+    }
+
+    /// `ty` with every solved variable replaced.
+    pub fn apply(&self, ty: &Type) -> Type {
+        ty.map(&mut |ty| match ty {
+            Type::Var(var) => match &self.solved[*var] {
+                Some((solved, _)) => Some(self.apply(solved)),
+                None => Some(ty.clone()),
+            },
+            _ => None,
+        })
+    }
+
+    /// [`apply`](Self::apply), with the variables nothing solved as
+    /// `Never`: nothing gave them a value.
+    pub fn resolve(&self, ty: &Type) -> Type {
+        self.apply(ty).map(&mut |ty| match ty {
+            Type::Var(_) => Some(Type::Never),
+            _ => None,
+        })
+    }
+
+    /// Solves variables so that `found`, the type of the expression at
+    /// `span`, is `expected`.
+    pub fn unify(&mut self, expected: &Type, found: &Type, span: Span) -> Result<(), Conflict> {
+        let (expected, origin) = self.head(expected);
+        let (found, _) = self.head(found);
+        let within = |conflict| match conflict {
+            Conflict::Differ(inner) => Conflict::Differ(inner.or(origin)),
+            infinite => infinite,
+        };
+        match (&expected, &found) {
+            (Type::Var(a), Type::Var(b)) if a == b => Ok(()),
+            (Type::Var(var), ty) | (ty, Type::Var(var)) => {
+                if self.occurs(*var, ty) {
+                    return Err(Conflict::Infinite(self.apply(ty)));
+                }
+                self.solved[*var] = Some((ty.clone(), Some(span)));
+                Ok(())
+            }
+            // Only a value that cannot exist has this type, so it is as
+            // good as any other.
+            (Type::Never, _) | (_, Type::Never) => Ok(()),
+            (Type::Effects(..), _) | (_, Type::Effects(..)) => self
+                .unify_rows(&expected, &found, span)
+                .map_err(|()| Conflict::Differ(origin)),
+            (Type::List(a), Type::List(b)) => self.unify(a, b, span).map_err(within),
+            (Type::Function(a, ret_a, row_a), Type::Function(b, ret_b, row_b))
+                if a.len() == b.len() =>
+            {
+                for (a, b) in a.iter().zip(b) {
+                    self.unify(a, b, span).map_err(within)?;
+                }
+                self.unify(ret_a, ret_b, span).map_err(within)?;
+                self.unify(row_a, row_b, span).map_err(within)
+            }
+            (Type::Record(a), Type::Record(b))
+                if a.len() == b.len() && a.iter().zip(b).all(|((a, _), (b, _))| a == b) =>
+            {
+                for ((_, a), (_, b)) in a.iter().zip(b) {
+                    self.unify(a, b, span).map_err(within)?;
+                }
+                Ok(())
+            }
+            (Type::Tuple(a), Type::Tuple(b)) if a.len() == b.len() => {
+                for (a, b) in a.iter().zip(b) {
+                    self.unify(a, b, span).map_err(within)?;
+                }
+                Ok(())
+            }
+            (Type::Sum(a, args_a), Type::Sum(b, args_b)) if a == b => {
+                for (a, b) in args_a.iter().zip(args_b) {
+                    self.unify(a, b, span).map_err(within)?;
+                }
+                Ok(())
+            }
+            (a, b) if a == b => Ok(()),
+            _ => Err(Conflict::Differ(origin)),
+        }
+    }
+
+    /// The effects row `ty` names, sorted, and the rest of it.
+    pub fn row(&self, ty: &Type) -> (Vec<String>, Type) {
+        match self.apply(ty) {
+            Type::Effects(names, rest) => (names, *rest),
+            rest => (Vec::new(), rest),
+        }
+    }
+
+    /// Solves the rests of rows `a` and `b` so that each has the effects
+    /// only the other names. Rows are sets: the order effects are added
+    /// in does not matter.
+    fn unify_rows(&mut self, a: &Type, b: &Type, span: Span) -> Result<(), ()> {
+        let (names_a, rest_a) = self.row(a);
+        let (names_b, rest_b) = self.row(b);
+        let only = |names: &[String], other: &[String]| -> Vec<String> {
+            names
+                .iter()
+                .filter(|name| !other.contains(name))
+                .cloned()
+                .collect()
+        };
+        let (only_a, only_b) = (only(&names_a, &names_b), only(&names_b, &names_a));
+        match (rest_a, rest_b) {
+            (Type::Var(a), Type::Var(b)) if a == b => {
+                if only_a.is_empty() && only_b.is_empty() {
+                    Ok(())
+                } else {
+                    Err(())
+                }
+            }
+            (Type::Var(a), Type::Var(b)) => {
+                let rest = self.fresh();
+                self.solved[a] = Some((Type::effects(only_b, rest.clone()), Some(span)));
+                self.solved[b] = Some((Type::effects(only_a, rest), Some(span)));
+                Ok(())
+            }
+            (Type::Var(var), rest) => self.extend(var, only_b, rest, &only_a, span),
+            (rest, Type::Var(var)) => self.extend(var, only_a, rest, &only_b, span),
+            (Type::Never, _) | (_, Type::Never) => Ok(()),
+            (a, b) if a == b && only_a.is_empty() && only_b.is_empty() => Ok(()),
+            _ => Err(()),
+        }
+    }
+
+    /// Solves row variable `var` to `names` and then `rest`, which must
+    /// have the effects `missing` too.
+    fn extend(
+        &mut self,
+        var: usize,
+        names: Vec<String>,
+        rest: Type,
+        missing: &[String],
+        span: Span,
+    ) -> Result<(), ()> {
+        if !missing.is_empty() && rest != Type::Never {
+            return Err(());
+        }
+        self.solved[var] = Some((Type::effects(names, rest), Some(span)));
+        Ok(())
+    }
+
+    /// Whether variable `var` is in `ty`.
+    fn occurs(&self, var: usize, ty: &Type) -> bool {
+        self.apply(ty).vars().contains(&var)
+    }
+
+    /// `ty` with each variable nothing solved turned into a type
+    /// parameter, numbered from `next` on in the order they appear, those
+    /// for rows of effects last.
+    pub fn generalize(&mut self, ty: &Type, next: &mut usize) -> Type {
+        let ty = self.apply(ty);
+        let rows = ty.rows();
+        let (types, rows): (Vec<_>, Vec<_>) =
+            ty.vars().into_iter().partition(|var| !rows.contains(var));
+        for var in types.into_iter().chain(rows) {
+            self.solved[var] = Some((Type::Param(*next), None));
+            *next += 1;
+        }
+        self.apply(&ty)
+    }
+}
@@ -1,6 +1,8 @@
 pub mod checker;
 pub mod errors;
+pub mod infer;
+pub mod prelude;
 pub mod types;
-pub use checker::{typecheck_expr, typecheck_program, typecheck_stmt};
+pub use checker::{Facts, check_stmt, typecheck_expr, typecheck_program, typecheck_stmt};
 pub use errors::TypeError;
-pub use types::Type;
+pub use types::{Type, VariantType};
@@ -0,0 +1,40 @@
+//! Sum types every program can use without declaring them:
+//! `Option<T> = Some(T) | None` and `Result<T, E> = Ok(T) | Err(E)`.
+
+use crate::typechecker::types::{Type, VariantType};
+
+pub const OPTION: &str = "Option";
+pub const RESULT: &str = "Result";
+
+/// Tags of the variants of [`OPTION`] and [`RESULT`].
+pub const SOME: usize = 0;
+pub const NONE: usize = 1;
+pub const OK: usize = 0;
+pub const ERR: usize = 1;
+
+/// Each prelude sum type, with its number of type parameters and its
+/// variants.
+pub fn sums() -> Vec<(&'static str, usize, Vec<VariantType>)> {
+    let variant = |name: &str, fields: Vec<Type>| VariantType {
+        name: name.to_string(),
+        fields,
+    };
+    vec![
+        (
+            OPTION,
+            1,
+            vec![
+                variant("Some", vec![Type::Param(0)]),
+                variant("None", vec![]),
+            ],
+        ),
+        (
+            RESULT,
+            2,
+            vec![
+                variant("Ok", vec![Type::Param(0)]),
+                variant("Err", vec![Type::Param(1)]),
+            ],
+        ),
+    ]
+}
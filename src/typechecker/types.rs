@@ -1,6 +1,234 @@
+use std::fmt;
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum Type {
     Int,
     String,
     Unit,
+    List(Box<Type>),
+    /// Parameter types, return type, and the effects calling it may
+    /// toss: a row of [`Effects`](Type::Effects), or the variable or
+    /// parameter the rest of one is.
+    Function(Vec<Type>, Box<Type>, Box<Type>),
+    /// Field names and types, sorted by name. Records with the same
+    /// fields have the same type, whatever they were declared as.
+    Record(Vec<(String, Type)>),
+    /// The types of a tuple's items, in order.
+    Tuple(Vec<Type>),
+    /// A sum type, by the name it was declared with, and its type
+    /// arguments. Unlike records, two sum types are the same only if they
+    /// are the same declaration.
+    Sum(String, Vec<Type>),
+    /// Type parameter `n` of a sum type, in the fields of its variants,
+    /// or of a binding that can be used at many types.
+    Param(usize),
+    /// A type inference has not worked out yet.
+    Var(usize),
+    /// A row of effects: those named, sorted, and then the rest, a
+    /// variable or parameter for effects not known yet, or `Never`.
+    Effects(Vec<String>, Box<Type>),
+    /// The type of values that cannot exist, such as the `T` of a `None`
+    /// nothing else gives a type: what a variable inference left unsolved
+    /// stands for.
+    Never,
+}
+
+/// One variant of a sum type, with the types of its fields.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VariantType {
+    pub name: String,
+    pub fields: Vec<Type>,
+}
+
+impl Type {
+    /// The record type with `fields`, in any order.
+    pub fn record(mut fields: Vec<(String, Type)>) -> Self {
+        fields.sort_by(|(a, _), (b, _)| a.cmp(b));
+        Type::Record(fields)
+    }
+
+    /// The row of effects `names` and then those of `rest`. A row with no
+    /// names is just its rest.
+    pub fn effects(mut names: Vec<String>, rest: Type) -> Self {
+        let rest = match rest {
+            Type::Effects(more, rest) => {
+                names.extend(more);
+                *rest
+            }
+            rest => rest,
+        };
+        if names.is_empty() {
+            return rest;
+        }
+        names.sort();
+        names.dedup();
+        Type::Effects(names, Box::new(rest))
+    }
+
+    /// This type with parameter `n` replaced by `args[n]`.
+    pub fn substitute(&self, args: &[Type]) -> Type {
+        let all = |types: &[Type]| types.iter().map(|ty| ty.substitute(args)).collect();
+        match self {
+            Type::Param(n) => args[*n].clone(),
+            Type::List(elem) => Type::List(Box::new(elem.substitute(args))),
+            Type::Function(params, ret, row) => Type::Function(
+                all(params),
+                Box::new(ret.substitute(args)),
+                Box::new(row.substitute(args)),
+            ),
+            Type::Effects(names, rest) => Type::effects(names.clone(), rest.substitute(args)),
+            Type::Record(fields) => Type::Record(
+                fields
+                    .iter()
+                    .map(|(name, ty)| (name.clone(), ty.substitute(args)))
+                    .collect(),
+            ),
+            Type::Tuple(items) => Type::Tuple(all(items)),
+            Type::Sum(name, params) => Type::Sum(name.clone(), all(params)),
+            Type::Int | Type::String | Type::Unit | Type::Var(_) | Type::Never => self.clone(),
+        }
+    }
+
+    /// This type with each part `f` gives a replacement for replaced.
+    pub fn map(&self, f: &mut impl FnMut(&Type) -> Option<Type>) -> Type {
+        if let Some(ty) = f(self) {
+            return ty;
+        }
+        match self {
+            Type::List(elem) => Type::List(Box::new(elem.map(f))),
+            Type::Function(params, ret, row) => Type::Function(
+                params.iter().map(|param| param.map(f)).collect(),
+                Box::new(ret.map(f)),
+                Box::new(row.map(f)),
+            ),
+            Type::Effects(names, rest) => Type::effects(names.clone(), rest.map(f)),
+            Type::Record(fields) => Type::Record(
+                fields
+                    .iter()
+                    .map(|(name, ty)| (name.clone(), ty.map(f)))
+                    .collect(),
+            ),
+            Type::Tuple(items) => Type::Tuple(items.iter().map(|item| item.map(f)).collect()),
+            Type::Sum(name, args) => {
+                Type::Sum(name.clone(), args.iter().map(|arg| arg.map(f)).collect())
+            }
+            _ => self.clone(),
+        }
+    }
+
+    /// The type variables in this type, in the order they appear.
+    pub fn vars(&self) -> Vec<usize> {
+        let mut vars = Vec::new();
+        self.map(&mut |ty| {
+            if let Type::Var(var) = ty
+                && !vars.contains(var)
+            {
+                vars.push(*var);
+            }
+            None
+        });
+        vars
+    }
+
+    /// The variables in this type that stand for rows of effects.
+    pub fn rows(&self) -> Vec<usize> {
+        let mut rows = Vec::new();
+        self.map(&mut |ty| {
+            let row = match ty {
+                Type::Function(_, _, row) => row,
+                Type::Effects(_, rest) => rest,
+                _ => return None,
+            };
+            if let Type::Var(var) = **row
+                && !rows.contains(&var)
+            {
+                rows.push(var);
+            }
+            None
+        });
+        rows
+    }
+
+    /// The number of type parameters this type has: one more than the
+    /// highest it mentions.
+    pub fn params(&self) -> usize {
+        let mut params = 0;
+        self.map(&mut |ty| {
+            if let Type::Param(n) = ty {
+                params = params.max(n + 1);
+            }
+            None
+        });
+        params
+    }
+
+    /// The type of field `name`, if this is a record that has one.
+    pub fn field(&self, name: &str) -> Option<&Type> {
+        match self {
+            Type::Record(fields) => fields.iter().find(|(n, _)| n == name).map(|(_, ty)| ty),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Type::Int => f.write_str("Int"),
+            Type::String => f.write_str("String"),
+            Type::Unit => f.write_str("Unit"),
+            Type::List(elem) => write!(f, "List<{elem}>"),
+            Type::Function(params, ret, row) => {
+                f.write_str("fn(")?;
+                for (i, param) in params.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{param}")?;
+                }
+                write!(f, ") -> {ret}")?;
+                if let Type::Effects(..) = **row {
+                    write!(f, " {row}")?;
+                }
+                Ok(())
+            }
+            // Only the effects known are shown.
+            Type::Effects(names, _) => write!(f, "tosses {}", names.join(", ")),
+            Type::Record(fields) => {
+                f.write_str("{ ")?;
+                for (i, (name, ty)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{name}: {ty}")?;
+                }
+                f.write_str(" }")
+            }
+            Type::Tuple(items) => {
+                f.write_str("(")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{item}")?;
+                }
+                f.write_str(if items.len() == 1 { ",)" } else { ")" })
+            }
+            Type::Sum(name, args) => {
+                f.write_str(name)?;
+                for (i, arg) in args.iter().enumerate() {
+                    f.write_str(if i == 0 { "<" } else { ", " })?;
+                    write!(f, "{arg}")?;
+                }
+                if !args.is_empty() {
+                    f.write_str(">")?;
+                }
+                Ok(())
+            }
+            // `A` to `Z`, then `T26`, `T27`, ...
+            Type::Param(n @ 0..26) => write!(f, "{}", char::from(b'A' + *n as u8)),
+            Type::Param(n) => write!(f, "T{n}"),
+            Type::Var(_) | Type::Never => f.write_str("_"),
+        }
+    }
 }
@@ -0,0 +1,32 @@
+/// Maps byte offsets in a source file to 1-based line and column numbers.
+#[derive(Debug, Clone)]
+pub struct LineIndex {
+    line_starts: Vec<usize>,
+}
+
+impl LineIndex {
+    pub fn new(src: &str) -> Self {
+        let mut line_starts = vec![0];
+        for (i, b) in src.bytes().enumerate() {
+            if b == b'\n' {
+                line_starts.push(i + 1);
+            }
+        }
+        Self { line_starts }
+    }
+
+    /// Returns the 1-based `(line, column)` of `offset`. Columns count bytes.
+    pub fn line_col(&self, offset: usize) -> (u32, u32) {
+        let line = match self.line_starts.binary_search(&offset) {
+            Ok(line) => line,
+            Err(next) => next - 1,
+        };
+        let col = offset - self.line_starts[line];
+        (line as u32 + 1, col as u32 + 1)
+    }
+
+    /// Byte offset of the start of 0-based `line`, clamped to the last line.
+    pub fn line_start(&self, line: usize) -> usize {
+        self.line_starts[line.min(self.line_starts.len() - 1)]
+    }
+}
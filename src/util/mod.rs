@@ -1 +1,4 @@
+pub mod line_index;
+pub mod source_map;
 pub mod span;
+pub mod suggest;
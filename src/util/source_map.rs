@@ -0,0 +1,67 @@
+use crate::errors::diagnostic::{Diagnostic, ErrorFormat};
+use crate::util::span::Span;
+
+/// One file in a `SourceMap`.
+#[derive(Debug, Clone)]
+pub struct SourceFile {
+    pub name: String,
+    pub src: String,
+    /// Offset of the file's first byte in the map.
+    pub base: usize,
+}
+
+/// Every file of a program, laid out one after another so a single `Span`
+/// can point into any of them.
+#[derive(Debug, Clone, Default)]
+pub struct SourceMap {
+    pub files: Vec<SourceFile>,
+}
+
+impl SourceMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The base offset the next file added will get.
+    pub fn next_base(&self) -> usize {
+        // One byte of padding keeps end-of-file spans apart from the next
+        // file.
+        self.files.last().map_or(0, |f| f.base + f.src.len() + 1)
+    }
+
+    /// Adds a file and returns its base offset. Lex it with
+    /// `Lexer::with_offset` so its spans land in the right place.
+    pub fn add(&mut self, name: impl Into<String>, src: impl Into<String>) -> usize {
+        let base = self.next_base();
+        self.files.push(SourceFile {
+            name: name.into(),
+            src: src.into(),
+            base,
+        });
+        base
+    }
+
+    /// The file `offset` points into.
+    pub fn file(&self, offset: usize) -> Option<&SourceFile> {
+        self.files.iter().rev().find(|f| f.base <= offset)
+    }
+
+    /// Renders `diagnostic` against the file its primary span points into.
+    pub fn render(&self, diagnostic: &Diagnostic, format: ErrorFormat) -> String {
+        let Some(file) = self.file(diagnostic.span.map_or(0, |s| s.start)) else {
+            return diagnostic.render(format, "", "");
+        };
+
+        let in_file =
+            |span: Span| span.start >= file.base && span.end <= file.base + file.src.len();
+        let local = |span: Span| Span::new(span.start - file.base, span.end - file.base);
+
+        let mut diagnostic = diagnostic.clone();
+        diagnostic.span = diagnostic.span.filter(|s| in_file(*s)).map(local);
+        diagnostic.labels.retain(|label| in_file(label.span));
+        for label in &mut diagnostic.labels {
+            label.span = local(label.span);
+        }
+        diagnostic.render(format, &file.name, &file.src)
+    }
+}
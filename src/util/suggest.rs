@@ -0,0 +1,44 @@
+/// Edit distance between `a` and `b`, counted in chars, where swapping
+/// two adjacent chars is one edit (optimal string alignment): `garb` is
+/// one edit from `grab`.
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut before: Vec<usize> = Vec::new();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, ca) in a.iter().enumerate() {
+        let mut row = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitute = prev[j] + usize::from(ca != cb);
+            let mut distance = substitute.min(prev[j + 1] + 1).min(row[j] + 1);
+            if i > 0 && j > 0 && *ca == b[j - 1] && a[i - 1] == *cb {
+                distance = distance.min(before[j - 1] + 1);
+            }
+            row.push(distance);
+        }
+        before = std::mem::replace(&mut prev, row);
+    }
+
+    prev[b.len()]
+}
+
+/// The candidate closest to `name`, if any is close enough to be a typo.
+///
+/// Up to a third of the name may differ, but always at least one edit, and
+/// never the whole name: `x` is not a typo of `y`.
+pub fn best_match<'a>(
+    name: &str,
+    candidates: impl IntoIterator<Item = &'a str>,
+) -> Option<&'a str> {
+    let len = name.chars().count();
+    let limit = (len / 3).max(1).min(len.saturating_sub(1));
+
+    candidates
+        .into_iter()
+        .filter(|c| *c != name)
+        .map(|c| (edit_distance(name, c), c))
+        .filter(|(distance, _)| *distance <= limit)
+        .min_by_key(|(distance, c)| (*distance, *c))
+        .map(|(_, c)| c)
+}
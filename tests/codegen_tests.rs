@@ -0,0 +1,620 @@
+use inkwell::OptimizationLevel;
+use inkwell::context::Context;
+use inkwell::targets::{InitializationConfig, Target};
+use sauce::codegen::context::Codegen;
+use sauce::codegen::error::CodegenError;
+use sauce::codegen::runtime::TAG_INT;
+use sauce::codegen::target::{CompileTarget, emit_object};
+use sauce::codegen::types::STRING_HEADER_SIZE;
+use sauce::codegen::{CodegenOptions, codegen, compile};
+use sauce::lexer::Lexer;
+use sauce::parser::SauceParser;
+use sauce::util::span::Span;
+use std::ffi::{CStr, c_char};
+use std::sync::atomic::{AtomicI64, Ordering};
+
+fn compile_to_ir(src: &str, debug_info: bool) -> String {
+    let tokens = Lexer::new(src)
+        .collect::<Result<Vec<_>, _>>()
+        .expect("lex failed");
+    let ast = SauceParser::new().parse(&tokens).expect("parse failed");
+
+    let context = Context::create();
+    let options = CodegenOptions {
+        debug_info,
+        filename: "examples/debug.sauce",
+        source: src,
+        target: None,
+        ..Default::default()
+    };
+    let cg = compile(&context, &ast, &options).expect("codegen failed");
+    cg.module.verify().expect("module should verify");
+    cg.module.print_to_string().to_string()
+}
+
+#[test]
+fn no_debug_info_without_flag() {
+    let ir = compile_to_ir("grab x = 1; yell x;", false);
+    assert!(!ir.contains("!DICompileUnit"));
+    assert!(!ir.contains("llvm.dbg.declare"));
+}
+
+#[test]
+fn debug_info_describes_main_and_bindings() {
+    let src = "grab x = 10 |> _;\ngrab s = \"hi\";\n\nyell x;\n";
+    let ir = compile_to_ir(src, true);
+
+    assert!(ir.contains("!DICompileUnit("));
+    assert!(ir.contains("!DIFile(filename: \"debug.sauce\", directory: \"examples\")"));
+    assert!(ir.contains("!DISubprogram(name: \"main\""));
+    assert!(ir.contains("!DILocalVariable(name: \"x\""));
+    assert!(ir.contains("!DILocalVariable(name: \"s\""));
+    assert!(ir.contains("llvm.dbg.declare"));
+    assert!(ir.contains("!DILocation(line: 1, column: 1"));
+    assert!(ir.contains("!DILocation(line: 2, column: 1"));
+    assert!(ir.contains("!DILocation(line: 4, column: 1"));
+}
+
+#[test]
+fn debug_info_pointers_follow_the_target() {
+    let src = "grab s = \"hi\";\nyell s;\n";
+    let tokens = Lexer::new(src)
+        .collect::<Result<Vec<_>, _>>()
+        .expect("lex failed");
+    let ast = SauceParser::new().parse(&tokens).expect("parse failed");
+
+    let context = Context::create();
+    let options = CodegenOptions {
+        debug_info: true,
+        filename: "debug.sauce",
+        source: src,
+        target: Some(CompileTarget::Wasm32),
+        ..Default::default()
+    };
+    let cg = compile(&context, &ast, &options).expect("codegen failed");
+    cg.module.verify().expect("module should verify");
+    let ir = cg.module.print_to_string().to_string();
+    let string = ir
+        .lines()
+        .find(|line| line.contains("name: \"String\""))
+        .expect("no `String` debug type");
+    assert!(string.contains("size: 32"), "{string}");
+
+    let ir = compile_to_ir(src, true);
+    let string = ir
+        .lines()
+        .find(|line| line.contains("name: \"String\""))
+        .expect("no `String` debug type");
+    assert!(string.contains("size: 64"), "{string}");
+}
+
+fn compile_to_object(src: &str, target: CompileTarget) -> (String, Vec<u8>) {
+    let tokens = Lexer::new(src)
+        .collect::<Result<Vec<_>, _>>()
+        .expect("lex failed");
+    let ast = SauceParser::new().parse(&tokens).expect("parse failed");
+
+    let context = Context::create();
+    let options = CodegenOptions {
+        target: Some(target),
+        ..Default::default()
+    };
+    let cg = compile(&context, &ast, &options).expect("codegen failed");
+    let machine = cg.target_machine.as_ref().expect("target machine");
+    let object = emit_object(&cg.module, machine).expect("emit failed");
+
+    (
+        cg.module
+            .get_triple()
+            .as_str()
+            .to_string_lossy()
+            .into_owned(),
+        object.as_slice().to_vec(),
+    )
+}
+
+const EM_X86_64: u16 = 0x3e;
+const EM_AARCH64: u16 = 0xb7;
+
+fn elf_machine(object: &[u8]) -> u16 {
+    assert_eq!(&object[..4], b"\x7fELF", "not an ELF object");
+    assert_eq!(object[4], 2, "expected ELFCLASS64");
+    assert_eq!(object[5], 1, "expected little endian");
+    u16::from_le_bytes([object[18], object[19]])
+}
+
+#[test]
+fn parse_target_triples() {
+    assert_eq!(
+        "aarch64-unknown-linux-gnu"
+            .parse::<CompileTarget>()
+            .unwrap(),
+        CompileTarget::Aarch64Linux
+    );
+    assert!("sparc-sun-solaris".parse::<CompileTarget>().is_err());
+}
+
+#[test]
+fn emit_x86_64_object() {
+    let (triple, object) = compile_to_object("yell 1;", CompileTarget::X86_64Linux);
+    assert_eq!(triple, "x86_64-unknown-linux-gnu");
+    assert_eq!(elf_machine(&object), EM_X86_64);
+}
+
+#[test]
+fn emit_aarch64_object() {
+    let (triple, object) = compile_to_object("grab x = 2; yell x;", CompileTarget::Aarch64Linux);
+    assert_eq!(triple, "aarch64-unknown-linux-gnu");
+    assert_eq!(elf_machine(&object), EM_AARCH64);
+}
+
+#[test]
+fn emit_wasm32_object() {
+    let (triple, object) = compile_to_object("yell \"hi\";", CompileTarget::Wasm32);
+    assert_eq!(triple, "wasm32-unknown-unknown");
+    assert_eq!(&object[..4], b"\0asm");
+    assert_eq!(&object[4..8], &[1, 0, 0, 0]);
+}
+
+#[test]
+fn wasm32_objects_import_libc() {
+    let tokens = Lexer::new("yell \"hi\";")
+        .collect::<Result<Vec<_>, _>>()
+        .expect("lex failed");
+    let ast = SauceParser::new().parse(&tokens).expect("parse failed");
+
+    let context = Context::create();
+    let options = CodegenOptions {
+        target: Some(CompileTarget::Wasm32),
+        ..Default::default()
+    };
+    let cg = compile(&context, &ast, &options).expect("codegen failed");
+    for name in [
+        "malloc", "free", "strlen", "strcmp", "printf", "fflush", "write", "exit",
+    ] {
+        let function = cg.module.get_function(name).expect(name);
+        assert_eq!(function.count_basic_blocks(), 0, "{name} is defined");
+    }
+
+    let machine = cg.target_machine.as_ref().expect("target machine");
+    let object = emit_object(&cg.module, machine).expect("emit failed");
+    let object = object.as_slice();
+    assert!(object.windows(6).any(|bytes| bytes == b"malloc"));
+}
+
+fn compile_source<'ctx>(context: &'ctx Context, src: &str) -> Codegen<'ctx> {
+    let tokens = Lexer::new(src)
+        .collect::<Result<Vec<_>, _>>()
+        .expect("lex failed");
+    let ast = SauceParser::new().parse(&tokens).expect("parse failed");
+    let cg = compile(context, &ast, &CodegenOptions::default()).expect("codegen failed");
+    cg.module.verify().expect("module should verify");
+    cg
+}
+
+#[test]
+fn runtime_is_emitted_with_c_abi_names() {
+    let ir = compile_to_ir("yell 1;", false);
+    for name in [
+        "sauce_rt_str_alloc",
+        "sauce_rt_str_concat",
+        "sauce_rt_print_int",
+        "sauce_rt_print_str",
+        "sauce_rt_panic",
+        "sauce_rt_register_handler",
+        "sauce_rt_toss",
+    ] {
+        assert!(
+            ir.lines()
+                .any(|l| l.starts_with("define weak_odr") && l.contains(&format!("@{name}("))),
+            "missing runtime function {name}"
+        );
+    }
+}
+
+#[test]
+fn yell_and_toss_call_into_runtime() {
+    let ir = compile_to_ir(
+        "effect network_error(String); yell 1; yell \"hi\"; toss network_error \"timeout\";",
+        false,
+    );
+    let main = &ir[ir.find("define i32 @main").expect("main")..];
+    assert!(main.contains("call void @sauce_rt_print_int"));
+    assert!(main.contains("call void @sauce_rt_print_str"));
+    assert!(main.contains("call void @sauce_rt_toss("));
+    assert!(main.contains(", i32 2, i64 ptrtoint"));
+}
+
+static HANDLED: AtomicI64 = AtomicI64::new(0);
+
+extern "C" fn record_effect(tag: i32, payload: i64) {
+    assert_eq!(tag as u64, TAG_INT);
+    HANDLED.store(payload, Ordering::SeqCst);
+}
+
+#[test]
+fn jit_runtime_concat_and_effect_dispatch() {
+    Target::initialize_native(&InitializationConfig::default()).expect("native target");
+
+    let context = Context::create();
+    let cg = compile_source(&context, "effect answer(Int); grab x = 41; toss answer x;");
+    let engine = cg
+        .module
+        .create_jit_execution_engine(OptimizationLevel::None)
+        .expect("jit");
+
+    type FromCStr = unsafe extern "C" fn(*const c_char) -> *const u8;
+    type Concat = unsafe extern "C" fn(*const u8, *const u8) -> *const u8;
+    type Release = unsafe extern "C" fn(*const u8);
+    type Live = unsafe extern "C" fn() -> i64;
+    type Register = unsafe extern "C" fn(*const c_char, extern "C" fn(i32, i64));
+    type Main = unsafe extern "C" fn() -> i32;
+
+    unsafe {
+        let from_cstr = engine
+            .get_function::<FromCStr>("sauce_rt_str_from_cstr")
+            .expect("from_cstr");
+        let concat = engine
+            .get_function::<Concat>("sauce_rt_str_concat")
+            .expect("concat");
+        let release = engine
+            .get_function::<Release>("sauce_rt_str_release")
+            .expect("release");
+        let live = engine
+            .get_function::<Live>("sauce_rt_strings_live")
+            .expect("strings_live");
+
+        let a = from_cstr.call(c"sau".as_ptr());
+        let b = from_cstr.call(c"ce".as_ptr());
+        let joined = concat.call(a, b);
+        let data = joined.add(STRING_HEADER_SIZE as usize) as *const c_char;
+        assert_eq!(CStr::from_ptr(data).to_str().unwrap(), "sauce");
+        assert_eq!(live.call(), 3);
+
+        release.call(a);
+        release.call(b);
+        release.call(joined);
+        assert_eq!(live.call(), 0);
+
+        let register = engine
+            .get_function::<Register>("sauce_rt_register_handler")
+            .expect("register");
+        register.call(c"answer".as_ptr(), record_effect);
+
+        let main = engine.get_function::<Main>("main").expect("main");
+        assert_eq!(main.call(), 0);
+    }
+
+    assert_eq!(HANDLED.load(Ordering::SeqCst), 41);
+}
+
+#[test]
+fn string_bindings_are_refcounted() {
+    let ir = compile_to_ir("grab s = \"hi\"; grab t = s; yell t;", false);
+
+    // Literals are static headers the runtime never frees.
+    assert!(ir.contains("{ i64 -1, i64 2, [3 x i8] c\"hi\\00\" }"));
+    assert!(ir.contains("call void @sauce_rt_str_retain("));
+    // `s` and `t` are both released before main returns.
+    let main = &ir[ir.find("define i32 @main").unwrap()..];
+    assert_eq!(main.matches("call void @sauce_rt_str_release(").count(), 3);
+}
+
+#[test]
+fn jit_lists_free_their_elements() {
+    Target::initialize_native(&InitializationConfig::default()).expect("native target");
+
+    let context = Context::create();
+    let src = "grab xs = [[\"a\", \"b\"], [\"c\"]];\ngrab c = xs[1][0];\ngrab n = xs |> len;\nyell xs;\nyell c;";
+    let cg = compile_source(&context, src);
+    let ir = cg.module.print_to_string().to_string();
+    assert!(ir.contains("{ ptr, i64, i64, [0 x { ptr, i64, i64, [0 x ptr] }] }"));
+    assert!(ir.contains("call void @sauce_rt_panic"));
+
+    let engine = cg
+        .module
+        .create_jit_execution_engine(OptimizationLevel::None)
+        .expect("jit");
+
+    type Live = unsafe extern "C" fn() -> i64;
+    type Main = unsafe extern "C" fn() -> i32;
+
+    unsafe {
+        let main = engine.get_function::<Main>("main").expect("main");
+        assert_eq!(main.call(), 0);
+
+        let lists = engine
+            .get_function::<Live>("sauce_rt_lists_live")
+            .expect("lists_live");
+        assert_eq!(lists.call(), 0);
+    }
+}
+
+#[test]
+fn jit_stages_fuse_into_one_loop() {
+    Target::initialize_native(&InitializationConfig::default()).expect("native target");
+
+    let context = Context::create();
+    let src = "grab xs = [\"a\", \"bb\", \"ccc\"];\ngrab ys = xs |> map(fn(s) s + \"!\") |> filter(fn(s) s |> len > 2) |> take(1);\ngrab n = [1, 2, 3] |> map(fn(x) x * x) |> sum;\nyell ys;\nyell n;";
+    let cg = compile_source(&context, src);
+    let ir = cg.module.print_to_string().to_string();
+    let main = &ir[ir.find("define i32 @main").unwrap()..];
+    // One loop per chain, and no list between the stages.
+    assert_eq!(main.matches("\nstages_head").count(), 2);
+    assert_eq!(main.matches("call ptr @sauce_rt_list_alloc(").count(), 3);
+
+    let engine = cg
+        .module
+        .create_jit_execution_engine(OptimizationLevel::None)
+        .expect("jit");
+
+    type Live = unsafe extern "C" fn() -> i64;
+    type Main = unsafe extern "C" fn() -> i32;
+
+    unsafe {
+        let main = engine.get_function::<Main>("main").expect("main");
+        assert_eq!(main.call(), 0);
+
+        for live in ["sauce_rt_lists_live", "sauce_rt_strings_live"] {
+            let live = engine.get_function::<Live>(live).expect(live);
+            assert_eq!(live.call(), 0);
+        }
+    }
+}
+
+#[test]
+fn jit_records_own_their_fields() {
+    Target::initialize_native(&InitializationConfig::default()).expect("native target");
+
+    let context = Context::create();
+    let src = "grab p = { name: \"a\" + \"b\", tags: [\"x\" + \"y\"], age: 3 };\ngrab q = { p with name: p.name + \"!\", age: p.age + 1 };\ngrab ps = [p, q] |> map(fn(r) { tag: r.tags[0] + r.name });\nyell q;\nyell ps;";
+    let cg = compile_source(&context, src);
+    cg.module.verify().expect("invalid module");
+    let ir = cg.module.print_to_string().to_string();
+    assert!(ir.contains("%\"record.age.name.tags<i64, ptr, "), "{ir}");
+
+    let engine = cg
+        .module
+        .create_jit_execution_engine(OptimizationLevel::None)
+        .expect("jit");
+
+    type Live = unsafe extern "C" fn() -> i64;
+    type Main = unsafe extern "C" fn() -> i32;
+
+    unsafe {
+        let main = engine.get_function::<Main>("main").expect("main");
+        assert_eq!(main.call(), 0);
+
+        for live in ["sauce_rt_lists_live", "sauce_rt_strings_live"] {
+            let live = engine.get_function::<Live>(live).expect(live);
+            assert_eq!(live.call(), 0);
+        }
+    }
+}
+
+#[test]
+fn jit_matches_switch_on_the_tag() {
+    Target::initialize_native(&InitializationConfig::default()).expect("native target");
+
+    let context = Context::create();
+    let src = "type Tree = Leaf | Node(Tree, String, Tree);\ngrab t = Node(Node(Leaf, \"a\" + \"b\", Leaf), \"c\", Leaf);\nyell match t { Node(Node(_, s, _), c, _) => s + c, Node(_, c, _) => c, Leaf => \"\" };\nyell [t, Leaf] |> map(fn(t) match t { Leaf => 0, _ => 1 });\nyell t;";
+    let cg = compile_source(&context, src);
+    cg.module.verify().expect("invalid module");
+    let ir = cg.module.print_to_string().to_string();
+    assert!(ir.contains("%sum.Tree = type { i64, ptr }"), "{ir}");
+    assert!(ir.contains("switch i64"), "{ir}");
+
+    let engine = cg
+        .module
+        .create_jit_execution_engine(OptimizationLevel::None)
+        .expect("jit");
+
+    type Live = unsafe extern "C" fn() -> i64;
+    type Main = unsafe extern "C" fn() -> i32;
+
+    unsafe {
+        let main = engine.get_function::<Main>("main").expect("main");
+        assert_eq!(main.call(), 0);
+
+        for live in ["sauce_rt_lists_live", "sauce_rt_strings_live"] {
+            let live = engine.get_function::<Live>(live).expect(live);
+            assert_eq!(live.call(), 0);
+        }
+    }
+}
+
+#[test]
+fn jit_catch_unwinds_and_releases() {
+    Target::initialize_native(&InitializationConfig::default()).expect("native target");
+
+    let context = Context::create();
+    let src = "effect net(String);\neffect e;\neffect stop(Option<String>);\ngrab r = catch net [\"a\" + \"b\", \"c\"] |> map(fn(s) match s { \"c\" => retoss net Err(s + \"!\"), _ => s + s });\nyell r;\nyell catch net { a: \"x\" + \"y\", b: catch e retoss e Some(\"z\" + \"z\"), c: retoss net Err(\"w\" + \"w\") };\nyell catch stop [1, 2, 3] |> fold(Some(\"s\" + \"t\"), fn(acc, x) match x { 3 => retoss stop Err(acc), _ => acc });\nyell [Some(1), None] |> map(fn(o) match o { Some(n) => n, None => 0 }) |> sum;";
+    let cg = compile_source(&context, src);
+    cg.module.verify().expect("invalid module");
+    let ir = cg.module.print_to_string().to_string();
+    assert!(ir.contains("%sum.Result = type { i64, ptr }"), "{ir}");
+    assert!(!ir.contains("sauce_rt_toss(ptr @effect"), "{ir}");
+
+    let engine = cg
+        .module
+        .create_jit_execution_engine(OptimizationLevel::None)
+        .expect("jit");
+
+    type Live = unsafe extern "C" fn() -> i64;
+    type Main = unsafe extern "C" fn() -> i32;
+
+    unsafe {
+        let main = engine.get_function::<Main>("main").expect("main");
+        assert_eq!(main.call(), 0);
+
+        for live in ["sauce_rt_lists_live", "sauce_rt_strings_live"] {
+            let live = engine.get_function::<Live>(live).expect(live);
+            assert_eq!(live.call(), 0);
+        }
+    }
+}
+
+#[test]
+fn jit_tuples_own_their_items() {
+    Target::initialize_native(&InitializationConfig::default()).expect("native target");
+
+    let context = Context::create();
+    let src = "effect e((String, List<String>));\ngrab pair = (\"a\" + \"b\", [\"c\" + \"d\"]);\ngrab (s, _) = pair;\ngrab ((n, _), o) = ((1, \"e\" + \"f\"), Some(\"g\" + \"h\"));\ngrab totals = [(1, \"x\"), (2, \"y\")] |> fold((0, \"\"), fn(acc, p) match (acc, p) { ((t, u), (m, v)) => (t + m, u + v) });\nyell (s, n, o, totals);\nyell catch e (\"i\" + \"j\", retoss e Err(pair));";
+    let cg = compile_source(&context, src);
+    cg.module.verify().expect("invalid module");
+    let ir = cg.module.print_to_string().to_string();
+    assert!(ir.contains("insertvalue { ptr, "), "{ir}");
+
+    let engine = cg
+        .module
+        .create_jit_execution_engine(OptimizationLevel::None)
+        .expect("jit");
+
+    type Live = unsafe extern "C" fn() -> i64;
+    type Main = unsafe extern "C" fn() -> i32;
+
+    unsafe {
+        let main = engine.get_function::<Main>("main").expect("main");
+        assert_eq!(main.call(), 0);
+
+        for live in ["sauce_rt_lists_live", "sauce_rt_strings_live"] {
+            let live = engine.get_function::<Live>(live).expect(live);
+            assert_eq!(live.call(), 0);
+        }
+    }
+}
+
+#[test]
+fn jit_functions_inline_where_called() {
+    Target::initialize_native(&InitializationConfig::default()).expect("native target");
+
+    let context = Context::create();
+    let src = "effect oops(String);\ngrab suffix = \"!\" + \"?\";\ngrab shout = fn(s: String) -> String s + suffix;\ngrab suffix = 1;\ngrab twice = fn(f: fn(String) -> String, s: String) f(f(s));\ngrab fail = fn(s: String) toss oops s;\nyell twice(shout, \"a\" + \"b\");\nyell [\"c\"] |> map(shout) |> map(fn(s: String) twice(fn(t: String) t + t, s));\nyell catch oops fail(\"d\" + \"e\");\nyell suffix;";
+    let cg = compile_source(&context, src);
+    cg.module.verify().expect("invalid module");
+
+    let engine = cg
+        .module
+        .create_jit_execution_engine(OptimizationLevel::None)
+        .expect("jit");
+
+    type Live = unsafe extern "C" fn() -> i64;
+    type Main = unsafe extern "C" fn() -> i32;
+
+    unsafe {
+        let main = engine.get_function::<Main>("main").expect("main");
+        assert_eq!(main.call(), 0);
+
+        for live in ["sauce_rt_lists_live", "sauce_rt_strings_live"] {
+            let live = engine.get_function::<Live>(live).expect(live);
+            assert_eq!(live.call(), 0);
+        }
+    }
+}
+
+#[test]
+fn jit_polymorphic_functions_are_specialized_where_used() {
+    Target::initialize_native(&InitializationConfig::default()).expect("native target");
+
+    let context = Context::create();
+    let src = "grab id = fn(x) x;\ngrab twice = fn(f, x) f(f(x));\ngrab unwrap = fn(o, d) match o { Some(v) => v, None => d };\ngrab alias = id;\nyell id(1) + alias(2);\nyell id(\"a\" + \"b\");\nyell twice(fn(s) s + \"!\", \"c\" + \"d\");\nyell unwrap(Some(\"e\" + \"f\"), \"g\") + unwrap(None, \"h\");\nyell [[1]] |> map(id) |> map(fn(xs) xs |> map(alias));";
+    let cg = compile_source(&context, src);
+    cg.module.verify().expect("invalid module");
+
+    let engine = cg
+        .module
+        .create_jit_execution_engine(OptimizationLevel::None)
+        .expect("jit");
+
+    type Live = unsafe extern "C" fn() -> i64;
+    type Main = unsafe extern "C" fn() -> i32;
+
+    unsafe {
+        let main = engine.get_function::<Main>("main").expect("main");
+        assert_eq!(main.call(), 0);
+
+        for live in ["sauce_rt_lists_live", "sauce_rt_strings_live"] {
+            let live = engine.get_function::<Live>(live).expect(live);
+            assert_eq!(live.call(), 0);
+        }
+    }
+}
+
+#[test]
+fn function_values_are_a_codegen_error() {
+    let tokens = Lexer::new("grab f = fn(x: Int) x;\ngrab fs = [f];")
+        .collect::<Result<Vec<_>, _>>()
+        .expect("lex failed");
+    let ast = SauceParser::new().parse(&tokens).expect("parse failed");
+    let context = Context::create();
+    let Err(error) = compile(&context, &ast, &CodegenOptions::default()) else {
+        panic!("a function in a list compiled");
+    };
+    assert!(matches!(error, CodegenError::FunctionValue(span) if span == Span::new(34, 35)));
+}
+
+#[test]
+fn functions_kept_in_values_are_a_codegen_error() {
+    for src in [
+        "grab r = { f: fn(x) fn(y) x };\ngrab g = r.f(1);",
+        "yell match Some(fn(x) x) { Some(f) => f(1), None => 0 };",
+    ] {
+        let tokens = Lexer::new(src)
+            .collect::<Result<Vec<_>, _>>()
+            .expect("lex failed");
+        let ast = SauceParser::new().parse(&tokens).expect("parse failed");
+        let context = Context::create();
+        let result = compile(&context, &ast, &CodegenOptions::default());
+        assert!(
+            matches!(result, Err(CodegenError::FunctionValue(_))),
+            "{src}"
+        );
+    }
+}
+
+#[test]
+fn ill_typed_programs_are_a_codegen_error() {
+    let tokens = Lexer::new("grab n = 1;\nyell n + \"one\";")
+        .collect::<Result<Vec<_>, _>>()
+        .expect("lex failed");
+    let ast = SauceParser::new().parse(&tokens).expect("parse failed");
+    let context = Context::create();
+    let Err(error) = compile(&context, &ast, &CodegenOptions::default()) else {
+        panic!("an ill-typed program compiled");
+    };
+    assert!(matches!(error, CodegenError::Type(_)));
+    assert_eq!(error.code(), "E0301");
+}
+
+#[test]
+fn build_writes_to_the_output_path() {
+    let tokens = Lexer::new("yell \"hi\";")
+        .collect::<Result<Vec<_>, _>>()
+        .expect("lex failed");
+    let ast = SauceParser::new().parse(&tokens).expect("parse failed");
+
+    let dir = std::env::temp_dir().join("sauce_codegen_output");
+    std::fs::create_dir_all(&dir).unwrap();
+    let output = dir.join("prog");
+    let output = output.to_str().unwrap();
+    for target in [CompileTarget::X86_64Linux, CompileTarget::Aarch64Linux] {
+        let options = CodegenOptions {
+            target: Some(target),
+            output,
+            ..Default::default()
+        };
+        codegen(&ast, &options).expect("codegen failed");
+        assert!(dir.join("prog.ll").exists());
+        assert!(dir.join("prog.o").exists());
+    }
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    let options = CodegenOptions {
+        output,
+        ..Default::default()
+    };
+    assert!(matches!(
+        codegen(&ast, &options),
+        Err(CodegenError::Emit(_))
+    ));
+}
@@ -0,0 +1,199 @@
+use sauce::errors::codes::{REGISTRY, lookup};
+use sauce::errors::diagnostic::{Diagnostic, ErrorFormat, Severity};
+use sauce::interpreter::eval::eval_program;
+use sauce::lexer::Lexer;
+use sauce::parser::SauceParser;
+use sauce::typechecker::typecheck_program;
+use serde_json::{Value, json};
+use std::process::Command;
+
+fn first_error(src: &str) -> Diagnostic {
+    let tokens = match Lexer::new(src).collect::<Result<Vec<_>, _>>() {
+        Ok(tokens) => tokens,
+        Err(e) => return (&e).into(),
+    };
+    let ast = match SauceParser::new().parse(&tokens) {
+        Ok(ast) => ast,
+        Err(e) => return (&e).into(),
+    };
+    if let Err(e) = typecheck_program(&ast) {
+        return (&e).into();
+    }
+    match eval_program(&ast) {
+        Err(e) => (&e).into(),
+        Ok(()) => panic!("expected an error from {src:?}"),
+    }
+}
+
+#[test]
+fn every_phase_reports_a_primary_span() {
+    let cases = [
+        ("grab x = @;", "lex", 9),
+        ("grab x = ;", "parse", 9),
+        ("grab x = 1;\nyell y;", "typecheck", 17),
+        (
+            "effect boom(Int);\ngrab x = 1;\ntoss boom x;",
+            "runtime",
+            30,
+        ),
+    ];
+
+    for (src, phase, start) in cases {
+        let diagnostic = first_error(src);
+        assert_eq!(diagnostic.severity, Severity::Error);
+        assert_eq!(diagnostic.phase, phase, "{src:?}");
+        assert_eq!(diagnostic.span.map(|s| s.start), Some(start), "{src:?}");
+    }
+}
+
+#[test]
+fn json_has_offsets_and_line_columns() {
+    let src = "grab x = 1;\nyell y;";
+    let json = first_error(src).to_json("main.sauce", src);
+
+    assert_eq!(json["severity"], "error");
+    assert_eq!(json["code"], "E0101");
+    assert_eq!(json["message"], "unknown identifier `y`");
+    assert_eq!(
+        json["span"],
+        json!({
+            "file": "main.sauce",
+            "start": 17,
+            "end": 18,
+            "line": 2,
+            "column": 6,
+            "end_line": 2,
+            "end_column": 7,
+        })
+    );
+    assert_eq!(json["labels"], json!([]));
+    assert_eq!(json["notes"], json!([]));
+}
+
+#[test]
+fn labels_and_notes_are_rendered() {
+    let src = "grab x = 1 |> 2;";
+    let diagnostic = first_error(src);
+    let json = diagnostic.to_json("main.sauce", src);
+    assert_eq!(json["labels"][0]["span"]["start"], 14);
+
+    let human = diagnostic.render(ErrorFormat::Human, "main.sauce", src);
+    assert_eq!(
+        human,
+        "typecheck error[E0201]: right side of pipeline cannot be a literal\n  --> main.sauce:1:15\n  1:15: a literal ignores the value piped into it"
+    );
+
+    let runtime = first_error("effect boom;\ntoss boom;");
+    assert_eq!(runtime.notes, ["`sauce run` has no effect handlers"]);
+}
+
+#[test]
+fn mismatches_point_to_what_inferred_the_expected_type() {
+    let src = "grab xs = [Some(1), Some(\"a\")];";
+    let diagnostic = first_error(src);
+    assert_eq!(diagnostic.code, Some("E0301"));
+    let human = diagnostic.render(ErrorFormat::Human, "main.sauce", src);
+    assert_eq!(
+        human,
+        "typecheck error[E0301]: type mismatch: expected Option<Int>, found Option<String>\n  --> main.sauce:1:21\n  1:17: expected Option<Int> because of this"
+    );
+}
+
+#[test]
+fn cli_prints_json_lines_on_stderr() {
+    let path = std::env::temp_dir().join("sauce_diagnostic_cli.sauce");
+    std::fs::write(&path, "yell missing;").unwrap();
+
+    for args in [
+        vec!["check", "--error-format=json"],
+        vec!["--error-format", "json", "run"],
+    ] {
+        let output = Command::new(env!("CARGO_BIN_EXE_sauce"))
+            .args(&args)
+            .arg(&path)
+            .output()
+            .unwrap();
+        assert!(!output.status.success());
+
+        let stderr = String::from_utf8(output.stderr).unwrap();
+        let json: Value = serde_json::from_str(stderr.trim()).expect(&stderr);
+        assert_eq!(json["phase"], "typecheck");
+        assert_eq!(json["span"]["column"], 6);
+    }
+}
+
+#[test]
+fn every_error_has_a_registered_code() {
+    let cases = [
+        ("grab x = @;", "E0001"),
+        ("grab x = ;", "E0010"),
+        ("grab x = 1 |>", "E0011"),
+        ("yel x;", "E0012"),
+        ("yell y;", "E0101"),
+        ("grab x = 1 |> 2;", "E0201"),
+        ("effect boom;\ntoss boom;", "E0401"),
+        ("toss boom;", "E0317"),
+    ];
+
+    for (src, code) in cases {
+        let diagnostic = first_error(src);
+        assert_eq!(diagnostic.code, Some(code), "{src:?}");
+        assert!(lookup(code).is_some(), "{code} is not registered");
+    }
+}
+
+#[test]
+fn registry_codes_are_unique_and_explained() {
+    for (i, entry) in REGISTRY.iter().enumerate() {
+        assert!(entry.code.starts_with(['E', 'W']) && entry.code.len() == 5);
+        assert!(!entry.explanation.trim().is_empty(), "{}", entry.code);
+        assert!(
+            REGISTRY[i + 1..]
+                .iter()
+                .all(|other| other.code != entry.code),
+            "{} is registered twice",
+            entry.code
+        );
+    }
+
+    assert_eq!(lookup("e0201").map(|c| c.code), Some("E0201"));
+    assert_eq!(lookup("0101").map(|c| c.title), Some("unknown identifier"));
+    assert!(lookup("E9999").is_none());
+    assert_eq!(lookup("w0001").map(|c| c.code), Some("W0001"));
+    assert_eq!(lookup("0001").map(|c| c.code), Some("E0001"));
+}
+
+#[test]
+fn explain_prints_the_explanation() {
+    let output = Command::new(env!("CARGO_BIN_EXE_sauce"))
+        .args(["explain", "E0201"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(
+        stdout.starts_with("E0201: pipeline into literal\n"),
+        "{stdout}"
+    );
+    assert!(stdout.contains("grab x = 10 |> 20;"), "{stdout}");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_sauce"))
+        .args(["explain", "E9999"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+}
+
+#[test]
+fn suggestions_become_notes() {
+    assert_eq!(
+        first_error("grab total = 1; yell totl;").notes,
+        ["did you mean `total`?"]
+    );
+    assert_eq!(first_error("grabb x = 1;").notes, ["did you mean `grab`?"]);
+    assert_eq!(
+        first_error("yell _;").notes,
+        ["`_` is only bound on the right side of a pipeline, as in `x |> _`"]
+    );
+}
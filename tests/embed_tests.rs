@@ -0,0 +1,208 @@
+use sauce::ast::ast::Ast;
+use sauce::interpreter::Interpreter;
+use sauce::interpreter::error::{RunError, RuntimeError};
+use sauce::interpreter::value::Value;
+use sauce::lexer::Lexer;
+use sauce::parser::SauceParser;
+use sauce::typechecker::TypeError;
+use sauce::typechecker::types::Type;
+use sauce::util::span::Span;
+use std::cell::RefCell;
+use std::io::{self, Write};
+use std::rc::Rc;
+
+fn parse(src: &str) -> Ast {
+    let tokens = Lexer::new(src)
+        .collect::<Result<Vec<_>, _>>()
+        .expect("lex failed");
+    SauceParser::new().parse(&tokens).expect("parse failed")
+}
+
+fn output(interpreter: Interpreter<Vec<u8>>) -> String {
+    String::from_utf8(interpreter.into_output()).expect("utf-8 output")
+}
+
+#[test]
+fn yells_are_written_to_the_output() {
+    let ast = parse("yell 1 + 2; yell \"hi\";");
+    let mut interpreter = Interpreter::new(Vec::new());
+    interpreter.run(&ast).expect("run failed");
+    assert_eq!(output(interpreter), "Int(3)\nString(\"hi\")\n");
+}
+
+#[test]
+fn handlers_get_effects_no_catch_catches() {
+    let src = r#"
+effect log(String);
+effect done;
+
+toss log "start";
+grab caught = catch log toss log "inner";
+toss done;
+yell "end";
+"#;
+    let ast = parse(src);
+
+    let logged = Rc::new(RefCell::new(Vec::new()));
+    let mut interpreter = Interpreter::new(Vec::new());
+    let log = Rc::clone(&logged);
+    interpreter.handle("log", move |effect| {
+        log.borrow_mut().push(effect.payload.clone());
+    });
+    let done = Rc::clone(&logged);
+    interpreter.handle("done", move |effect| {
+        done.borrow_mut()
+            .push(Some(Value::String(effect.name.clone())));
+    });
+    interpreter.run(&ast).expect("run failed");
+
+    assert_eq!(
+        *logged.borrow(),
+        [
+            Some(Value::String("start".into())),
+            Some(Value::String("done".into()))
+        ]
+    );
+    assert_eq!(
+        interpreter.get("caught"),
+        Some(Value::Variant {
+            name: "Err".into(),
+            tag: 1,
+            fields: vec![Value::String("inner".into())],
+        })
+    );
+    assert_eq!(output(interpreter), "String(\"end\")\n");
+}
+
+/// Output that is always closed.
+struct Closed;
+
+impl Write for Closed {
+    fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+        Err(io::ErrorKind::BrokenPipe.into())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn failed_yells_are_errors() {
+    let ast = parse("grab a = 1; yell a; grab b = 2;");
+    let mut interpreter = Interpreter::new(Closed);
+    assert!(matches!(
+        interpreter.run(&ast),
+        Err(RunError::Runtime(RuntimeError::Output(span, _))) if span == Span::new(12, 19)
+    ));
+    assert_eq!(interpreter.get("a"), Some(Value::Int(1)));
+    assert_eq!(interpreter.get("b"), None);
+}
+
+#[test]
+fn effects_without_handlers_stop_the_program() {
+    let ast = parse("effect net(String); toss net \"down\"; yell 1;");
+    let mut interpreter = Interpreter::new(Vec::new());
+    interpreter.handle("other", |_| {});
+    assert!(matches!(
+        interpreter.run(&ast),
+        Err(RunError::Runtime(RuntimeError::UnhandledEffect(_, name))) if name == "net"
+    ));
+    assert_eq!(output(interpreter), "");
+}
+
+#[test]
+fn handled_retosses_cannot_resume() {
+    let ast = parse("effect missing; grab n = retoss missing None; yell n;");
+    let handled = Rc::new(RefCell::new(0));
+    let count = Rc::clone(&handled);
+    let mut interpreter = Interpreter::new(Vec::new());
+    interpreter.handle("missing", move |_| *count.borrow_mut() += 1);
+    assert!(matches!(
+        interpreter.run(&ast),
+        Err(RunError::Runtime(RuntimeError::CannotResume(span, name))) if span == Span::new(25, 44) && name == "missing"
+    ));
+    assert_eq!(*handled.borrow(), 0);
+}
+
+#[test]
+fn handlers_see_tosses_but_not_retosses() {
+    let src = r#"
+effect log(String);
+toss log "first";
+grab line = retoss log Err("second");
+yell line;
+"#;
+    let ast = parse(src);
+    let logged = Rc::new(RefCell::new(Vec::new()));
+    let log = Rc::clone(&logged);
+    let mut interpreter = Interpreter::new(Vec::new());
+    interpreter.handle("log", move |effect| {
+        log.borrow_mut().push(effect.payload.clone());
+    });
+    assert!(matches!(
+        interpreter.run(&ast),
+        Err(RunError::Runtime(RuntimeError::CannotResume(_, name))) if name == "log"
+    ));
+    assert_eq!(*logged.borrow(), [Some(Value::String("first".into()))]);
+    assert_eq!(interpreter.get("line"), None);
+    assert_eq!(output(interpreter), "");
+}
+
+#[test]
+fn globals_go_in_and_bindings_come_out() {
+    let ast = parse("grab total = limit * 2; grab names = [greeting, \"bye\"];");
+
+    let mut interpreter = Interpreter::new(Vec::new());
+    interpreter.set("limit", Value::Int(21), Type::Int);
+    interpreter.set("greeting", Value::String("hi".into()), Type::String);
+    interpreter.run(&ast).expect("run failed");
+
+    assert_eq!(interpreter.get("total"), Some(Value::Int(42)));
+    assert_eq!(
+        interpreter.get("names"),
+        Some(Value::List(vec![
+            Value::String("hi".into()),
+            Value::String("bye".into())
+        ]))
+    );
+    let mut names: Vec<_> = interpreter
+        .bindings()
+        .map(|(name, _)| name)
+        .filter(|name| name.starts_with(char::is_lowercase))
+        .collect();
+    names.sort();
+    assert_eq!(names, ["greeting", "limit", "names", "total"]);
+
+    // Bindings stay for the next program.
+    interpreter
+        .run(&parse("yell total + 1;"))
+        .expect("run failed");
+    assert_eq!(output(interpreter), "Int(43)\n");
+}
+
+#[test]
+fn ill_typed_programs_do_not_run() {
+    let mut interpreter = Interpreter::new(Vec::new());
+    interpreter.set("limit", Value::Int(3), Type::Int);
+    let ast = parse("yell 1; grab n = limit + \"one\";");
+    assert!(matches!(
+        interpreter.run(&ast),
+        Err(RunError::Type(TypeError::Mismatch(..)))
+    ));
+    assert_eq!(interpreter.get("n"), None);
+
+    // What a program binds is checked in the programs after it.
+    interpreter
+        .run(&parse("grab n = limit * 2;"))
+        .expect("run failed");
+    assert!(matches!(
+        interpreter.run(&parse("yell n + \"x\";")),
+        Err(RunError::Type(_))
+    ));
+    assert!(matches!(
+        interpreter.run(&parse("yell missing;")),
+        Err(RunError::Type(TypeError::UnknownIdent(..)))
+    ));
+    assert_eq!(output(interpreter), "");
+}
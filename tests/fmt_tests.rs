@@ -0,0 +1,626 @@
+use sauce::ast::ast::{
+    Ast, BinOp, Expr, ExprKind, MatchArm, Param, Pattern, PatternKind, Statement, StatementKind,
+    TypeExpr, TypeExprKind, Variant,
+};
+use sauce::formatter::{format_source, print_ast};
+use sauce::lexer::Lexer;
+use sauce::parser::SauceParser;
+use sauce::util::span::Span;
+
+fn parse(src: &str) -> Ast {
+    let tokens = Lexer::new(src)
+        .collect::<Result<Vec<_>, _>>()
+        .expect("lex failed");
+    SauceParser::new().parse(&tokens).expect("parse failed")
+}
+
+fn strip_expr(expr: &Expr) -> Expr {
+    let kind = match &expr.kind {
+        ExprKind::Pipeline(lhs, rhs) => {
+            ExprKind::Pipeline(Box::new(strip_expr(lhs)), Box::new(strip_expr(rhs)))
+        }
+        ExprKind::Toss { effect, arg } => ExprKind::Toss {
+            effect: effect.clone(),
+            arg: arg.as_ref().map(|arg| Box::new(strip_expr(arg))),
+        },
+        ExprKind::Catch { effect, expr } => ExprKind::Catch {
+            effect: effect.clone(),
+            expr: Box::new(strip_expr(expr)),
+        },
+        ExprKind::Retoss { effect, expr } => ExprKind::Retoss {
+            effect: effect.clone(),
+            expr: Box::new(strip_expr(expr)),
+        },
+        ExprKind::List(items) => ExprKind::List(items.iter().map(strip_expr).collect()),
+        ExprKind::Tuple(items) => ExprKind::Tuple(items.iter().map(strip_expr).collect()),
+        ExprKind::Index(target, index) => {
+            ExprKind::Index(Box::new(strip_expr(target)), Box::new(strip_expr(index)))
+        }
+        ExprKind::Lambda { params, ret, body } => ExprKind::Lambda {
+            params: params
+                .iter()
+                .map(|param| Param {
+                    name: param.name.clone(),
+                    ty: param.ty.as_ref().map(strip_type),
+                    span: Span::new(0, 0),
+                })
+                .collect(),
+            ret: ret.as_ref().map(strip_type),
+            body: Box::new(strip_expr(body)),
+        },
+        ExprKind::Call(callee, args) => ExprKind::Call(
+            Box::new(strip_expr(callee)),
+            args.iter().map(strip_expr).collect(),
+        ),
+        ExprKind::Binary(op, left, right) => {
+            ExprKind::Binary(*op, Box::new(strip_expr(left)), Box::new(strip_expr(right)))
+        }
+        ExprKind::Record(fields) => ExprKind::Record(strip_fields(fields)),
+        ExprKind::Field(record, name) => {
+            ExprKind::Field(Box::new(strip_expr(record)), name.clone())
+        }
+        ExprKind::Update { record, fields } => ExprKind::Update {
+            record: Box::new(strip_expr(record)),
+            fields: strip_fields(fields),
+        },
+        ExprKind::Match { scrutinee, arms } => ExprKind::Match {
+            scrutinee: Box::new(strip_expr(scrutinee)),
+            arms: arms
+                .iter()
+                .map(|arm| MatchArm {
+                    pattern: strip_pattern(&arm.pattern),
+                    body: strip_expr(&arm.body),
+                })
+                .collect(),
+        },
+        other => other.clone(),
+    };
+    Expr::new(kind, Span::new(0, 0))
+}
+
+fn strip_pattern(pattern: &Pattern) -> Pattern {
+    let kind = match &pattern.kind {
+        PatternKind::Variant(name, args) => {
+            PatternKind::Variant(name.clone(), args.iter().map(strip_pattern).collect())
+        }
+        PatternKind::Tuple(items) => PatternKind::Tuple(items.iter().map(strip_pattern).collect()),
+        other => other.clone(),
+    };
+    Pattern::new(kind, Span::new(0, 0))
+}
+
+fn strip_fields(fields: &[(String, Expr)]) -> Vec<(String, Expr)> {
+    fields
+        .iter()
+        .map(|(name, value)| (name.clone(), strip_expr(value)))
+        .collect()
+}
+
+fn strip_type(ty: &TypeExpr) -> TypeExpr {
+    let kind = match &ty.kind {
+        TypeExprKind::Named(name, args) => {
+            TypeExprKind::Named(name.clone(), args.iter().map(strip_type).collect())
+        }
+        TypeExprKind::Record(fields) => TypeExprKind::Record(
+            fields
+                .iter()
+                .map(|(name, ty)| (name.clone(), strip_type(ty)))
+                .collect(),
+        ),
+        TypeExprKind::Tuple(items) => TypeExprKind::Tuple(items.iter().map(strip_type).collect()),
+        TypeExprKind::Function(params, ret) => TypeExprKind::Function(
+            params.iter().map(strip_type).collect(),
+            Box::new(strip_type(ret)),
+        ),
+    };
+    TypeExpr::new(kind, Span::new(0, 0))
+}
+
+/// `ast` with every span zeroed, so trees can be compared by shape.
+fn strip_spans(ast: &Ast) -> Ast {
+    Ast {
+        items: strip_stmts(&ast.items),
+    }
+}
+
+fn strip_stmts(stmts: &[Statement]) -> Vec<Statement> {
+    stmts
+        .iter()
+        .map(|stmt| {
+            let kind = match &stmt.kind {
+                StatementKind::Let { name, ty, expr } => StatementKind::Let {
+                    name: name.clone(),
+                    ty: ty.as_ref().map(strip_type),
+                    expr: strip_expr(expr),
+                },
+                StatementKind::Destructure { pattern, ty, expr } => StatementKind::Destructure {
+                    pattern: strip_pattern(pattern),
+                    ty: ty.as_ref().map(strip_type),
+                    expr: strip_expr(expr),
+                },
+                StatementKind::Yell { expr } => StatementKind::Yell {
+                    expr: strip_expr(expr),
+                },
+                StatementKind::ExprStmt(expr) => StatementKind::ExprStmt(strip_expr(expr)),
+                StatementKind::Toss { expr } => StatementKind::Toss {
+                    expr: strip_expr(expr),
+                },
+                StatementKind::Test { name, body } => StatementKind::Test {
+                    name: name.clone(),
+                    body: strip_stmts(body),
+                },
+                StatementKind::Assert { expr } => StatementKind::Assert {
+                    expr: strip_expr(expr),
+                },
+                StatementKind::AssertEq { left, right } => StatementKind::AssertEq {
+                    left: strip_expr(left),
+                    right: strip_expr(right),
+                },
+                StatementKind::TypeDecl { name, ty } => StatementKind::TypeDecl {
+                    name: name.clone(),
+                    ty: strip_type(ty),
+                },
+                StatementKind::SumDecl { name, variants } => StatementKind::SumDecl {
+                    name: name.clone(),
+                    variants: variants
+                        .iter()
+                        .map(|variant| Variant {
+                            name: variant.name.clone(),
+                            fields: variant.fields.iter().map(strip_type).collect(),
+                            span: Span::new(0, 0),
+                        })
+                        .collect(),
+                },
+                StatementKind::EffectDecl { name, payload } => StatementKind::EffectDecl {
+                    name: name.clone(),
+                    payload: payload.as_ref().map(strip_type),
+                },
+                StatementKind::Use { .. } => stmt.kind.clone(),
+            };
+            let mut stripped = Statement::new(kind, Span::new(0, 0));
+            stripped.public = stmt.public;
+            stripped
+        })
+        .collect()
+}
+
+#[test]
+fn formats_canonical_spacing() {
+    let src = "grab   x=10|>_ ;\nyell x;toss   oops \"bad\";";
+    assert_eq!(
+        format_source(src).unwrap(),
+        "grab x = 10 |> _;\nyell x;\ntoss oops \"bad\";\n"
+    );
+}
+
+#[test]
+fn breaks_long_pipelines() {
+    let src =
+        "grab message = \"a fairly long string literal\" |> first_stage |> second_stage |> third;";
+    assert_eq!(
+        format_source(src).unwrap(),
+        "grab message = \"a fairly long string literal\"\n    |> first_stage\n    |> second_stage\n    |> third;\n"
+    );
+}
+
+#[test]
+fn keeps_comments_and_blank_lines() {
+    let src = "// setup\ngrab x = 1; // one\n\n\n\ngrab y = x\n// between\n|> _;\n// done\n";
+    assert_eq!(
+        format_source(src).unwrap(),
+        "// setup\ngrab x = 1; // one\n\ngrab y = x\n    // between\n    |> _;\n// done\n"
+    );
+}
+
+#[test]
+fn keeps_comments_inside_statements() {
+    let src = "grab label = match shape {\n// round\nCircle(r) => \"circle\", // common\n\n  Rect(w, h) => \"rect\",\n    // no more\n};\ngrab xs = [\n    1, // one\n    2,\n];\n";
+    let formatted = "grab label = match shape {\n    // round\n    Circle(r) => \"circle\", // common\n\n    Rect(w, h) => \"rect\",\n    // no more\n};\ngrab xs = [\n    1, // one\n    2,\n];\n";
+    assert_eq!(format_source(src).unwrap(), formatted);
+    assert_eq!(format_source(formatted).unwrap(), formatted);
+}
+
+#[test]
+fn keeps_allow_attributes() {
+    assert_eq!(
+        format_source("// note\n#allow( unused_binding ,shadowing)  grab x=1;").unwrap(),
+        "// note\n#allow(unused_binding, shadowing)\ngrab x = 1;\n"
+    );
+}
+
+#[test]
+fn indents_test_blocks() {
+    let src = "yell 1;\ntest \"t\"{grab x=1;\n\n// check\nassert_eq x,1;}\ntest \"empty\" { }";
+    let formatted = format_source(src).unwrap();
+    assert_eq!(
+        formatted,
+        "yell 1;\ntest \"t\" {\n    grab x = 1;\n\n    // check\n    assert_eq x, 1;\n}\ntest \"empty\" {}\n"
+    );
+    assert_eq!(strip_spans(&parse(&formatted)), strip_spans(&parse(src)));
+}
+
+#[test]
+fn formats_modules() {
+    assert_eq!(
+        format_source("use  util :: text::shout ;pub   grab x=shout;").unwrap(),
+        "use util::text::shout;\npub grab x = shout;\n"
+    );
+}
+
+#[test]
+fn adds_parens_the_parser_needs() {
+    let src = "grab y = (toss e 1) |> (a |> b) |> toss f 2 |> c;";
+    let formatted = format_source(src).unwrap();
+    assert_eq!(
+        formatted,
+        "grab y = (toss e 1) |> (a |> b) |> toss f 2 |> c;\n"
+    );
+    assert_eq!(strip_spans(&parse(&formatted)), strip_spans(&parse(src)));
+}
+
+#[test]
+fn formats_lists_and_indexing() {
+    let src = "grab xs=[ 1,2 , 3, ];yell (a |> b)[xs[0]];yell (toss e)[0];";
+    let formatted = format_source(src).unwrap();
+    assert_eq!(
+        formatted,
+        "grab xs = [1, 2, 3];\nyell (a |> b)[xs[0]];\nyell (toss e)[0];\n"
+    );
+    assert_eq!(strip_spans(&parse(&formatted)), strip_spans(&parse(src)));
+}
+
+#[test]
+fn formats_functions_and_operators() {
+    let src = "yell xs|>map( fn(x)x*2 )|>filter(fn(x) x%3==0)|>sum;grab y=(1+2)*3-(4-5);yell (fn(x) x)(1) ;yell (a|>fn(x) x)==1;";
+    let formatted = format_source(src).unwrap();
+    assert_eq!(
+        formatted,
+        "yell xs |> map(fn(x) x * 2) |> filter(fn(x) x % 3 == 0) |> sum;\ngrab y = (1 + 2) * 3 - (4 - 5);\nyell (fn(x) x)(1);\nyell (a |> fn(x) x) == 1;\n"
+    );
+    assert_eq!(strip_spans(&parse(&formatted)), strip_spans(&parse(src)));
+}
+
+#[test]
+fn formats_records() {
+    let src = "pub type P={name:String,tags:List< Int >};grab p={name:\"a\",tags:[1],};yell {p with name:p.name+\"!\"}.name;yell (a|>_).x;";
+    let formatted = format_source(src).unwrap();
+    assert_eq!(
+        formatted,
+        "pub type P = { name: String, tags: List<Int> };\ngrab p = { name: \"a\", tags: [1] };\nyell { p with name: p.name + \"!\" }.name;\nyell (a |> _).x;\n"
+    );
+    assert_eq!(strip_spans(&parse(&formatted)), strip_spans(&parse(src)));
+}
+
+#[test]
+fn breaks_wide_records_one_field_per_line() {
+    let src = "type Person = { name: String, occupation: String, born: Int, friends: List<String> };\ngrab ada = { name: \"ada lovelace\", occupation: \"mathematician\", born: 1815, friends: [] };\ngrab older = { ada with occupation: \"the first programmer of them all\", born: 1816 };";
+    assert_eq!(
+        format_source(src).unwrap(),
+        "type Person = {
+    name: String,
+    occupation: String,
+    born: Int,
+    friends: List<String>,
+};
+grab ada = {
+    name: \"ada lovelace\",
+    occupation: \"mathematician\",
+    born: 1815,
+    friends: [],
+};
+grab older = { ada with
+    occupation: \"the first programmer of them all\",
+    born: 1816,
+};
+"
+    );
+}
+
+#[test]
+fn formats_sum_types_and_matches() {
+    let src = "type Shape=Circle( Int )|Rect(Int,Int);pub type One=|Only;grab a=match s{Circle(r)=>r*r,Rect(w,_)=>w,};yell match n{-1=>\"neg\",\"x\"=>fn(x) x,_=>match n{Only=>0}}|>f;";
+    let formatted = format_source(src).unwrap();
+    assert_eq!(
+        formatted,
+        "type Shape = Circle(Int) | Rect(Int, Int);\npub type One = | Only;\ngrab a = match s { Circle(r) => r * r, Rect(w, _) => w };\nyell match n { -1 => \"neg\", \"x\" => fn(x) x, _ => match n { Only => 0 } } |> f;\n"
+    );
+    assert_eq!(strip_spans(&parse(&formatted)), strip_spans(&parse(src)));
+}
+
+#[test]
+fn breaks_long_matches_one_arm_per_line() {
+    let src = "grab area = shapes |> map(fn(s) match s { Circle(r) => 3 * r * r, Rect(w, h) => w * h, Empty => 0 });\ngrab label = match shape { Circle(r) => \"a circle with a long description\", Rect(w, h) => \"a rectangle\", Empty => \"nothing\" };";
+    assert_eq!(
+        format_source(src).unwrap(),
+        "grab area = shapes |> map(fn(s) match s {
+    Circle(r) => 3 * r * r,
+    Rect(w, h) => w * h,
+    Empty => 0,
+});
+grab label = match shape {
+    Circle(r) => \"a circle with a long description\",
+    Rect(w, h) => \"a rectangle\",
+    Empty => \"nothing\",
+};
+"
+    );
+}
+
+#[test]
+fn formatting_is_idempotent() {
+    let src = "grab t = first\n  // middle\n  |> second // after\n  |> third\n  ;\nyell t;";
+    let once = format_source(src).unwrap();
+    assert_eq!(format_source(&once).unwrap(), once);
+}
+
+/// A small xorshift generator, so the property test needs no extra crates
+/// and failures are reproducible.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, n: u64) -> u64 {
+        self.next() % n
+    }
+
+    fn pick<'a>(&mut self, items: &[&'a str]) -> &'a str {
+        items[self.below(items.len() as u64) as usize]
+    }
+}
+
+const NAMES: [&str; 4] = ["x", "total", "_", "name_2"];
+
+const OPS: [BinOp; 5] = [BinOp::Add, BinOp::Sub, BinOp::Mul, BinOp::Rem, BinOp::Lt];
+
+fn gen_expr(rng: &mut Rng, depth: u32) -> Expr {
+    let span = Span::new(0, 0);
+    let choice = if depth == 0 {
+        rng.below(3)
+    } else {
+        rng.below(18)
+    };
+
+    let kind = match choice {
+        0 => ExprKind::Int(rng.below(1000) as i64),
+        1 => ExprKind::Ident(rng.pick(&NAMES).to_string()),
+        2 => ExprKind::String(rng.pick(&["", "sauce", "hot sauce"]).to_string()),
+        3 => ExprKind::Toss {
+            effect: rng.pick(&["oops", "network_error"]).to_string(),
+            arg: (rng.below(2) == 0).then(|| Box::new(gen_expr(rng, depth - 1))),
+        },
+        4 => ExprKind::List(
+            (0..rng.below(3) + 1)
+                .map(|_| gen_expr(rng, depth - 1))
+                .collect(),
+        ),
+        5 => ExprKind::Index(
+            Box::new(gen_expr(rng, depth - 1)),
+            Box::new(gen_expr(rng, depth - 1)),
+        ),
+        6 => ExprKind::Lambda {
+            params: (0..rng.below(3))
+                .map(|_| Param {
+                    name: rng.pick(&NAMES).to_string(),
+                    ty: gen_annotation(rng),
+                    span,
+                })
+                .collect(),
+            ret: gen_annotation(rng),
+            body: Box::new(gen_expr(rng, depth - 1)),
+        },
+        7 => ExprKind::Call(
+            Box::new(gen_expr(rng, depth - 1)),
+            (0..rng.below(3))
+                .map(|_| gen_expr(rng, depth - 1))
+                .collect(),
+        ),
+        8 => ExprKind::Binary(
+            OPS[rng.below(OPS.len() as u64) as usize],
+            Box::new(gen_expr(rng, depth - 1)),
+            Box::new(gen_expr(rng, depth - 1)),
+        ),
+        9 => ExprKind::Record(gen_fields(rng, depth - 1)),
+        10 => ExprKind::Field(
+            Box::new(gen_expr(rng, depth - 1)),
+            rng.pick(&NAMES).to_string(),
+        ),
+        11 => ExprKind::Update {
+            record: Box::new(gen_expr(rng, depth - 1)),
+            fields: gen_fields(rng, depth - 1),
+        },
+        12 => ExprKind::Match {
+            scrutinee: Box::new(gen_expr(rng, depth - 1)),
+            arms: (0..rng.below(3) + 1)
+                .map(|_| MatchArm {
+                    pattern: gen_pattern(rng, 2),
+                    body: gen_expr(rng, depth - 1),
+                })
+                .collect(),
+        },
+        13 => ExprKind::Catch {
+            effect: rng.pick(&["oops", "network_error"]).to_string(),
+            expr: Box::new(gen_expr(rng, depth - 1)),
+        },
+        14 => ExprKind::Retoss {
+            effect: rng.pick(&["oops", "network_error"]).to_string(),
+            expr: Box::new(gen_expr(rng, depth - 1)),
+        },
+        15 => ExprKind::Tuple(
+            (0..rng.below(3) + 1)
+                .map(|_| gen_expr(rng, depth - 1))
+                .collect(),
+        ),
+        _ => ExprKind::Pipeline(
+            Box::new(gen_expr(rng, depth - 1)),
+            Box::new(gen_expr(rng, depth - 1)),
+        ),
+    };
+    Expr::new(kind, span)
+}
+
+fn gen_pattern(rng: &mut Rng, depth: u32) -> Pattern {
+    let choice = if depth == 0 {
+        rng.below(4)
+    } else {
+        rng.below(6)
+    };
+    let kind = match choice {
+        0 => PatternKind::Wildcard,
+        1 => PatternKind::Bind(rng.pick(&["x", "total", "name_2"]).to_string()),
+        2 => PatternKind::Int(rng.below(200) as i64 - 100),
+        3 => PatternKind::String(rng.pick(&["", "sauce"]).to_string()),
+        4 => PatternKind::Tuple(
+            (0..rng.below(3) + 1)
+                .map(|_| gen_pattern(rng, depth - 1))
+                .collect(),
+        ),
+        _ => PatternKind::Variant(
+            rng.pick(&["Circle", "Empty"]).to_string(),
+            (0..rng.below(3))
+                .map(|_| gen_pattern(rng, depth - 1))
+                .collect(),
+        ),
+    };
+    Pattern::new(kind, Span::new(0, 0))
+}
+
+/// The tuple pattern of a destructuring `grab`.
+fn gen_tuple_pattern(rng: &mut Rng, depth: u32) -> Pattern {
+    let items = (0..rng.below(3) + 1)
+        .map(|_| {
+            let kind = match rng.below(if depth == 0 { 2 } else { 3 }) {
+                0 => PatternKind::Wildcard,
+                1 => PatternKind::Bind(rng.pick(&["x", "total", "name_2"]).to_string()),
+                _ => return gen_tuple_pattern(rng, depth - 1),
+            };
+            Pattern::new(kind, Span::new(0, 0))
+        })
+        .collect();
+    Pattern::new(PatternKind::Tuple(items), Span::new(0, 0))
+}
+
+fn gen_type(rng: &mut Rng, depth: u32) -> TypeExpr {
+    let choice = if depth == 0 {
+        rng.below(2)
+    } else {
+        rng.below(5)
+    };
+    let kind = match choice {
+        0 => TypeExprKind::Named(
+            rng.pick(&["Int", "String", "Shape"]).to_string(),
+            Vec::new(),
+        ),
+        1 => TypeExprKind::Named("Unit".to_string(), Vec::new()),
+        2 => TypeExprKind::Named(
+            rng.pick(&["List", "Option"]).to_string(),
+            vec![gen_type(rng, depth - 1)],
+        ),
+        3 => TypeExprKind::Tuple(
+            (0..rng.below(3) + 1)
+                .map(|_| gen_type(rng, depth - 1))
+                .collect(),
+        ),
+        _ => TypeExprKind::Function(
+            (0..rng.below(3))
+                .map(|_| gen_type(rng, depth - 1))
+                .collect(),
+            Box::new(gen_type(rng, depth - 1)),
+        ),
+    };
+    TypeExpr::new(kind, Span::new(0, 0))
+}
+
+/// An annotation half of the time.
+fn gen_annotation(rng: &mut Rng) -> Option<TypeExpr> {
+    (rng.below(2) == 0).then(|| gen_type(rng, 2))
+}
+
+fn gen_fields(rng: &mut Rng, depth: u32) -> Vec<(String, Expr)> {
+    (0..rng.below(2) + 1)
+        .map(|_| (rng.pick(&NAMES).to_string(), gen_expr(rng, depth)))
+        .collect()
+}
+
+fn gen_ast(rng: &mut Rng) -> Ast {
+    let items = (0..rng.below(4) + 1)
+        .map(|_| {
+            let expr = gen_expr(rng, 4);
+            let kind = match rng.below(4) {
+                0 => StatementKind::Let {
+                    name: rng.pick(&NAMES).to_string(),
+                    ty: gen_annotation(rng),
+                    expr,
+                },
+                1 => StatementKind::Yell { expr },
+                2 => StatementKind::Destructure {
+                    pattern: gen_tuple_pattern(rng, 2),
+                    ty: gen_annotation(rng),
+                    expr,
+                },
+                _ => StatementKind::ExprStmt(expr),
+            };
+            Statement::new(kind, Span::new(0, 0))
+        })
+        .collect();
+    Ast { items }
+}
+
+#[test]
+fn print_then_parse_round_trips() {
+    let mut rng = Rng(0x5a0c_e5a0_ce5a_0ce5);
+
+    for _ in 0..500 {
+        let ast = gen_ast(&mut rng);
+        let printed = print_ast(&ast);
+        assert_eq!(strip_spans(&parse(&printed)), ast, "printed:\n{printed}");
+        assert_eq!(format_source(&printed).unwrap(), printed);
+    }
+}
+
+#[test]
+fn formats_catch_and_retoss() {
+    let src = "grab r=catch  net (toss net \"timeout\");yell (catch e retoss e r)|>f;yell ( retoss e r )[0];";
+    let formatted = format_source(src).unwrap();
+    assert_eq!(
+        formatted,
+        "grab r = catch net toss net \"timeout\";\nyell (catch e retoss e r) |> f;\nyell (retoss e r)[0];\n"
+    );
+    assert_eq!(strip_spans(&parse(&formatted)), strip_spans(&parse(src)));
+}
+
+#[test]
+fn formats_tuples() {
+    let src = "grab  (a,( b ,),_)=( 1 ,(\"x\",) , [2] ) ;type P=( Int,String, );yell match (a,b) {(1,(s,))=>s,_=>\"\"};";
+    let formatted = format_source(src).unwrap();
+    assert_eq!(
+        formatted,
+        "grab (a, (b,), _) = (1, (\"x\",), [2]);\ntype P = (Int, String);\nyell match (a, b) { (1, (s,)) => s, _ => \"\" };\n"
+    );
+    assert_eq!(strip_spans(&parse(&formatted)), strip_spans(&parse(src)));
+}
+
+#[test]
+fn formats_annotations() {
+    let src =
+        "grab n:Int=1;grab twice :fn( fn(Int)->Int,Int )->Int=fn(f:fn(Int)->Int , x)->Int f(f(x));";
+    let formatted = format_source(src).unwrap();
+    assert_eq!(
+        formatted,
+        "grab n: Int = 1;\ngrab twice: fn(fn(Int) -> Int, Int) -> Int = fn(f: fn(Int) -> Int, x) -> Int f(f(x));\n"
+    );
+    assert_eq!(strip_spans(&parse(&formatted)), strip_spans(&parse(src)));
+}
+
+#[test]
+fn formats_effect_declarations() {
+    let src = "effect  net ( { code:Int } ) ;effect done;";
+    let formatted = format_source(src).unwrap();
+    assert_eq!(formatted, "effect net({ code: Int });\neffect done;\n");
+    assert_eq!(strip_spans(&parse(&formatted)), strip_spans(&parse(src)));
+}
@@ -80,3 +80,25 @@ fn lexer_skips_whitespace() {
         other => panic!("expected ident 'x', got {other:?}"),
     }
 }
+
+#[test]
+fn comments_only_in_lossless_mode() {
+    let src = "grab x = 1; // note\nyell x;";
+
+    let plain: Vec<_> = Lexer::new(src).map(|t| t.unwrap().token).collect();
+    assert!(!plain.iter().any(|t| matches!(t, Token::Comment(_))));
+
+    let lossless: Vec<_> = Lexer::lossless(src).map(|t| t.unwrap()).collect();
+    let comment = &lossless[5];
+    assert_eq!(comment.token, Token::Comment("// note".to_string()));
+    assert_eq!(comment.span, Span::new(12, 19));
+}
+
+#[test]
+fn lexing_with_an_offset() {
+    let tokens: Vec<_> = Lexer::with_offset("use a::b; @", 100).collect();
+
+    assert_eq!(unwrap_ok_token(&tokens[0]).span, Span::new(100, 103));
+    assert_eq!(unwrap_ok_token(&tokens[2]).token, Token::PathSep);
+    assert_eq!(tokens[5], Err(LexError::InvalidToken(Span::new(110, 111))));
+}
@@ -0,0 +1,245 @@
+use sauce::lexer::Lexer;
+use sauce::lint::{Warning, lint_program};
+use sauce::modules::Loader;
+use sauce::parser::SauceParser;
+use sauce::typechecker::{Type, typecheck_program};
+use sauce::util::span::Span;
+use std::process::Command;
+
+fn lint(src: &str) -> Vec<Warning> {
+    let tokens = Lexer::new(src)
+        .collect::<Result<Vec<_>, _>>()
+        .expect("lex failed");
+    let ast = SauceParser::new().parse(&tokens).expect("parse failed");
+    typecheck_program(&ast).expect("typecheck failed");
+    lint_program(&ast)
+}
+
+fn codes(src: &str) -> Vec<&'static str> {
+    lint(src).iter().map(Warning::code).collect()
+}
+
+#[test]
+fn clean_programs_have_no_warnings() {
+    assert!(lint("grab a = 10 |> _;\ngrab b = a |> _;\nyell b;").is_empty());
+    assert!(lint("effect oops(String);\nyell \"hi\";\ntoss oops \"bad\";").is_empty());
+}
+
+#[test]
+fn lint_unused_bindings() {
+    assert_eq!(
+        lint("grab x = 1; yell 2;"),
+        [Warning::UnusedBinding(Span::new(0, 11), "x".to_string())]
+    );
+    assert!(lint("grab _x = 1;").is_empty());
+
+    // Each name a tuple pattern binds is its own binding.
+    assert_eq!(
+        lint("grab (a, (b, _c)) = (1, (2, 3)); yell a;"),
+        [Warning::UnusedBinding(Span::new(10, 11), "b".to_string())]
+    );
+}
+
+#[test]
+fn lint_shadowing() {
+    // The first `x` is read by the second, so it is not unused.
+    assert_eq!(
+        lint("grab x = 1; grab x = x |> _; yell x;"),
+        [Warning::Shadowed(
+            Span::new(12, 28),
+            "x".to_string(),
+            Span::new(0, 11)
+        )]
+    );
+    assert_eq!(codes("grab x = 1; grab x = 2; yell x;"), ["W0001", "W0002"]);
+}
+
+#[test]
+fn lint_pipelines_that_ignore_placeholder() {
+    assert_eq!(
+        lint("grab y = 2; grab x = 10 |> y; yell x;"),
+        [Warning::IgnoredPlaceholder(Span::new(27, 28))]
+    );
+    // `_` on the right of a nested pipeline belongs to the inner one.
+    assert_eq!(codes("grab x = 1 |> (2 |> _); yell x;"), ["W0003"]);
+    assert!(lint("grab x = 1 |> (_ |> _); yell x;").is_empty());
+}
+
+#[test]
+fn lint_discarded_values() {
+    assert_eq!(
+        lint("10;"),
+        [Warning::UnusedValue(Span::new(0, 2), Type::Int)]
+    );
+    assert!(lint("effect oops; toss oops;").is_empty());
+}
+
+#[test]
+fn lint_unreachable_after_toss() {
+    assert_eq!(
+        lint("effect oops; toss oops; yell 1; yell 2;"),
+        [Warning::Unreachable(Span::new(24, 31), Span::new(13, 22))]
+    );
+    assert_eq!(
+        codes("effect oops; grab x = toss oops; yell 1;"),
+        ["W0001", "W0005"]
+    );
+}
+
+#[test]
+fn allow_silences_lints() {
+    assert!(lint("#allow(unused_binding)\ngrab x = 1;").is_empty());
+    assert!(lint("#allow(unused_value, unreachable)\n10;").is_empty());
+    assert!(lint("effect oops;\ntoss oops;\n#allow(unreachable)\nyell 1;").is_empty());
+
+    // Only the statement it is attached to.
+    assert_eq!(codes("#allow(unused_value)\n10;\n20;"), ["W0004"]);
+}
+
+#[test]
+fn allow_reports_unknown_lints() {
+    assert_eq!(
+        lint("#allow(unused_bindings)\ngrab x = 1;"),
+        [
+            Warning::UnknownLint(
+                Span::new(7, 22),
+                "unused_bindings".to_string(),
+                Some("unused_binding")
+            ),
+            Warning::UnusedBinding(Span::new(24, 35), "x".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn cli_denies_warnings() {
+    let path = std::env::temp_dir().join("sauce_lint_deny.sauce");
+    std::fs::write(&path, "grab x = 1;\n").unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_sauce"))
+        .arg("check")
+        .arg(&path)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.starts_with("warning[W0001]: unused binding `x`"),
+        "{stderr}"
+    );
+
+    let output = Command::new(env!("CARGO_BIN_EXE_sauce"))
+        .args(["check", "--deny-warnings"])
+        .arg(&path)
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("aborting due to 1 warning"), "{stderr}");
+}
+
+#[test]
+fn lint_test_bodies_as_their_own_scope() {
+    // `x` in the test is a new binding, not a use of the top-level one.
+    assert_eq!(
+        codes("grab x = 1;\ntest \"t\" { grab x = 2; assert x; }"),
+        ["W0001"]
+    );
+    assert!(lint("#allow(unused_binding)\ntest \"t\" { grab x = 2; }").is_empty());
+    assert!(lint("effect oops;\ntoss oops;\ntest \"t\" { assert 1; }").is_empty());
+}
+
+#[test]
+fn lint_functions_passed_to_stages() {
+    assert!(lint("grab xs = [1];\nyell xs |> map(fn(x) x + 1) |> sum;").is_empty());
+    // The parameter hides `x`, which stays unused.
+    assert_eq!(
+        lint("grab x = 1;\nyell [2] |> map(fn(x) x) |> sum;"),
+        [Warning::UnusedBinding(Span::new(0, 11), "x".to_string())]
+    );
+}
+
+/// Lints `main.sauce` in a fresh project holding `files`.
+fn lint_project(name: &str, files: &[(&str, &str)]) -> Vec<Warning> {
+    let dir = std::env::temp_dir().join(format!("sauce_lint_{name}"));
+    let _ = std::fs::remove_dir_all(&dir);
+    for (path, src) in files {
+        let path = dir.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, src).unwrap();
+    }
+    let main = std::fs::read_to_string(dir.join("main.sauce")).unwrap();
+    let ast = Loader::new(&dir)
+        .load("main.sauce", &main)
+        .expect("load failed");
+    typecheck_program(&ast).expect("typecheck failed");
+    lint_program(&ast)
+}
+
+#[test]
+fn lint_modules_by_the_names_written() {
+    let warnings = lint_project(
+        "names",
+        &[
+            (
+                "main.sauce",
+                "use util::text::shout;\ngrab loud = 1;\ngrab loud = 2;\nyell shout;\nyell loud;\n",
+            ),
+            (
+                "util/text.sauce",
+                "grab secret = 1;\ngrab secret = 2;\npub grab shout = \"HEY\";\n",
+            ),
+        ],
+    );
+    let names: Vec<_> = warnings
+        .iter()
+        .map(|w| match w {
+            Warning::UnusedBinding(_, name) | Warning::Shadowed(_, name, _) => {
+                format!("{} {name}", w.code())
+            }
+            other => panic!("unexpected {other:?}"),
+        })
+        .collect();
+    assert_eq!(
+        names,
+        [
+            "W0001 loud",
+            "W0002 loud",
+            "W0001 secret",
+            "W0002 secret",
+            "W0001 secret"
+        ]
+    );
+}
+
+#[test]
+fn lint_duplicate_uses() {
+    let files = [
+        (
+            "main.sauce",
+            "use util::text::shout;\nuse util::text::shout;\nyell shout;\n",
+        ),
+        ("util/text.sauce", "pub grab shout = \"HEY\";\n"),
+    ];
+    assert_eq!(
+        lint_project("duplicate", &files),
+        [Warning::DuplicateUse(
+            Span::new(23, 45),
+            "shout".to_string(),
+            Span::new(0, 22)
+        )]
+    );
+
+    let files = [
+        (
+            "main.sauce",
+            "use util::text::shout;\n#allow(duplicate_use)\nuse util::text::shout;\n",
+        ),
+        ("util/text.sauce", "pub grab shout = \"HEY\";\n"),
+    ];
+    let codes: Vec<_> = lint_project("duplicate_allowed", &files)
+        .iter()
+        .map(Warning::code)
+        .collect();
+    assert_eq!(codes, ["W0001"]);
+}
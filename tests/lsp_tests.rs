@@ -0,0 +1,226 @@
+use sauce::lsp;
+use sauce::lsp::transport::{read_message, write_message};
+use serde_json::{Value, json};
+use std::io::{BufReader, Cursor, Write};
+use std::process::{Command, Stdio};
+
+const URI: &str = "file:///tmp/example.sauce";
+
+/// Builds the framed input for a scripted session: `initialize`, opening
+/// `text`, then `requests`, then `shutdown` and `exit`.
+fn script(text: &str, requests: &[Value]) -> Vec<u8> {
+    let mut messages = vec![
+        json!({ "jsonrpc": "2.0", "id": 0, "method": "initialize", "params": {} }),
+        json!({ "jsonrpc": "2.0", "method": "initialized", "params": {} }),
+        json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/didOpen",
+            "params": {
+                "textDocument": { "uri": URI, "languageId": "sauce", "version": 1, "text": text },
+            },
+        }),
+    ];
+    messages.extend(requests.iter().cloned());
+    messages.push(json!({ "jsonrpc": "2.0", "id": 99, "method": "shutdown" }));
+    messages.push(json!({ "jsonrpc": "2.0", "method": "exit" }));
+
+    let mut input = Vec::new();
+    for message in &messages {
+        write_message(&mut input, message).unwrap();
+    }
+    input
+}
+
+fn read_all(output: &[u8]) -> Vec<Value> {
+    let mut reader = Cursor::new(output);
+    let mut messages = Vec::new();
+    while let Some(message) = read_message(&mut reader).unwrap() {
+        messages.push(message);
+    }
+    messages
+}
+
+fn session(text: &str, requests: &[Value]) -> Vec<Value> {
+    let mut output = Vec::new();
+    lsp::run(Cursor::new(script(text, requests)), &mut output).unwrap();
+    read_all(&output)
+}
+
+fn request(id: u64, method: &str, line: u32, character: u32) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "method": method,
+        "params": {
+            "textDocument": { "uri": URI },
+            "position": { "line": line, "character": character },
+        },
+    })
+}
+
+fn response(messages: &[Value], id: u64) -> &Value {
+    &messages
+        .iter()
+        .find(|m| m["id"] == id)
+        .unwrap_or_else(|| panic!("no response to request {id}"))["result"]
+}
+
+fn diagnostics(messages: &[Value]) -> &Vec<Value> {
+    messages
+        .iter()
+        .find(|m| m["method"] == "textDocument/publishDiagnostics")
+        .expect("no diagnostics published")["params"]["diagnostics"]
+        .as_array()
+        .unwrap()
+}
+
+#[test]
+fn initialize_advertises_capabilities() {
+    let messages = session("", &[]);
+    let capabilities = &response(&messages, 0)["capabilities"];
+
+    assert_eq!(capabilities["hoverProvider"], true);
+    assert_eq!(capabilities["definitionProvider"], true);
+    assert_eq!(capabilities["documentSymbolProvider"], true);
+    assert_eq!(response(&messages, 99), &Value::Null);
+}
+
+#[test]
+fn publishes_diagnostics_with_ranges() {
+    let messages = session("grab x = 1;\nyell y;\n", &[]);
+    let published = diagnostics(&messages);
+
+    assert_eq!(published.len(), 1);
+    assert_eq!(published[0]["message"], "unknown identifier `y`");
+    assert_eq!(
+        published[0]["range"],
+        json!({
+            "start": { "line": 1, "character": 5 },
+            "end": { "line": 1, "character": 6 },
+        })
+    );
+
+    let messages = session("grab x = ;", &[]);
+    assert_eq!(diagnostics(&messages)[0]["message"], "unexpected `;`");
+
+    let messages = session("grab x = 1;\nyell x;", &[]);
+    assert!(diagnostics(&messages).is_empty());
+}
+
+#[test]
+fn publishes_warnings() {
+    let messages = session("grab x = 1;\n", &[]);
+    let published = diagnostics(&messages);
+
+    assert_eq!(published.len(), 1);
+    assert_eq!(published[0]["severity"], 2);
+    assert_eq!(published[0]["code"], "W0001");
+}
+
+#[test]
+fn hover_shows_types_and_placeholder_binding() {
+    let text = "grab name = \"sauce\";\ngrab n = name |> _;\n";
+    let messages = session(
+        text,
+        &[
+            request(1, "textDocument/hover", 0, 6),
+            request(2, "textDocument/hover", 1, 17),
+            request(3, "textDocument/hover", 1, 0),
+        ],
+    );
+
+    let name = response(&messages, 1)["contents"]["value"]
+        .as_str()
+        .unwrap();
+    assert!(name.contains("name: String"), "{name}");
+
+    let placeholder = response(&messages, 2)["contents"]["value"]
+        .as_str()
+        .unwrap();
+    assert!(placeholder.contains("_: String"), "{placeholder}");
+    assert!(placeholder.contains("bound to `name`"), "{placeholder}");
+
+    assert_eq!(response(&messages, 3), &Value::Null);
+}
+
+#[test]
+fn goes_to_grab_definitions() {
+    let text = "grab x = 1;\ngrab x = x |> _;\nyell x;\n";
+    let messages = session(
+        text,
+        &[
+            request(1, "textDocument/definition", 1, 9),
+            request(2, "textDocument/definition", 2, 5),
+        ],
+    );
+
+    // The second `grab` reads the first `x` and shadows it.
+    assert_eq!(response(&messages, 1)["uri"], URI);
+    assert_eq!(response(&messages, 1)["range"]["start"]["line"], 0);
+    assert_eq!(response(&messages, 2)["range"]["start"]["line"], 1);
+    assert_eq!(response(&messages, 2)["range"]["start"]["character"], 5);
+}
+
+#[test]
+fn lists_document_symbols() {
+    let messages = session(
+        "grab a = 1;\nyell a;\ngrab b = \"two\";\n",
+        &[json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "textDocument/documentSymbol",
+            "params": { "textDocument": { "uri": URI } },
+        })],
+    );
+
+    let symbols = response(&messages, 1).as_array().unwrap();
+    let names: Vec<_> = symbols
+        .iter()
+        .map(|s| (s["name"].as_str().unwrap(), s["detail"].as_str().unwrap()))
+        .collect();
+    assert_eq!(names, [("a", "Int"), ("b", "String")]);
+}
+
+#[test]
+fn answers_malformed_messages_and_keeps_serving() {
+    let mut input = b"Content-Length: 9\r\n\r\n{\"id\": 1,".to_vec();
+    input.extend(script(
+        "grab x = 1;",
+        &[request(2, "textDocument/hover", 0, 5)],
+    ));
+    let mut output = Vec::new();
+    lsp::run(Cursor::new(input), &mut output).unwrap();
+    let messages = read_all(&output);
+
+    assert_eq!(messages[0]["id"], Value::Null);
+    assert_eq!(messages[0]["error"]["code"], -32700);
+    let hover = response(&messages, 2)["contents"]["value"]
+        .as_str()
+        .unwrap();
+    assert!(hover.contains("x: Int"), "{hover}");
+}
+
+#[test]
+fn serves_over_stdio() {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_sauce"))
+        .arg("lsp")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("failed to start sauce lsp");
+
+    let input = script("grab x = 1;", &[request(1, "textDocument/hover", 0, 5)]);
+    child.stdin.take().unwrap().write_all(&input).unwrap();
+
+    let mut stdout = BufReader::new(child.stdout.take().unwrap());
+    let mut messages = Vec::new();
+    while let Some(message) = read_message(&mut stdout).unwrap() {
+        messages.push(message);
+    }
+    assert!(child.wait().unwrap().success());
+
+    let hover = response(&messages, 1)["contents"]["value"]
+        .as_str()
+        .unwrap();
+    assert!(hover.contains("x: Int"), "{hover}");
+}
@@ -0,0 +1,250 @@
+use inkwell::context::Context;
+use sauce::ast::ast::StatementKind;
+use sauce::codegen::{CodegenOptions, compile};
+use sauce::errors::diagnostic::{Diagnostic, ErrorFormat};
+use sauce::interpreter::eval::eval_program;
+use sauce::interpreter::runner::count_tests;
+use sauce::modules::{LoadError, Loader, ModuleError};
+use sauce::typechecker::{TypeError, typecheck_program};
+use std::path::PathBuf;
+
+/// A fresh directory holding `files`, for one test.
+fn project(name: &str, files: &[(&str, &str)]) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("sauce_modules_{name}"));
+    let _ = std::fs::remove_dir_all(&dir);
+    for (path, src) in files {
+        let path = dir.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, src).unwrap();
+    }
+    dir
+}
+
+fn load(dir: &PathBuf, src: &str) -> (Loader, Result<sauce::ast::ast::Ast, LoadError>) {
+    let mut loader = Loader::new(dir);
+    let result = loader.load("main.sauce", src);
+    (loader, result)
+}
+
+fn module_error(dir: &PathBuf, src: &str) -> ModuleError {
+    match load(dir, src).1 {
+        Err(LoadError::Module(e)) => e,
+        other => panic!("expected a module error, got {other:?}"),
+    }
+}
+
+#[test]
+fn links_used_modules_first_with_qualified_names() {
+    let dir = project(
+        "link",
+        &[
+            (
+                "math.sauce",
+                "use util::text::shout;\npub grab square = 16 |> _;\ngrab loud = shout;\nyell loud;\n",
+            ),
+            ("util/text.sauce", "pub grab shout = \"HEY\";\n"),
+        ],
+    );
+    let (_, ast) = load(&dir, "use math::square;\nyell square;\n");
+    let ast = ast.unwrap();
+
+    let names: Vec<_> = ast
+        .items
+        .iter()
+        .map(|stmt| match &stmt.kind {
+            StatementKind::Let { name, .. } => format!("grab {name}"),
+            StatementKind::Use { alias, .. } => format!("use {alias}"),
+            StatementKind::Yell { .. } => "yell".to_string(),
+            other => panic!("unexpected {other:?}"),
+        })
+        .collect();
+    assert_eq!(
+        names,
+        [
+            "grab util::text::shout",
+            "use math::shout",
+            "grab math::square",
+            "grab math::loud",
+            "yell",
+            "use square",
+            "yell",
+        ]
+    );
+
+    assert!(typecheck_program(&ast).is_ok());
+    assert!(eval_program(&ast).is_ok());
+}
+
+#[test]
+fn modules_are_linked_once() {
+    let dir = project(
+        "once",
+        &[
+            ("a.sauce", "use shared::x;\npub grab a = x;\n"),
+            ("b.sauce", "use shared::x;\npub grab b = x;\n"),
+            ("shared.sauce", "pub grab x = 1;\n"),
+        ],
+    );
+    let (loader, ast) = load(&dir, "use a::a;\nuse b::b;\nyell a;\nyell b;\n");
+    let ast = ast.unwrap();
+
+    let shared = ast
+        .items
+        .iter()
+        .filter(|stmt| matches!(&stmt.kind, StatementKind::Let { name, .. } if name == "shared::x"))
+        .count();
+    assert_eq!(shared, 1);
+    assert_eq!(loader.sources.files.len(), 4);
+    assert!(typecheck_program(&ast).is_ok());
+}
+
+#[test]
+fn only_the_entry_modules_tests_are_kept() {
+    let dir = project(
+        "tests",
+        &[(
+            "math.sauce",
+            "pub grab one = 1;\ntest \"math\" { assert 0; }\n",
+        )],
+    );
+    let (_, ast) = load(
+        &dir,
+        "use math::one;\nyell one;\ntest \"main\" { assert 1; }\n",
+    );
+    assert_eq!(count_tests(&ast.unwrap()), 1);
+}
+
+#[test]
+fn reports_private_and_unknown_names() {
+    let dir = project(
+        "names",
+        &[(
+            "math.sauce",
+            "pub grab square = 16;\ngrab helper = 1;\nyell helper;\n",
+        )],
+    );
+
+    assert!(matches!(
+        module_error(&dir, "use math::helper;"),
+        ModuleError::Private(_, module, name) if module == "math" && name == "helper"
+    ));
+    assert!(matches!(
+        module_error(&dir, "use math::sqare;"),
+        ModuleError::UnknownExport(_, _, _, Some(suggestion)) if suggestion == "square"
+    ));
+    assert!(matches!(
+        module_error(&dir, "use maths::square;"),
+        ModuleError::NotFound(..)
+    ));
+}
+
+#[test]
+fn reports_import_cycles() {
+    let dir = project(
+        "cycle",
+        &[
+            ("a.sauce", "use b::y;\npub grab x = 1;\n"),
+            ("b.sauce", "use a::x;\npub grab y = 2;\n"),
+        ],
+    );
+
+    let cycle = module_error(&dir, "use a::x;");
+    assert_eq!(cycle.to_string(), "import cycle: a -> b -> a");
+
+    let cycle = module_error(&dir, "use main::x;\npub grab x = 1;");
+    assert_eq!(cycle.to_string(), "import cycle: main -> main");
+}
+
+#[test]
+fn diagnostics_point_into_the_right_file() {
+    let dir = project(
+        "spans",
+        &[("bad.sauce", "pub grab z = 1;\n\npub grab w = nope;\n")],
+    );
+    let (loader, ast) = load(&dir, "use bad::z;\nyell z;\n");
+
+    let error = typecheck_program(&ast.unwrap()).unwrap_err();
+    let rendered = loader
+        .sources
+        .render(&Diagnostic::from(&error), ErrorFormat::Human);
+    let bad = dir.join("bad.sauce");
+    assert!(
+        rendered.contains(&format!("--> {}:3:14", bad.display())),
+        "{rendered}"
+    );
+}
+
+#[test]
+fn codegen_links_into_one_module() {
+    let dir = project(
+        "codegen",
+        &[("greeting.sauce", "pub grab hello = \"hello\";\n")],
+    );
+    let (_, ast) = load(
+        &dir,
+        "use greeting::hello;\ngrab again = hello;\nyell again;\n",
+    );
+
+    let context = Context::create();
+    let cg = compile(&context, &ast.unwrap(), &CodegenOptions::default()).unwrap();
+    cg.module.verify().expect("module should verify");
+}
+
+#[test]
+fn exported_types_can_be_used() {
+    let dir = project(
+        "types",
+        &[(
+            "geo.sauce",
+            "pub type Point = { x: Int, y: Int };\ntype Id = Int;\npub type Line = { from: Point, to: Point, id: Id };\npub grab origin = { x: 0, y: 0 };\n",
+        )],
+    );
+    let (_, ast) = load(
+        &dir,
+        "use geo::Point;\nuse geo::origin;\ntype Path = List<Point>;\nyell { origin with x: 1 }.x;\n",
+    );
+    let ast = ast.unwrap();
+    typecheck_program(&ast).unwrap();
+
+    // A `use` of a type becomes a declaration naming it.
+    let decls: Vec<_> = ast
+        .items
+        .iter()
+        .filter_map(|stmt| match &stmt.kind {
+            StatementKind::TypeDecl { name, .. } => Some(name.as_str()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        decls,
+        ["geo::Point", "geo::Id", "geo::Line", "Point", "Path"]
+    );
+
+    assert!(matches!(
+        module_error(&dir, "use geo::Id;"),
+        ModuleError::Private(_, _, name) if name == "Id"
+    ));
+}
+
+#[test]
+fn exported_constructors_can_be_matched() {
+    let dir = project(
+        "sums",
+        &[(
+            "geo.sauce",
+            "pub type Shape = Circle(Int) | Square(Int);\npub grab unit = Square(1);\n",
+        )],
+    );
+    let (_, ast) = load(
+        &dir,
+        "use geo::Circle;\nuse geo::Square;\nuse geo::unit;\nyell match unit { Circle(r) => r, Square(s) => s * s };\n",
+    );
+    typecheck_program(&ast.unwrap()).unwrap();
+
+    // Constructors are exported by name, like any other binding.
+    let (_, ast) = load(&dir, "use geo::Shape;\nyell Circle(1);\n");
+    assert!(matches!(
+        typecheck_program(&ast.unwrap()),
+        Err(TypeError::UnknownIdent(_, name, _)) if name == "Circle"
+    ));
+}
@@ -1,7 +1,8 @@
-use sauce::ast::ast::{Expr, Statement};
+use sauce::ast::ast::{BinOp, ExprKind, PatternKind, StatementKind, TypeExprKind};
 use sauce::errors::parse::ParseError;
 use sauce::lexer::Lexer;
 use sauce::parser::SauceParser;
+use sauce::util::span::Span;
 
 #[test]
 fn parse_basic_pipeline() {
@@ -15,14 +16,14 @@ fn parse_basic_pipeline() {
 
     assert_eq!(ast.items.len(), 1);
 
-    match &ast.items[0] {
-        Statement::Let { name, expr } => {
+    match &ast.items[0].kind {
+        StatementKind::Let { name, expr, .. } => {
             assert_eq!(name, "x");
 
-            match expr {
-                Expr::Pipeline(left, right) => {
-                    assert!(matches!(**left, Expr::Int(1)));
-                    assert!(matches!(**right, Expr::Int(2)));
+            match &expr.kind {
+                ExprKind::Pipeline(left, right) => {
+                    assert!(matches!(left.kind, ExprKind::Int(1)));
+                    assert!(matches!(right.kind, ExprKind::Int(2)));
                 }
                 other => panic!("expected pipeline, got {other:?}"),
             }
@@ -40,7 +41,7 @@ fn parse_incomplete_input() {
     let parser = SauceParser::new();
     let result = parser.parse(&tokens);
 
-    assert!(matches!(result, Err(ParseError::Generic(_))));
+    assert!(matches!(result, Err(ParseError::Incomplete(_))));
 }
 
 #[test]
@@ -54,13 +55,13 @@ fn parse_parenthesized_pipeline() {
     let ast = parser.parse(&tokens).expect("parse ok");
 
     assert_eq!(ast.items.len(), 1);
-    match &ast.items[0] {
-        Statement::Let { name, expr } => {
+    match &ast.items[0].kind {
+        StatementKind::Let { name, expr, .. } => {
             assert_eq!(name, "x");
-            match expr {
-                Expr::Pipeline(left, right) => {
-                    assert!(matches!(**left, Expr::Int(1)));
-                    assert!(matches!(**right, Expr::Int(2)));
+            match &expr.kind {
+                ExprKind::Pipeline(left, right) => {
+                    assert!(matches!(left.kind, ExprKind::Int(1)));
+                    assert!(matches!(right.kind, ExprKind::Int(2)));
                 }
                 other => panic!("expected pipeline, got {other:?}"),
             }
@@ -80,11 +81,357 @@ fn parse_string_literal() {
     let ast = parser.parse(&tokens).expect("parse ok");
 
     assert_eq!(ast.items.len(), 1);
-    match &ast.items[0] {
-        Statement::Yell { expr } => match expr {
-            Expr::String(s) => assert_eq!(s, "\"sauce\""),
+    match &ast.items[0].kind {
+        StatementKind::Yell { expr } => match &expr.kind {
+            ExprKind::String(s) => assert_eq!(s, "sauce"),
             other => panic!("expected string expr, got {other:?}"),
         },
         other => panic!("expected yell, got {other:?}"),
     }
 }
+
+#[test]
+fn parse_records_spans() {
+    let src = "grab x = 1 |> _;\nyell x;";
+    let tokens = Lexer::new(src)
+        .collect::<Result<Vec<_>, _>>()
+        .expect("lex ok");
+
+    let ast = SauceParser::new().parse(&tokens).expect("parse ok");
+
+    assert_eq!(ast.items[0].span, Span::new(0, 16));
+    assert_eq!(ast.items[1].span, Span::new(17, 24));
+
+    match &ast.items[0].kind {
+        StatementKind::Let { expr, .. } => assert_eq!(expr.span, Span::new(9, 15)),
+        other => panic!("expected let, got {other:?}"),
+    }
+}
+
+#[test]
+fn parse_misspelled_keyword() {
+    let src = "grab x = 1;\nyel x;";
+    let tokens: Vec<_> = Lexer::new(src).collect::<Result<_, _>>().unwrap();
+
+    match SauceParser::new().parse(&tokens) {
+        Err(ParseError::MisspelledKeyword(span, name, keyword)) => {
+            assert_eq!(span, Span::new(12, 15));
+            assert_eq!(name, "yel");
+            assert_eq!(keyword, "yell");
+        }
+        other => panic!("expected misspelled keyword, got {other:?}"),
+    }
+
+    // Swapped and doubled letters are one edit.
+    for (src, misspelled, keyword) in [("garb y = 2;", "garb", "grab"), ("yeel x;", "yeel", "yell")]
+    {
+        let tokens: Vec<_> = Lexer::new(src).collect::<Result<_, _>>().unwrap();
+        match SauceParser::new().parse(&tokens) {
+            Err(ParseError::MisspelledKeyword(_, name, suggestion)) => {
+                assert_eq!(name, misspelled);
+                assert_eq!(suggestion, keyword);
+            }
+            other => panic!("expected misspelled keyword in {src:?}, got {other:?}"),
+        }
+    }
+
+    // Far from any keyword: a plain unexpected token.
+    let tokens: Vec<_> = Lexer::new("foo x;").collect::<Result<_, _>>().unwrap();
+    assert!(matches!(
+        SauceParser::new().parse(&tokens),
+        Err(ParseError::UnexpectedToken(..))
+    ));
+}
+
+#[test]
+fn parse_allow_attributes() {
+    let src = "#allow(unused_binding)\n#allow(shadowing,)\ngrab x = 1;";
+    let tokens = Lexer::new(src).collect::<Result<Vec<_>, _>>().unwrap();
+    let ast = SauceParser::new().parse(&tokens).unwrap();
+
+    let stmt = &ast.items[0];
+    let lints: Vec<_> = stmt.allow.iter().map(|a| a.lint.as_str()).collect();
+    assert_eq!(lints, ["unused_binding", "shadowing"]);
+    assert_eq!(stmt.allow[0].span, Span::new(7, 21));
+    assert_eq!(stmt.span, Span::new(42, 53));
+
+    let tokens = Lexer::new("#allow() grab x = 1;")
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert!(SauceParser::new().parse(&tokens).is_err());
+}
+
+#[test]
+fn parse_lists_and_indexing() {
+    let tokens = Lexer::new("yell [1, [2], ][0] |> xs[1];")
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    let ast = SauceParser::new().parse(&tokens).unwrap();
+
+    let StatementKind::Yell { expr } = &ast.items[0].kind else {
+        panic!("expected yell, got {:?}", ast.items[0].kind);
+    };
+    let ExprKind::Pipeline(lhs, rhs) = &expr.kind else {
+        panic!("expected pipeline, got {:?}", expr.kind);
+    };
+    let ExprKind::Index(list, index) = &lhs.kind else {
+        panic!("expected index, got {:?}", lhs.kind);
+    };
+    assert!(matches!(&list.kind, ExprKind::List(items) if items.len() == 2));
+    assert_eq!(list.span, Span::new(5, 15));
+    assert!(matches!(index.kind, ExprKind::Int(0)));
+    assert_eq!(lhs.span, Span::new(5, 18));
+    assert!(matches!(rhs.kind, ExprKind::Index(..)));
+}
+
+#[test]
+fn parse_operators_and_functions() {
+    let tokens = Lexer::new("yell 1 + 2 * 3 |> map(fn(x, y) x) == 4;")
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    let ast = SauceParser::new().parse(&tokens).unwrap();
+
+    // `|>` binds looser than arithmetic and tighter than comparisons.
+    let StatementKind::Yell { expr } = &ast.items[0].kind else {
+        panic!("expected yell, got {:?}", ast.items[0].kind);
+    };
+    let ExprKind::Binary(BinOp::Eq, lhs, _) = &expr.kind else {
+        panic!("expected comparison, got {:?}", expr.kind);
+    };
+    let ExprKind::Pipeline(sum, call) = &lhs.kind else {
+        panic!("expected pipeline, got {:?}", lhs.kind);
+    };
+    let ExprKind::Binary(BinOp::Add, _, product) = &sum.kind else {
+        panic!("expected sum, got {:?}", sum.kind);
+    };
+    assert!(matches!(product.kind, ExprKind::Binary(BinOp::Mul, ..)));
+
+    let ExprKind::Call(callee, args) = &call.kind else {
+        panic!("expected call, got {:?}", call.kind);
+    };
+    assert!(matches!(&callee.kind, ExprKind::Ident(name) if name == "map"));
+    let ExprKind::Lambda { params, .. } = &args[0].kind else {
+        panic!("expected lambda, got {:?}", args[0].kind);
+    };
+    let names: Vec<_> = params.iter().map(|param| param.name.as_str()).collect();
+    assert_eq!(names, ["x", "y"]);
+    assert_eq!(args[0].span, Span::new(22, 32));
+
+    // Comparisons do not chain.
+    let tokens = Lexer::new("yell 1 < 2 < 3;")
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert!(SauceParser::new().parse(&tokens).is_err());
+}
+
+#[test]
+fn parse_records() {
+    let src = "pub type P = { name: String, tags: List<Int> }; yell { { a: 1 }.a with b: 2 }.c;";
+    let tokens = Lexer::new(src).collect::<Result<Vec<_>, _>>().unwrap();
+    let ast = SauceParser::new().parse(&tokens).unwrap();
+
+    assert!(ast.items[0].public);
+    let StatementKind::TypeDecl { name, ty } = &ast.items[0].kind else {
+        panic!("expected type declaration, got {:?}", ast.items[0].kind);
+    };
+    assert_eq!(name, "P");
+    let TypeExprKind::Record(fields) = &ty.kind else {
+        panic!("expected record type, got {:?}", ty.kind);
+    };
+    assert_eq!(fields[0].0, "name");
+    assert!(
+        matches!(&fields[1].1.kind, TypeExprKind::Named(list, args) if list == "List" && args.len() == 1)
+    );
+    assert_eq!(fields[1].1.span, Span::new(35, 44));
+
+    // Field access binds tighter than anything, even on an update.
+    let StatementKind::Yell { expr } = &ast.items[1].kind else {
+        panic!("expected yell, got {:?}", ast.items[1].kind);
+    };
+    let ExprKind::Field(update, c) = &expr.kind else {
+        panic!("expected field access, got {:?}", expr.kind);
+    };
+    assert_eq!(c, "c");
+    let ExprKind::Update { record, fields } = &update.kind else {
+        panic!("expected update, got {:?}", update.kind);
+    };
+    assert!(
+        matches!(&record.kind, ExprKind::Field(inner, a) if a == "a" && matches!(inner.kind, ExprKind::Record(_)))
+    );
+    assert_eq!(fields[0].0, "b");
+    assert_eq!(expr.span, Span::new(53, 79));
+}
+
+#[test]
+fn parse_sum_types_and_matches() {
+    let src = "type S = | Only; type T = Leaf | Node(T, Int); yell match t { Node(_, -1) => 0, n => 1, };";
+    let tokens = Lexer::new(src).collect::<Result<Vec<_>, _>>().unwrap();
+    let ast = SauceParser::new().parse(&tokens).unwrap();
+
+    // A leading bar makes a lone nullary variant a sum rather than an alias.
+    let StatementKind::SumDecl { variants, .. } = &ast.items[0].kind else {
+        panic!("expected sum declaration, got {:?}", ast.items[0].kind);
+    };
+    assert_eq!(variants[0].name, "Only");
+    let StatementKind::SumDecl { name, variants } = &ast.items[1].kind else {
+        panic!("expected sum declaration, got {:?}", ast.items[1].kind);
+    };
+    assert_eq!(name, "T");
+    assert_eq!(variants.len(), 2);
+    assert_eq!(variants[1].fields.len(), 2);
+    assert_eq!(variants[1].span, Span::new(33, 45));
+
+    let StatementKind::Yell { expr } = &ast.items[2].kind else {
+        panic!("expected yell, got {:?}", ast.items[2].kind);
+    };
+    let ExprKind::Match { arms, .. } = &expr.kind else {
+        panic!("expected match, got {:?}", expr.kind);
+    };
+    assert_eq!(arms.len(), 2);
+    let PatternKind::Variant(node, args) = &arms[0].pattern.kind else {
+        panic!("expected variant pattern, got {:?}", arms[0].pattern.kind);
+    };
+    assert_eq!(node, "Node");
+    assert_eq!(args[0].kind, PatternKind::Wildcard);
+    assert_eq!(args[1].kind, PatternKind::Int(-1));
+    assert_eq!(args[1].span, Span::new(70, 72));
+    assert_eq!(arms[1].pattern.kind, PatternKind::Bind("n".into()));
+    assert_eq!(expr.span, Span::new(52, 89));
+
+    let tokens = Lexer::new("yell match t { };")
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert!(SauceParser::new().parse(&tokens).is_err());
+}
+
+#[test]
+fn parse_catch_and_retoss() {
+    let src = "yell catch net retoss parse r + 1;";
+    let tokens = Lexer::new(src).collect::<Result<Vec<_>, _>>().unwrap();
+    let ast = SauceParser::new().parse(&tokens).unwrap();
+
+    // Both take everything to their right.
+    let StatementKind::Yell { expr } = &ast.items[0].kind else {
+        panic!("expected yell, got {:?}", ast.items[0].kind);
+    };
+    let ExprKind::Catch { effect, expr: body } = &expr.kind else {
+        panic!("expected catch, got {:?}", expr.kind);
+    };
+    assert_eq!(effect, "net");
+    assert_eq!(expr.span, Span::new(5, 33));
+    let ExprKind::Retoss {
+        effect,
+        expr: value,
+    } = &body.kind
+    else {
+        panic!("expected retoss, got {:?}", body.kind);
+    };
+    assert_eq!(effect, "parse");
+    assert!(matches!(value.kind, ExprKind::Binary(..)));
+}
+
+#[test]
+fn parse_effect_declarations() {
+    let src = "effect net(List<String>); effect done;";
+    let tokens = Lexer::new(src).collect::<Result<Vec<_>, _>>().unwrap();
+    let ast = SauceParser::new().parse(&tokens).unwrap();
+
+    let StatementKind::EffectDecl { name, payload } = &ast.items[0].kind else {
+        panic!("expected effect declaration, got {:?}", ast.items[0].kind);
+    };
+    assert_eq!(name, "net");
+    let payload = payload.as_ref().expect("a payload");
+    assert!(
+        matches!(&payload.kind, TypeExprKind::Named(name, args) if name == "List" && args.len() == 1)
+    );
+    assert_eq!(ast.items[0].span, Span::new(0, 25));
+    assert_eq!(
+        ast.items[1].kind,
+        StatementKind::EffectDecl {
+            name: "done".into(),
+            payload: None
+        }
+    );
+
+    let tokens = Lexer::new("efect net(Int);")
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert!(matches!(
+        SauceParser::new().parse(&tokens),
+        Err(ParseError::MisspelledKeyword(_, _, keyword)) if keyword == "effect"
+    ));
+}
+
+#[test]
+fn parse_tuples_and_destructuring() {
+    let src = "grab (a, (b,), _) = ((1), (2,), (3, 4,));";
+    let tokens = Lexer::new(src).collect::<Result<Vec<_>, _>>().unwrap();
+    let ast = SauceParser::new().parse(&tokens).unwrap();
+
+    let StatementKind::Destructure { pattern, expr, .. } = &ast.items[0].kind else {
+        panic!("expected destructure, got {:?}", ast.items[0].kind);
+    };
+    let names: Vec<_> = pattern.bindings().into_iter().map(|(n, _)| n).collect();
+    assert_eq!(names, ["a", "b"]);
+    let PatternKind::Tuple(parts) = &pattern.kind else {
+        panic!("expected a tuple pattern, got {:?}", pattern.kind);
+    };
+    assert!(matches!(&parts[1].kind, PatternKind::Tuple(inner) if inner.len() == 1));
+
+    // Only a comma makes a tuple.
+    let ExprKind::Tuple(items) = &expr.kind else {
+        panic!("expected a tuple, got {:?}", expr.kind);
+    };
+    assert_eq!(expr.span, Span::new(20, 40));
+    assert!(matches!(items[0].kind, ExprKind::Int(1)));
+    assert!(matches!(&items[1].kind, ExprKind::Tuple(inner) if inner.len() == 1));
+    assert!(matches!(&items[2].kind, ExprKind::Tuple(inner) if inner.len() == 2));
+
+    let tokens = Lexer::new("type P = (Int, String);")
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    let ast = SauceParser::new().parse(&tokens).unwrap();
+    let StatementKind::TypeDecl { ty, .. } = &ast.items[0].kind else {
+        panic!("expected a type declaration, got {:?}", ast.items[0].kind);
+    };
+    assert!(matches!(&ty.kind, TypeExprKind::Tuple(items) if items.len() == 2));
+
+    // `grab (a) = ...` is neither a name nor a tuple.
+    let tokens = Lexer::new("grab (a) = 1;")
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert!(matches!(
+        SauceParser::new().parse(&tokens),
+        Err(ParseError::UnexpectedToken(span, _)) if span == Span::new(7, 8)
+    ));
+}
+
+#[test]
+fn parse_type_annotations() {
+    let src = "grab twice: fn(Int) -> Int = fn(x: Int, f) -> Int f(x);";
+    let tokens = Lexer::new(src).collect::<Result<Vec<_>, _>>().unwrap();
+    let ast = SauceParser::new().parse(&tokens).unwrap();
+
+    let StatementKind::Let { name, ty, expr } = &ast.items[0].kind else {
+        panic!("expected let, got {:?}", ast.items[0].kind);
+    };
+    assert_eq!(name, "twice");
+    let ty = ty.as_ref().expect("an annotation");
+    assert_eq!(ty.span, Span::new(12, 26));
+    let TypeExprKind::Function(params, ret) = &ty.kind else {
+        panic!("expected a function type, got {:?}", ty.kind);
+    };
+    assert_eq!(params.len(), 1);
+    assert!(matches!(&ret.kind, TypeExprKind::Named(name, _) if name == "Int"));
+
+    let ExprKind::Lambda { params, ret, body } = &expr.kind else {
+        panic!("expected lambda, got {:?}", expr.kind);
+    };
+    assert_eq!(params[0].name, "x");
+    assert_eq!(params[0].span, Span::new(32, 38));
+    assert!(params[0].ty.is_some());
+    assert_eq!(params[1].name, "f");
+    assert!(params[1].ty.is_none());
+    assert!(ret.is_some());
+    assert!(matches!(body.kind, ExprKind::Call(..)));
+}
@@ -0,0 +1,193 @@
+use sauce::project::manifest::EDITIONS;
+use sauce::project::{Manifest, Project, ProjectError, scaffold};
+use std::path::PathBuf;
+use std::process::Command;
+
+/// A fresh directory holding `files`, for one test.
+fn workspace(name: &str, files: &[(&str, &str)]) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("sauce_project_{name}"));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    for (path, src) in files {
+        let path = dir.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, src).unwrap();
+    }
+    dir
+}
+
+fn sauce(dir: &PathBuf, args: &[&str]) -> std::process::Output {
+    Command::new(env!("CARGO_BIN_EXE_sauce"))
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn parses_a_manifest_with_defaults() {
+    let src = "[package]\nname = \"app\"\nversion = \"0.1.0\"\n\n[dependencies]\nutil = { path = \"../util\" }\n";
+    let manifest = Manifest::parse("Sauce.toml", src).unwrap();
+
+    assert_eq!(manifest.package.name, "app");
+    assert_eq!(manifest.package.entry, "src/main.sauce");
+    assert_eq!(manifest.package.edition, *EDITIONS.last().unwrap());
+    assert_eq!(manifest.dependencies["util"].path, "../util");
+}
+
+#[test]
+fn rejects_invalid_manifests() {
+    let invalid = |src: &str| match Manifest::parse("Sauce.toml", src) {
+        Err(ProjectError::Invalid(file, message)) => {
+            assert_eq!(file, "Sauce.toml");
+            message
+        }
+        other => panic!("expected an invalid manifest, got {other:?}"),
+    };
+
+    assert!(invalid("[package]\nname = \"app\"\n").contains("version"));
+    assert!(invalid("[package]\nname = \"my-app\"\nversion = \"0.1.0\"\n").contains("my-app"));
+    assert!(invalid("[package]\nname = \"use\"\nversion = \"0.1.0\"\n").contains("use"));
+    assert!(
+        invalid("[package]\nname = \"app\"\nversion = \"0.1.0\"\nedition = \"1999\"\n")
+            .contains("1999")
+    );
+    assert!(
+        invalid(
+            "[package]\nname = \"app\"\nversion = \"0.1.0\"\n\n[dependencies]\nutil = \"1.0\"\n"
+        )
+        .contains("line 6")
+    );
+}
+
+#[test]
+fn finds_the_project_from_a_subdirectory() {
+    let dir = workspace(
+        "find",
+        &[
+            (
+                "Sauce.toml",
+                "[package]\nname = \"app\"\nversion = \"0.1.0\"\n",
+            ),
+            ("src/main.sauce", "yell 1;\n"),
+        ],
+    );
+
+    let project = Project::find(&dir.join("src")).unwrap().unwrap();
+    assert_eq!(project.manifest.package.name, "app");
+    assert_eq!(project.entry(), dir.join("src/main.sauce"));
+    assert_eq!(project.source_root(), dir.join("src"));
+    assert!(Project::find(&std::env::temp_dir()).unwrap().is_none());
+}
+
+#[test]
+fn resolves_dependencies_transitively() {
+    let dir = workspace(
+        "deps",
+        &[
+            (
+                "app/Sauce.toml",
+                "[package]\nname = \"app\"\nversion = \"0.1.0\"\n\n[dependencies]\nutil = { path = \"../util\" }\n",
+            ),
+            (
+                "util/Sauce.toml",
+                "[package]\nname = \"util\"\nversion = \"0.1.0\"\nentry = \"lib.sauce\"\n\n[dependencies]\ntext = { path = \"../text\" }\n",
+            ),
+            (
+                "text/Sauce.toml",
+                "[package]\nname = \"text\"\nversion = \"0.1.0\"\n",
+            ),
+            (
+                "broken/Sauce.toml",
+                "[package]\nname = \"broken\"\nversion = \"0.1.0\"\n\n[dependencies]\nutil = { path = \"../nowhere\" }\n",
+            ),
+        ],
+    );
+
+    let dependencies = Project::open(dir.join("app"))
+        .unwrap()
+        .dependencies()
+        .unwrap();
+    assert_eq!(dependencies["util"], dir.join("util/lib.sauce"));
+    assert_eq!(dependencies["text"], dir.join("text/src/main.sauce"));
+
+    let error = Project::open(dir.join("broken")).unwrap().dependencies();
+    assert!(matches!(error, Err(ProjectError::MissingDependency(name, _)) if name == "util"));
+}
+
+#[test]
+fn refuses_to_overwrite_projects() {
+    let dir = workspace("scaffold", &[("Sauce.toml", "")]);
+
+    assert_eq!(
+        scaffold::new_project(&dir, "app"),
+        Err(ProjectError::AlreadyExists(dir.to_string_lossy().into()))
+    );
+    assert!(matches!(
+        scaffold::init_project(&dir, "app"),
+        Err(ProjectError::AlreadyExists(_))
+    ));
+    assert_eq!(
+        scaffold::new_project(&dir.join("fresh"), "my-app"),
+        Err(ProjectError::InvalidName("my-app".into()))
+    );
+}
+
+#[test]
+fn new_projects_run_and_test_without_a_filename() {
+    let dir = workspace("new", &[]);
+    assert!(sauce(&dir, &["new", "app"]).status.success());
+    let app = dir.join("app");
+
+    let output = sauce(&app, &["run"]);
+    assert!(output.status.success());
+    assert!(
+        String::from_utf8(output.stdout)
+            .unwrap()
+            .contains("Hello from app!")
+    );
+
+    let output = sauce(&app.join("src"), &["test"]);
+    assert!(output.status.success());
+    assert!(
+        String::from_utf8(output.stdout)
+            .unwrap()
+            .contains("1 passed")
+    );
+}
+
+#[test]
+fn runs_projects_with_path_dependencies() {
+    let dir = workspace(
+        "run",
+        &[
+            (
+                "app/Sauce.toml",
+                "[package]\nname = \"app\"\nversion = \"0.1.0\"\n\n[dependencies]\nutil = { path = \"../util\" }\n",
+            ),
+            (
+                "app/src/main.sauce",
+                "use util::shout;\nuse util::text::whisper;\nyell shout;\nyell whisper;\n",
+            ),
+            (
+                "util/Sauce.toml",
+                "[package]\nname = \"util\"\nversion = \"0.1.0\"\n",
+            ),
+            ("util/src/main.sauce", "pub grab shout = \"HEY\";\n"),
+            ("util/src/text.sauce", "pub grab whisper = \"hey\";\n"),
+        ],
+    );
+
+    let output = sauce(&dir.join("app"), &["run"]);
+    assert!(output.status.success(), "{output:?}");
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("HEY") && stdout.contains("hey"));
+}
+
+#[test]
+fn reports_a_missing_project() {
+    let dir = workspace("none", &[]);
+    let output = sauce(&dir, &["run"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8(output.stderr).unwrap().contains("E0704"));
+}
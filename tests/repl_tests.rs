@@ -0,0 +1,89 @@
+use sauce::repl::{Reply, Session};
+
+fn lines(reply: Reply) -> Vec<String> {
+    match reply {
+        Reply::Lines(lines) => lines,
+        other => panic!("expected output, got {other:?}"),
+    }
+}
+
+#[test]
+fn bindings_persist_across_inputs() {
+    let mut session = Session::new();
+
+    assert_eq!(lines(session.feed("grab x = 5;")), ["x : Int"]);
+    assert_eq!(lines(session.feed("x |> _;")), ["5 : Int"]);
+    assert_eq!(lines(session.feed("\"hi\";")), ["\"hi\" : String"]);
+}
+
+#[test]
+fn waits_for_semicolon() {
+    let mut session = Session::new();
+
+    assert_eq!(session.feed("grab s = \"sauce\""), Reply::More);
+    assert!(session.is_pending());
+    assert_eq!(session.feed("  |> _"), Reply::More);
+    assert_eq!(lines(session.feed("  ;")), ["s : String"]);
+    assert!(!session.is_pending());
+}
+
+#[test]
+fn failed_statements_leave_no_bindings() {
+    let mut session = Session::new();
+
+    session.feed("effect boom(Int);");
+    let out = lines(session.feed("grab x = toss boom 1;"));
+    assert!(out[0].starts_with("runtime error[E0401]:"), "{out:?}");
+
+    let out = lines(session.feed("x;"));
+    assert!(out[0].starts_with("typecheck error[E0101]:"), "{out:?}");
+}
+
+#[test]
+fn type_ast_and_tokens_commands() {
+    let mut session = Session::new();
+    session.feed("grab name = \"sauce\";");
+
+    assert_eq!(lines(session.feed(":type name |> _")), ["String"]);
+    assert!(lines(session.feed(":ast 1 |> _"))[0].contains("Pipeline"));
+    assert_eq!(
+        lines(session.feed(":tokens grab x")),
+        ["Grab 0..4", "Ident(\"x\") 5..6"]
+    );
+}
+
+#[test]
+fn reset_and_load() {
+    let path = std::env::temp_dir().join("sauce_repl_load.sauce");
+    std::fs::write(&path, "grab loaded = 42;\n").unwrap();
+    let path = path.to_str().unwrap();
+
+    let mut session = Session::new();
+    assert_eq!(
+        lines(session.feed(&format!(":load {path}"))),
+        ["loaded : Int".to_string(), format!("loaded {path}")]
+    );
+    assert_eq!(lines(session.feed("loaded;")), ["42 : Int"]);
+
+    session.feed(":reset");
+    let out = lines(session.feed(":type loaded"));
+    assert!(out[0].starts_with("typecheck error[E0101]:"), "{out:?}");
+
+    assert_eq!(session.feed(":quit"), Reply::Quit);
+}
+
+#[test]
+fn load_stops_at_errors() {
+    let path = std::env::temp_dir().join("sauce_repl_load_error.sauce");
+    std::fs::write(&path, "grab a = 1;\ngrab b = a + \"one\";\ngrab c = 3;\n").unwrap();
+    let path = path.to_str().unwrap();
+
+    let mut session = Session::new();
+    let out = lines(session.feed(&format!(":load {path}")));
+    assert_eq!(out.len(), 2, "{out:?}");
+    assert_eq!(out[0], "a : Int");
+    assert!(out[1].starts_with("typecheck error[E0301]:"), "{out:?}");
+
+    let out = lines(session.feed("c;"));
+    assert!(out[0].starts_with("typecheck error[E0101]:"), "{out:?}");
+}
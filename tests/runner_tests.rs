@@ -0,0 +1,354 @@
+use sauce::ast::ast::{Ast, StatementKind};
+use sauce::interpreter::error::RuntimeError;
+use sauce::interpreter::eval::eval_program;
+use sauce::interpreter::runner::{count_tests, run_tests};
+use sauce::lexer::Lexer;
+use sauce::parser::SauceParser;
+use sauce::typechecker::{TypeError, typecheck_program};
+use std::process::Command;
+
+fn parse(src: &str) -> Ast {
+    let tokens = Lexer::new(src)
+        .collect::<Result<Vec<_>, _>>()
+        .expect("lex failed");
+    SauceParser::new().parse(&tokens).expect("parse failed")
+}
+
+const SRC: &str = r#"
+yell "top level";
+
+test "numbers" {
+    grab n = 4 |> _;
+    assert n;
+    assert_eq n, 4;
+}
+
+test "strings" {
+    assert_eq "sauce", "salsa";
+}
+
+test "zero" {
+    assert 0;
+}
+"#;
+
+#[test]
+fn parse_test_blocks() {
+    let ast = parse(SRC);
+    let StatementKind::Test { name, body } = &ast.items[1].kind else {
+        panic!("expected a test, got {:?}", ast.items[1].kind);
+    };
+    assert_eq!(name, "numbers");
+    assert_eq!(body.len(), 3);
+    assert!(matches!(body[2].kind, StatementKind::AssertEq { .. }));
+    assert_eq!(count_tests(&ast), 3);
+}
+
+#[test]
+fn assertions_only_parse_inside_tests() {
+    let tokens = Lexer::new("assert 1;")
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert!(SauceParser::new().parse(&tokens).is_err());
+
+    let tokens = Lexer::new("test \"nested\" { test \"inner\" {} }")
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert!(SauceParser::new().parse(&tokens).is_err());
+}
+
+#[test]
+fn typecheck_assertions() {
+    assert!(typecheck_program(&parse(SRC)).is_ok());
+    assert!(matches!(
+        typecheck_program(&parse("test \"t\" { assert \"yes\"; }")),
+        Err(TypeError::Mismatch(_, expected, found)) if expected == "Int" && found == "String"
+    ));
+    assert!(matches!(
+        typecheck_program(&parse("test \"t\" { assert_eq 1, \"1\"; }")),
+        Err(TypeError::Mismatch(..))
+    ));
+}
+
+#[test]
+fn tests_do_not_see_top_level_bindings() {
+    let ast = parse("grab x = 1; yell x; test \"t\" { assert x; }");
+    assert!(matches!(
+        typecheck_program(&ast),
+        Err(TypeError::UnknownIdent(_, name, _)) if name == "x"
+    ));
+}
+
+#[test]
+fn run_tests_reports_each_outcome() {
+    let ast = parse(SRC);
+    let outcomes = run_tests(&ast, None);
+
+    let names: Vec<_> = outcomes.iter().map(|o| o.name.as_str()).collect();
+    assert_eq!(names, ["numbers", "strings", "zero"]);
+    assert!(outcomes[0].result.is_ok());
+    assert!(matches!(
+        &outcomes[1].result,
+        Err(RuntimeError::NotEqual(_, left, right)) if left == "\"sauce\"" && right == "\"salsa\""
+    ));
+    assert!(matches!(
+        outcomes[2].result,
+        Err(RuntimeError::AssertionFailed(_))
+    ));
+
+    let filtered = run_tests(&ast, Some("str"));
+    assert_eq!(filtered.len(), 1);
+    assert_eq!(filtered[0].name, "strings");
+}
+
+#[test]
+fn lists_index_and_report_out_of_bounds() {
+    let src = r#"
+test "lists" {
+    grab xs = [[1, 2], [3]];
+    assert_eq xs[0][1], 2;
+    assert_eq xs |> len, 2;
+    assert_eq xs[1], [3];
+    assert_eq "sauce" |> len, 5;
+}
+
+test "out of bounds" {
+    grab xs = [1, 2, 3];
+    assert xs[3];
+}
+"#;
+    let outcomes = run_tests(&parse(src), None);
+    assert!(outcomes[0].result.is_ok(), "{:?}", outcomes[0].result);
+    assert!(matches!(
+        outcomes[1].result,
+        Err(RuntimeError::IndexOutOfBounds(_, 3, 3))
+    ));
+}
+
+#[test]
+fn stages_run_one_element_at_a_time() {
+    let src = r#"
+test "combinators" {
+    grab xs = [1, 2, 3, 4];
+    assert_eq xs |> map(fn(x) x * x) |> filter(fn(x) x % 2 == 0) |> sum, 20;
+    assert_eq xs |> fold(0, fn(acc, x) acc * 10 + x), 1234;
+    assert_eq xs |> take(2) |> map(fn(x) [x]), [[1], [2]];
+    assert_eq ["a", "b"] |> map(fn(s) s + s), ["aa", "bb"];
+}
+
+test "take stops early" {
+    grab xs = [1, 2, 3];
+    assert_eq xs |> map(fn(x) 10 / (2 - x)) |> take(1), [10];
+}
+
+test "division by zero" {
+    grab xs = [1, 2, 3];
+    assert_eq xs |> map(fn(x) 10 / (2 - x)) |> take(2), [10];
+}
+"#;
+    let ast = parse(src);
+    typecheck_program(&ast).expect("typecheck failed");
+    let outcomes = run_tests(&ast, None);
+    assert!(outcomes[0].result.is_ok(), "{:?}", outcomes[0].result);
+    assert!(outcomes[1].result.is_ok(), "{:?}", outcomes[1].result);
+    assert!(matches!(
+        outcomes[2].result,
+        Err(RuntimeError::DivisionByZero(_))
+    ));
+}
+
+#[test]
+fn programs_skip_their_tests() {
+    assert!(eval_program(&parse("test \"t\" { assert 0; }")).is_ok());
+}
+
+#[test]
+fn cli_exits_non_zero_on_failure() {
+    let path = std::env::temp_dir().join("sauce_runner_tests.sauce");
+    std::fs::write(&path, SRC).unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_sauce"))
+        .arg("test")
+        .arg(&path)
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("test numbers ... ok"), "{stdout}");
+    assert!(stdout.contains("test strings ... FAILED"), "{stdout}");
+    assert!(
+        stdout.contains("test result: FAILED. 1 passed; 2 failed; 0 filtered out"),
+        "{stdout}"
+    );
+    assert!(!stdout.contains("top level"), "{stdout}");
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("error[E0501]"), "{stderr}");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_sauce"))
+        .args(["test", "--filter", "numbers"])
+        .arg(&path)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(
+        stdout.contains("test result: ok. 1 passed; 0 failed; 2 filtered out"),
+        "{stdout}"
+    );
+}
+
+#[test]
+fn records_are_copied_on_update() {
+    let src = r#"
+test "records" {
+    grab ada = { name: "ada", age: 36 };
+    grab older = { ada with age: ada.age + 1 };
+    assert_eq ada.age, 36;
+    assert_eq older, { age: 37, name: "ada" };
+    assert_eq [ada, older] |> map(fn(p) p.age) |> sum, 73;
+    assert_eq { outer: { inner: 1 } }.outer.inner, 1;
+}
+"#;
+    let ast = parse(src);
+    typecheck_program(&ast).expect("typecheck failed");
+    let outcomes = run_tests(&ast, None);
+    assert!(outcomes[0].result.is_ok(), "{:?}", outcomes[0].result);
+}
+
+#[test]
+fn matches_pick_the_first_arm_that_fits() {
+    let src = r#"
+type Shape = Circle(Int) | Rect(Int, Int) | Empty;
+
+test "shapes" {
+    grab shapes = [Circle(2), Rect(2, 3), Empty, Rect(5, 5)];
+    grab areas = shapes |> map(fn(s) match s {
+        Circle(r) => 3 * r * r,
+        Rect(w, h) => w * h,
+        Empty => 0,
+    });
+    assert_eq areas, [12, 6, 0, 25];
+    assert_eq Rect(1, 2), Rect(1, 2);
+    assert_eq match Rect(5, 5) { Rect(n, 5) => n, _ => 0 }, 5;
+    assert_eq match "b" { "a" => 1, "b" => 2, _ => 3 }, 2;
+}
+"#;
+    let ast = parse(src);
+    typecheck_program(&ast).expect("typecheck failed");
+    let outcomes = run_tests(&ast, None);
+    assert!(outcomes[0].result.is_ok(), "{:?}", outcomes[0].result);
+}
+
+#[test]
+fn catch_turns_tosses_into_results() {
+    let src = r#"
+effect net(String);
+effect missing;
+effect inner;
+effect outer(Int);
+test "catch and retoss" {
+    grab replies = [200, 404] |> map(fn(code) match code {
+        200 => Ok({ code: code, body: Some("hi") }),
+        _ => Err("status " + "bad"),
+    });
+    assert_eq replies[1], Err("status bad");
+    assert_eq catch net replies |> map(fn(r) retoss net r), Err("status bad");
+    assert_eq catch net replies |> take(1) |> map(fn(r) (retoss net r).code), Ok([200]);
+    assert_eq catch net retoss net Ok(1), Ok(1);
+    assert_eq catch net [1, 2] |> map(fn(x) match x { 2 => retoss net Err("two"), _ => x }), Err("two");
+    assert_eq match catch net (toss net "down") { Err(_) => 1, Ok(_) => 0 }, 1;
+    assert_eq match catch missing retoss missing None { Err(_) => 1, Ok(n) => n }, 1;
+    assert_eq catch outer (catch inner match 1 { 0 => retoss inner None, _ => toss outer 5 }), Err(5);
+    assert_eq match Some(2) { Some(n) => n, None => 0 }, 2;
+}
+"#;
+    let ast = parse(src);
+    typecheck_program(&ast).expect("typecheck failed");
+    let outcomes = run_tests(&ast, None);
+    assert!(outcomes[0].result.is_ok(), "{:?}", outcomes[0].result);
+}
+
+#[test]
+fn tuples_destructure_and_match() {
+    let src = r#"
+effect e(String);
+test "tuples" {
+    grab pair = (1, "one");
+    grab (n, s) = pair;
+    assert_eq n, 1;
+    assert_eq s, "one";
+    grab ((a, _), (b,)) = ((2, "x"), ([3],));
+    assert_eq a + b[0], 5;
+    assert_eq [(1, "a"), (2, "b")] |> map(fn(p) match p { (1, s) => s, (_, s) => s + s }), ["a", "bb"];
+    assert_eq [1, 2, 3] |> fold((0, 1), fn(acc, x) match acc { (sum, product) => (sum + x, product * x) }), (6, 6);
+    assert_eq catch e (1, retoss e Err("no")), Err("no");
+}
+"#;
+    let ast = parse(src);
+    typecheck_program(&ast).expect("typecheck failed");
+    let outcomes = run_tests(&ast, None);
+    assert!(outcomes[0].result.is_ok(), "{:?}", outcomes[0].result);
+}
+
+#[test]
+fn annotated_functions_are_called_and_passed() {
+    let src = r#"
+effect oops(String);
+test "functions" {
+    grab offset: Int = 10;
+    grab shift = fn(x: Int) -> Int x + offset;
+    grab offset = 100;
+    grab twice = fn(f: fn(Int) -> Int, x: Int) f(f(x));
+    assert_eq shift(1), 11;
+    assert_eq twice(shift, 1), 21;
+    assert_eq twice(fn(x: Int) x * offset, 2), 20000;
+    assert_eq [1, 2] |> map(shift), [11, 12];
+    grab boom = fn(s: String) toss oops s;
+    assert_eq catch oops boom("no"), Err("no");
+}
+"#;
+    let ast = parse(src);
+    typecheck_program(&ast).expect("typecheck failed");
+    let outcomes = run_tests(&ast, None);
+    assert!(outcomes[0].result.is_ok(), "{:?}", outcomes[0].result);
+}
+
+#[test]
+fn inferred_functions_are_used_at_many_types() {
+    let src = r#"
+test "polymorphism" {
+    grab id = fn(x) x;
+    grab twice = fn(f, x) f(f(x));
+    grab unwrap = fn(o, d) match o { Some(v) => v, None => d };
+    assert_eq id(1), 1;
+    assert_eq id("a"), "a";
+    assert_eq twice(fn(s) s + "!", "hi"), "hi!!";
+    assert_eq twice(fn(n) n * 3, 2), 18;
+    assert_eq unwrap(Some(4), 0) + unwrap(None, 1), 5;
+    assert_eq [1, 2] |> map(id), [1, 2];
+}
+"#;
+    let ast = parse(src);
+    typecheck_program(&ast).expect("typecheck failed");
+    let outcomes = run_tests(&ast, None);
+    assert!(outcomes[0].result.is_ok(), "{:?}", outcomes[0].result);
+}
+
+#[test]
+fn combinators_toss_what_their_callbacks_do() {
+    let src = r#"
+effect not_found(Int);
+
+test "effect polymorphism" {
+    grab each = fn(xs, f) xs |> map(f);
+    grab fetch = fn(code) match code { 404 => retoss not_found Err(code), _ => code };
+    assert_eq each([1, 2], fn(n) n * 2), [2, 4];
+    assert_eq catch not_found each([200, 404], fetch), Err(404);
+    assert_eq catch not_found each([200], fetch), Ok([200]);
+}
+"#;
+    let ast = parse(src);
+    typecheck_program(&ast).expect("typecheck failed");
+    let outcomes = run_tests(&ast, None);
+    assert!(outcomes[0].result.is_ok(), "{:?}", outcomes[0].result);
+}
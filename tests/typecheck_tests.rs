@@ -1,6 +1,7 @@
 use sauce::lexer::Lexer;
 use sauce::parser::SauceParser;
-use sauce::typechecker::typecheck_program;
+use sauce::typechecker::{TypeError, typecheck_program};
+use sauce::util::span::Span;
 
 #[test]
 fn typecheck_simple_let() {
@@ -31,7 +32,7 @@ fn typecheck_unknown_ident() {
 
 #[test]
 fn typecheck_toss() {
-    let src = "toss oops \"bad\";";
+    let src = "effect oops(String); toss oops \"bad\";";
     let tokens = Lexer::new(src)
         .collect::<Result<Vec<_>, _>>()
         .expect("lex failed");
@@ -53,3 +54,688 @@ fn typecheck_pipeline_into_literal_should_fail() {
 
     assert!(result.is_err());
 }
+
+#[test]
+fn typecheck_suggests_names_in_scope() {
+    let src = "grab total = 1; grab other = 2; yell totl;";
+    let tokens = Lexer::new(src).collect::<Result<Vec<_>, _>>().unwrap();
+    let ast = SauceParser::new().parse(&tokens).unwrap();
+
+    match typecheck_program(&ast) {
+        Err(TypeError::UnknownIdent(span, name, suggestion)) => {
+            assert_eq!(span, Span::new(37, 41));
+            assert_eq!(name, "totl");
+            assert_eq!(suggestion.as_deref(), Some("total"));
+        }
+        other => panic!("expected unknown identifier, got {other:?}"),
+    }
+}
+
+#[test]
+fn typecheck_only_suggests_close_names() {
+    let src = "grab total = 1; yell count;";
+    let tokens = Lexer::new(src).collect::<Result<Vec<_>, _>>().unwrap();
+    let ast = SauceParser::new().parse(&tokens).unwrap();
+
+    assert!(matches!(
+        typecheck_program(&ast),
+        Err(TypeError::UnknownIdent(_, _, None))
+    ));
+}
+
+fn check(src: &str) -> Result<(), TypeError> {
+    let tokens = Lexer::new(src).collect::<Result<Vec<_>, _>>().unwrap();
+    typecheck_program(&SauceParser::new().parse(&tokens).unwrap())
+}
+
+#[test]
+fn typecheck_lists() {
+    assert!(check("grab xs = [[1], [2, 3]]; grab n = xs[1][0]; yell n;").is_ok());
+    assert!(check("grab n = [\"a\", \"b\"] |> len; grab m = \"abc\" |> len;").is_ok());
+
+    assert_eq!(
+        check("grab xs = [1, \"two\"];"),
+        Err(TypeError::Conflict(
+            Span::new(14, 19),
+            "Int".into(),
+            "String".into(),
+            Span::new(11, 12)
+        ))
+    );
+    assert_eq!(
+        check("grab xs = [];"),
+        Err(TypeError::EmptyList(Span::new(10, 12)))
+    );
+    assert_eq!(
+        check("grab xs = [1]; yell xs[\"0\"];"),
+        Err(TypeError::Mismatch(
+            Span::new(23, 26),
+            "Int".into(),
+            "String".into()
+        ))
+    );
+    assert_eq!(
+        check("grab n = 1; yell n[0];"),
+        Err(TypeError::Mismatch(
+            Span::new(17, 18),
+            "List".into(),
+            "Int".into()
+        ))
+    );
+    assert_eq!(
+        check("yell 5 |> len;"),
+        Err(TypeError::Mismatch(
+            Span::new(5, 6),
+            "List or String".into(),
+            "Int".into()
+        ))
+    );
+}
+
+#[test]
+fn bindings_hide_builtin_stages() {
+    // With `len` bound, `|> len` is that binding, not the built-in.
+    assert!(check("grab len = \"x\"; grab s = 5 |> len; grab t = s |> len;").is_ok());
+}
+
+#[test]
+fn typecheck_stages_and_functions() {
+    assert!(
+        check("grab xs = [1, 2]; grab n = xs |> map(fn(x) x * 2) |> filter(fn(x) x > 2) |> take(1) |> sum;")
+            .is_ok()
+    );
+    assert!(check("grab s = [1] |> fold(\"\", fn(acc, x) acc + \"!\"); yell s + s;").is_ok());
+    assert!(check("grab xs = [[1]] |> map(fn(ys) ys |> map(fn(y) y + 1));").is_ok());
+
+    assert_eq!(
+        check("yell [1] |> take;"),
+        Err(TypeError::ArgumentCount(Span::new(12, 16), 1, 0))
+    );
+    assert_eq!(
+        check("yell [1] |> map(fn(x, y) x);"),
+        Err(TypeError::ParamCount(Span::new(16, 26), 1, 2))
+    );
+    assert!(check("grab f = fn(x) x;").is_ok());
+    assert_eq!(
+        check("yell [\"a\"] |> sum;"),
+        Err(TypeError::Mismatch(
+            Span::new(5, 10),
+            "List<Int>".into(),
+            "List<String>".into()
+        ))
+    );
+    assert_eq!(
+        check("yell [1] |> filter(fn(x) \"no\");"),
+        Err(TypeError::Mismatch(
+            Span::new(25, 29),
+            "Int".into(),
+            "String".into()
+        ))
+    );
+    assert_eq!(
+        check("yell [1] |> fold(0, fn(acc, x) \"s\");"),
+        Err(TypeError::Mismatch(
+            Span::new(31, 34),
+            "Int".into(),
+            "String".into()
+        ))
+    );
+    assert_eq!(
+        check("yell 1 + \"a\";"),
+        Err(TypeError::Mismatch(
+            Span::new(9, 12),
+            "Int".into(),
+            "String".into()
+        ))
+    );
+}
+
+#[test]
+fn typecheck_records() {
+    assert!(
+        check(
+            "type Tags = List<Int>; type P = { name: String, tags: Tags };
+             grab p = { tags: [1], name: \"a\" };
+             grab q = { p with name: p.name + \"!\" };
+             grab n = q.tags |> sum;"
+        )
+        .is_ok()
+    );
+    assert!(check("grab ps = [{ a: 1, b: \"x\" }, { b: \"y\", a: 2 }];").is_ok());
+
+    assert_eq!(
+        check("yell { a: 1 }.b;"),
+        Err(TypeError::UnknownField(
+            Span::new(5, 15),
+            "{ a: Int }".into(),
+            "b".into(),
+            None
+        ))
+    );
+    assert_eq!(
+        check("grab p = { a: 1, a: 2 };"),
+        Err(TypeError::DuplicateField(Span::new(20, 21), "a".into()))
+    );
+    assert_eq!(
+        check("grab p = { a: 1 }; yell { p with a: \"s\" };"),
+        Err(TypeError::Mismatch(
+            Span::new(36, 39),
+            "Int".into(),
+            "String".into()
+        ))
+    );
+    assert_eq!(
+        check("yell 1.a;"),
+        Err(TypeError::Mismatch(
+            Span::new(5, 6),
+            "a record".into(),
+            "Int".into()
+        ))
+    );
+    assert_eq!(
+        check("type P = { a: Strng };"),
+        Err(TypeError::UnknownType(
+            Span::new(14, 19),
+            "Strng".into(),
+            Some("String".into())
+        ))
+    );
+    assert_eq!(
+        check("type L = List;"),
+        Err(TypeError::ArgumentCount(Span::new(9, 13), 1, 0))
+    );
+}
+
+#[test]
+fn typecheck_sums_and_matches() {
+    assert!(
+        check(
+            "type Tree = Leaf | Node(Tree, Int, Tree);
+             grab t = Node(Leaf, 1, Node(Leaf, 2, Leaf));
+             grab n = match t { Leaf => 0, Node(Node(_, m, _), n, _) => n + m, Node(_, n, _) => n };
+             yell [t, Leaf] |> map(fn(t) match t { Leaf => \"leaf\", _ => \"node\" });"
+        )
+        .is_ok()
+    );
+
+    assert_eq!(
+        check("type S = C(Int) | R(Int, Int) | E; yell match C(1) { C(_) => 1 };"),
+        Err(TypeError::NonExhaustive(
+            Span::new(46, 50),
+            vec!["R(_, _)".into(), "E".into()]
+        ))
+    );
+    assert_eq!(
+        check(
+            "type B = T | F; type P = P(B, B); yell match P(T, F) { P(T, T) => 1, P(F, F) => 2 };"
+        ),
+        Err(TypeError::NonExhaustive(
+            Span::new(45, 52),
+            vec!["P(T, F)".into(), "P(F, T)".into()]
+        ))
+    );
+    assert_eq!(check("yell match 1 { 0 => 1, n => n };"), Ok(()));
+    assert_eq!(
+        check("yell match 1 { 0 => 1 };"),
+        Err(TypeError::NonExhaustive(
+            Span::new(11, 12),
+            vec!["_".into()]
+        ))
+    );
+    assert_eq!(
+        check("type B = T | F; yell match T { _ => 1, T => 2 };"),
+        Err(TypeError::UnreachablePattern(Span::new(39, 40)))
+    );
+    assert_eq!(
+        check("type B = T | T;"),
+        Err(TypeError::DuplicateVariant(
+            Span::new(13, 14),
+            "B".into(),
+            "T".into()
+        ))
+    );
+    assert_eq!(
+        check("type P = P(Int, Int); yell match P(1, 2) { P(a, a) => a };"),
+        Err(TypeError::DuplicateBinding(Span::new(48, 49), "a".into()))
+    );
+    assert_eq!(
+        check("type P = P(Int, Int); grab f = P;"),
+        Err(TypeError::UnappliedConstructor(
+            Span::new(31, 32),
+            "P".into(),
+            2
+        ))
+    );
+    assert_eq!(
+        check("type B = T | F; yell match T { T => 1, F => \"f\" };"),
+        Err(TypeError::Conflict(
+            Span::new(44, 47),
+            "Int".into(),
+            "String".into(),
+            Span::new(36, 37)
+        ))
+    );
+}
+
+#[test]
+fn typecheck_option_result_and_catch() {
+    assert!(
+        check(
+            "type Reply = { code: Int, body: Option<String> };
+             effect net(String);
+             effect parse;
+             grab o = Some(1);
+             grab n = match o { Some(x) => x, None => 0 };
+             grab xs = [None, Some(\"a\")];
+             grab r = catch net match n { 0 => retoss net Err(\"zero\"), n => 10 / n };
+             yell match r { Ok(n) => n, Err(e) => 0 };
+             yell catch parse [\"1\", \"x\"] |> map(fn(s) match s { \"1\" => 1, _ => retoss parse None });"
+        )
+        .is_ok()
+    );
+
+    // A type argument nothing gives is `_`, and joins with any other.
+    let found = |src: &str| match check(src) {
+        Err(TypeError::Mismatch(_, _, found)) => found,
+        other => panic!("expected a mismatch, got {other:?}"),
+    };
+    assert_eq!(found("yell 1 + None;"), "Option<_>");
+    assert_eq!(found("yell 1 + [None, Some(1)];"), "List<Option<Int>>");
+    assert_eq!(
+        found("effect e(String); yell 1 + catch e toss e \"x\";"),
+        "Result<Unit, String>"
+    );
+    assert_eq!(found("effect e; yell \"\" + retoss e Ok(1);"), "Int");
+
+    assert_eq!(
+        check("effect net; yell catch net 1;"),
+        Err(TypeError::NothingToCatch(
+            Span::new(17, 28),
+            "net".into(),
+            None
+        ))
+    );
+    assert_eq!(
+        check("effect net(Int); yell catch nett toss net 1;"),
+        Err(TypeError::UnknownEffect(
+            Span::new(22, 43),
+            "nett".into(),
+            Some("net".into())
+        ))
+    );
+    assert_eq!(
+        check("effect e(Int); yell catch e match 1 { 0 => toss e 1, _ => toss e \"x\" };"),
+        Err(TypeError::Mismatch(
+            Span::new(65, 68),
+            "Int".into(),
+            "String".into()
+        ))
+    );
+    assert_eq!(
+        check("effect e; yell retoss e 1;"),
+        Err(TypeError::Mismatch(
+            Span::new(24, 25),
+            "Option or Result".into(),
+            "Int".into()
+        ))
+    );
+    assert_eq!(
+        check("yell [Some(1), Some(\"a\")];"),
+        Err(TypeError::Conflict(
+            Span::new(15, 24),
+            "Option<Int>".into(),
+            "Option<String>".into(),
+            Span::new(11, 12)
+        ))
+    );
+    assert_eq!(
+        check("type Option = Some(Int);"),
+        Err(TypeError::PreludeType(Span::new(0, 24), "Option".into()))
+    );
+    assert_eq!(
+        check("type R = { r: Result<Int> };"),
+        Err(TypeError::ArgumentCount(Span::new(14, 25), 2, 1))
+    );
+    assert_eq!(
+        check("yell match Some(1) { Some(1) => 1 };"),
+        Err(TypeError::NonExhaustive(
+            Span::new(11, 18),
+            vec!["None".into()]
+        ))
+    );
+}
+
+#[test]
+fn typecheck_tuples() {
+    assert!(
+        check(
+            "type Pair = (Int, String);
+             grab (n, s) = (1, \"a\");
+             grab ((a, _), b) = ((n, [s]), None);
+             grab ps = [(1, \"a\"), (2, \"b\")] |> map(fn(p) match p { (1, s) => s, (_, s) => s + s });
+             yell match (Some(a), b) { (Some(x), None) => x, (_, _) => 0 };"
+        )
+        .is_ok()
+    );
+
+    let found = |src: &str| match check(src) {
+        Err(TypeError::Mismatch(_, _, found)) => found,
+        other => panic!("expected a mismatch, got {other:?}"),
+    };
+    assert_eq!(found("yell 1 + (1, \"a\");"), "(Int, String)");
+    assert_eq!(found("yell 1 + (None,);"), "(Option<_>,)");
+    assert_eq!(
+        found("yell 1 + [(1, None), (2, Some(\"a\"))];"),
+        "List<(Int, Option<String>)>"
+    );
+
+    assert_eq!(
+        check("grab (a, b) = (1, 2, 3);"),
+        Err(TypeError::Mismatch(
+            Span::new(5, 11),
+            "(Int, Int, Int)".into(),
+            "(_, _)".into()
+        ))
+    );
+    assert_eq!(
+        check("yell match (1, None) { (1, _) => 0, (_, Some(x)) => x };"),
+        Err(TypeError::NonExhaustive(
+            Span::new(11, 20),
+            vec!["(_, None)".into()]
+        ))
+    );
+    assert_eq!(
+        check("yell match (1, 2) { (a, b) => a, (1, _) => 0 };"),
+        Err(TypeError::UnreachablePattern(Span::new(33, 39)))
+    );
+}
+
+#[test]
+fn typecheck_annotations() {
+    assert!(
+        check(
+            "grab double = fn(x: Int) -> Int x * 2;
+             grab twice = fn(f: fn(Int) -> Int, x: Int) f(f(x));
+             grab (a, b): (Int, String) = (1, \"a\");
+             grab o: Option<Int> = None;
+             yell twice(double, a);
+             yell [1] |> map(double) |> map(fn(s: Int) b + \"!\");
+             effect oops(Int);
+             grab boom = fn(x: Int) toss oops x;
+             grab bang = boom;
+             yell catch oops bang(1);"
+        )
+        .is_ok()
+    );
+
+    assert_eq!(
+        check("grab x: Int = \"a\";"),
+        Err(TypeError::Mismatch(
+            Span::new(14, 17),
+            "Int".into(),
+            "String".into()
+        ))
+    );
+    assert_eq!(
+        check("grab f = fn(x: Int) -> String x;"),
+        Err(TypeError::Mismatch(
+            Span::new(30, 31),
+            "String".into(),
+            "Int".into()
+        ))
+    );
+    assert_eq!(
+        check("yell [1] |> map(fn(x: String) x);"),
+        Err(TypeError::Mismatch(
+            Span::new(22, 28),
+            "String".into(),
+            "Int".into()
+        ))
+    );
+    assert_eq!(
+        check("grab f = fn(x: Int) x; yell f(\"a\");"),
+        Err(TypeError::Mismatch(
+            Span::new(30, 33),
+            "Int".into(),
+            "String".into()
+        ))
+    );
+    assert_eq!(
+        check("grab f: fn(Int) -> Int = fn(x: String) x;"),
+        Err(TypeError::Mismatch(
+            Span::new(25, 40),
+            "fn(Int) -> Int".into(),
+            "fn(String) -> String".into()
+        ))
+    );
+    assert!(check("grab f = fn(x, y: Int) x;").is_ok());
+    assert_eq!(
+        check("grab x: Strng = \"a\";"),
+        Err(TypeError::UnknownType(
+            Span::new(8, 13),
+            "Strng".into(),
+            Some("String".into())
+        ))
+    );
+}
+
+#[test]
+fn typecheck_inference() {
+    assert!(
+        check(
+            "grab id = fn(x) x;
+             grab twice = fn(f, x) f(f(x));
+             grab unwrap = fn(o, d) match o { Some(v) => v, None => d };
+             yell id(1) + 1;
+             yell id(\"a\") + \"b\";
+             yell twice(fn(s) s + \"!\", \"hi\");
+             yell unwrap(Some(1), 0) + unwrap(None, 2);
+             yell [1, 2] |> map(id) |> map(fn(n) twice(id, n));
+             effect oops(Int);
+             grab boom = fn(x) toss oops x;
+             yell catch oops boom(1);
+             grab (f, g) = (id, id);
+             yell f(1) + 1;
+             yell g(\"a\");"
+        )
+        .is_ok()
+    );
+
+    assert_eq!(
+        check("grab add = fn(a, b) a + b; yell add(1, \"b\");"),
+        Err(TypeError::Mismatch(
+            Span::new(39, 42),
+            "Int".into(),
+            "String".into()
+        ))
+    );
+    assert_eq!(
+        check("grab f = fn(x) [x, 1]; yell f(\"s\");"),
+        Err(TypeError::Mismatch(
+            Span::new(30, 33),
+            "Int".into(),
+            "String".into()
+        ))
+    );
+    assert_eq!(
+        check("grab f = fn(x) match x { 1 => \"a\", _ => 2 };"),
+        Err(TypeError::Conflict(
+            Span::new(40, 41),
+            "String".into(),
+            "Int".into(),
+            Span::new(30, 33)
+        ))
+    );
+    // Items and arguments that differ point to what they differ from.
+    assert_eq!(
+        check("grab f = fn(x) [x + 1, x, \"a\"];"),
+        Err(TypeError::Conflict(
+            Span::new(26, 29),
+            "Int".into(),
+            "String".into(),
+            Span::new(16, 21)
+        ))
+    );
+    assert_eq!(
+        check("grab g = fn(f) (f(1), f(\"a\"));"),
+        Err(TypeError::Conflict(
+            Span::new(24, 27),
+            "Int".into(),
+            "String".into(),
+            Span::new(18, 19)
+        ))
+    );
+    assert_eq!(
+        check("grab f = fn(x) x(x);"),
+        Err(TypeError::InfiniteType(
+            Span::new(17, 18),
+            "fn(_) -> _".into()
+        ))
+    );
+    assert_eq!(
+        check("grab name = fn(p) p.name;"),
+        Err(TypeError::Ambiguous(Span::new(18, 19)))
+    );
+    assert_eq!(
+        check("grab size = fn(xs) xs |> len;"),
+        Err(TypeError::Ambiguous(Span::new(19, 21)))
+    );
+}
+
+#[test]
+fn typecheck_effect_declarations() {
+    assert!(
+        check(
+            "type Reply = { code: Int };
+             effect net(Reply);
+             effect done;
+             effect done;
+             grab fetch = fn(code) match code { 200 => code, _ => retoss net Err({ code: code }) };
+             yell catch net fetch(404);
+             yell catch done toss done;"
+        )
+        .is_ok()
+    );
+
+    assert_eq!(
+        check("effect net(String); toss nett \"down\";"),
+        Err(TypeError::UnknownEffect(
+            Span::new(20, 36),
+            "nett".into(),
+            Some("net".into())
+        ))
+    );
+    assert_eq!(
+        check("effect net(String); toss net 1;"),
+        Err(TypeError::Mismatch(
+            Span::new(29, 30),
+            "String".into(),
+            "Int".into()
+        ))
+    );
+    assert_eq!(
+        check("effect net(String); toss net;"),
+        Err(TypeError::Mismatch(
+            Span::new(20, 28),
+            "String".into(),
+            "nothing".into()
+        ))
+    );
+    assert_eq!(
+        check("effect done; toss done 1;"),
+        Err(TypeError::Mismatch(
+            Span::new(23, 24),
+            "Unit".into(),
+            "Int".into()
+        ))
+    );
+    assert_eq!(
+        check("effect net(Int); yell catch net retoss net Err(\"x\");"),
+        Err(TypeError::Mismatch(
+            Span::new(43, 51),
+            "Int".into(),
+            "String".into()
+        ))
+    );
+    assert_eq!(
+        check("effect net(String);\neffect net(Int);"),
+        Err(TypeError::EffectRedeclared(
+            Span::new(20, 36),
+            "net".into(),
+            "String".into()
+        ))
+    );
+    assert_eq!(
+        check("effect net(Strng);"),
+        Err(TypeError::UnknownType(
+            Span::new(11, 16),
+            "Strng".into(),
+            Some("String".into())
+        ))
+    );
+}
+
+#[test]
+fn typecheck_effect_polymorphism() {
+    assert!(
+        check(
+            "effect net(String);
+             effect parse(String);
+             grab each = fn(xs, f) xs |> map(f);
+             grab apply = fn(f, x) f(x);
+             grab fetch = fn(x) match x { 404 => retoss net Err(\"gone\"), _ => x };
+             grab read = fn(x) match x { 0 => retoss parse Err(\"zero\"), _ => x };
+             yell each([1, 2], fn(n) n * 2);
+             yell catch net each([1, 404], fetch);
+             yell catch net apply(fetch, 404);
+             yell catch parse (catch net each([1, 0], fn(n) read(fetch(n))));
+             grab both = fn(n) read(fetch(n));
+             yell catch parse (catch net both(0));"
+        )
+        .is_ok()
+    );
+
+    assert_eq!(
+        check(
+            "effect net(String);
+grab each = fn(xs, f) xs |> map(f);
+yell catch net each([1], fn(n) n);"
+        ),
+        Err(TypeError::NothingToCatch(
+            Span::new(61, 89),
+            "net".into(),
+            None
+        ))
+    );
+    assert_eq!(
+        check(
+            "effect net(String);
+effect parse(String);
+grab fetch = fn(x) toss net x;
+yell catch parse (catch net fetch(\"a\"));"
+        ),
+        Err(TypeError::NothingToCatch(
+            Span::new(78, 112),
+            "parse".into(),
+            None
+        ))
+    );
+    assert_eq!(
+        check("effect net(String); grab boom = fn(x) toss net x; grab n: Int = boom;"),
+        Err(TypeError::Mismatch(
+            Span::new(64, 68),
+            "Int".into(),
+            "fn(String) -> Unit tosses net".into()
+        ))
+    );
+}
+
+#[test]
+fn type_parameters_past_z_are_numbered() {
+    use sauce::typechecker::types::Type;
+
+    assert_eq!(Type::Param(0).to_string(), "A");
+    assert_eq!(Type::Param(25).to_string(), "Z");
+    assert_eq!(Type::Param(26).to_string(), "T26");
+    assert_eq!(Type::Param(300).to_string(), "T300");
+}